@@ -0,0 +1,199 @@
+use shipyard::*;
+use crate::{
+    events::{
+        Events,
+        EventReader,
+        EventWorkloadSystems,
+    },
+    physics::{
+        world::PhysicsWorld,
+        PhysicsBody,
+    },
+};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Health {
+    pub current: f32,
+    pub max: f32,
+    /// How many ticks the entity is invulnerable for after taking damage
+    pub invulnerability_ticks: u32,
+}
+
+impl Health {
+    pub fn new(max: f32) -> Self {
+        Health {
+            current: max,
+            max,
+            invulnerability_ticks: 0,
+        }
+    }
+
+    /// Sets the number of ticks of invulnerability given after taking damage
+    pub fn with_invulnerability(mut self, ticks: u32) -> Self {
+        self.invulnerability_ticks = ticks;
+        self
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.current <= 0.0
+    }
+
+    pub fn heal(&mut self, amount: f32) {
+        self.current = f32::min(self.current + amount, self.max);
+    }
+}
+
+/// Deals damage to any entity with Health whose colliders touch this entity's body,
+/// as long as the touching collider's layer is in layer_mask. The target's collider has to
+/// include this entity's layer in its collides_with for the contact to be recorded.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Damage {
+    pub amount: f32,
+    pub layer_mask: u64,
+}
+
+impl Damage {
+    pub fn new(amount: f32, layer_mask: u64) -> Self {
+        Damage {
+            amount,
+            layer_mask,
+        }
+    }
+}
+
+/// Counts down the ticks until the entity can take damage again
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Invulnerable(pub u32);
+
+impl Invulnerable {
+    pub fn is_active(&self) -> bool {
+        self.0 > 0
+    }
+}
+
+/// A request to damage an entity, sent by the contact damage system or by game code (e.g. on a ProjectileHit)
+#[derive(Copy, Clone, Debug)]
+pub struct DamageEvent {
+    pub source: Option<EntityId>,
+    pub target: EntityId,
+    pub amount: f32,
+}
+
+/// Sent when damage takes an entity's health to zero
+#[derive(Copy, Clone, Debug)]
+pub struct DeathEvent {
+    pub entity: EntityId,
+    pub killer: Option<EntityId>,
+}
+
+/// The damage events `apply_damage` has already processed
+#[derive(Default)]
+pub struct DamageReader(pub EventReader<DamageEvent>);
+
+/// Sends a DamageEvent for every Damage entity touching an entity with Health
+pub fn contact_damage(
+    damages: View<Damage>,
+    healths: View<Health>,
+    bodies: View<PhysicsBody>,
+    world: UniqueView<PhysicsWorld>,
+    mut events: UniqueViewMut<Events<DamageEvent>>,
+) {
+    for (id, (_, _)) in (&healths, &bodies).iter().with_id() {
+        let body = world.collider(id);
+
+        // A source touching several colliders only deals its damage once
+        let mut sources = vec![];
+        for collision in body.colliders.iter().chain(body.sensors.iter()).flat_map(|c| c.overlapping.iter()) {
+            if sources.contains(&collision.entity2) {
+                continue;
+            }
+
+            if let Ok(damage) = damages.get(collision.entity2) {
+                if damage.layer_mask & collision.collision_layer1 > 0 {
+                    sources.push(collision.entity2);
+                    events.send(DamageEvent {
+                        source: Some(collision.entity2),
+                        target: id,
+                        amount: damage.amount,
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Applies new DamageEvents to Health, giving invulnerability ticks and sending DeathEvents
+pub fn apply_damage(
+    mut entities: EntitiesViewMut,
+    mut healths: ViewMut<Health>,
+    mut invulnerables: ViewMut<Invulnerable>,
+    damage_events: UniqueView<Events<DamageEvent>>,
+    mut reader: UniqueViewMut<DamageReader>,
+    mut deaths: UniqueViewMut<Events<DeathEvent>>,
+) {
+    for event in reader.0.read(&damage_events) {
+        if let Ok(invulnerable) = (&invulnerables).get(event.target) {
+            if invulnerable.is_active() {
+                continue;
+            }
+        }
+
+        let health = match (&mut healths).get(event.target) {
+            Ok(health) => health,
+            Err(_) => continue,
+        };
+        if health.is_dead() {
+            continue;
+        }
+
+        health.current -= event.amount;
+
+        if health.invulnerability_ticks > 0 {
+            let ticks = health.invulnerability_ticks;
+            entities.add_component(&mut invulnerables, Invulnerable(ticks), event.target);
+        }
+
+        if health.is_dead() {
+            deaths.send(DeathEvent {
+                entity: event.target,
+                killer: event.source,
+            });
+        }
+    }
+}
+
+pub fn tick_invulnerability(mut invulnerables: ViewMut<Invulnerable>) {
+    for invulnerable in (&mut invulnerables).iter() {
+        invulnerable.0 = invulnerable.0.saturating_sub(1);
+    }
+}
+
+/// Dummy trait to allow adding a method to World
+pub trait CombatCreator {
+    fn add_combat(&mut self);
+}
+
+impl CombatCreator for World {
+    fn add_combat(&mut self) {
+        self.add_unique(Events::<DamageEvent>::new());
+        self.add_unique(Events::<DeathEvent>::new());
+        self.add_unique(DamageReader::default());
+    }
+}
+
+/// Dummy trait to allow adding a method to WorkloadBuilder
+pub trait CombatWorkloadSystems<'a> {
+    fn with_combat_systems(self) -> WorkloadBuilder<'a>;
+}
+
+impl<'a> CombatWorkloadSystems<'a> for WorkloadBuilder<'a> {
+    /// Should run after the physics systems so that contacts from this tick are seen
+    fn with_combat_systems(self) -> WorkloadBuilder<'a> {
+        self
+            .with_system(system!(tick_invulnerability))
+            .with_system(system!(contact_damage))
+            .with_system(system!(apply_damage))
+            .with_event::<DamageEvent>()
+            .with_event::<DeathEvent>()
+    }
+}
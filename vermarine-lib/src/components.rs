@@ -1,4 +1,9 @@
-#[derive(Clone, Copy, Debug, PartialEq)]
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Transform {
     pub x: f64,
     pub y: f64,
@@ -0,0 +1,143 @@
+use shipyard::*;
+use tetra::{
+    input,
+    math::Vec2,
+    window,
+    Context,
+};
+
+/// A change to the window or input state asked for by a system, applied by `CtxProxy::apply`
+#[derive(Clone, Debug, PartialEq)]
+pub enum CtxRequest {
+    SetFullscreen(bool),
+    ToggleFullscreen,
+    SetMouseVisible(bool),
+    SetClipboard(String),
+    SetTitle(String),
+}
+
+/// The parts of tetra's Context that systems are allowed to see and change, as the Context itself never enters the World.
+///
+/// Call `update` before running the workloads each frame so systems see the current state, and `apply` after so the
+/// requests made by systems take effect. Both can be run with `world.run_with_data`.
+#[derive(Clone, Debug, Default)]
+pub struct CtxProxy {
+    window_width: i32,
+    window_height: i32,
+    fullscreen: bool,
+    mouse_visible: bool,
+
+    clipboard: Option<String>,
+    clipboard_requested: bool,
+
+    requests: Vec<CtxRequest>,
+}
+
+impl CtxProxy {
+    pub fn new(ctx: &Context) -> Self {
+        let mut proxy = CtxProxy::default();
+        proxy.read(ctx);
+        proxy
+    }
+
+    pub fn window_width(&self) -> i32 {
+        self.window_width
+    }
+
+    pub fn window_height(&self) -> i32 {
+        self.window_height
+    }
+
+    pub fn window_size(&self) -> Vec2<f32> {
+        Vec2::new(self.window_width as f32, self.window_height as f32)
+    }
+
+    pub fn is_fullscreen(&self) -> bool {
+        self.fullscreen
+    }
+
+    pub fn is_mouse_visible(&self) -> bool {
+        self.mouse_visible
+    }
+
+    /// The clipboard text read by the last `update` after `request_clipboard` was called
+    pub fn clipboard(&self) -> Option<&str> {
+        self.clipboard.as_deref()
+    }
+
+    /// Reads the clipboard on the next `update`, it isn't read every frame as it can be slow
+    pub fn request_clipboard(&mut self) {
+        self.clipboard_requested = true;
+    }
+
+    pub fn set_fullscreen(&mut self, fullscreen: bool) {
+        self.requests.push(CtxRequest::SetFullscreen(fullscreen));
+    }
+
+    pub fn toggle_fullscreen(&mut self) {
+        self.requests.push(CtxRequest::ToggleFullscreen);
+    }
+
+    pub fn set_mouse_visible(&mut self, visible: bool) {
+        self.requests.push(CtxRequest::SetMouseVisible(visible));
+    }
+
+    pub fn set_clipboard(&mut self, text: &str) {
+        self.requests.push(CtxRequest::SetClipboard(text.to_string()));
+    }
+
+    pub fn set_title(&mut self, title: &str) {
+        self.requests.push(CtxRequest::SetTitle(title.to_string()));
+    }
+
+    /// The requests that haven't been applied yet, oldest first
+    pub fn requests(&self) -> &[CtxRequest] {
+        &self.requests
+    }
+
+    fn read(&mut self, ctx: &Context) {
+        let (width, height) = window::get_size(ctx);
+        self.window_width = width;
+        self.window_height = height;
+        self.fullscreen = window::is_fullscreen(ctx);
+        self.mouse_visible = window::is_mouse_visible(ctx);
+    }
+
+    /// Copies the current window state into the CtxProxy unique, run before the workloads
+    pub fn update(ctx: &mut Context, mut proxy: UniqueViewMut<CtxProxy>) {
+        proxy.read(ctx);
+
+        if proxy.clipboard_requested {
+            proxy.clipboard_requested = false;
+            proxy.clipboard = input::get_clipboard_text(ctx).ok();
+        }
+    }
+
+    /// Applies the requests made by systems in the order they were made, run after the workloads.
+    /// Stops at the first request that fails, the remaining requests are dropped.
+    pub fn apply(ctx: &mut Context, mut proxy: UniqueViewMut<CtxProxy>) -> tetra::Result {
+        for request in proxy.requests.drain(..) {
+            match request {
+                CtxRequest::SetFullscreen(fullscreen) => window::set_fullscreen(ctx, fullscreen)?,
+                CtxRequest::ToggleFullscreen => window::toggle_fullscreen(ctx)?,
+                CtxRequest::SetMouseVisible(visible) => window::set_mouse_visible(ctx, visible)?,
+                CtxRequest::SetClipboard(text) => input::set_clipboard_text(ctx, &text)?,
+                CtxRequest::SetTitle(title) => window::set_title(ctx, title),
+            }
+        }
+
+        proxy.read(ctx);
+        Ok(())
+    }
+}
+
+/// Dummy trait to allow adding a method to World
+pub trait CtxProxyCreator {
+    fn add_ctx_proxy(&mut self, ctx: &Context);
+}
+
+impl CtxProxyCreator for World {
+    fn add_ctx_proxy(&mut self, ctx: &Context) {
+        self.add_unique(CtxProxy::new(ctx));
+    }
+}
@@ -0,0 +1,160 @@
+use shipyard::*;
+use tetra::{
+    input::{
+        self,
+        Key,
+    },
+    Context,
+};
+
+/// The engine debug features that can be toggled at runtime
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum DebugFlag {
+    PhysicsWireframes,
+    SpatialHashHeatmap,
+    DrawOrderLabels,
+    AiGizmos,
+    FpsOverlay,
+    LogConsole,
+}
+
+impl DebugFlag {
+    pub const ALL: [DebugFlag; 6] = [
+        DebugFlag::PhysicsWireframes,
+        DebugFlag::SpatialHashHeatmap,
+        DebugFlag::DrawOrderLabels,
+        DebugFlag::AiGizmos,
+        DebugFlag::FpsOverlay,
+        DebugFlag::LogConsole,
+    ];
+
+    /// The name used to refer to the flag in console commands
+    pub fn name(&self) -> &'static str {
+        match self {
+            DebugFlag::PhysicsWireframes => "physics_wireframes",
+            DebugFlag::SpatialHashHeatmap => "spatial_hash_heatmap",
+            DebugFlag::DrawOrderLabels => "draw_order_labels",
+            DebugFlag::AiGizmos => "ai_gizmos",
+            DebugFlag::FpsOverlay => "fps_overlay",
+            DebugFlag::LogConsole => "log_console",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<DebugFlag> {
+        Self::ALL.iter().copied().find(|flag| flag.name() == name)
+    }
+}
+
+/// A set of keys that toggles a flag when the last key is pressed while the rest are held
+#[derive(Clone, Debug)]
+pub struct DebugChord {
+    pub keys: Vec<Key>,
+    pub flag: DebugFlag,
+}
+
+/// Single switchboard for all engine debug features. Added by `add_rendering_workload`,
+/// debug systems check the relevant flag before doing any work.
+#[derive(Clone, Debug, Default)]
+pub struct DebugFlags {
+    /// Read by `Gizmos::draw_physics_wireframes`
+    pub physics_wireframes: bool,
+    /// Read by `Gizmos::draw_broadphase_heatmap`
+    pub spatial_hash_heatmap: bool,
+    /// Read by `Gizmos::draw_order_labels`
+    pub draw_order_labels: bool,
+    /// Read by `HexFlowField::draw_gizmos`
+    pub ai_gizmos: bool,
+    /// Read by `PerfStats::draw_fps_overlay`
+    pub fps_overlay: bool,
+    /// Read by `logging::draw_log_console`
+    pub log_console: bool,
+
+    pub chords: Vec<DebugChord>,
+}
+
+impl DebugFlags {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, flag: DebugFlag) -> bool {
+        match flag {
+            DebugFlag::PhysicsWireframes => self.physics_wireframes,
+            DebugFlag::SpatialHashHeatmap => self.spatial_hash_heatmap,
+            DebugFlag::DrawOrderLabels => self.draw_order_labels,
+            DebugFlag::AiGizmos => self.ai_gizmos,
+            DebugFlag::FpsOverlay => self.fps_overlay,
+            DebugFlag::LogConsole => self.log_console,
+        }
+    }
+
+    pub fn set(&mut self, flag: DebugFlag, value: bool) {
+        let field = match flag {
+            DebugFlag::PhysicsWireframes => &mut self.physics_wireframes,
+            DebugFlag::SpatialHashHeatmap => &mut self.spatial_hash_heatmap,
+            DebugFlag::DrawOrderLabels => &mut self.draw_order_labels,
+            DebugFlag::AiGizmos => &mut self.ai_gizmos,
+            DebugFlag::FpsOverlay => &mut self.fps_overlay,
+            DebugFlag::LogConsole => &mut self.log_console,
+        };
+        *field = value;
+    }
+
+    /// Flips the flag and returns its new value
+    pub fn toggle(&mut self, flag: DebugFlag) -> bool {
+        let value = !self.get(flag);
+        self.set(flag, value);
+        value
+    }
+
+    /// Returns true if any debug feature is enabled
+    pub fn any(&self) -> bool {
+        DebugFlag::ALL.iter().any(|flag| self.get(*flag))
+    }
+
+    /// Binds a key chord, e.g. `&[Key::LeftCtrl, Key::F1]`, to toggle the flag
+    pub fn bind_chord(&mut self, keys: &[Key], flag: DebugFlag) {
+        self.chords.push(DebugChord {
+            keys: keys.to_vec(),
+            flag,
+        });
+    }
+
+    /// Runs a console command of the form `debug <flag> [on|off|toggle]`.
+    /// Returns false if the command was not recognized.
+    pub fn run_command(&mut self, command: &str) -> bool {
+        let mut words = command.split_whitespace();
+        if words.next() != Some("debug") {
+            return false;
+        }
+
+        let flag = match words.next().and_then(DebugFlag::from_name) {
+            Some(flag) => flag,
+            None => return false,
+        };
+
+        match words.next() {
+            Some("on") => self.set(flag, true),
+            Some("off") => self.set(flag, false),
+            Some("toggle") | None => { self.toggle(flag); },
+            Some(_) => return false,
+        }
+        true
+    }
+
+    /// Toggles the flags of any chords that were completed this frame
+    pub fn handle_chords(ctx: &mut Context, mut flags: UniqueViewMut<DebugFlags>) {
+        let mut toggled = vec![];
+        for chord in flags.chords.iter() {
+            if let Some((last, held)) = chord.keys.split_last() {
+                if input::is_key_pressed(ctx, *last) && held.iter().all(|key| input::is_key_down(ctx, *key)) {
+                    toggled.push(chord.flag);
+                }
+            }
+        }
+
+        for flag in toggled.into_iter() {
+            flags.toggle(flag);
+        }
+    }
+}
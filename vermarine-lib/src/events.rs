@@ -0,0 +1,213 @@
+use std::marker::PhantomData;
+use shipyard::*;
+
+/// Double buffered queue of events of a single type. Events live for two updates so that every
+/// system gets a chance to see them regardless of the order the systems run in.
+pub struct Events<T> {
+    previous: Vec<T>,
+    current: Vec<T>,
+
+    // Events are numbered in the order they are sent, readers remember the number of the last event they saw
+    previous_start: usize,
+    current_start: usize,
+    count: usize,
+}
+
+impl<T> Default for Events<T> {
+    fn default() -> Self {
+        Events {
+            previous: vec![],
+            current: vec![],
+            previous_start: 0,
+            current_start: 0,
+            count: 0,
+        }
+    }
+}
+
+impl<T> Events<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn send(&mut self, event: T) {
+        self.current.push(event);
+        self.count += 1;
+    }
+
+    /// Drops the events from the previous update and swaps the buffers, should be called once per frame
+    pub fn update(&mut self) {
+        std::mem::swap(&mut self.previous, &mut self.current);
+        self.current.clear();
+
+        self.previous_start = self.current_start;
+        self.current_start = self.count;
+    }
+
+    /// Creates a reader that will only see events sent after this point
+    pub fn reader(&self) -> EventReader<T> {
+        EventReader {
+            last: self.count,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Iterates over every event that is still alive, oldest first
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.previous.iter().chain(self.current.iter())
+    }
+
+    /// Removes and returns every event that is still alive, oldest first
+    pub fn drain(&mut self) -> impl Iterator<Item = T> + '_ {
+        self.previous_start = self.count;
+        self.current_start = self.count;
+        self.previous.drain(..).chain(self.current.drain(..))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.previous.is_empty() && self.current.is_empty()
+    }
+
+    /// System that updates the Events<T> unique, added by `with_event::<T>()`
+    pub fn update_system(mut events: UniqueViewMut<Events<T>>) where T: 'static + Send + Sync {
+        events.update();
+    }
+}
+
+/// Remembers which events have already been read so that each event is only seen once.
+/// Usually stored in a unique or component belonging to the system that reads the events.
+pub struct EventReader<T> {
+    last: usize,
+    phantom: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for EventReader<T> {
+    fn clone(&self) -> Self {
+        EventReader {
+            last: self.last,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T> Default for EventReader<T> {
+    /// A reader that will see every event that is still alive the first time it reads
+    fn default() -> Self {
+        EventReader {
+            last: 0,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T> EventReader<T> {
+    /// Iterates over the events that have been sent since this reader last read
+    pub fn read<'a>(&mut self, events: &'a Events<T>) -> impl Iterator<Item = &'a T> {
+        let start = usize::max(self.last, events.previous_start);
+        self.last = events.count;
+
+        let previous_skip = start.saturating_sub(events.previous_start);
+        let current_skip = start.saturating_sub(events.current_start);
+
+        events.previous.iter().skip(previous_skip)
+            .chain(events.current.iter().skip(current_skip))
+    }
+}
+
+/// Dummy trait to allow adding a method to World
+pub trait EventsCreator {
+    fn add_events<T: 'static + Send + Sync>(&mut self);
+}
+
+impl EventsCreator for World {
+    fn add_events<T: 'static + Send + Sync>(&mut self) {
+        self.add_unique(Events::<T>::new());
+    }
+}
+
+/// Dummy trait to allow adding a method to WorkloadBuilder
+pub trait EventWorkloadSystems<'a> {
+    fn with_event<T: 'static + Send + Sync>(self) -> WorkloadBuilder<'a>;
+}
+
+impl<'a> EventWorkloadSystems<'a> for WorkloadBuilder<'a> {
+    /// Adds the system that swaps the Events<T> buffers each time the workload runs.
+    /// The unique itself has to be added with `World::add_events::<T>()`.
+    fn with_event<T: 'static + Send + Sync>(self) -> WorkloadBuilder<'a> {
+        self
+            .with_system(system!(Events::<T>::update_system))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(reader: &mut EventReader<u32>, events: &Events<u32>) -> Vec<u32> {
+        reader.read(events).copied().collect()
+    }
+
+    #[test]
+    fn events_live_for_two_updates() {
+        let mut events = Events::new();
+        events.send(1);
+        events.update();
+        events.send(2);
+
+        assert_eq!(events.iter().copied().collect::<Vec<_>>(), vec![1, 2]);
+        events.update();
+        assert_eq!(events.iter().copied().collect::<Vec<_>>(), vec![2]);
+        events.update();
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn readers_see_each_event_once() {
+        let mut events = Events::new();
+        let mut reader = EventReader::default();
+
+        events.send(1);
+        events.send(2);
+        assert_eq!(read(&mut reader, &events), vec![1, 2]);
+        assert!(read(&mut reader, &events).is_empty());
+
+        events.send(3);
+        events.update();
+        events.send(4);
+        assert_eq!(read(&mut reader, &events), vec![3, 4]);
+
+        // Readers made from the events skip everything already sent
+        let mut late = events.reader();
+        events.send(5);
+        assert_eq!(read(&mut late, &events), vec![5]);
+        assert_eq!(read(&mut reader, &events), vec![5]);
+    }
+
+    #[test]
+    fn slow_readers_miss_dropped_events() {
+        let mut events = Events::new();
+        let mut reader = EventReader::default();
+
+        events.send(1);
+        events.update();
+        events.send(2);
+        events.update();
+        events.send(3);
+        assert_eq!(read(&mut reader, &events), vec![2, 3]);
+    }
+
+    #[test]
+    fn drained_events_are_not_read() {
+        let mut events = Events::new();
+        let mut reader = EventReader::default();
+
+        events.send(1);
+        events.update();
+        events.send(2);
+        assert_eq!(events.drain().collect::<Vec<_>>(), vec![1, 2]);
+        assert!(events.is_empty());
+
+        events.send(3);
+        assert_eq!(read(&mut reader, &events), vec![3]);
+    }
+}
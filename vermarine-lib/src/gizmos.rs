@@ -0,0 +1,267 @@
+use std::sync::Mutex;
+use shipyard::*;
+use tetra::{
+    graphics::{
+        self,
+        text::{
+            Font,
+            Text,
+        },
+        Color,
+        DrawParams,
+    },
+    math::Vec2,
+    time,
+    Context,
+};
+use crate::{
+    components::Transform,
+    debug::{
+        DebugFlag,
+        DebugFlags,
+    },
+    physics::world::PhysicsWorld,
+    rendering::{
+        Drawables,
+        Sprite,
+        draw_buffer::{
+            DrawBuffer,
+            DrawCommand,
+        },
+    },
+};
+
+/// A shape drawn by Gizmos, in world space
+#[derive(Clone, Debug, PartialEq)]
+pub enum GizmoShape {
+    Line { a: Vec2<f32>, b: Vec2<f32> },
+    Circle { center: Vec2<f32>, radius: f32 },
+    /// An outline with its top left at min
+    Aabb { min: Vec2<f32>, size: Vec2<f32> },
+    Text { position: Vec2<f32>, text: String },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gizmo {
+    pub shape: GizmoShape,
+    pub color: Color,
+    /// Seconds left before the gizmo is cleared, gizmos at 0.0 or below are drawn for a single frame
+    pub remaining: f32,
+}
+
+/// Immediate mode debug drawing, any system can add shapes through a shared `UniqueView<Gizmos>` and they are
+/// drawn over everything by `draw_gizmos` after the DrawBuffer has been flushed. Every call takes how many seconds
+/// to keep the shape around, 0.0 draws it for the next frame only. Expired shapes are cleared automatically.
+pub struct Gizmos {
+    gizmos: Mutex<Vec<Gizmo>>,
+
+    /// Thickness of lines and outlines. Defaults to 1.0.
+    pub thickness: f32,
+    /// Set to false to skip drawing and recording gizmos entirely. Defaults to true.
+    pub enabled: bool,
+}
+
+impl Default for Gizmos {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Gizmos {
+    const CIRCLE_SEGMENTS: usize = 24;
+
+    pub fn new() -> Self {
+        Gizmos {
+            gizmos: Mutex::new(vec![]),
+            thickness: 1.0,
+            enabled: true,
+        }
+    }
+
+    pub fn add(&self, shape: GizmoShape, color: Color, duration: f32) {
+        if !self.enabled {
+            return;
+        }
+
+        self.gizmos.lock().unwrap().push(Gizmo {
+            shape,
+            color,
+            remaining: duration,
+        });
+    }
+
+    pub fn line(&self, a: Vec2<f32>, b: Vec2<f32>, color: Color, duration: f32) {
+        self.add(GizmoShape::Line { a, b }, color, duration);
+    }
+
+    pub fn circle(&self, center: Vec2<f32>, radius: f32, color: Color, duration: f32) {
+        self.add(GizmoShape::Circle { center, radius }, color, duration);
+    }
+
+    pub fn aabb(&self, min: Vec2<f32>, size: Vec2<f32>, color: Color, duration: f32) {
+        self.add(GizmoShape::Aabb { min, size }, color, duration);
+    }
+
+    /// Only drawn if `draw_gizmos` is given a font
+    pub fn text<S: Into<String>>(&self, position: Vec2<f32>, text: S, color: Color, duration: f32) {
+        self.add(GizmoShape::Text { position, text: text.into() }, color, duration);
+    }
+
+    pub fn len(&self) -> usize {
+        self.gizmos.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        self.gizmos.lock().unwrap().clear();
+    }
+
+    /// The line commands needed to draw every shape other than text
+    pub fn commands(&self) -> Vec<DrawCommand> {
+        let mut commands = vec![];
+        let mut line = |a: Vec2<f32>, b: Vec2<f32>, color: Color| {
+            commands.push(DrawCommand::line(a, b, self.thickness).color(color));
+        };
+
+        for gizmo in self.gizmos.lock().unwrap().iter() {
+            match &gizmo.shape {
+                GizmoShape::Line { a, b } => line(*a, *b, gizmo.color),
+                GizmoShape::Circle { center, radius } => {
+                    let point = |i: usize| {
+                        let angle = i as f32 / Self::CIRCLE_SEGMENTS as f32 * std::f32::consts::PI * 2.0;
+                        *center + Vec2::new(angle.cos(), angle.sin()) * *radius
+                    };
+                    for i in 0..Self::CIRCLE_SEGMENTS {
+                        line(point(i), point(i + 1), gizmo.color);
+                    }
+                },
+                GizmoShape::Aabb { min, size } => {
+                    let corners = [*min, *min + Vec2::new(size.x, 0.0), *min + *size, *min + Vec2::new(0.0, size.y)];
+                    for i in 0..corners.len() {
+                        line(corners[i], corners[(i + 1) % corners.len()], gizmo.color);
+                    }
+                },
+                GizmoShape::Text { .. } => {},
+            }
+        }
+
+        commands
+    }
+
+    /// Counts down every gizmo's duration and clears the ones that have run out
+    pub fn expire(&self, delta: f32) {
+        let mut gizmos = self.gizmos.lock().unwrap();
+        for gizmo in gizmos.iter_mut() {
+            gizmo.remaining -= delta;
+        }
+        gizmos.retain(|gizmo| gizmo.remaining > 0.0);
+    }
+
+    /// Draws every gizmo with the DrawBuffer's transform_mat then clears the expired ones. Run after `DrawBuffer::flush`,
+    /// the font is the game's as the engine doesn't ship one. Text gizmos are skipped without one.
+    pub fn draw_gizmos(
+        ctx: &mut Context,
+        font: Option<&Font>,
+        gizmos: UniqueView<Gizmos>,
+        draw_buffer: UniqueView<DrawBuffer>,
+        drawables: NonSendSync<UniqueViewMut<Drawables>>,
+    ) {
+        if gizmos.enabled && !gizmos.is_empty() {
+            let mut buffer = DrawBuffer::new();
+            buffer.transform_mat = draw_buffer.transform_mat;
+            for command in gizmos.commands().into_iter() {
+                buffer.draw(command);
+            }
+            buffer.flush_to(ctx, &drawables);
+
+            if let Some(font) = font {
+                graphics::set_transform_matrix(ctx, draw_buffer.transform_mat);
+                for gizmo in gizmos.gizmos.lock().unwrap().iter() {
+                    if let GizmoShape::Text { position, text } = &gizmo.shape {
+                        Text::new(text.as_str(), font.clone()).draw(ctx, DrawParams::new()
+                            .position(*position)
+                            .color(gizmo.color)
+                        );
+                    }
+                }
+            }
+        }
+
+        gizmos.expire(time::get_delta_time(ctx).as_secs_f32());
+    }
+
+    /// Outlines every body's AABB while `DebugFlag::PhysicsWireframes` is on, disabled bodies in grey
+    pub fn draw_physics_wireframes(world: UniqueView<PhysicsWorld>, flags: UniqueView<DebugFlags>, gizmos: UniqueView<Gizmos>) {
+        if !flags.get(DebugFlag::PhysicsWireframes) {
+            return;
+        }
+
+        for (_, transform, body) in world.iter() {
+            let aabb = body.aabb();
+            let color = if body.is_enabled() { Color::rgb(0.2, 1.0, 0.2) } else { Color::rgb(0.5, 0.5, 0.5) };
+            gizmos.aabb(
+                Vec2::new((transform.x + aabb.dx) as f32, (transform.y + aabb.dy) as f32),
+                Vec2::new(aabb.width as f32, aabb.height as f32),
+                color,
+                0.0,
+            );
+        }
+    }
+
+    /// Outlines every occupied broadphase cell while `DebugFlag::SpatialHashHeatmap` is on, from blue for cells
+    /// holding a single body to red for the fullest cell
+    pub fn draw_broadphase_heatmap(world: UniqueView<PhysicsWorld>, flags: UniqueView<DebugFlags>, gizmos: UniqueView<Gizmos>) {
+        if !flags.get(DebugFlag::SpatialHashHeatmap) {
+            return;
+        }
+
+        let cells = world.broadphase_cells();
+        let fullest = cells.iter().map(|(_, count)| *count).max().unwrap_or(1);
+        for (cell, count) in cells.into_iter() {
+            let heat = if fullest > 1 { (count - 1) as f32 / (fullest - 1) as f32 } else { 0.0 };
+            gizmos.aabb(
+                Vec2::new(cell.dx as f32, cell.dy as f32),
+                Vec2::new(cell.width as f32, cell.height as f32),
+                Color::rgb(heat, 0.2, 1.0 - heat),
+                0.0,
+            );
+        }
+    }
+
+    /// Labels every sprite with its draw layer and z while `DebugFlag::DrawOrderLabels` is on, needs a font
+    /// passed to `draw_gizmos` to show up
+    pub fn draw_order_labels(
+        flags: UniqueView<DebugFlags>,
+        gizmos: UniqueView<Gizmos>,
+        sprites: View<Sprite>,
+        transforms: View<Transform>,
+    ) {
+        if !flags.get(DebugFlag::DrawOrderLabels) {
+            return;
+        }
+
+        for (sprite, transform) in (&sprites, &transforms).iter() {
+            let command = &sprite.0;
+            gizmos.text(
+                Vec2::new(transform.x as f32 + command.position.x, transform.y as f32 + command.position.y),
+                format!("{} z{}", command.draw_layer, command.position.z),
+                Color::WHITE,
+                0.0,
+            );
+        }
+    }
+}
+
+/// Dummy trait to allow adding a method to World
+pub trait GizmosCreator {
+    fn add_gizmos(&mut self);
+}
+
+impl GizmosCreator for World {
+    fn add_gizmos(&mut self) {
+        self.add_unique(Gizmos::new());
+    }
+}
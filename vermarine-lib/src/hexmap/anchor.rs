@@ -0,0 +1,67 @@
+use shipyard::*;
+use tetra::math::Vec2;
+use crate::components::Transform;
+use super::*;
+use super::maps::{
+    HexMaps,
+    MapHandle,
+};
+
+/// Pins an entity's Transform to a hex of one of the maps in HexMaps, `sync_hex_anchors` moves the Transform
+/// whenever the hex, the height or the map's position change so units stay snapped to their tile while the map scrolls.
+///
+/// The Transform is set directly, so entities with a PhysicsBody should be moved through the PhysicsWorld instead.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct HexAnchor {
+    pub map: MapHandle,
+    pub hex: Axial,
+    /// Raises the entity by `hex_depth_step` per step, like `elevated_position` with `draw_iso`. Defaults to 0.
+    pub height: u8,
+    /// Added to the top left of the hex, e.g. half the hex size to stand on its centre. Defaults to `(0.0, 0.0)`.
+    pub offset: Vec2<f32>,
+}
+
+impl HexAnchor {
+    pub fn new(map: MapHandle, hex: Axial) -> Self {
+        HexAnchor {
+            map,
+            hex,
+            height: 0,
+            offset: Vec2::zero(),
+        }
+    }
+
+    /// Sets the height.
+    pub fn with_height(mut self, height: u8) -> Self {
+        self.height = height;
+        self
+    }
+
+    /// Sets the offset from the top left of the hex.
+    pub fn with_offset(mut self, offset: Vec2<f32>) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Where the anchor puts the Transform on the map
+    pub fn position<T, const W: usize, const H: usize>(&self, map: &HexMap<T, W, H>) -> Transform {
+        let elevated = map.elevated_position(self.hex, self.height);
+        Transform::new(
+            (elevated.x + self.offset.x) as f64,
+            (elevated.y - elevated.z + self.offset.y) as f64,
+        )
+    }
+}
+
+/// Moves every anchored entity's Transform to its hex, anchors to maps that no longer exist are left where they are
+pub fn sync_hex_anchors<T: 'static + Send + Sync>(
+    maps: UniqueView<HexMaps<T>>,
+    anchors: View<HexAnchor>,
+    mut transforms: ViewMut<Transform>,
+) {
+    for (anchor, transform) in (&anchors, &mut transforms).iter() {
+        if let Some(map) = maps.get(anchor.map) {
+            *transform = anchor.position(map);
+        }
+    }
+}
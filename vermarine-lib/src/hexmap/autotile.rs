@@ -0,0 +1,66 @@
+use crate::rendering::Drawables;
+use super::*;
+
+/// Returns a 6 bit mask of which neighbors of the hex satisfy the predicate. Bit `i` is set for
+/// `Hex::neighbors()[i]`, so bit 0 is the neighbor at `r - 1` and the bits go clockwise from there.
+/// Missing tiles never satisfy the predicate.
+pub fn neighbor_mask<T, F: Fn(&T) -> bool, const W: usize, const H: usize>(map: &HexMap<T, W, H>, hex: Axial, predicate: F) -> u8 {
+    let mut mask = 0;
+    for (i, neighbor) in hex.to_hex().neighbors().iter().enumerate() {
+        if let Some(tile) = map.get_tile(*neighbor) {
+            if predicate(tile) {
+                mask |= 1 << i;
+            }
+        }
+    }
+    mask
+}
+
+/// Maps every neighbor mask to the drawable to use for it
+#[derive(Clone, Debug)]
+pub struct AutotileTable {
+    pub drawables: [Option<u64>; 64],
+    /// Used for masks that have no drawable of their own
+    pub fallback: Option<u64>,
+}
+
+impl AutotileTable {
+    pub fn new() -> Self {
+        AutotileTable {
+            drawables: [None; 64],
+            fallback: None,
+        }
+    }
+
+    /// Builds the table from drawables named `<prefix>_<mask>` with the mask written in decimal,
+    /// e.g. `wall_0` to `wall_63`. A drawable named just `<prefix>` is used as the fallback.
+    pub fn from_atlas(drawables: &Drawables, prefix: &str) -> Self {
+        let mut table = Self::new();
+        table.fallback = drawables.get_id(prefix);
+
+        for mask in 0..64u8 {
+            table.drawables[mask as usize] = drawables.get_id(&format!("{}_{}", prefix, mask));
+        }
+        table
+    }
+
+    /// Sets the drawable for a mask
+    pub fn set(&mut self, mask: u8, drawable: u64) {
+        self.drawables[(mask & 0b11_1111) as usize] = Some(drawable);
+    }
+
+    pub fn lookup(&self, mask: u8) -> Option<u64> {
+        self.drawables[(mask & 0b11_1111) as usize].or(self.fallback)
+    }
+
+    /// Picks the drawable for the hex based on which of its neighbors satisfy the predicate
+    pub fn autotile<T, F: Fn(&T) -> bool, const W: usize, const H: usize>(&self, map: &HexMap<T, W, H>, hex: Axial, predicate: F) -> Option<u64> {
+        self.lookup(neighbor_mask(map, hex, predicate))
+    }
+}
+
+impl Default for AutotileTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
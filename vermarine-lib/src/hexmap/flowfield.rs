@@ -0,0 +1,495 @@
+use std::{
+    cmp::Reverse,
+    collections::{
+        BinaryHeap,
+        HashMap,
+        HashSet,
+    },
+};
+use tetra::{
+    graphics::Color,
+    math::Vec2,
+};
+use crate::{
+    debug::{
+        DebugFlag,
+        DebugFlags,
+    },
+    gizmos::Gizmos,
+};
+use super::*;
+
+/// Extra cost for moving between tiles of different heights, as given by `HexMap::get_height`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ElevationCosts {
+    /// Each height step climbed adds this many times the tile's cost. Defaults to 0.0.
+    pub climb_multiplier: f32,
+    /// Each height step descended adds this many times the tile's cost. Defaults to 0.0.
+    pub descend_multiplier: f32,
+    /// Steps that climb more than this are impassable. Defaults to `None`.
+    pub max_climb: Option<u8>,
+    /// Steps that drop more than this are impassable. Defaults to `None`.
+    pub max_descend: Option<u8>,
+}
+
+impl Default for ElevationCosts {
+    fn default() -> Self {
+        ElevationCosts {
+            climb_multiplier: 0.0,
+            descend_multiplier: 0.0,
+            max_climb: None,
+            max_descend: None,
+        }
+    }
+}
+
+impl ElevationCosts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the cost multiplier per height step climbed.
+    pub fn climb_multiplier(mut self, climb_multiplier: f32) -> Self {
+        self.climb_multiplier = climb_multiplier;
+        self
+    }
+
+    /// Sets the cost multiplier per height step descended.
+    pub fn descend_multiplier(mut self, descend_multiplier: f32) -> Self {
+        self.descend_multiplier = descend_multiplier;
+        self
+    }
+
+    /// Sets the most height steps that can be climbed at once.
+    pub fn max_climb(mut self, max_climb: u8) -> Self {
+        self.max_climb = Some(max_climb);
+        self
+    }
+
+    /// Sets the most height steps that can be descended at once.
+    pub fn max_descend(mut self, max_descend: u8) -> Self {
+        self.max_descend = Some(max_descend);
+        self
+    }
+
+    /// The cost of stepping from a tile at height `from` onto a tile at height `to` that costs `cost`,
+    /// `None` if the height difference is over the limit
+    pub fn step_cost(&self, cost: u32, from: u8, to: u8) -> Option<u32> {
+        let (steps, multiplier, limit) = if to >= from {
+            (to - from, self.climb_multiplier, self.max_climb)
+        } else {
+            (from - to, self.descend_multiplier, self.max_descend)
+        };
+
+        if limit.map_or(false, |limit| steps > limit) {
+            return None;
+        }
+
+        let extra = (cost as f32 * multiplier * steps as f32).ceil().max(0.0) as u32;
+        Some(cost.saturating_add(extra))
+    }
+}
+
+/// For every tile that can reach the goal, the direction of the next step on a cheapest path to it.
+/// Built once and shared by any number of units heading to the same goal.
+///
+/// The field doesn't watch the map, call `invalidate` for tiles that change and then `update`,
+/// which only recomputes the tiles whose paths went through a changed tile.
+pub struct HexFlowField<T> {
+    goal: Axial,
+    /// The cost of stepping onto a tile, `None` for impassable tiles
+    cost: fn(&T) -> Option<u32>,
+    elevation: Option<ElevationCosts>,
+
+    distances: HashMap<Axial, u32>,
+    directions: HashMap<Axial, HexDirection>,
+    dirty: Vec<Axial>,
+}
+
+impl<T, const W: usize, const H: usize> HexMap<T, W, H> {
+    /// Builds a flow field towards the goal over all the tiles in the map
+    pub fn flow_field(&self, goal: Axial, cost: fn(&T) -> Option<u32>) -> HexFlowField<T> {
+        self.build_flow_field(goal, cost, None)
+    }
+
+    /// Builds a flow field towards the goal where moving between tiles of different heights costs extra
+    /// or is impossible, so that units path around cliffs
+    pub fn flow_field_with_elevation(&self, goal: Axial, cost: fn(&T) -> Option<u32>, elevation: ElevationCosts) -> HexFlowField<T> {
+        self.build_flow_field(goal, cost, Some(elevation))
+    }
+
+    fn build_flow_field(&self, goal: Axial, cost: fn(&T) -> Option<u32>, elevation: Option<ElevationCosts>) -> HexFlowField<T> {
+        let mut field = HexFlowField {
+            goal,
+            cost,
+            elevation,
+
+            distances: HashMap::new(),
+            directions: HashMap::new(),
+            dirty: vec![],
+        };
+        field.rebuild(self);
+        field
+    }
+}
+
+impl<T> HexFlowField<T> {
+    pub fn goal(&self) -> Axial {
+        self.goal
+    }
+
+    /// The direction to step in from the hex, `None` at the goal or if the goal can't be reached
+    pub fn direction(&self, hex: Axial) -> Option<HexDirection> {
+        self.directions.get(&hex).copied()
+    }
+
+    /// The hex to step onto next from the hex
+    pub fn next(&self, hex: Axial) -> Option<Axial> {
+        self.direction(hex).map(|direction| hex + direction.offset())
+    }
+
+    /// The total cost of getting from the hex to the goal
+    pub fn distance(&self, hex: Axial) -> Option<u32> {
+        self.distances.get(&hex).copied()
+    }
+
+    /// Returns true if the goal can be reached from the hex
+    pub fn reaches_goal(&self, hex: Axial) -> bool {
+        self.distances.contains_key(&hex)
+    }
+
+    /// Marks a tile as changed, the field is fixed up on the next `update`
+    pub fn invalidate(&mut self, hex: Axial) {
+        self.dirty.push(hex);
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        !self.dirty.is_empty()
+    }
+
+    /// Draws a line from every tile halfway towards its next step and circles the goal while `DebugFlag::AiGizmos` is on
+    pub fn draw_gizmos<const W: usize, const H: usize>(&self, map: &HexMap<T, W, H>, flags: &DebugFlags, gizmos: &Gizmos) {
+        if !flags.get(DebugFlag::AiGizmos) {
+            return;
+        }
+
+        let half_hex = Vec2::new(map.hex_width, map.hex_height) / 2.0;
+        let center = |hex: Axial| map.axial_to_pixel(hex) + half_hex;
+
+        for (hex, direction) in self.directions.iter() {
+            let from = center(*hex);
+            let to = center(*hex + direction.offset());
+            gizmos.line(from, from + (to - from) / 2.0, Color::rgb(1.0, 0.6, 0.0), 0.0);
+        }
+        gizmos.circle(center(self.goal), half_hex.x / 2.0, Color::rgb(1.0, 0.6, 0.0), 0.0);
+    }
+
+    /// Changes the goal and rebuilds the whole field
+    pub fn set_goal<const W: usize, const H: usize>(&mut self, map: &HexMap<T, W, H>, goal: Axial) {
+        self.goal = goal;
+        self.rebuild(map);
+    }
+
+    /// Recomputes the field from scratch
+    pub fn rebuild<const W: usize, const H: usize>(&mut self, map: &HexMap<T, W, H>) {
+        self.distances.clear();
+        self.directions.clear();
+        self.dirty.clear();
+
+        let mut open = BinaryHeap::new();
+        if self.tile_cost(map, self.goal).is_some() {
+            self.distances.insert(self.goal, 0);
+            open.push(Reverse((0, self.goal.q, self.goal.r)));
+        }
+        self.propagate(map, open);
+    }
+
+    /// Recomputes the tiles affected by the invalidated tiles
+    pub fn update<const W: usize, const H: usize>(&mut self, map: &HexMap<T, W, H>) {
+        if self.dirty.is_empty() {
+            return;
+        }
+        let dirty: HashSet<Axial> = self.dirty.drain(..).collect();
+
+        // Every tile whose path to the goal steps onto a changed tile has to be recomputed
+        let mut affected: HashSet<Axial> = dirty.clone();
+        let mut memo = HashMap::new();
+        let hexes: Vec<Axial> = self.distances.keys().copied().collect();
+        for hex in hexes.into_iter() {
+            if self.path_touches(hex, &dirty, &mut memo) {
+                affected.insert(hex);
+            }
+        }
+
+        for hex in affected.iter() {
+            self.distances.remove(hex);
+            self.directions.remove(hex);
+        }
+
+        // Seed the affected tiles from their unaffected neighbors, whose distances are still correct
+        let mut open = BinaryHeap::new();
+        for hex in affected.iter().copied() {
+            if self.tile_cost(map, hex).is_none() {
+                continue;
+            }
+
+            if hex == self.goal {
+                self.distances.insert(hex, 0);
+                self.directions.remove(&hex);
+                open.push(Reverse((0, hex.q, hex.r)));
+                continue;
+            }
+
+            for direction in HexDirection::ALL.iter() {
+                let neighbor = hex + direction.offset();
+                let distance = match self.distances.get(&neighbor) {
+                    Some(distance) if !affected.contains(&neighbor) => *distance,
+                    _ => continue,
+                };
+                let candidate = match self.step_cost(map, hex, neighbor) {
+                    Some(cost) => distance.saturating_add(cost),
+                    None => continue,
+                };
+
+                if self.distances.get(&hex).map_or(true, |current| candidate < *current) {
+                    self.distances.insert(hex, candidate);
+                    self.directions.insert(hex, *direction);
+                    open.push(Reverse((candidate, hex.q, hex.r)));
+                }
+            }
+        }
+
+        self.propagate(map, open);
+    }
+
+    fn tile_cost<const W: usize, const H: usize>(&self, map: &HexMap<T, W, H>, hex: Axial) -> Option<u32> {
+        map.get_tile(hex.to_hex()).and_then(self.cost)
+    }
+
+    /// The cost of a unit on `from` stepping onto `to`
+    fn step_cost<const W: usize, const H: usize>(&self, map: &HexMap<T, W, H>, from: Axial, to: Axial) -> Option<u32> {
+        let from_tile = map.get_tile(from.to_hex())?;
+        let to_tile = map.get_tile(to.to_hex())?;
+        (self.cost)(from_tile)?;
+        let cost = (self.cost)(to_tile)?;
+
+        match &self.elevation {
+            Some(elevation) => elevation.step_cost(cost, (map.get_height)(from_tile), (map.get_height)(to_tile)),
+            None => Some(cost),
+        }
+    }
+
+    /// Dijkstra outwards from the open tiles, tiles already in the field are only replaced by cheaper paths
+    fn propagate<const W: usize, const H: usize>(&mut self, map: &HexMap<T, W, H>, mut open: BinaryHeap<Reverse<(u32, i32, i32)>>) {
+        while let Some(Reverse((distance, q, r))) = open.pop() {
+            let hex = Axial::new(q, r);
+            if self.distances.get(&hex) != Some(&distance) {
+                continue;
+            }
+
+            for direction in HexDirection::ALL.iter() {
+                let neighbor = hex + direction.offset();
+                if neighbor == self.goal {
+                    continue;
+                }
+
+                // Units on the neighbors pay to step onto this tile
+                let candidate = match self.step_cost(map, neighbor, hex) {
+                    Some(cost) => distance.saturating_add(cost),
+                    None => continue,
+                };
+
+                if self.distances.get(&neighbor).map_or(true, |current| candidate < *current) {
+                    self.distances.insert(neighbor, candidate);
+                    self.directions.insert(neighbor, direction.opposite());
+                    open.push(Reverse((candidate, neighbor.q, neighbor.r)));
+                }
+            }
+        }
+    }
+
+    /// Follows the directions from the hex, returning true if they step onto any of the tiles
+    fn path_touches(&self, hex: Axial, tiles: &HashSet<Axial>, memo: &mut HashMap<Axial, bool>) -> bool {
+        let mut path = vec![];
+        let mut current = hex;
+
+        let touches = loop {
+            if let Some(touches) = memo.get(&current) {
+                break *touches;
+            }
+            if tiles.contains(&current) {
+                break true;
+            }
+            path.push(current);
+
+            match self.next(current) {
+                Some(next) if path.len() <= self.distances.len() => current = next,
+                _ => break false,
+            }
+        };
+
+        for hex in path.into_iter() {
+            memo.insert(hex, touches);
+        }
+        touches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WALL: u8 = 9;
+
+    fn cost(tile: &u8) -> Option<u32> {
+        if *tile == WALL {
+            None
+        } else {
+            Some(*tile as u32)
+        }
+    }
+
+    /// A parallelogram of tiles costing 1 from (0, 0) to (size - 1, size - 1)
+    fn open_map(size: i32) -> HexMap<u8> {
+        let mut map = HexMap::new(36.0, 36.0, 28.0, 6.0, 0.0, 0.0);
+        for q in 0..size {
+            for r in 0..size {
+                map.set_tile(Axial::new(q, r).to_hex(), 1);
+            }
+        }
+        map
+    }
+
+    /// Follows the field from the hex, checking every step gets cheaper, and returns the hex it stopped at
+    fn follow(field: &HexFlowField<u8>, hex: Axial) -> Axial {
+        let mut current = hex;
+        while let Some(next) = field.next(current) {
+            assert!(field.distance(next) < field.distance(current), "{:?} doesn't get closer to the goal", current);
+            current = next;
+        }
+        current
+    }
+
+    #[test]
+    fn open_map_distances_are_hex_distances() {
+        let map = open_map(6);
+        let goal = Axial::new(2, 3);
+        let field = map.flow_field(goal, cost);
+
+        assert_eq!(field.distance(goal), Some(0));
+        assert_eq!(field.direction(goal), None);
+        for q in 0..6 {
+            for r in 0..6 {
+                let hex = Axial::new(q, r);
+                assert_eq!(field.distance(hex), Some(hex.distance(goal) as u32), "hex {:?}", hex);
+                assert_eq!(follow(&field, hex), goal);
+            }
+        }
+        assert!(!field.reaches_goal(Axial::new(6, 0)));
+    }
+
+    #[test]
+    fn paths_go_around_walls() {
+        let mut map = open_map(6);
+        // A wall along q = 2 with a gap at the bottom
+        for r in 0..5 {
+            map.set_tile(Axial::new(2, r).to_hex(), WALL);
+        }
+        let goal = Axial::new(0, 0);
+        let field = map.flow_field(goal, cost);
+
+        assert!(!field.reaches_goal(Axial::new(2, 0)));
+        let start = Axial::new(4, 0);
+        assert!(field.distance(start).unwrap() > start.distance(goal) as u32);
+        assert_eq!(follow(&field, start), goal);
+
+        // Closing the gap cuts the right side off
+        map.set_tile(Axial::new(2, 5).to_hex(), WALL);
+        let field = map.flow_field(goal, cost);
+        assert!(!field.reaches_goal(start));
+        assert_eq!(field.next(start), None);
+    }
+
+    #[test]
+    fn update_matches_rebuild() {
+        let mut map = open_map(8);
+        let goal = Axial::new(1, 1);
+        let mut field = map.flow_field(goal, cost);
+
+        let changes = [
+            (Axial::new(3, 0), WALL),
+            (Axial::new(3, 1), WALL),
+            (Axial::new(3, 2), WALL),
+            (Axial::new(5, 5), 4),
+            (Axial::new(2, 6), WALL),
+        ];
+        for (hex, tile) in changes.iter() {
+            map.set_tile(hex.to_hex(), *tile);
+            field.invalidate(*hex);
+        }
+        assert!(field.is_dirty());
+        field.update(&map);
+        assert!(!field.is_dirty());
+
+        // Removing a wall lets paths through again
+        map.set_tile(Axial::new(3, 1).to_hex(), 1);
+        field.invalidate(Axial::new(3, 1));
+        field.update(&map);
+
+        let rebuilt = map.flow_field(goal, cost);
+        for q in -1..9 {
+            for r in -1..9 {
+                let hex = Axial::new(q, r);
+                assert_eq!(field.distance(hex), rebuilt.distance(hex), "hex {:?}", hex);
+                if field.reaches_goal(hex) {
+                    assert_eq!(follow(&field, hex), goal);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn invalidating_the_goal() {
+        let mut map = open_map(4);
+        let goal = Axial::new(0, 0);
+        let mut field = map.flow_field(goal, cost);
+
+        map.set_tile(goal.to_hex(), WALL);
+        field.invalidate(goal);
+        field.update(&map);
+        assert!(!field.reaches_goal(Axial::new(3, 3)));
+
+        map.set_tile(goal.to_hex(), 1);
+        field.invalidate(goal);
+        field.update(&map);
+        assert_eq!(field.distance(Axial::new(3, 3)), Some(6));
+    }
+
+    #[test]
+    fn elevation_step_costs() {
+        let elevation = ElevationCosts::new()
+            .climb_multiplier(1.0)
+            .descend_multiplier(0.5)
+            .max_climb(2);
+
+        assert_eq!(elevation.step_cost(2, 0, 0), Some(2));
+        assert_eq!(elevation.step_cost(2, 0, 2), Some(6));
+        assert_eq!(elevation.step_cost(2, 0, 3), None);
+        assert_eq!(elevation.step_cost(2, 3, 0), Some(5));
+    }
+
+    #[test]
+    fn cliffs_are_avoided() {
+        let mut map = open_map(5);
+        map.get_height = |tile| if *tile == 2 { 3 } else { 0 };
+        // A row of tall tiles across the middle is too high to climb onto or drop off of
+        for q in 0..5 {
+            map.set_tile(Axial::new(q, 2).to_hex(), 2);
+        }
+        let elevation = ElevationCosts::new().max_climb(1).max_descend(1);
+        let field = map.flow_field_with_elevation(Axial::new(0, 0), cost, elevation);
+
+        assert!(!field.reaches_goal(Axial::new(0, 4)));
+        assert!(field.reaches_goal(Axial::new(0, 1)));
+    }
+}
@@ -0,0 +1,272 @@
+use std::collections::HashMap;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use shipyard::*;
+use tetra::graphics::Color;
+use crate::{
+    components::Transform,
+    rendering::{
+        draw_buffer::{
+            DrawBuffer,
+            DrawCommand,
+            PassId,
+        },
+        layers::DrawLayer,
+    },
+};
+use super::*;
+use super::render::HexPosition;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum FogState {
+    /// Never seen, the tile is hidden completely
+    Unseen,
+    /// Seen before but not currently in view, the tile is darkened and anything on it is hidden
+    Seen,
+    Visible,
+}
+
+/// Which tiles of a HexMap have been seen, stored separately from the map so it can be saved on its own
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FogOfWar {
+    /// Tiles that aren't in here are unseen
+    states: HashMap<Axial, FogState>,
+
+    /// Drawn over seen tiles. Defaults to half transparent black.
+    #[serde(skip, default = "default_seen_color")]
+    pub seen_color: Color,
+    /// Drawn over unseen tiles. Defaults to black.
+    #[serde(skip, default = "default_unseen_color")]
+    pub unseen_color: Color,
+}
+
+fn default_seen_color() -> Color {
+    Color::rgba(0.0, 0.0, 0.0, 0.5)
+}
+
+fn default_unseen_color() -> Color {
+    Color::BLACK
+}
+
+impl Default for FogOfWar {
+    fn default() -> Self {
+        FogOfWar {
+            states: HashMap::new(),
+            seen_color: default_seen_color(),
+            unseen_color: default_unseen_color(),
+        }
+    }
+}
+
+impl FogOfWar {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn state(&self, hex: Axial) -> FogState {
+        self.states.get(&hex).copied().unwrap_or(FogState::Unseen)
+    }
+
+    pub fn is_visible(&self, hex: Axial) -> bool {
+        self.state(hex) == FogState::Visible
+    }
+
+    /// Replaces the visible tiles, e.g. with the hexes in view of the player's units. Tiles that were visible
+    /// and aren't any more become seen.
+    pub fn set_visible<I: IntoIterator<Item = Axial>>(&mut self, visible: I) {
+        for state in self.states.values_mut() {
+            if *state == FogState::Visible {
+                *state = FogState::Seen;
+            }
+        }
+
+        for hex in visible.into_iter() {
+            self.states.insert(hex, FogState::Visible);
+        }
+    }
+
+    /// Marks tiles as seen without making them visible, e.g. for a revealed map. Visible tiles stay visible.
+    pub fn reveal<I: IntoIterator<Item = Axial>>(&mut self, hexes: I) {
+        for hex in hexes.into_iter() {
+            self.states.entry(hex).or_insert(FogState::Seen);
+        }
+    }
+
+    /// Forgets every tile
+    pub fn clear(&mut self) {
+        self.states.clear();
+    }
+
+    /// Darkens the seen tiles and covers the unseen ones in the FX pass, so the overlay is above the whole world
+    pub fn draw<T, const W: usize, const H: usize>(&self, map: &HexMap<T, W, H>, draw_buffer: &mut DrawBuffer) {
+        let (w, h) = (map.hex_width, map.hex_height);
+        let outline = draw_buffer.polygons.add(vec![
+            Vec2::new(w / 2.0, 0.0),
+            Vec2::new(w, h / 4.0),
+            Vec2::new(w, h * 3.0 / 4.0),
+            Vec2::new(w / 2.0, h),
+            Vec2::new(0.0, h * 3.0 / 4.0),
+            Vec2::new(0.0, h / 4.0),
+        ]);
+
+        for chunk in map.chunks() {
+            for (hex, tile) in chunk.tiles() {
+                let color = match self.state(hex) {
+                    FogState::Visible => continue,
+                    FogState::Seen => self.seen_color,
+                    FogState::Unseen => self.unseen_color,
+                };
+
+                draw_buffer.draw(
+                    DrawCommand::polygon(outline)
+                        .position(map.elevated_position(hex, (map.get_height)(tile)))
+                        .draw_iso(true)
+                        .color(color)
+                        .draw_layer(DrawLayer::FX)
+                        .pass(PassId::FX)
+                );
+            }
+        }
+    }
+
+    /// Draws the fog for the HexMap unique
+    pub fn draw_fog<T: 'static + Send + Sync>(
+        map: UniqueView<HexMap<T>>,
+        fog: UniqueView<FogOfWar>,
+        mut draw_buffer: UniqueViewMut<DrawBuffer>,
+    ) {
+        fog.draw(&map, &mut draw_buffer);
+    }
+}
+
+/// Added to entities standing on tiles that aren't visible, the sprite drawing systems skip them
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FogHidden;
+
+/// Adds or removes FogHidden on every entity with a Transform or HexPosition depending on whether its tile is visible
+pub fn update_fog_hidden<T: 'static + Send + Sync>(
+    map: UniqueView<HexMap<T>>,
+    fog: UniqueView<FogOfWar>,
+    mut entities: EntitiesViewMut,
+    positions: View<HexPosition>,
+    transforms: View<Transform>,
+    mut hidden: ViewMut<FogHidden>,
+) {
+    let mut changes = vec![];
+
+    for (id, position) in positions.iter().with_id() {
+        changes.push((id, fog.is_visible(position.hex)));
+    }
+
+    for (id, transform) in transforms.iter().with_id() {
+        if positions.get(id).is_ok() {
+            continue;
+        }
+
+        let hex = map.pixel_to_hex(Vec2::new(transform.x as f32, transform.y as f32));
+        changes.push((id, hex.map_or(false, |hex| fog.is_visible(hex))));
+    }
+
+    for (id, visible) in changes.into_iter() {
+        let is_hidden = hidden.get(id).is_ok();
+        if visible && is_hidden {
+            hidden.remove(id);
+        } else if !visible && !is_hidden {
+            entities.add_component(&mut hidden, FogHidden, id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(q: i32, r: i32) -> Axial {
+        Axial::new(q, r)
+    }
+
+    #[test]
+    fn visibility_transitions() {
+        let mut fog = FogOfWar::new();
+        assert_eq!(fog.state(hex(0, 0)), FogState::Unseen);
+
+        fog.set_visible(vec![hex(0, 0), hex(1, 0)]);
+        assert!(fog.is_visible(hex(0, 0)) && fog.is_visible(hex(1, 0)));
+
+        // Tiles that drop out of view are remembered as seen
+        fog.set_visible(vec![hex(1, 0), hex(2, 0)]);
+        assert_eq!(fog.state(hex(0, 0)), FogState::Seen);
+        assert_eq!(fog.state(hex(1, 0)), FogState::Visible);
+        assert_eq!(fog.state(hex(2, 0)), FogState::Visible);
+
+        // Revealing never hides visible tiles or makes anything visible
+        fog.reveal(vec![hex(0, 0), hex(2, 0), hex(5, 5)]);
+        assert_eq!(fog.state(hex(0, 0)), FogState::Seen);
+        assert_eq!(fog.state(hex(2, 0)), FogState::Visible);
+        assert_eq!(fog.state(hex(5, 5)), FogState::Seen);
+
+        fog.set_visible(vec![]);
+        assert!([hex(0, 0), hex(1, 0), hex(2, 0), hex(5, 5)].iter().all(|hex| fog.state(*hex) == FogState::Seen));
+
+        fog.clear();
+        assert_eq!(fog.state(hex(1, 0)), FogState::Unseen);
+    }
+
+    #[test]
+    fn fog_survives_serialization() {
+        let mut fog = FogOfWar::new();
+        fog.set_visible(vec![hex(1, 2)]);
+        fog.set_visible(vec![hex(3, 4)]);
+
+        let loaded: FogOfWar = bincode::deserialize(&bincode::serialize(&fog).unwrap()).unwrap();
+        assert_eq!(loaded.state(hex(1, 2)), FogState::Seen);
+        assert_eq!(loaded.state(hex(3, 4)), FogState::Visible);
+        assert_eq!(loaded.seen_color, default_seen_color());
+    }
+
+    #[test]
+    fn entities_on_hidden_tiles_get_fog_hidden() {
+        let mut map: HexMap<u8> = HexMap::new(36.0, 36.0, 28.0, 6.0, 0.0, 0.0);
+        for q in 0..4 {
+            for r in 0..4 {
+                map.set_tile(hex(q, r).to_hex(), 1);
+            }
+        }
+        let center = |map: &HexMap<u8>, hex: Axial| {
+            let pixel = map.axial_to_pixel(hex) + Vec2::new(map.hex_width, map.hex_height) / 2.0;
+            Transform::new(pixel.x as f64, pixel.y as f64)
+        };
+        let (in_view, out_of_view) = (center(&map, hex(1, 1)), center(&map, hex(3, 3)));
+
+        let mut fog = FogOfWar::new();
+        fog.set_visible(vec![hex(1, 1), hex(2, 2)]);
+
+        let world = World::new();
+        world.add_unique(map);
+        world.add_unique(fog);
+
+        let entities = world.run(|mut entities: EntitiesViewMut, mut positions: ViewMut<HexPosition>, mut transforms: ViewMut<Transform>| {
+            [
+                entities.add_entity(&mut positions, HexPosition::new(hex(2, 2))),
+                entities.add_entity(&mut positions, HexPosition::new(hex(0, 3))),
+                entities.add_entity(&mut transforms, in_view),
+                entities.add_entity(&mut transforms, out_of_view),
+                // Off the map entirely
+                entities.add_entity(&mut transforms, Transform::new(-500.0, -500.0)),
+            ]
+        });
+
+        let hidden = |world: &World| -> Vec<bool> {
+            world.run(|hidden: View<FogHidden>| entities.iter().map(|id| hidden.get(*id).is_ok()).collect())
+        };
+
+        world.run(update_fog_hidden::<u8>);
+        assert_eq!(hidden(&world), vec![false, true, false, true, true]);
+
+        world.run(|mut fog: UniqueViewMut<FogOfWar>| fog.set_visible(vec![hex(0, 3), hex(3, 3)]));
+        world.run(update_fog_hidden::<u8>);
+        assert_eq!(hidden(&world), vec![true, false, true, false, true]);
+    }
+}
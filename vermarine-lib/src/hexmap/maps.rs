@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+use shipyard::*;
+use super::*;
+
+/// Refers to a map stored in HexMaps
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct MapHandle(usize);
+
+/// Holds several HexMaps of the same tile type so that e.g. an overworld and a dungeon can share a World.
+/// Systems that only deal with one map at a time use the active map. Each map keeps its own `position`.
+pub struct HexMaps<T> {
+    maps: Vec<Option<HexMap<T>>>,
+    names: HashMap<&'static str, MapHandle>,
+    active: Option<MapHandle>,
+}
+
+impl<T> HexMaps<T> {
+    pub fn new() -> Self {
+        HexMaps {
+            maps: vec![],
+            names: HashMap::new(),
+            active: None,
+        }
+    }
+
+    /// Adds the map under the name, replacing any map that already had the name.
+    /// The first map inserted becomes the active map.
+    pub fn insert(&mut self, name: &'static str, map: HexMap<T>) -> MapHandle {
+        if let Some(handle) = self.names.get(name) {
+            self.maps[handle.0] = Some(map);
+            return *handle;
+        }
+
+        let handle = MapHandle(self.maps.len());
+        self.maps.push(Some(map));
+        self.names.insert(name, handle);
+
+        if self.active.is_none() {
+            self.active = Some(handle);
+        }
+        handle
+    }
+
+    /// Removes the map, if it was active there is no active map afterwards
+    pub fn remove(&mut self, handle: MapHandle) -> Option<HexMap<T>> {
+        self.names.retain(|_, h| *h != handle);
+        if self.active == Some(handle) {
+            self.active = None;
+        }
+        self.maps.get_mut(handle.0)?.take()
+    }
+
+    pub fn handle(&self, name: &str) -> Option<MapHandle> {
+        self.names.get(name).copied()
+    }
+
+    pub fn get(&self, handle: MapHandle) -> Option<&HexMap<T>> {
+        self.maps.get(handle.0)?.as_ref()
+    }
+
+    pub fn get_mut(&mut self, handle: MapHandle) -> Option<&mut HexMap<T>> {
+        self.maps.get_mut(handle.0)?.as_mut()
+    }
+
+    pub fn by_name(&self, name: &str) -> Option<&HexMap<T>> {
+        self.get(self.handle(name)?)
+    }
+
+    pub fn by_name_mut(&mut self, name: &str) -> Option<&mut HexMap<T>> {
+        let handle = self.handle(name)?;
+        self.get_mut(handle)
+    }
+
+    /// Sets the map used by rendering and picking, returns false if there is no such map
+    pub fn set_active(&mut self, handle: MapHandle) -> bool {
+        if self.get(handle).is_some() {
+            self.active = Some(handle);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn active_handle(&self) -> Option<MapHandle> {
+        self.active
+    }
+
+    pub fn active(&self) -> Option<&HexMap<T>> {
+        self.get(self.active?)
+    }
+
+    pub fn active_mut(&mut self) -> Option<&mut HexMap<T>> {
+        let handle = self.active?;
+        self.get_mut(handle)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (MapHandle, &HexMap<T>)> {
+        self.maps.iter()
+            .enumerate()
+            .filter_map(|(index, map)| map.as_ref().map(|map| (MapHandle(index), map)))
+    }
+}
+
+impl<T> Default for HexMaps<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Dummy trait to allow adding a method to World
+pub trait HexMapsCreator {
+    fn add_hex_maps<T: 'static + Send + Sync>(&mut self);
+}
+
+impl HexMapsCreator for World {
+    fn add_hex_maps<T: 'static + Send + Sync>(&mut self) {
+        self.add_unique(HexMaps::<T>::new());
+    }
+}
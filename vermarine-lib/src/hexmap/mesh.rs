@@ -0,0 +1,232 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use shipyard::*;
+use tetra::{
+    graphics::{
+        self,
+        Color,
+        DrawParams,
+        Mesh,
+        Rectangle,
+        Texture,
+        Vertex,
+        VertexBuffer,
+    },
+    math::{
+        Vec2,
+        Vec3,
+    },
+    Context,
+};
+use crate::rendering::{
+    Drawables,
+    draw_buffer::DrawBuffer,
+};
+use super::*;
+use super::maps::{
+    HexMaps,
+    MapHandle,
+};
+
+/// A single textured quad baked into a chunk mesh, `clip` is the region of the atlas in pixels
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TileQuad {
+    /// The top left of the quad, with z subtracted from y when `draw_iso` is set
+    pub position: Vec3<f32>,
+    pub clip: Rectangle,
+    pub color: Color,
+    /// Defaults to false
+    pub draw_iso: bool,
+}
+
+impl TileQuad {
+    pub fn new(position: Vec3<f32>, clip: Rectangle) -> Self {
+        TileQuad {
+            position,
+            clip,
+            color: Color::WHITE,
+            draw_iso: false,
+        }
+    }
+
+    /// Sets the color the quad is tinted with.
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Sets whether z is subtracted from y.
+    pub fn with_iso(mut self, draw_iso: bool) -> Self {
+        self.draw_iso = draw_iso;
+        self
+    }
+
+    fn top_left(&self) -> Vec2<f32> {
+        if self.draw_iso {
+            Vec2::new(self.position.x, self.position.y - self.position.z)
+        } else {
+            Vec2::new(self.position.x, self.position.y)
+        }
+    }
+}
+
+/// Bakes the quads into a single mesh textured with the atlas, sorted the same way as `SortMode::Isometric`
+/// so that taller tiles further down overlap the ones behind them. Returns `None` if there are no quads.
+/// Works for any grid, square tile layers can bake their own chunks with it.
+pub fn bake_quads(ctx: &mut Context, atlas: &Texture, quads: &mut Vec<TileQuad>) -> tetra::Result<Option<Mesh>> {
+    if quads.is_empty() {
+        return Ok(None);
+    }
+
+    quads.sort_by(|a, b| {
+        a.position.y.partial_cmp(&b.position.y).unwrap_or(Ordering::Equal)
+            .then(a.position.z.partial_cmp(&b.position.z).unwrap_or(Ordering::Equal))
+    });
+
+    let atlas_size = Vec2::new(atlas.width() as f32, atlas.height() as f32);
+    let mut vertices = Vec::with_capacity(quads.len() * 6);
+    for quad in quads.iter() {
+        let top_left = quad.top_left();
+        let size = Vec2::new(quad.clip.width, quad.clip.height);
+        let uv_min = Vec2::new(quad.clip.x, quad.clip.y) / atlas_size;
+        let uv_max = uv_min + size / atlas_size;
+
+        let corners = [
+            (top_left, uv_min),
+            (top_left + Vec2::new(size.x, 0.0), Vec2::new(uv_max.x, uv_min.y)),
+            (top_left + size, uv_max),
+            (top_left + Vec2::new(0.0, size.y), Vec2::new(uv_min.x, uv_max.y)),
+        ];
+
+        for index in [0, 1, 2, 0, 2, 3].iter() {
+            let (position, uv) = corners[*index];
+            vertices.push(Vertex::new(position, uv, quad.color));
+        }
+    }
+
+    let mut mesh = Mesh::new(VertexBuffer::new(ctx, &vertices)?);
+    mesh.set_texture(atlas.clone());
+    Ok(Some(mesh))
+}
+
+/// Draws a HexMap as one mesh per chunk instead of a DrawCommand per tile, every tile has to come from the same
+/// atlas texture. Only dirty chunks are re-baked, so it shouldn't be used on the same map as a HexMapRenderer
+/// as they would clear each other's dirty flags.
+///
+/// The meshes are drawn straight away with the DrawBuffer's `transform_mat` rather than through the DrawBuffer,
+/// so run `draw_chunk_meshes` before `DrawBuffer::flush` to have everything in the DrawBuffer drawn on top.
+/// Tall tiles overlapping the chunk below them are drawn under that chunk's tiles.
+///
+/// Meshes can't leave the main thread, so add it with `add_unique_non_send_sync` like Drawables.
+pub struct HexChunkMeshes<T> {
+    meshes: HashMap<ChunkPos, Mesh>,
+    // The map the meshes were baked from when drawing from HexMaps
+    cached_map: Option<MapHandle>,
+    quads: Vec<TileQuad>,
+
+    /// The drawable every quad's clip is taken from
+    pub atlas: u64,
+    /// Pushes the quads needed to draw a single tile
+    pub tile_quads: fn(&HexMap<T>, Axial, &T, &mut Vec<TileQuad>),
+}
+
+impl<T> HexChunkMeshes<T> {
+    pub fn new(atlas: u64, tile_quads: fn(&HexMap<T>, Axial, &T, &mut Vec<TileQuad>)) -> Self {
+        HexChunkMeshes {
+            meshes: HashMap::new(),
+            cached_map: None,
+            quads: vec![],
+            atlas,
+            tile_quads,
+        }
+    }
+
+    /// Re-bakes the mesh of every dirty chunk and clears their dirty flags.
+    /// Returns the number of chunks that were re-baked.
+    pub fn rebuild_dirty(&mut self, ctx: &mut Context, map: &mut HexMap<T>, drawables: &Drawables) -> tetra::Result<usize> {
+        self.meshes.retain(|pos, _| map.does_chunk_exist(*pos));
+
+        let atlas = &drawables.lookup[self.atlas as usize];
+        let dirty = map.dirty_chunks();
+        for pos in dirty.iter() {
+            let chunk = map.get_chunk(*pos).unwrap();
+            self.quads.clear();
+            for (hex, tile) in chunk.tiles() {
+                (self.tile_quads)(map, hex, tile, &mut self.quads);
+            }
+
+            match bake_quads(ctx, atlas, &mut self.quads)? {
+                Some(mesh) => { self.meshes.insert(*pos, mesh); },
+                None => { self.meshes.remove(pos); },
+            }
+        }
+
+        for pos in dirty.iter() {
+            map.clear_dirty(*pos);
+        }
+
+        Ok(dirty.len())
+    }
+
+    /// Throws away every mesh, they are baked again the next time the chunks are dirty
+    pub fn clear(&mut self) {
+        self.meshes.clear();
+    }
+
+    /// How many chunks have a mesh, which is also how many draw calls `draw` makes
+    pub fn mesh_count(&self) -> usize {
+        self.meshes.len()
+    }
+
+    /// Draws every chunk's mesh with the current transform matrix, a row of chunks at a time from the top down
+    pub fn draw(&self, ctx: &mut Context) {
+        let mut order: Vec<&ChunkPos> = self.meshes.keys().collect();
+        order.sort_by_key(|pos| (pos.r, pos.q));
+
+        for pos in order.into_iter() {
+            self.meshes[pos].draw(ctx, DrawParams::new());
+        }
+    }
+
+    /// Re-bakes any dirty chunks then draws the whole map with the DrawBuffer's transform_mat
+    pub fn draw_chunk_meshes(
+        ctx: &mut Context,
+        mut map: UniqueViewMut<HexMap<T>>,
+        mut meshes: NonSendSync<UniqueViewMut<HexChunkMeshes<T>>>,
+        drawables: NonSendSync<UniqueViewMut<Drawables>>,
+        draw_buffer: UniqueView<DrawBuffer>,
+    ) -> tetra::Result where T: 'static + Send + Sync {
+        meshes.rebuild_dirty(ctx, &mut map, &drawables)?;
+
+        graphics::set_transform_matrix(ctx, draw_buffer.transform_mat);
+        meshes.draw(ctx);
+        Ok(())
+    }
+
+    /// Same as `draw_chunk_meshes` for the active map of a HexMaps unique, re-baking everything when the active map changes
+    pub fn draw_active_chunk_meshes(
+        ctx: &mut Context,
+        mut maps: UniqueViewMut<HexMaps<T>>,
+        mut meshes: NonSendSync<UniqueViewMut<HexChunkMeshes<T>>>,
+        drawables: NonSendSync<UniqueViewMut<Drawables>>,
+        draw_buffer: UniqueView<DrawBuffer>,
+    ) -> tetra::Result where T: 'static + Send + Sync {
+        let active = maps.active_handle();
+        if meshes.cached_map != active {
+            meshes.clear();
+            meshes.cached_map = active;
+            if let Some(map) = maps.active_mut() {
+                map.mark_all_dirty();
+            }
+        }
+
+        if let Some(map) = maps.active_mut() {
+            meshes.rebuild_dirty(ctx, map, &drawables)?;
+
+            graphics::set_transform_matrix(ctx, draw_buffer.transform_mat);
+            meshes.draw(ctx);
+        }
+
+        Ok(())
+    }
+}
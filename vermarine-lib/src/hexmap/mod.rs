@@ -1,6 +1,29 @@
-use crate::tetra::math::Vec2;
-
-#[derive(Copy, Clone, Debug)]
+pub mod render;
+pub mod autotile;
+pub mod maps;
+pub mod flowfield;
+pub mod templates;
+pub mod fog;
+pub mod walls;
+pub mod regions;
+pub mod mesh;
+pub mod anchor;
+pub mod tiled;
+
+use crate::tetra::{
+    graphics::Camera,
+    math::{
+        Vec2,
+        Vec3,
+    },
+};
+use crate::picking::screen_to_world;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct ChunkPos {
     pub q: i32,
     pub r: i32,
@@ -27,77 +50,218 @@ impl ChunkPos {
     }
 }
 
+/// The default chunk size of HexChunk and HexMap
 pub const CHUNK_WIDTH: usize = 16;
 pub const CHUNK_HEIGHT: usize = 16;
 pub const CHUNK_TOTAL: usize = CHUNK_WIDTH * CHUNK_HEIGHT;
-pub struct HexChunk<T> {
-    tiles: [Option<T>; CHUNK_WIDTH * CHUNK_HEIGHT],
+
+/// Stores every tile of a chunk directly instead of as an `Option`, setting a tile that is empty makes it
+/// absent. Better suited to maps where most tiles are set.
+pub struct DenseTiles<T> {
+    /// Creates the tile new chunks are filled with
+    pub empty: fn() -> T,
+    /// Checked when a tile is set, a tile changed to be empty through `get_tile_mut` is still present until it is set again
+    pub is_empty: fn(&T) -> bool,
+}
+
+impl<T: Default + PartialEq> Default for DenseTiles<T> {
+    /// Uses `T::default()` as the empty tile
+    fn default() -> Self {
+        DenseTiles {
+            empty: T::default,
+            is_empty: |tile| *tile == T::default(),
+        }
+    }
+}
+
+impl<T> Clone for DenseTiles<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for DenseTiles<T> {}
+
+/// One bit per tile of a dense chunk, set for the tiles that are present
+struct Occupancy(Box<[u64]>);
+
+impl Occupancy {
+    fn new(len: usize) -> Self {
+        Occupancy(vec![0; (len + 63) / 64].into_boxed_slice())
+    }
+
+    fn get(&self, index: usize) -> bool {
+        self.0[index / 64] & (1 << (index % 64)) != 0
+    }
+
+    fn set(&mut self, index: usize, occupied: bool) {
+        if occupied {
+            self.0[index / 64] |= 1 << (index % 64);
+        } else {
+            self.0[index / 64] &= !(1 << (index % 64));
+        }
+    }
+}
+
+enum ChunkTiles<T, const W: usize, const H: usize> {
+    Sparse([[Option<T>; W]; H]),
+    Dense {
+        tiles: Box<[T]>,
+        occupied: Occupancy,
+        dense: DenseTiles<T>,
+    },
+}
+
+/// W by H tiles of a HexMap, 16 by 16 unless the map says otherwise
+pub struct HexChunk<T, const W: usize = CHUNK_WIDTH, const H: usize = CHUNK_HEIGHT> {
+    tiles: ChunkTiles<T, W, H>,
     pos: ChunkPos,
+    dirty: bool,
 }
 
-impl<T> HexChunk<T> {
-    pub fn new(tiles: [Option<T>; CHUNK_WIDTH * CHUNK_HEIGHT], q: i32, r: i32) -> HexChunk<T> {
+impl<T, const W: usize, const H: usize> HexChunk<T, W, H> {
+    pub const WIDTH: usize = W;
+    pub const HEIGHT: usize = H;
+    pub const TOTAL: usize = W * H;
+
+    /// Creates a chunk from H rows of W tiles
+    pub fn new(tiles: [[Option<T>; W]; H], q: i32, r: i32) -> HexChunk<T, W, H> {
         HexChunk {
-            tiles,
+            tiles: ChunkTiles::Sparse(tiles),
             pos: ChunkPos::new(q, r),
+            dirty: true,
         }
     }
 
+    /// Creates a chunk without any tiles
+    pub fn new_empty(q: i32, r: i32) -> HexChunk<T, W, H> {
+        Self::new(std::array::from_fn(|_| std::array::from_fn(|_| None)), q, r)
+    }
+
+    /// Creates a chunk filled with the empty tile
+    pub fn new_dense(dense: DenseTiles<T>, q: i32, r: i32) -> HexChunk<T, W, H> {
+        let tiles = std::iter::repeat_with(dense.empty)
+            .take(Self::TOTAL)
+            .collect::<Vec<_>>()
+            .into_boxed_slice();
+
+        HexChunk {
+            tiles: ChunkTiles::Dense {
+                tiles,
+                occupied: Occupancy::new(Self::TOTAL),
+                dense,
+            },
+            pos: ChunkPos::new(q, r),
+            dirty: true,
+        }
+    }
+
+    pub fn is_dense(&self) -> bool {
+        matches!(self.tiles, ChunkTiles::Dense { .. })
+    }
+
+    pub fn pos(&self) -> ChunkPos {
+        self.pos
+    }
+
+    /// Returns true if a tile has been set or mutably borrowed since the dirty flag was last cleared
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    pub fn mark_dirty(&mut self) {
+        self.dirty = true;
+    }
+
+    pub fn clear_dirty(&mut self) {
+        self.dirty = false;
+    }
+
+    /// Iterates over all the tiles in the chunk along with their position in the map
+    pub fn tiles(&self) -> impl Iterator<Item = (Axial, &T)> {
+        let base_q = self.pos.q * W as i32;
+        let base_r = self.pos.r * H as i32;
+
+        (0..Self::TOTAL)
+            .filter_map(move |index| {
+                let (q, r) = (index % W, index / W);
+                self.tile_at(q, r).map(|tile| (Axial::new(base_q + q as i32, base_r + r as i32), tile))
+            })
+    }
+
     pub fn sparse_index(&self) -> (usize, usize) {
         self.pos.sparse_index()
     }
 
-    pub fn set_tile(&mut self, hex: &Hex, tile: T) {
+    fn tile_index(hex: &Hex) -> (usize, usize) {
         let axial = hex.to_axial();
         
-        if axial.q < 0 || (axial.q as usize) >= CHUNK_WIDTH {
+        if axial.q < 0 || (axial.q as usize) >= W {
             panic!();
         }
-        if axial.r < 0 || (axial.r as usize) >= CHUNK_HEIGHT {
+        if axial.r < 0 || (axial.r as usize) >= H {
             panic!();
         }
 
-        let (q, r) = (axial.q as usize, axial.r as usize);
-        self.tiles[q + r * CHUNK_WIDTH] = Some(tile);
+        (axial.q as usize, axial.r as usize)
     }
 
-    pub fn get_tile(&self, hex: &Hex) -> Option<&T> {
-        let axial = hex.to_axial();
-        
-        if axial.q < 0 || (axial.q as usize) >= CHUNK_WIDTH {
-            panic!();
+    fn tile_at(&self, q: usize, r: usize) -> Option<&T> {
+        match &self.tiles {
+            ChunkTiles::Sparse(tiles) => tiles[r][q].as_ref(),
+            ChunkTiles::Dense { tiles, occupied, .. } => {
+                let index = q + r * W;
+                tiles.get(index).filter(|_| occupied.get(index))
+            },
         }
-        if axial.r < 0 || (axial.r as usize) >= CHUNK_HEIGHT {
-            panic!();
+    }
+
+    pub fn set_tile(&mut self, hex: &Hex, tile: T) {
+        let (q, r) = Self::tile_index(hex);
+        match &mut self.tiles {
+            ChunkTiles::Sparse(tiles) => tiles[r][q] = Some(tile),
+            ChunkTiles::Dense { tiles, occupied, dense } => {
+                let index = q + r * W;
+                occupied.set(index, !(dense.is_empty)(&tile));
+                tiles[index] = tile;
+            },
         }
+        self.dirty = true;
+    }
 
-        let (q, r) = (axial.q as usize, axial.r as usize);
-        let tile = self.tiles.get(q + r * CHUNK_WIDTH)?;
-        tile.as_ref()
+    pub fn get_tile(&self, hex: &Hex) -> Option<&T> {
+        let (q, r) = Self::tile_index(hex);
+        self.tile_at(q, r)
     }
 
     pub fn get_tile_mut(&mut self, hex: &Hex) -> Option<&mut T> {
-        let axial = hex.to_axial();
-        
-        if axial.q < 0 || (axial.q as usize) >= CHUNK_WIDTH {
-            panic!();
-        }
-        if axial.r < 0 || (axial.r as usize) >= CHUNK_HEIGHT {
-            panic!();
-        }
-
-        let (q, r) = (axial.q as usize, axial.r as usize);
-        let tile = self.tiles.get_mut(q + r * CHUNK_WIDTH)?;
-        tile.as_mut()
+        let (q, r) = Self::tile_index(hex);
+        let tile = match &mut self.tiles {
+            ChunkTiles::Sparse(tiles) => tiles[r][q].as_mut()?,
+            ChunkTiles::Dense { tiles, occupied, .. } => {
+                let index = q + r * W;
+                if !occupied.get(index) {
+                    return None;
+                }
+                tiles.get_mut(index)?
+            },
+        };
+        self.dirty = true;
+        Some(tile)
     }
 }
 
 //
 // Hex map
 
-pub struct HexMap<T> {
-    chunks: Vec<HexChunk<T>>,
+/// Tiles stored in chunks of W by H, which default to 16 by 16. Smaller chunks suit small maps that are rebuilt
+/// often, larger ones cut down on sparse lookups for big overworlds. The renderers, HexMaps and the systems taking
+/// a `UniqueView<HexMap<T>>` work with the default size.
+pub struct HexMap<T, const W: usize = CHUNK_WIDTH, const H: usize = CHUNK_HEIGHT> {
+    chunks: Vec<HexChunk<T, W, H>>,
     chunks_sparse: Vec<Vec<Option<usize>>>,
+    dense: Option<DenseTiles<T>>,
+    regions: regions::HexRegions,
 
     pub get_height: fn(&T) -> u8,
 
@@ -113,7 +277,7 @@ pub struct HexMap<T> {
     pub wall_vert_offset: f32,
 }
 
-impl<T> HexMap<T> {
+impl<T, const W: usize, const H: usize> HexMap<T, W, H> {
     pub fn new(
         hex_width: f32,
         hex_height: f32,
@@ -125,6 +289,8 @@ impl<T> HexMap<T> {
         HexMap {
             chunks: vec![],
             chunks_sparse: vec![], 
+            dense: None,
+            regions: regions::HexRegions::new(),
 
             get_height: |_| 0,
 
@@ -141,9 +307,20 @@ impl<T> HexMap<T> {
         }
     }
 
+    /// Sets the map to store new chunks densely, existing chunks are not converted
+    pub fn with_dense_tiles(mut self, dense: DenseTiles<T>) -> Self {
+        self.dense = Some(dense);
+        self
+    }
+
+    pub fn is_dense(&self) -> bool {
+        self.dense.is_some()
+    }
+
     pub fn clear_map(&mut self) {
         self.chunks = vec![];
         self.chunks_sparse = vec![];
+        self.regions.clear();
         self.tallest = 0;
     }
 
@@ -152,7 +329,7 @@ impl<T> HexMap<T> {
 
         let (q, r) = (axial.q, axial.r);
 
-        let chunk_width = CHUNK_WIDTH as i32;
+        let chunk_width = W as i32;
         let (chunk_q, q_offset) = 
             if q < 0 {
                 (
@@ -166,7 +343,7 @@ impl<T> HexMap<T> {
                 )
             };
 
-        let chunk_height = CHUNK_HEIGHT as i32;
+        let chunk_height = H as i32;
         let (chunk_r, r_offset) = 
             if r < 0 {
                 (
@@ -186,7 +363,7 @@ impl<T> HexMap<T> {
         (chunk_pos, hex_pos)
     }
 
-    pub fn insert_chunk(&mut self, chunk: HexChunk<T>) {
+    pub fn insert_chunk(&mut self, chunk: HexChunk<T, W, H>) {
         let (q, r) = chunk.sparse_index();
         let chunk_index = self.chunks.len();
         self.chunks.push(chunk);
@@ -213,7 +390,55 @@ impl<T> HexMap<T> {
         false
     }
 
-    pub fn pixel_to_hex_raw(&mut self, pos: Vec2<f32>, height_offset: f32) -> FractionalAxial {
+    pub fn get_chunk(&self, pos: ChunkPos) -> Option<&HexChunk<T, W, H>> {
+        let (q, r) = pos.sparse_index();
+        let index = (*self.chunks_sparse.get(q)?.get(r)?)?;
+        self.chunks.get(index)
+    }
+
+    pub fn get_chunk_mut(&mut self, pos: ChunkPos) -> Option<&mut HexChunk<T, W, H>> {
+        let (q, r) = pos.sparse_index();
+        let index = (*self.chunks_sparse.get(q)?.get(r)?)?;
+        self.chunks.get_mut(index)
+    }
+
+    pub fn chunks(&self) -> impl Iterator<Item = &HexChunk<T, W, H>> {
+        self.chunks.iter()
+    }
+
+    /// Returns the positions of all chunks that have changed since their dirty flag was last cleared
+    pub fn dirty_chunks(&self) -> Vec<ChunkPos> {
+        self.chunks
+            .iter()
+            .filter(|chunk| chunk.is_dirty())
+            .map(|chunk| chunk.pos())
+            .collect()
+    }
+
+    pub fn is_chunk_dirty(&self, pos: ChunkPos) -> bool {
+        self.get_chunk(pos).map_or(false, |chunk| chunk.is_dirty())
+    }
+
+    pub fn clear_dirty(&mut self, pos: ChunkPos) {
+        if let Some(chunk) = self.get_chunk_mut(pos) {
+            chunk.clear_dirty();
+        }
+    }
+
+    pub fn clear_all_dirty(&mut self) {
+        for chunk in self.chunks.iter_mut() {
+            chunk.clear_dirty();
+        }
+    }
+
+    /// Marks every chunk as dirty, e.g. after changing `position` or the hex sizes
+    pub fn mark_all_dirty(&mut self) {
+        for chunk in self.chunks.iter_mut() {
+            chunk.mark_dirty();
+        }
+    }
+
+    pub fn pixel_to_hex_raw(&self, pos: Vec2<f32>, height_offset: f32) -> FractionalAxial {
         let mut pos = pos;
         pos -= Vec2::new(18., 18.);
         pos.x -= self.position.x;
@@ -244,7 +469,8 @@ impl<T> HexMap<T> {
         )
     }
 
-    pub fn pixel_to_hex(&mut self, pos: Vec2<f32>) -> Option<Axial> {
+    /// Finds the tallest tile drawn under the world position, taking the map's position into account
+    pub fn pixel_to_hex(&self, pos: Vec2<f32>) -> Option<Axial> {
         let mut tallest_height: Option<(u8, Axial)> = None;
 
         for height in 0..=self.tallest {
@@ -275,6 +501,20 @@ impl<T> HexMap<T> {
         None
     }
 
+    /// Finds the tallest tile drawn under the screen position, going through the inverse of the camera's
+    /// matrix so that the camera's position, zoom and rotation are accounted for
+    pub fn pick(&self, screen_pos: Vec2<f32>, camera: &Camera) -> Option<Axial> {
+        self.pixel_to_hex(screen_to_world(camera, screen_pos))
+    }
+
+    /// The position to draw something standing on the hex at the height with, when `draw_iso` is set.
+    /// The z is what `pixel_to_hex` expects tiles to be raised by, and sorts things on taller tiles above
+    /// things on shorter tiles in the same row with `SortMode::Isometric`.
+    pub fn elevated_position(&self, hex: Axial, height: u8) -> Vec3<f32> {
+        let pixel = self.axial_to_pixel(hex);
+        Vec3::new(pixel.x, pixel.y, height as f32 * self.hex_depth_step)
+    }
+
     /// Returns the top left pixel of a bounding box around the hex
     pub fn axial_to_pixel(&self, hex: Axial) -> Vec2<f32> {
         let size_x = self.hex_width / f32::sqrt(3.0);
@@ -297,8 +537,11 @@ impl<T> HexMap<T> {
     pub fn set_tile(&mut self, hex: Hex, tile: T) {
         let (chunk_pos, axial) = self.hex_to_chunk(&hex);
         if !self.does_chunk_exist(chunk_pos) {
-            let array = none_array::create_array::<T, CHUNK_TOTAL>();
-            self.insert_chunk(HexChunk::new(array, chunk_pos.q, chunk_pos.r))
+            let chunk = match self.dense {
+                Some(dense) => HexChunk::new_dense(dense, chunk_pos.q, chunk_pos.r),
+                None => HexChunk::new_empty(chunk_pos.q, chunk_pos.r),
+            };
+            self.insert_chunk(chunk)
         }
 
         let (q, r) = chunk_pos.sparse_index();
@@ -333,7 +576,7 @@ impl<T> HexMap<T> {
 //
 // Hex structs
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum Hex {
     Axial(Axial),
     Cube(Cube),
@@ -407,6 +650,21 @@ impl Hex {
         }
     }
 
+    /// Rotates the hex around center by steps of 60 degrees, positive steps go clockwise.
+    /// The result uses the same coordinate system as self.
+    pub fn rotate_around(&self, center: Hex, steps: i32) -> Hex {
+        let mut offset = (self.to_axial() - center.to_axial()).to_cube();
+        for _ in 0..steps.rem_euclid(6) {
+            offset = offset.rotate_right();
+        }
+
+        let rotated = center.to_axial() + offset.to_axial();
+        match self {
+            Hex::Axial(_) => rotated.to_hex(),
+            Hex::Cube(_) => rotated.to_cube().to_hex(),
+        }
+    }
+
     pub fn neighbors(&self) -> [Hex; 6] {
         match self {
             Hex::Axial(Axial { q, r }) => {
@@ -433,7 +691,7 @@ impl Hex {
     }
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct Axial {
     pub q: i32,
     pub r: i32,
@@ -456,6 +714,39 @@ impl std::ops::AddAssign for Axial {
     }
 }
 
+impl std::ops::Sub for Axial {
+    type Output = Axial;
+
+    fn sub(mut self, other: Axial) -> Axial {
+        self.q -= other.q;
+        self.r -= other.r;
+        self
+    }
+}
+
+impl std::ops::SubAssign for Axial {
+    fn sub_assign(&mut self, other: Axial) {
+        self.q -= other.q;
+        self.r -= other.r;
+    }
+}
+
+impl std::ops::Neg for Axial {
+    type Output = Axial;
+
+    fn neg(self) -> Axial {
+        Axial::new(-self.q, -self.r)
+    }
+}
+
+impl std::ops::Mul<i32> for Axial {
+    type Output = Axial;
+
+    fn mul(self, scale: i32) -> Axial {
+        Axial::new(self.q * scale, self.r * scale)
+    }
+}
+
 impl Axial {
     pub fn new(q: i32, r: i32) -> Axial {
         Axial {
@@ -470,9 +761,96 @@ impl Axial {
     pub fn to_hex(&self) -> Hex {
         Hex::Axial(*self)
     }
+
+    /// Rotates 60 degrees clockwise around the origin
+    pub fn rotate_right(&self) -> Axial {
+        self.to_cube().rotate_right().to_axial()
+    }
+
+    /// Rotates 60 degrees counter clockwise around the origin
+    pub fn rotate_left(&self) -> Axial {
+        self.to_cube().rotate_left().to_axial()
+    }
+
+    /// The number of steps between the two hexes
+    pub fn distance(&self, other: Axial) -> i32 {
+        let cube = (*self - other).to_cube();
+        cube.q.abs().max(cube.r.abs()).max(cube.s.abs())
+    }
+
+    /// Packs the coordinates into a single number that is stable between runs, for use as a key in save files
+    pub fn to_index(&self) -> u64 {
+        ((self.q as u32 as u64) << 32) | self.r as u32 as u64
+    }
+
+    /// Unpacks coordinates packed by `to_index`
+    pub fn from_index(index: u64) -> Axial {
+        Axial::new((index >> 32) as u32 as i32, index as u32 as i32)
+    }
+}
+
+/// The six neighbors of a hex, in the same order as `Hex::neighbors`
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum HexDirection {
+    NorthWest,
+    NorthEast,
+    East,
+    SouthEast,
+    SouthWest,
+    West,
+}
+
+impl HexDirection {
+    pub const ALL: [HexDirection; 6] = [
+        HexDirection::NorthWest,
+        HexDirection::NorthEast,
+        HexDirection::East,
+        HexDirection::SouthEast,
+        HexDirection::SouthWest,
+        HexDirection::West,
+    ];
+
+    /// The axial offset to the neighbor in this direction
+    pub fn offset(&self) -> Axial {
+        match self {
+            HexDirection::NorthWest => Axial::new(0, -1),
+            HexDirection::NorthEast => Axial::new(1, -1),
+            HexDirection::East => Axial::new(1, 0),
+            HexDirection::SouthEast => Axial::new(0, 1),
+            HexDirection::SouthWest => Axial::new(-1, 1),
+            HexDirection::West => Axial::new(-1, 0),
+        }
+    }
+
+    /// The next direction clockwise
+    pub fn rotate_right(&self) -> HexDirection {
+        self.rotate(1)
+    }
+
+    /// The next direction counter clockwise
+    pub fn rotate_left(&self) -> HexDirection {
+        self.rotate(-1)
+    }
+
+    /// Rotates by 60 degree steps, positive steps go clockwise
+    pub fn rotate(&self, steps: i32) -> HexDirection {
+        let index = Self::ALL.iter().position(|direction| direction == self).unwrap() as i32;
+        Self::ALL[(index + steps).rem_euclid(6) as usize]
+    }
+
+    pub fn opposite(&self) -> HexDirection {
+        match self {
+            HexDirection::NorthWest => HexDirection::SouthEast,
+            HexDirection::NorthEast => HexDirection::SouthWest,
+            HexDirection::East => HexDirection::West,
+            HexDirection::SouthEast => HexDirection::NorthWest,
+            HexDirection::SouthWest => HexDirection::NorthEast,
+            HexDirection::West => HexDirection::East,
+        }
+    }
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct Cube {
     pub q: i32,
     pub r: i32,
@@ -497,6 +875,36 @@ impl std::ops::AddAssign for Cube {
     }
 }
 
+impl std::ops::Sub for Cube {
+    type Output = Cube;
+
+    fn sub(self, other: Cube) -> Cube {
+        (self.to_axial() - other.to_axial()).to_cube()
+    }
+}
+
+impl std::ops::SubAssign for Cube {
+    fn sub_assign(&mut self, other: Cube) {
+        *self = *self - other;
+    }
+}
+
+impl std::ops::Neg for Cube {
+    type Output = Cube;
+
+    fn neg(self) -> Cube {
+        Cube::new(-self.q, -self.r, -self.s)
+    }
+}
+
+impl std::ops::Mul<i32> for Cube {
+    type Output = Cube;
+
+    fn mul(self, scale: i32) -> Cube {
+        Cube::new(self.q * scale, self.r * scale, self.s * scale)
+    }
+}
+
 impl Cube {
     pub fn new(q: i32, r: i32, s: i32) -> Cube {
         Cube {
@@ -514,6 +922,25 @@ impl Cube {
         Hex::Cube(*self)
     }
 
+    /// Same as `Axial::to_index`, s isn't stored as it can be derived from q and r
+    pub fn to_index(&self) -> u64 {
+        self.to_axial().to_index()
+    }
+
+    pub fn from_index(index: u64) -> Cube {
+        Axial::from_index(index).to_cube()
+    }
+
+    /// Rotates 60 degrees clockwise around the origin
+    pub fn rotate_right(&self) -> Cube {
+        Cube::new(-self.r, -self.s, -self.q)
+    }
+
+    /// Rotates 60 degrees counter clockwise around the origin
+    pub fn rotate_left(&self) -> Cube {
+        Cube::new(-self.s, -self.q, -self.r)
+    }
+
     pub fn is_valid(&self) -> bool {
         self.q + self.r + self.s == 0
     }
@@ -586,4 +1013,104 @@ impl FractionalCube {
     pub fn is_valid(&self) -> bool {
         f32::abs(self.q + self.r + self.s) < 0.05
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(q: i32, r: i32) -> Hex {
+        Hex::Axial(Axial::new(q, r))
+    }
+
+    fn new_map() -> HexMap<u8> {
+        HexMap::new(36.0, 36.0, 28.0, 6.0, 0.0, 0.0)
+    }
+
+    #[test]
+    fn dense_tiles_track_occupancy() {
+        let mut map = new_map().with_dense_tiles(DenseTiles::default());
+        assert!(map.is_dense());
+
+        map.set_tile(hex(3, -5), 7);
+        assert_eq!(map.get_tile(hex(3, -5)), Some(&7));
+        // The rest of the chunk is filled with the empty tile but absent
+        assert_eq!(map.get_tile(hex(4, -5)), None);
+        assert!(map.get_tile_mut(hex(4, -5)).is_none());
+
+        *map.get_tile_mut(hex(3, -5)).unwrap() = 9;
+        assert_eq!(map.get_tile(hex(3, -5)), Some(&9));
+        assert_eq!(map.chunks().map(|chunk| chunk.tiles().count()).sum::<usize>(), 1);
+
+        // Setting the empty tile removes it
+        map.set_tile(hex(3, -5), 0);
+        assert_eq!(map.get_tile(hex(3, -5)), None);
+        assert!(map.chunks().all(|chunk| chunk.is_dense() && chunk.tiles().count() == 0));
+    }
+
+    #[test]
+    fn dense_and_sparse_maps_agree() {
+        let mut sparse = new_map();
+        let mut dense = new_map().with_dense_tiles(DenseTiles::default());
+
+        // Covers several chunks on both sides of the origin and tiles either side of each 64 bit word
+        for q in -20..20 {
+            for r in -20..20 {
+                let tile = ((q * 7 + r * 3).rem_euclid(5)) as u8;
+                if tile != 0 {
+                    sparse.set_tile(hex(q, r), tile);
+                }
+                dense.set_tile(hex(q, r), tile);
+            }
+        }
+
+        for q in -20..20 {
+            for r in -20..20 {
+                assert_eq!(sparse.get_tile(hex(q, r)), dense.get_tile(hex(q, r)), "tile {}, {}", q, r);
+            }
+        }
+
+        let count = |map: &HexMap<u8>| map.chunks().map(|chunk| chunk.tiles().count()).sum::<usize>();
+        assert_eq!(count(&sparse), count(&dense));
+    }
+
+    #[test]
+    fn small_chunks_split_at_their_size() {
+        let mut map: HexMap<u8, 8, 8> = HexMap::new(36.0, 36.0, 28.0, 6.0, 0.0, 0.0);
+
+        // Either side of the chunk edges at 0 and 8, and at -1 and -9 going the other way
+        let edges = [-9, -8, -1, 0, 7, 8];
+        for &q in edges.iter() {
+            for &r in edges.iter() {
+                map.set_tile(hex(q, r), (q * 16 + r).rem_euclid(251) as u8 + 1);
+            }
+        }
+
+        for &q in edges.iter() {
+            for &r in edges.iter() {
+                assert_eq!(map.get_tile(hex(q, r)), Some(&((q * 16 + r).rem_euclid(251) as u8 + 1)), "tile {}, {}", q, r);
+            }
+        }
+        assert_eq!(map.get_tile(hex(1, 1)), None);
+        assert_eq!(map.get_tile(hex(-2, -2)), None);
+
+        let (pos, offset) = map.hex_to_chunk(&hex(-9, 8));
+        assert_eq!(pos, ChunkPos::new(-2, 1));
+        assert_eq!(offset, Axial::new(7, 0));
+
+        // -9 falls in chunk -2, -8 and -1 in chunk -1, 0 and 7 in chunk 0 and 8 in chunk 1
+        let mut positions = map.chunks().map(|chunk| chunk.pos()).collect::<Vec<_>>();
+        positions.sort();
+        let mut expected = Vec::new();
+        for q in -2..=1 {
+            for r in -2..=1 {
+                expected.push(ChunkPos::new(q, r));
+            }
+        }
+        assert_eq!(positions, expected);
+        assert!(map.chunks().all(|chunk| chunk.tiles().all(|(axial, _)| {
+            let (pos, _) = map.hex_to_chunk(&axial.to_hex());
+            pos == chunk.pos()
+        })));
+        assert_eq!(map.chunks().map(|chunk| chunk.tiles().count()).sum::<usize>(), edges.len() * edges.len());
+    }
+}
@@ -0,0 +1,272 @@
+use std::collections::{
+    BTreeSet,
+    HashMap,
+    HashSet,
+    VecDeque,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use super::*;
+
+/// Identifies a region of a HexMap, e.g. a biome, a room or an AI's territory. What the ids mean is up to the game.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct RegionId(pub u32);
+
+/// Which region each hex belongs to, a hex is in at most one region.
+/// Stored on the HexMap but independent of the tiles, so hexes without a tile can still be tagged.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct HexRegions {
+    regions: HashMap<Axial, RegionId>,
+    // Kept sorted so that iterating a region is deterministic
+    tiles: HashMap<RegionId, BTreeSet<Axial>>,
+}
+
+impl HexRegions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&mut self, hex: Axial, region: RegionId) {
+        self.remove(hex);
+        self.regions.insert(hex, region);
+        self.tiles.entry(region).or_default().insert(hex);
+    }
+
+    /// Untags the hex, returning the region it was in
+    pub fn remove(&mut self, hex: Axial) -> Option<RegionId> {
+        let region = self.regions.remove(&hex)?;
+        if let Some(tiles) = self.tiles.get_mut(&region) {
+            tiles.remove(&hex);
+            if tiles.is_empty() {
+                self.tiles.remove(&region);
+            }
+        }
+        Some(region)
+    }
+
+    pub fn get(&self, hex: Axial) -> Option<RegionId> {
+        self.regions.get(&hex).copied()
+    }
+
+    /// The hexes in the region, ordered by q then r
+    pub fn tiles(&self, region: RegionId) -> impl Iterator<Item = Axial> + '_ {
+        self.tiles.get(&region).into_iter().flat_map(|tiles| tiles.iter().copied())
+    }
+
+    pub fn len(&self, region: RegionId) -> usize {
+        self.tiles.get(&region).map_or(0, |tiles| tiles.len())
+    }
+
+    pub fn contains(&self, region: RegionId, hex: Axial) -> bool {
+        self.get(hex) == Some(region)
+    }
+
+    /// Every region with at least one hex, in ascending order
+    pub fn ids(&self) -> Vec<RegionId> {
+        let mut ids: Vec<RegionId> = self.tiles.keys().copied().collect();
+        ids.sort();
+        ids
+    }
+
+    /// One more than the highest region in use, for picking ids of new regions
+    pub fn next_id(&self) -> RegionId {
+        RegionId(self.tiles.keys().map(|id| id.0 + 1).max().unwrap_or(0))
+    }
+
+    /// Untags every hex of the region
+    pub fn clear_region(&mut self, region: RegionId) {
+        if let Some(tiles) = self.tiles.remove(&region) {
+            for hex in tiles.into_iter() {
+                self.regions.remove(&hex);
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.regions.clear();
+        self.tiles.clear();
+    }
+}
+
+impl<T, const W: usize, const H: usize> HexMap<T, W, H> {
+    pub fn regions(&self) -> &HexRegions {
+        &self.regions
+    }
+
+    pub fn regions_mut(&mut self) -> &mut HexRegions {
+        &mut self.regions
+    }
+
+    pub fn set_region(&mut self, hex: Axial, region: RegionId) {
+        self.regions.set(hex, region);
+    }
+
+    pub fn region_of(&self, hex: Axial) -> Option<RegionId> {
+        self.regions.get(hex)
+    }
+
+    /// The hexes in the region, ordered by q then r
+    pub fn tiles_in_region(&self, region: RegionId) -> impl Iterator<Item = Axial> + '_ {
+        self.regions.tiles(region)
+    }
+
+    /// Every hex connected to start through tiles matching the predicate, start included.
+    /// Empty if start has no tile or its tile doesn't match.
+    pub fn flood_fill(&self, start: Axial, predicate: fn(&T) -> bool) -> Vec<Axial> {
+        self.flood(start, |_, tile| predicate(tile))
+    }
+
+    fn flood<F: Fn(Axial, &T) -> bool>(&self, start: Axial, accept: F) -> Vec<Axial> {
+        let accepts = |hex: Axial| self.get_tile(hex.to_hex()).map_or(false, |tile| accept(hex, tile));
+
+        let mut filled = vec![];
+        if !accepts(start) {
+            return filled;
+        }
+
+        let mut visited = HashSet::new();
+        let mut frontier = VecDeque::new();
+        visited.insert(start);
+        frontier.push_back(start);
+
+        while let Some(hex) = frontier.pop_front() {
+            filled.push(hex);
+
+            for direction in HexDirection::ALL.iter() {
+                let neighbor = hex + direction.offset();
+                if !visited.contains(&neighbor) && accepts(neighbor) {
+                    visited.insert(neighbor);
+                    frontier.push_back(neighbor);
+                }
+            }
+        }
+
+        filled
+    }
+
+    /// Tags the hexes found by `flood_fill` as the region, returning how many were tagged
+    pub fn fill_region(&mut self, start: Axial, region: RegionId, predicate: fn(&T) -> bool) -> usize {
+        let filled = self.flood_fill(start, predicate);
+        for hex in filled.iter() {
+            self.regions.set(*hex, region);
+        }
+        filled.len()
+    }
+
+    /// Splits every tile matching the predicate into connected regions, each given a new id after `next_id`.
+    /// Tiles that are already in a region are left alone. Returns the new regions in the order they were made.
+    pub fn build_regions(&mut self, predicate: fn(&T) -> bool) -> Vec<RegionId> {
+        let mut starts: Vec<Axial> = self.chunks()
+            .flat_map(|chunk| chunk.tiles())
+            .filter(|(hex, tile)| predicate(tile) && self.regions.get(*hex).is_none())
+            .map(|(hex, _)| hex)
+            .collect();
+        starts.sort();
+
+        let mut created = vec![];
+        for start in starts.into_iter() {
+            if self.regions.get(start).is_some() {
+                continue;
+            }
+
+            let region = self.regions.next_id();
+            let regions = &self.regions;
+            let filled = self.flood(start, |hex, tile| predicate(tile) && regions.get(hex).is_none());
+            for hex in filled.into_iter() {
+                self.regions.set(hex, region);
+            }
+            created.push(region);
+        }
+
+        created
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(q: i32, r: i32) -> Axial {
+        Axial::new(q, r)
+    }
+
+    fn is_land(tile: &u8) -> bool {
+        *tile == 1
+    }
+
+    /// Two 2x3 islands of land at q 0..2 and q 4..6 with water between them
+    fn islands() -> HexMap<u8> {
+        let mut map = HexMap::new(36.0, 36.0, 28.0, 6.0, 0.0, 0.0);
+        for q in 0..6 {
+            for r in 0..3 {
+                let tile = if q == 2 || q == 3 { 0 } else { 1 };
+                map.set_tile(hex(q, r).to_hex(), tile);
+            }
+        }
+        map
+    }
+
+    #[test]
+    fn set_and_remove_keep_both_sides_in_sync() {
+        let mut regions = HexRegions::new();
+        regions.set(hex(0, 0), RegionId(1));
+        regions.set(hex(1, 0), RegionId(1));
+        regions.set(hex(0, 1), RegionId(2));
+        assert_eq!(regions.len(RegionId(1)), 2);
+        assert_eq!(regions.next_id(), RegionId(3));
+
+        // Moving a hex takes it out of its old region
+        regions.set(hex(1, 0), RegionId(2));
+        assert_eq!(regions.get(hex(1, 0)), Some(RegionId(2)));
+        assert_eq!(regions.tiles(RegionId(1)).collect::<Vec<_>>(), vec![hex(0, 0)]);
+        assert_eq!(regions.tiles(RegionId(2)).collect::<Vec<_>>(), vec![hex(0, 1), hex(1, 0)]);
+
+        // Emptied regions disappear
+        assert_eq!(regions.remove(hex(0, 0)), Some(RegionId(1)));
+        assert_eq!(regions.remove(hex(0, 0)), None);
+        assert_eq!(regions.ids(), vec![RegionId(2)]);
+        assert_eq!(regions.len(RegionId(1)), 0);
+
+        regions.clear_region(RegionId(2));
+        assert_eq!(regions.get(hex(0, 1)), None);
+        assert!(regions.ids().is_empty());
+        assert_eq!(regions.next_id(), RegionId(0));
+    }
+
+    #[test]
+    fn flood_fill_stays_on_one_island() {
+        let mut map = islands();
+
+        let mut filled = map.flood_fill(hex(0, 0), is_land);
+        filled.sort();
+        assert_eq!(filled, vec![hex(0, 0), hex(0, 1), hex(0, 2), hex(1, 0), hex(1, 1), hex(1, 2)]);
+
+        assert!(map.flood_fill(hex(2, 0), is_land).is_empty());
+        assert!(map.flood_fill(hex(-5, -5), is_land).is_empty());
+
+        assert_eq!(map.fill_region(hex(5, 2), RegionId(7), is_land), 6);
+        assert!(map.tiles_in_region(RegionId(7)).all(|hex| hex.q >= 4));
+    }
+
+    #[test]
+    fn build_regions_splits_disconnected_areas() {
+        let mut map = islands();
+        // Already tagged hexes are left alone and new ids start after them
+        map.set_region(hex(5, 2), RegionId(3));
+
+        let created = map.build_regions(is_land);
+        assert_eq!(created, vec![RegionId(4), RegionId(5)]);
+
+        assert_eq!(map.regions().len(RegionId(4)), 6);
+        assert!(map.tiles_in_region(RegionId(4)).all(|hex| hex.q < 2));
+        assert_eq!(map.regions().len(RegionId(5)), 5);
+        assert!(map.tiles_in_region(RegionId(5)).all(|hex| hex.q >= 4));
+        assert_eq!(map.region_of(hex(5, 2)), Some(RegionId(3)));
+        assert_eq!(map.region_of(hex(2, 1)), None);
+
+        // Everything is in a region now so nothing new is made
+        assert!(map.build_regions(is_land).is_empty());
+    }
+}
@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+use shipyard::*;
+use crate::pool::Inactive;
+use crate::rendering::{
+    Sprite,
+    draw_buffer::{
+        DrawBuffer,
+        DrawCommand,
+    },
+    tint::{
+        Flash,
+        Tint,
+        apply_tint,
+    },
+};
+use super::*;
+use super::fog::FogHidden;
+use super::maps::{
+    HexMaps,
+    MapHandle,
+};
+
+/// Caches the draw commands of every chunk in a HexMap and only rebuilds the chunks that are dirty.
+/// Tiles whose appearance depends on their neighbours (e.g. walls) may need the neighbouring chunk
+/// marked dirty by hand when a tile on the edge of a chunk changes.
+pub struct HexMapRenderer<T> {
+    cache: HashMap<ChunkPos, Vec<DrawCommand>>,
+    // The map the cache was built from when drawing from HexMaps
+    cached_map: Option<MapHandle>,
+
+    /// Pushes the commands needed to draw a single tile
+    pub tile_commands: fn(&HexMap<T>, Axial, &T, &mut Vec<DrawCommand>),
+}
+
+impl<T> HexMapRenderer<T> {
+    pub fn new(tile_commands: fn(&HexMap<T>, Axial, &T, &mut Vec<DrawCommand>)) -> Self {
+        HexMapRenderer {
+            cache: HashMap::new(),
+            cached_map: None,
+            tile_commands,
+        }
+    }
+
+    /// Rebuilds the cached commands of every dirty chunk and clears their dirty flags.
+    /// Returns the number of chunks that were rebuilt.
+    pub fn rebuild_dirty(&mut self, map: &mut HexMap<T>) -> usize {
+        self.cache.retain(|pos, _| map.does_chunk_exist(*pos));
+
+        let dirty = map.dirty_chunks();
+        for pos in dirty.iter() {
+            let chunk = map.get_chunk(*pos).unwrap();
+            let commands = self.cache.entry(*pos).or_insert_with(Vec::new);
+            commands.clear();
+
+            for (hex, tile) in chunk.tiles() {
+                (self.tile_commands)(map, hex, tile, commands);
+            }
+        }
+
+        for pos in dirty.iter() {
+            map.clear_dirty(*pos);
+        }
+
+        dirty.len()
+    }
+
+    /// Adds all cached commands to the DrawBuffer
+    pub fn draw(&self, draw_buffer: &mut DrawBuffer) {
+        for commands in self.cache.values() {
+            for command in commands.iter() {
+                draw_buffer.draw(*command);
+            }
+        }
+    }
+
+    /// Rebuilds any dirty chunks then draws the whole map
+    pub fn draw_hexmap(
+        mut map: UniqueViewMut<HexMap<T>>, 
+        mut renderer: UniqueViewMut<HexMapRenderer<T>>, 
+        mut draw_buffer: UniqueViewMut<DrawBuffer>,
+    ) where T: 'static + Send + Sync {
+        renderer.rebuild_dirty(&mut map);
+        renderer.draw(&mut draw_buffer);
+    }
+
+    /// Same as `draw_hexmap` for the active map of a HexMaps unique, rebuilding everything when the active map changes
+    pub fn draw_active_hexmap(
+        mut maps: UniqueViewMut<HexMaps<T>>, 
+        mut renderer: UniqueViewMut<HexMapRenderer<T>>, 
+        mut draw_buffer: UniqueViewMut<DrawBuffer>,
+    ) where T: 'static + Send + Sync {
+        let active = maps.active_handle();
+        if renderer.cached_map != active {
+            renderer.cache.clear();
+            renderer.cached_map = active;
+            if let Some(map) = maps.active_mut() {
+                map.mark_all_dirty();
+            }
+        }
+
+        if let Some(map) = maps.active_mut() {
+            renderer.rebuild_dirty(map);
+            renderer.draw(&mut draw_buffer);
+        }
+    }
+}
+
+/// Places an entity's Sprite on a hex of the HexMap unique instead of at its Transform, `draw_sprites` skips entities with this
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct HexPosition {
+    pub hex: Axial,
+    /// Defaults to `None`, which stands the entity on the tile's height. Heights below the tile's are raised to it
+    /// so that units never draw behind the tile they are on.
+    pub height: Option<u8>,
+}
+
+impl HexPosition {
+    pub fn new(hex: Axial) -> Self {
+        HexPosition {
+            hex,
+            height: None,
+        }
+    }
+
+    /// Sets the height, e.g. for flying units.
+    pub fn with_height(mut self, height: u8) -> Self {
+        self.height = Some(height);
+        self
+    }
+}
+
+/// Adds commands to DrawBuffer for every Sprite with a HexPosition. The sprite's position is an offset from
+/// `HexMap::elevated_position`, z included, and the command is drawn with `draw_iso` so it sorts with the tiles
+/// under `SortMode::Isometric` when tile commands use the same position. Entities with FogHidden or Inactive are skipped.
+/// The entity's Tint and Flash are applied like in `draw_sprites`.
+#[allow(clippy::too_many_arguments)]
+pub fn draw_hex_sprites<T: 'static + Send + Sync>(
+    map: UniqueView<HexMap<T>>,
+    positions: View<HexPosition>,
+    sprites: View<Sprite>,
+    tints: View<Tint>,
+    flashes: View<Flash>,
+    hidden: View<FogHidden>,
+    inactive: View<Inactive>,
+    mut draw_buffer: UniqueViewMut<DrawBuffer>,
+) {
+    for (id, (position, sprite)) in (&positions, &sprites).iter().with_id() {
+        if hidden.get(id).is_ok() || inactive.get(id).is_ok() {
+            continue;
+        }
+
+        let tile_height = map.get_tile(position.hex.to_hex()).map_or(0, |tile| (map.get_height)(tile));
+        let height = position.height.map_or(tile_height, |height| height.max(tile_height));
+
+        let mut command = sprite.0;
+        if command.sequence.is_none() {
+            command.sequence = Some(id.uindex() as u64);
+        }
+
+        apply_tint(&mut command, tints.get(id).ok(), flashes.get(id).ok());
+
+        // Ties with the tile itself go to the unit
+        command.position += map.elevated_position(position.hex, height) + Vec3::new(0.0, 0.0, 0.01);
+        command.draw_iso = true;
+        draw_buffer.draw(command);
+    }
+}
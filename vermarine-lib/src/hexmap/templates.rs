@@ -0,0 +1,156 @@
+use super::*;
+
+/// `length` hexes in a straight line, not including the origin
+pub fn line(direction: HexDirection, length: u32) -> Vec<Axial> {
+    (1..=length as i32)
+        .map(|step| direction.offset() * step)
+        .collect()
+}
+
+/// Every hex within `radius` steps, including the origin
+pub fn blast(radius: u32) -> Vec<Axial> {
+    donut(0, radius)
+}
+
+/// The hexes exactly `radius` steps away, going clockwise from the north west corner
+pub fn ring(radius: u32) -> Vec<Axial> {
+    if radius == 0 {
+        return vec![Axial::new(0, 0)];
+    }
+
+    let radius = radius as i32;
+    let mut hexes = vec![];
+    let mut hex = HexDirection::NorthWest.offset() * radius;
+    // Walking along an edge of the ring goes two directions clockwise from the corner it started at
+    for corner in HexDirection::ALL.iter() {
+        let step = corner.rotate(2).offset();
+        for _ in 0..radius {
+            hexes.push(hex);
+            hex += step;
+        }
+    }
+    hexes
+}
+
+/// The hexes between `inner` and `outer` steps away, both inclusive
+pub fn donut(inner: u32, outer: u32) -> Vec<Axial> {
+    (inner..=outer)
+        .flat_map(ring)
+        .collect()
+}
+
+/// A 60 degree cone `length` hexes long centered on the direction, not including the origin
+pub fn cone(direction: HexDirection, length: u32) -> Vec<Axial> {
+    let left = direction.rotate(-2).offset();
+    let right = direction.rotate(2).offset();
+
+    let mut hexes = vec![];
+    for distance in 1..=length as i32 {
+        let center = direction.offset() * distance;
+        hexes.push(center);
+        for side in 1..=distance / 2 {
+            hexes.push(center + left * side);
+            hexes.push(center + right * side);
+        }
+    }
+    hexes
+}
+
+/// Rotates every offset of a template around the origin by 60 degree steps, positive steps go clockwise.
+/// Templates that face a direction are built facing it, so this is mostly useful for custom templates.
+pub fn rotate(template: &[Axial], steps: i32) -> Vec<Axial> {
+    let steps = steps.rem_euclid(6);
+    template
+        .iter()
+        .map(|hex| (0..steps).fold(*hex, |hex, _| hex.rotate_right()))
+        .collect()
+}
+
+/// Rotates a template built facing `from` to face `to`
+pub fn rotate_to(template: &[Axial], from: HexDirection, to: HexDirection) -> Vec<Axial> {
+    let index = |direction: HexDirection| HexDirection::ALL.iter().position(|d| *d == direction).unwrap() as i32;
+    rotate(template, index(to) - index(from))
+}
+
+/// Moves the offsets to be relative to the origin
+pub fn translate(template: &[Axial], origin: Axial) -> Vec<Axial> {
+    template
+        .iter()
+        .map(|hex| *hex + origin)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn set(hexes: &[Axial]) -> HashSet<Axial> {
+        hexes.iter().copied().collect()
+    }
+
+    fn origin() -> Axial {
+        Axial::new(0, 0)
+    }
+
+    #[test]
+    fn lines() {
+        assert_eq!(line(HexDirection::East, 3), vec![Axial::new(1, 0), Axial::new(2, 0), Axial::new(3, 0)]);
+        assert_eq!(line(HexDirection::NorthWest, 2), vec![Axial::new(0, -1), Axial::new(0, -2)]);
+        assert!(line(HexDirection::West, 0).is_empty());
+    }
+
+    #[test]
+    fn rings() {
+        assert_eq!(ring(0), vec![origin()]);
+        for radius in 1..5 {
+            let hexes = ring(radius);
+            assert_eq!(hexes.len(), 6 * radius as usize);
+            assert_eq!(set(&hexes).len(), hexes.len());
+            assert!(hexes.iter().all(|hex| hex.distance(origin()) == radius as i32));
+        }
+        assert_eq!(ring(2)[0], Axial::new(0, -2));
+    }
+
+    #[test]
+    fn blasts_and_donuts() {
+        for radius in 0..5 {
+            let hexes = blast(radius);
+            let radius = radius as usize;
+            assert_eq!(hexes.len(), 3 * radius * (radius + 1) + 1);
+            assert_eq!(set(&hexes).len(), hexes.len());
+            assert!(hexes.iter().all(|hex| hex.distance(origin()) <= radius as i32));
+        }
+
+        let hexes = donut(2, 3);
+        assert_eq!(hexes.len(), 12 + 18);
+        assert!(hexes.iter().all(|hex| (2..=3).contains(&hex.distance(origin()))));
+    }
+
+    #[test]
+    fn cones() {
+        let hexes = cone(HexDirection::East, 4);
+        // 1 + 3 + 3 + 5 hexes at each distance
+        assert_eq!(hexes.len(), 12);
+        assert_eq!(set(&hexes).len(), hexes.len());
+        assert!(set(&hexes).is_superset(&set(&line(HexDirection::East, 4))));
+        assert!(!set(&hexes).contains(&origin()));
+        assert!(hexes.iter().all(|hex| hex.distance(origin()) <= 4 && hex.q > 0));
+
+        // Every direction is the east cone rotated
+        for direction in HexDirection::ALL.iter() {
+            assert_eq!(set(&cone(*direction, 4)), set(&rotate_to(&hexes, HexDirection::East, *direction)));
+        }
+    }
+
+    #[test]
+    fn rotating_and_translating() {
+        let template = cone(HexDirection::NorthEast, 3);
+        assert_eq!(rotate(&template, 6), template);
+        assert_eq!(rotate(&template, -1), rotate(&template, 5));
+        assert_eq!(rotate(&line(HexDirection::East, 2), 1), line(HexDirection::SouthEast, 2));
+
+        let moved = translate(&line(HexDirection::East, 2), Axial::new(3, -1));
+        assert_eq!(moved, vec![Axial::new(4, -1), Axial::new(5, -1)]);
+    }
+}
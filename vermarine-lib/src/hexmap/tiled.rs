@@ -0,0 +1,474 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+use serde::Deserialize;
+use tetra::{
+    math::Vec2,
+    TetraError,
+};
+use super::*;
+
+/// The bits Tiled sets on a gid when the tile is flipped or rotated
+const FLIP_FLAGS: u32 = 0xF000_0000;
+
+/// Why a Tiled map couldn't be imported
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TiledError {
+    /// The file isn't valid Tiled JSON, with serde_json's description of the problem
+    Json(String),
+    /// The map's orientation isn't hexagonal, with the orientation it has
+    NotHexagonal(String),
+    /// HexMap draws pointy topped hexes in rows, which is Tiled's stagger axis "y"
+    UnsupportedStagger(String),
+    /// The tile layer is base64 encoded or compressed, save the map with the CSV layer format instead
+    EncodedLayer(String),
+}
+
+impl std::fmt::Display for TiledError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            TiledError::Json(error) => write!(f, "Invalid Tiled JSON: {}", error),
+            TiledError::NotHexagonal(orientation) => write!(f, "Tiled map is {}, not hexagonal", orientation),
+            TiledError::UnsupportedStagger(axis) => write!(f, "Tiled map is staggered along {}, only y is supported", axis),
+            TiledError::EncodedLayer(layer) => write!(f, "Tiled layer \"{}\" is encoded, save it as CSV", layer),
+        }
+    }
+}
+
+impl std::error::Error for TiledError {}
+
+/// A tile layer, or one chunk of a tile layer in an infinite map. Gids are row by row with the flip flags removed,
+/// 0 means there is no tile.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TiledTiles {
+    pub layer: String,
+    /// The offset coordinate of the first tile
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub gids: Vec<u32>,
+}
+
+/// An object from an object layer, for spawning entities. `position` is in Tiled's pixels and `hex` is the hex
+/// whose row and column contain the position.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TiledSpawn {
+    pub id: u32,
+    pub layer: String,
+    pub name: String,
+    /// The object's class, called type before Tiled 1.9
+    pub kind: String,
+    pub position: Vec2<f32>,
+    pub size: Vec2<f32>,
+    pub hex: Axial,
+    /// Custom properties, with anything other than strings in their JSON form
+    pub properties: HashMap<String, String>,
+}
+
+/// A hexagonal map made in Tiled and saved as JSON, with CSV tile layers. Tiled stores tiles by offset coordinates,
+/// which are converted to axial using the map's stagger index.
+///
+/// ```ignore
+/// let tiled = TiledMap::load("assets/maps/island.json")?;
+/// tiled.fill_hexmap(&mut map, |layer, gid| match (layer, gid) {
+///     ("ground", 1) => Some(Tile::Grass),
+///     ("ground", 2) => Some(Tile::Water),
+///     _ => None,
+/// });
+/// for spawn in tiled.spawns() { ... }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct TiledMap {
+    pub tile_width: u32,
+    pub tile_height: u32,
+    pub hex_side_length: u32,
+    /// Whether odd rows are shifted right, otherwise even rows are
+    pub stagger_odd: bool,
+    pub tiles: Vec<TiledTiles>,
+    pub spawns: Vec<TiledSpawn>,
+}
+
+impl TiledMap {
+    pub fn from_json(source: &str) -> Result<Self, TiledError> {
+        let raw: RawMap = serde_json::from_str(source).map_err(|error| TiledError::Json(error.to_string()))?;
+
+        if raw.orientation != "hexagonal" {
+            return Err(TiledError::NotHexagonal(raw.orientation));
+        }
+        let axis = raw.staggeraxis.unwrap_or_else(|| "y".to_string());
+        if axis != "y" {
+            return Err(TiledError::UnsupportedStagger(axis));
+        }
+
+        let mut map = TiledMap {
+            tile_width: raw.tilewidth,
+            tile_height: raw.tileheight,
+            hex_side_length: raw.hexsidelength,
+            stagger_odd: raw.staggerindex.as_deref() != Some("even"),
+            tiles: vec![],
+            spawns: vec![],
+        };
+        map.add_layers(raw.layers)?;
+
+        Ok(map)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> tetra::Result<Self> {
+        let path = path.as_ref();
+        let to_error = |reason| TetraError::FailedToLoadAsset { reason, path: path.to_path_buf() };
+
+        let source = std::fs::read_to_string(path).map_err(to_error)?;
+        Self::from_json(&source)
+            .map_err(|error| to_error(io::Error::new(io::ErrorKind::InvalidData, error.to_string())))
+    }
+
+    fn add_layers(&mut self, layers: Vec<RawLayer>) -> Result<(), TiledError> {
+        for layer in layers.into_iter() {
+            match layer {
+                RawLayer::TileLayer { name, x, y, width, data, chunks, encoding } => {
+                    if encoding.map_or(false, |encoding| encoding != "csv") {
+                        return Err(TiledError::EncodedLayer(name));
+                    }
+
+                    // Infinite maps store their tiles in chunks instead of data
+                    let parts = data.map(|data| vec![RawChunk { x, y, width, data }]).unwrap_or(chunks);
+                    for part in parts.into_iter() {
+                        let gids = match part.data {
+                            RawData::Csv(gids) => gids,
+                            RawData::Encoded(_) => return Err(TiledError::EncodedLayer(name)),
+                        };
+
+                        self.tiles.push(TiledTiles {
+                            layer: name.clone(),
+                            x: part.x,
+                            y: part.y,
+                            width: part.width,
+                            gids: gids.into_iter().map(|gid| gid & !FLIP_FLAGS).collect(),
+                        });
+                    }
+                },
+                RawLayer::ObjectGroup { name, objects } => {
+                    for object in objects.into_iter() {
+                        let position = Vec2::new(object.x, object.y);
+                        let hex = self.pixel_to_axial(position);
+                        self.spawns.push(TiledSpawn {
+                            id: object.id,
+                            layer: name.clone(),
+                            name: object.name,
+                            kind: object.kind,
+                            position,
+                            size: Vec2::new(object.width, object.height),
+                            hex,
+                            properties: object.properties.into_iter()
+                                .map(|property| {
+                                    let value = match property.value {
+                                        serde_json::Value::String(value) => value,
+                                        value => value.to_string(),
+                                    };
+                                    (property.name, value)
+                                })
+                                .collect(),
+                        });
+                    }
+                },
+                RawLayer::Group { layers } => self.add_layers(layers)?,
+                RawLayer::ImageLayer {} => {},
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Converts Tiled's column and row to axial coordinates
+    pub fn offset_to_axial(&self, column: i32, row: i32) -> Axial {
+        let shifted = if self.stagger_odd { row & 1 } else { -(row & 1) };
+        Axial::new(column - (row - shifted).div_euclid(2), row)
+    }
+
+    /// The hex whose row and column contain the point in Tiled's pixels, ignoring the corners hexes share with the row above
+    pub fn pixel_to_axial(&self, position: Vec2<f32>) -> Axial {
+        let row_height = ((self.tile_height + self.hex_side_length) as f32 / 2.0).max(1.0);
+        let row = (position.y / row_height).floor() as i32;
+
+        let shifted = (row & 1 == 1) == self.stagger_odd;
+        let x = if shifted { position.x - self.tile_width as f32 / 2.0 } else { position.x };
+        let column = (x / (self.tile_width as f32).max(1.0)).floor() as i32;
+
+        self.offset_to_axial(column, row)
+    }
+
+    /// Sets a tile on the map for every gid the mapper turns into a tile, later layers replacing earlier ones.
+    /// The mapper is given the layer's name and the gid. Returns how many tiles were set.
+    pub fn fill_hexmap<T, const W: usize, const H: usize>(&self, map: &mut HexMap<T, W, H>, mapper: fn(&str, u32) -> Option<T>) -> usize {
+        let mut count = 0;
+        for tiles in self.tiles.iter() {
+            let width = tiles.width.max(1) as usize;
+            for (index, gid) in tiles.gids.iter().enumerate() {
+                if *gid == 0 {
+                    continue;
+                }
+
+                if let Some(tile) = mapper(&tiles.layer, *gid) {
+                    let column = tiles.x + (index % width) as i32;
+                    let row = tiles.y + (index / width) as i32;
+                    map.set_tile(self.offset_to_axial(column, row).to_hex(), tile);
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+
+    /// Every object of every object layer, in the order Tiled saved them
+    pub fn spawns(&self) -> &[TiledSpawn] {
+        &self.spawns
+    }
+}
+
+//
+// Tiled's JSON format
+
+#[derive(Deserialize)]
+struct RawMap {
+    orientation: String,
+    #[serde(default)]
+    staggeraxis: Option<String>,
+    #[serde(default)]
+    staggerindex: Option<String>,
+    tilewidth: u32,
+    tileheight: u32,
+    #[serde(default)]
+    hexsidelength: u32,
+    #[serde(default)]
+    layers: Vec<RawLayer>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type")]
+enum RawLayer {
+    #[serde(rename = "tilelayer")]
+    TileLayer {
+        name: String,
+        #[serde(default)]
+        x: i32,
+        #[serde(default)]
+        y: i32,
+        #[serde(default)]
+        width: u32,
+        #[serde(default)]
+        data: Option<RawData>,
+        #[serde(default)]
+        chunks: Vec<RawChunk>,
+        #[serde(default)]
+        encoding: Option<String>,
+    },
+    #[serde(rename = "objectgroup")]
+    ObjectGroup {
+        name: String,
+        #[serde(default)]
+        objects: Vec<RawObject>,
+    },
+    #[serde(rename = "group")]
+    Group {
+        #[serde(default)]
+        layers: Vec<RawLayer>,
+    },
+    #[serde(rename = "imagelayer")]
+    ImageLayer {},
+}
+
+#[derive(Deserialize)]
+struct RawChunk {
+    x: i32,
+    y: i32,
+    width: u32,
+    data: RawData,
+}
+
+/// CSV layers are saved as an array of gids, base64 ones as a string
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawData {
+    Csv(Vec<u32>),
+    Encoded(String),
+}
+
+#[derive(Deserialize)]
+struct RawObject {
+    id: u32,
+    #[serde(default)]
+    name: String,
+    #[serde(default, rename = "type", alias = "class")]
+    kind: String,
+    x: f32,
+    y: f32,
+    #[serde(default)]
+    width: f32,
+    #[serde(default)]
+    height: f32,
+    #[serde(default)]
+    properties: Vec<RawProperty>,
+}
+
+#[derive(Deserialize)]
+struct RawProperty {
+    name: String,
+    value: serde_json::Value,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAP: &str = r#"{
+        "orientation": "hexagonal",
+        "staggeraxis": "y",
+        "staggerindex": "odd",
+        "tilewidth": 32,
+        "tileheight": 32,
+        "hexsidelength": 16,
+        "layers": [
+            { "type": "tilelayer", "name": "ground", "x": 0, "y": 0, "width": 3, "height": 2, "data": [1, 2, 0, 0, 2147483649, 3] },
+            { "type": "group", "name": "decoration", "layers": [
+                { "type": "tilelayer", "name": "trees", "chunks": [
+                    { "x": -16, "y": -16, "width": 2, "height": 2, "data": [0, 4, 5, 0] }
+                ] }
+            ] },
+            { "type": "imagelayer", "name": "sky", "image": "sky.png" },
+            { "type": "objectgroup", "name": "spawns", "objects": [
+                { "id": 7, "name": "hero", "type": "player", "x": 20, "y": 30, "width": 32, "height": 32, "properties": [
+                    { "name": "health", "type": "int", "value": 10 },
+                    { "name": "team", "type": "string", "value": "blue" }
+                ] },
+                { "id": 8, "class": "chest", "x": 10, "y": -10 }
+            ] }
+        ]
+    }"#;
+
+    fn staggered(stagger_odd: bool) -> TiledMap {
+        TiledMap {
+            tile_width: 32,
+            tile_height: 32,
+            hex_side_length: 16,
+            stagger_odd,
+            tiles: vec![],
+            spawns: vec![],
+        }
+    }
+
+    #[test]
+    fn csv_layers_and_chunks() {
+        let tiled = TiledMap::from_json(MAP).unwrap();
+        assert!(tiled.stagger_odd);
+        assert_eq!(tiled.tiles, vec![
+            TiledTiles { layer: "ground".to_string(), x: 0, y: 0, width: 3, gids: vec![1, 2, 0, 0, 1, 3] },
+            TiledTiles { layer: "trees".to_string(), x: -16, y: -16, width: 2, gids: vec![0, 4, 5, 0] },
+        ]);
+
+        let mut map: HexMap<u32> = HexMap::new(36.0, 36.0, 28.0, 6.0, 0.0, 0.0);
+        assert_eq!(tiled.fill_hexmap(&mut map, |_, gid| Some(gid)), 6);
+
+        let tile = |q, r| map.get_tile(Axial::new(q, r).to_hex()).copied();
+        assert_eq!(tile(0, 0), Some(1));
+        assert_eq!(tile(1, 0), Some(2));
+        assert_eq!(tile(1, 1), Some(1));
+        assert_eq!(tile(2, 1), Some(3));
+        assert_eq!(tile(-7, -16), Some(4));
+        assert_eq!(tile(-8, -15), Some(5));
+        assert_eq!(tile(0, 1), None);
+
+        let mut trees: HexMap<u32> = HexMap::new(36.0, 36.0, 28.0, 6.0, 0.0, 0.0);
+        let count = tiled.fill_hexmap(&mut trees, |layer, gid| if layer == "trees" { Some(gid) } else { None });
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn object_layers_become_spawns() {
+        let tiled = TiledMap::from_json(MAP).unwrap();
+        let spawns = tiled.spawns();
+        assert_eq!(spawns.len(), 2);
+
+        let hero = &spawns[0];
+        assert_eq!((hero.id, hero.layer.as_str(), hero.name.as_str(), hero.kind.as_str()), (7, "spawns", "hero", "player"));
+        assert_eq!(hero.position, Vec2::new(20.0, 30.0));
+        assert_eq!(hero.size, Vec2::new(32.0, 32.0));
+        assert_eq!(hero.hex, Axial::new(0, 1));
+        assert_eq!(hero.properties.get("health").map(String::as_str), Some("10"));
+        assert_eq!(hero.properties.get("team").map(String::as_str), Some("blue"));
+
+        // Tiled 1.9 renamed type to class
+        let chest = &spawns[1];
+        assert_eq!(chest.kind, "chest");
+        assert_eq!(chest.hex, Axial::new(0, -1));
+        assert!(chest.properties.is_empty());
+    }
+
+    #[test]
+    fn encoded_layers_are_rejected() {
+        let layer = r#"{
+            "orientation": "hexagonal", "tilewidth": 32, "tileheight": 32, "hexsidelength": 16,
+            "layers": [{ "type": "tilelayer", "name": "ground", "width": 2, "encoding": "base64", "data": "AQAAAAIAAAA=" }]
+        }"#;
+        assert_eq!(TiledMap::from_json(layer), Err(TiledError::EncodedLayer("ground".to_string())));
+
+        let chunks = r#"{
+            "orientation": "hexagonal", "tilewidth": 32, "tileheight": 32, "hexsidelength": 16,
+            "layers": [{ "type": "tilelayer", "name": "trees", "encoding": "base64", "compression": "zlib", "chunks": [
+                { "x": 0, "y": 0, "width": 16, "height": 16, "data": "eJxjYGBgAAAABAAB" }
+            ] }]
+        }"#;
+        assert_eq!(TiledMap::from_json(chunks), Err(TiledError::EncodedLayer("trees".to_string())));
+
+        let orthogonal = r#"{ "orientation": "orthogonal", "tilewidth": 32, "tileheight": 32 }"#;
+        assert_eq!(TiledMap::from_json(orthogonal), Err(TiledError::NotHexagonal("orthogonal".to_string())));
+
+        let columns = r#"{ "orientation": "hexagonal", "staggeraxis": "x", "tilewidth": 32, "tileheight": 32 }"#;
+        assert_eq!(TiledMap::from_json(columns), Err(TiledError::UnsupportedStagger("x".to_string())));
+
+        assert!(matches!(TiledMap::from_json("{"), Err(TiledError::Json(_))));
+    }
+
+    #[test]
+    fn offset_to_axial_staggers() {
+        let odd = staggered(true);
+        assert_eq!(odd.offset_to_axial(0, 0), Axial::new(0, 0));
+        assert_eq!(odd.offset_to_axial(0, 1), Axial::new(0, 1));
+        assert_eq!(odd.offset_to_axial(0, 2), Axial::new(-1, 2));
+        assert_eq!(odd.offset_to_axial(0, -1), Axial::new(1, -1));
+        assert_eq!(odd.offset_to_axial(0, -2), Axial::new(1, -2));
+        assert_eq!(odd.offset_to_axial(3, -3), Axial::new(5, -3));
+
+        let even = staggered(false);
+        assert_eq!(even.offset_to_axial(0, 0), Axial::new(0, 0));
+        assert_eq!(even.offset_to_axial(0, 1), Axial::new(-1, 1));
+        assert_eq!(even.offset_to_axial(0, 2), Axial::new(-1, 2));
+        assert_eq!(even.offset_to_axial(0, -1), Axial::new(0, -1));
+        assert_eq!(even.offset_to_axial(3, -3), Axial::new(4, -3));
+
+        // The same column in neighbouring rows is always adjacent, on whichever side the stagger puts it
+        for tiled in [odd, even].iter() {
+            for row in -4..4 {
+                let above = tiled.offset_to_axial(2, row).to_hex();
+                let below = tiled.offset_to_axial(2, row + 1).to_hex();
+                assert!(above.neighbors().contains(&below), "rows {} and {}", row, row + 1);
+            }
+        }
+    }
+
+    #[test]
+    fn pixel_to_axial_finds_the_row_and_column() {
+        // Rows are (32 + 16) / 2 = 24 pixels apart, shifted rows start half a tile to the right
+        let odd = staggered(true);
+        assert_eq!(odd.pixel_to_axial(Vec2::new(10.0, 10.0)), Axial::new(0, 0));
+        assert_eq!(odd.pixel_to_axial(Vec2::new(40.0, 10.0)), Axial::new(1, 0));
+        assert_eq!(odd.pixel_to_axial(Vec2::new(10.0, 30.0)), Axial::new(-1, 1));
+        assert_eq!(odd.pixel_to_axial(Vec2::new(20.0, 30.0)), Axial::new(0, 1));
+        assert_eq!(odd.pixel_to_axial(Vec2::new(10.0, -10.0)), Axial::new(0, -1));
+
+        let even = staggered(false);
+        assert_eq!(even.pixel_to_axial(Vec2::new(10.0, 10.0)), Axial::new(-1, 0));
+        assert_eq!(even.pixel_to_axial(Vec2::new(20.0, 10.0)), Axial::new(0, 0));
+        assert_eq!(even.pixel_to_axial(Vec2::new(10.0, 30.0)), Axial::new(-1, 1));
+        assert_eq!(even.pixel_to_axial(Vec2::new(10.0, -10.0)), Axial::new(0, -1));
+    }
+}
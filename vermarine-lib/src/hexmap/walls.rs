@@ -0,0 +1,91 @@
+use crate::rendering::draw_buffer::DrawCommand;
+use super::*;
+
+/// A side of a tile that is taller than its neighbour in that direction, missing neighbours count as height 0
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct WallFace {
+    pub direction: HexDirection,
+    /// The height of the tile
+    pub top: u8,
+    /// The height of the neighbour
+    pub bottom: u8,
+    /// How many wall sprites of `wall_vert_step` pixels it takes to cover the face
+    pub segments: u32,
+}
+
+/// Where to draw one wall sprite of a WallFace, with `draw_iso` set
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct WallSegment {
+    pub direction: HexDirection,
+    /// 0 is the segment just under the top of the tile, counting down
+    pub index: u32,
+    pub position: Vec3<f32>,
+}
+
+impl<T, const W: usize, const H: usize> HexMap<T, W, H> {
+    /// The exposed side faces of the tile at the hex, in the order of `HexDirection::ALL`.
+    /// Empty if there is no tile at the hex.
+    pub fn wall_faces(&self, hex: Axial) -> Vec<WallFace> {
+        let top = match self.get_tile(hex.to_hex()) {
+            Some(tile) => (self.get_height)(tile),
+            None => return vec![],
+        };
+
+        HexDirection::ALL.iter()
+            .filter_map(|direction| {
+                let bottom = self.get_tile((hex + direction.offset()).to_hex()).map_or(0, |tile| (self.get_height)(tile));
+                if bottom >= top {
+                    return None;
+                }
+
+                Some(WallFace {
+                    direction: *direction,
+                    top,
+                    bottom,
+                    segments: self.wall_segment_count(top - bottom),
+                })
+            })
+            .collect()
+    }
+
+    /// How many wall sprites cover a drop of `levels` heights
+    pub fn wall_segment_count(&self, levels: u8) -> u32 {
+        if self.wall_vert_step <= 0.0 {
+            return levels as u32;
+        }
+
+        (levels as f32 * self.hex_depth_step / self.wall_vert_step).ceil() as u32
+    }
+
+    /// Every wall sprite needed for the tile at the hex. Segments are positioned like `elevated_position`, the
+    /// z going down by `wall_vert_step` per segment from the top of the tile and the y offset by `wall_vert_offset`.
+    pub fn wall_segments(&self, hex: Axial) -> Vec<WallSegment> {
+        let mut segments = vec![];
+
+        for face in self.wall_faces(hex).into_iter() {
+            let top = self.elevated_position(hex, face.top) + Vec3::new(0.0, self.wall_vert_offset, 0.0);
+            for index in 0..face.segments {
+                segments.push(WallSegment {
+                    direction: face.direction,
+                    index,
+                    position: top - Vec3::new(0.0, 0.0, index as f32 * self.wall_vert_step),
+                });
+            }
+        }
+
+        segments
+    }
+
+    /// Pushes a command for every wall segment of the tile, for use in `HexMapRenderer::tile_commands`.
+    /// The command's position is an offset from the segment's position and it is set to `draw_iso`,
+    /// `segment_command` can return `None` to skip faces the camera never sees.
+    pub fn wall_commands(&self, hex: Axial, segment_command: fn(&WallSegment) -> Option<DrawCommand>, commands: &mut Vec<DrawCommand>) {
+        for segment in self.wall_segments(hex).iter() {
+            if let Some(mut command) = segment_command(segment) {
+                command.position += segment.position;
+                command.draw_iso = true;
+                commands.push(command);
+            }
+        }
+    }
+}
@@ -5,6 +5,25 @@ pub mod rendering;
 pub mod components;
 pub mod pushdown_automaton_state;
 pub mod hexmap;
+pub mod debug;
+pub mod gizmos;
+pub mod picking;
+pub mod events;
+pub mod projectiles;
+pub mod combat;
+pub mod random;
+pub mod net;
+pub mod turns;
+pub mod time;
+pub mod schedule;
+pub mod pause;
+pub mod ctx_proxy;
+pub mod pool;
+pub mod logging;
+pub mod perf;
+pub mod window_events;
+pub mod text_input;
+pub mod prelude;
 
 pub use tetra;
 pub use shipyard;
\ No newline at end of file
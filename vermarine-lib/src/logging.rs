@@ -0,0 +1,174 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        Arc,
+        Mutex,
+    },
+};
+use log::{
+    Level,
+    LevelFilter,
+    Log,
+    Metadata,
+    Record,
+};
+use shipyard::*;
+use tetra::{
+    graphics::{
+        text::{
+            Font,
+            Text,
+        },
+        Color,
+        DrawParams,
+    },
+    math::Vec2,
+    Context,
+};
+use crate::debug::{
+    DebugFlag,
+    DebugFlags,
+};
+
+/// Log targets used by the engine, e.g. `RUST_LOG=vermarine::physics=warn` with env_logger
+pub const PHYSICS: &str = "vermarine::physics";
+pub const RENDER: &str = "vermarine::render";
+pub const ASSETS: &str = "vermarine::assets";
+pub const NET: &str = "vermarine::net";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogLine {
+    pub level: Level,
+    pub target: String,
+    pub message: String,
+}
+
+type SharedLines = Arc<Mutex<VecDeque<LogLine>>>;
+
+/// Forwards every record to the LogBuffer it was created with, and to stderr for warnings and errors
+struct BufferLogger {
+    lines: SharedLines,
+    capacity: usize,
+    level: LevelFilter,
+}
+
+impl Log for BufferLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        if record.level() <= Level::Warn {
+            eprintln!("[{}] {}: {}", record.target(), record.level(), record.args());
+        }
+
+        let mut lines = self.lines.lock().unwrap();
+        if lines.len() >= self.capacity {
+            lines.pop_front();
+        }
+        lines.push_back(LogLine {
+            level: record.level(),
+            target: record.target().to_string(),
+            message: record.args().to_string(),
+        });
+    }
+
+    fn flush(&self) {}
+}
+
+/// The most recent log lines, filled by the logger installed by `add_log_buffer` and shown by `draw_log_console`
+/// while `DebugFlag::LogConsole` is on
+pub struct LogBuffer {
+    lines: SharedLines,
+
+    /// How many lines the console shows. Defaults to 20.
+    pub console_lines: usize,
+    /// Defaults to 16.0
+    pub line_height: f32,
+    pub position: Vec2<f32>,
+}
+
+impl LogBuffer {
+    /// Creates a buffer and installs a logger feeding it as the global logger.
+    /// If another logger has already been installed the buffer stays empty.
+    pub fn install(capacity: usize, level: LevelFilter) -> Self {
+        let lines = Arc::new(Mutex::new(VecDeque::with_capacity(capacity)));
+        let logger = BufferLogger {
+            lines: Arc::clone(&lines),
+            capacity: capacity.max(1),
+            level,
+        };
+
+        if log::set_boxed_logger(Box::new(logger)).is_ok() {
+            log::set_max_level(level);
+        }
+
+        LogBuffer {
+            lines,
+            console_lines: 20,
+            line_height: 16.0,
+            position: Vec2::new(4.0, 4.0),
+        }
+    }
+
+    /// Sets how many lines the console shows.
+    pub fn with_console_lines(mut self, console_lines: usize) -> Self {
+        self.console_lines = console_lines;
+        self
+    }
+
+    /// Copies of the buffered lines, oldest first
+    pub fn lines(&self) -> Vec<LogLine> {
+        self.lines.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// The newest `count` lines, oldest first
+    pub fn latest(&self, count: usize) -> Vec<LogLine> {
+        let lines = self.lines.lock().unwrap();
+        lines.iter().skip(lines.len().saturating_sub(count)).cloned().collect()
+    }
+
+    pub fn clear(&self) {
+        self.lines.lock().unwrap().clear();
+    }
+
+    fn level_color(level: Level) -> Color {
+        match level {
+            Level::Error => Color::rgb(1.0, 0.3, 0.3),
+            Level::Warn => Color::rgb(1.0, 0.8, 0.2),
+            Level::Info => Color::WHITE,
+            Level::Debug | Level::Trace => Color::rgb(0.6, 0.6, 0.6),
+        }
+    }
+}
+
+/// Draws the newest lines of the LogBuffer over everything else while `DebugFlag::LogConsole` is on.
+/// Run after the DrawBuffer has been flushed, the font is the game's as the engine doesn't ship one.
+pub fn draw_log_console(ctx: &mut Context, font: &Font, buffer: UniqueView<LogBuffer>, flags: UniqueView<DebugFlags>) {
+    if !flags.get(DebugFlag::LogConsole) {
+        return;
+    }
+
+    for (row, line) in buffer.latest(buffer.console_lines).iter().enumerate() {
+        let mut text = Text::new(format!("[{}] {}", line.target, line.message), font.clone());
+        text.draw(ctx, DrawParams::new()
+            .position(buffer.position + Vec2::new(0.0, row as f32 * buffer.line_height))
+            .color(LogBuffer::level_color(line.level))
+        );
+    }
+}
+
+/// Dummy trait to allow adding a method to World
+pub trait LogBufferCreator {
+    fn add_log_buffer(&mut self, capacity: usize, level: LevelFilter);
+}
+
+impl LogBufferCreator for World {
+    fn add_log_buffer(&mut self, capacity: usize, level: LevelFilter) {
+        self.add_unique(LogBuffer::install(capacity, level));
+    }
+}
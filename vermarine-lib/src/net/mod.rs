@@ -0,0 +1,112 @@
+pub mod udp;
+pub mod replication;
+
+use std::{
+    io,
+    net::SocketAddr,
+};
+use shipyard::*;
+
+/// Identifies a remote peer for the lifetime of its connection
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct PeerId(pub u32);
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum NetEvent {
+    Connected(PeerId),
+    Disconnected(PeerId),
+    Message(PeerId, Vec<u8>),
+}
+
+/// Anything that can move byte packets between peers. Implement this to plug a different
+/// transport (e.g. a relay service or a websocket) into the Network unique.
+pub trait Transport: Send + Sync {
+    /// Starts connecting to the address, a `NetEvent::Connected` is sent once the peer answers
+    fn connect(&mut self, addr: SocketAddr) -> io::Result<PeerId>;
+
+    fn disconnect(&mut self, peer: PeerId);
+
+    fn send(&mut self, peer: PeerId, data: &[u8]) -> io::Result<()>;
+
+    /// Receives everything that has arrived since the last poll without blocking
+    fn poll(&mut self, events: &mut Vec<NetEvent>) -> io::Result<()>;
+
+    fn peers(&self) -> Vec<PeerId>;
+}
+
+/// Transport and message queues for systems to use, poll it once per frame with `Network::poll_system`
+pub struct Network {
+    transport: Box<dyn Transport>,
+
+    /// Connection events and messages received during the last poll
+    pub events: Vec<NetEvent>,
+    /// The last error returned by the transport while polling, if any
+    pub last_error: Option<io::Error>,
+}
+
+impl Network {
+    pub fn new<T: Transport + 'static>(transport: T) -> Self {
+        Network {
+            transport: Box::new(transport),
+            events: vec![],
+            last_error: None,
+        }
+    }
+
+    pub fn connect(&mut self, addr: SocketAddr) -> io::Result<PeerId> {
+        self.transport.connect(addr)
+    }
+
+    pub fn disconnect(&mut self, peer: PeerId) {
+        self.transport.disconnect(peer);
+    }
+
+    pub fn send(&mut self, peer: PeerId, data: &[u8]) -> io::Result<()> {
+        self.transport.send(peer, data)
+    }
+
+    /// Sends the data to every connected peer
+    pub fn broadcast(&mut self, data: &[u8]) -> io::Result<()> {
+        for peer in self.transport.peers().into_iter() {
+            self.transport.send(peer, data)?;
+        }
+        Ok(())
+    }
+
+    pub fn peers(&self) -> Vec<PeerId> {
+        self.transport.peers()
+    }
+
+    /// Iterates over the messages received during the last poll
+    pub fn messages(&self) -> impl Iterator<Item = (PeerId, &[u8])> {
+        self.events.iter().filter_map(|event| match event {
+            NetEvent::Message(peer, data) => Some((*peer, data.as_slice())),
+            _ => None,
+        })
+    }
+
+    /// Clears the previous frame's events and receives new ones
+    pub fn poll(&mut self) {
+        self.events.clear();
+        if let Err(error) = self.transport.poll(&mut self.events) {
+            self.last_error = Some(error);
+        }
+    }
+
+    pub fn poll_system(mut network: UniqueViewMut<Network>) {
+        network.poll();
+    }
+}
+
+/// Dummy trait to allow adding a method to WorkloadBuilder
+pub trait NetworkWorkloadSystems<'a> {
+    fn with_network_systems(self) -> WorkloadBuilder<'a>;
+}
+
+impl<'a> NetworkWorkloadSystems<'a> for WorkloadBuilder<'a> {
+    /// Should be the first systems in the workload so that every system sees this frame's messages
+    fn with_network_systems(self) -> WorkloadBuilder<'a> {
+        self
+            .with_system(system!(Network::poll_system))
+    }
+}
@@ -0,0 +1,1117 @@
+use std::collections::{
+    HashMap,
+    VecDeque,
+};
+use serde::{
+    de::DeserializeOwned,
+    Serialize,
+};
+use crate::{
+    components::Transform,
+    logging,
+    rendering::Sprite,
+};
+use super::*;
+
+/// First byte of every replication packet, game packets sent through the same Network should start with something else
+pub const REPLICATION_MAGIC: u8 = 0xFE;
+
+// The byte after the magic
+const PACKET_SNAPSHOT: u8 = 0;
+const PACKET_ACK: u8 = 1;
+
+// Each record in a snapshot is one component of one entity, or the whole entity once it's deleted
+const RECORD_UPDATE: u8 = 0;
+const RECORD_REMOVED: u8 = 1;
+const RECORD_DELETED: u8 = 2;
+
+// Magic, packet kind, tick, part and part count
+const SNAPSHOT_HEADER_SIZE: usize = 14;
+
+/// A component that can be sent from the server to clients. IDs 0 to 15 are reserved for the library.
+/// Components that implement Serialize should implement `SerdeReplicate` instead of writing the encoding by hand.
+pub trait Replicate: Sized + Send + Sync + 'static {
+    const ID: u16;
+
+    fn encode(&self, buffer: &mut Vec<u8>);
+    fn decode(reader: &mut ByteReader) -> Option<Self>;
+}
+
+/// Replicates a component by serializing it with bincode, e.g. `impl SerdeReplicate for Health { const ID: u16 = 16; }`
+pub trait SerdeReplicate: Serialize + DeserializeOwned + Send + Sync + 'static {
+    const ID: u16;
+}
+
+impl<T: SerdeReplicate> Replicate for T {
+    const ID: u16 = <T as SerdeReplicate>::ID;
+
+    fn encode(&self, buffer: &mut Vec<u8>) {
+        if let Err(error) = bincode::serialize_into(&mut *buffer, self) {
+            log::error!(target: logging::NET, "Failed to serialize replicated component {}: {}", std::any::type_name::<T>(), error);
+        }
+    }
+
+    fn decode(reader: &mut ByteReader) -> Option<Self> {
+        bincode::deserialize(reader.rest()).ok()
+    }
+}
+
+impl SerdeReplicate for Transform {
+    const ID: u16 = 0;
+}
+
+/// Only the drawable ID is replicated, the rest of the DrawCommand is left at its defaults
+impl Replicate for Sprite {
+    const ID: u16 = 1;
+
+    fn encode(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(&self.0.drawable.to_le_bytes());
+    }
+
+    fn decode(reader: &mut ByteReader) -> Option<Self> {
+        Some(Sprite::new(reader.u64()?))
+    }
+}
+
+/// Reads little endian values from the front of a packet
+pub struct ByteReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> ByteReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        ByteReader {
+            data,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Takes everything that hasn't been read yet
+    pub fn rest(&mut self) -> &'a [u8] {
+        std::mem::take(&mut self.data)
+    }
+
+    pub fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.data.len() < len {
+            return None;
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Some(bytes)
+    }
+
+    pub fn u8(&mut self) -> Option<u8> {
+        self.bytes(1).map(|b| b[0])
+    }
+
+    pub fn u16(&mut self) -> Option<u16> {
+        let mut bytes = [0; 2];
+        bytes.copy_from_slice(self.bytes(2)?);
+        Some(u16::from_le_bytes(bytes))
+    }
+
+    pub fn u32(&mut self) -> Option<u32> {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(self.bytes(4)?);
+        Some(u32::from_le_bytes(bytes))
+    }
+
+    pub fn u64(&mut self) -> Option<u64> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.bytes(8)?);
+        Some(u64::from_le_bytes(bytes))
+    }
+
+    pub fn f64(&mut self) -> Option<f64> {
+        self.u64().map(f64::from_bits)
+    }
+}
+
+// Entities are identified across the network by the server's index and generation
+type NetEntity = (u64, u64);
+
+fn write_record_header(buffer: &mut Vec<u8>, component: u16, kind: u8, id: EntityId) {
+    buffer.extend_from_slice(&component.to_le_bytes());
+    buffer.push(kind);
+    buffer.extend_from_slice(&(id.uindex() as u64).to_le_bytes());
+    buffer.extend_from_slice(&(id.gen() as u64).to_le_bytes());
+}
+
+fn update_record<T: Replicate>(id: EntityId, component: &T) -> Vec<u8> {
+    let mut data = vec![];
+    component.encode(&mut data);
+
+    let mut record = vec![];
+    write_record_header(&mut record, T::ID, RECORD_UPDATE, id);
+    record.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    record.extend_from_slice(&data);
+    record
+}
+
+fn removal_record(component: u16, id: EntityId) -> Vec<u8> {
+    let mut record = vec![];
+    write_record_header(&mut record, component, RECORD_REMOVED, id);
+    record
+}
+
+fn deletion_record(component: u16, id: EntityId) -> Vec<u8> {
+    let mut record = vec![];
+    write_record_header(&mut record, component, RECORD_DELETED, id);
+    record
+}
+
+fn snapshot_header(tick: u64) -> Vec<u8> {
+    let mut packet = vec![REPLICATION_MAGIC, PACKET_SNAPSHOT];
+    packet.extend_from_slice(&tick.to_le_bytes());
+    // The part and part count are filled in once the snapshot has been split
+    packet.extend_from_slice(&[0; 4]);
+    packet
+}
+
+/// Packs the records into as few packets of at most max_size bytes as possible. There is always at least
+/// one packet so that the peer can acknowledge the tick even when nothing changed.
+fn split_snapshot(tick: u64, records: &[Vec<u8>], max_size: usize) -> Vec<Vec<u8>> {
+    let mut packets = vec![snapshot_header(tick)];
+    for record in records.iter() {
+        if SNAPSHOT_HEADER_SIZE + record.len() > max_size {
+            log::warn!(target: logging::NET, "A replicated component is {} bytes, too large to fit in a packet", record.len());
+            continue;
+        }
+        if packets.last().unwrap().len() + record.len() > max_size {
+            packets.push(snapshot_header(tick));
+        }
+        packets.last_mut().unwrap().extend_from_slice(record);
+    }
+
+    if packets.len() > u16::MAX as usize {
+        log::error!(target: logging::NET, "Snapshot for tick {} needs {} packets, it won't be sent", tick, packets.len());
+        return vec![];
+    }
+
+    let parts = packets.len() as u16;
+    for (part, packet) in packets.iter_mut().enumerate() {
+        packet[10..12].copy_from_slice(&(part as u16).to_le_bytes());
+        packet[12..14].copy_from_slice(&parts.to_le_bytes());
+    }
+    packets
+}
+
+fn read_ack(data: &[u8]) -> Option<u64> {
+    let mut reader = ByteReader::new(data);
+    if reader.u8()? != REPLICATION_MAGIC || reader.u8()? != PACKET_ACK {
+        return None;
+    }
+    let tick = reader.u64()?;
+    if !reader.is_empty() {
+        return None;
+    }
+    Some(tick)
+}
+
+//
+// Server
+
+/// What the server knows about a connected peer
+struct PeerState {
+    /// The newest tick that every part of the snapshot has been received for
+    acked: Option<u64>,
+    /// The tick the peer was first seen on
+    connected: u64,
+    /// The records to send this tick
+    records: Vec<Vec<u8>>,
+}
+
+impl PeerState {
+    /// The peer has every change made up to and including this tick
+    fn baseline(&self) -> u64 {
+        // Peers are only seen after the first tick has started, so connected is never 0
+        self.acked.unwrap_or(self.connected - 1)
+    }
+}
+
+/// The tick each entity's component last changed on, and the removals and deletions that a peer might not have received yet
+#[derive(Default)]
+struct History {
+    changed: HashMap<EntityId, u64>,
+    removed: Vec<(u64, EntityId)>,
+    deleted: Vec<(u64, EntityId)>,
+}
+
+/// Builds a snapshot of the replicated components for every peer each tick. A peer gets everything that
+/// changed since the last snapshot it acknowledged, or every component until it has acknowledged one,
+/// so lost packets are made up for by the next snapshot. Changes are found through the components' update packs.
+pub struct ReplicationServer {
+    /// Counts up once per `begin_system`, a u64 so that it never wraps around
+    pub tick: u64,
+    /// Snapshots larger than this are split over several packets, defaults to what `UdpTransport` can send
+    pub max_packet_size: usize,
+
+    peers: HashMap<PeerId, PeerState>,
+    history: HashMap<u16, History>,
+}
+
+impl ReplicationServer {
+    pub fn new() -> Self {
+        ReplicationServer {
+            tick: 0,
+            max_packet_size: super::udp::MAX_PAYLOAD_SIZE,
+
+            peers: HashMap::new(),
+            history: HashMap::new(),
+        }
+    }
+
+    /// The newest tick the peer has received in full, `None` until it has acknowledged its first snapshot
+    pub fn acked_tick(&self, peer: PeerId) -> Option<u64> {
+        self.peers.get(&peer).and_then(|state| state.acked)
+    }
+
+    /// Starts a new snapshot and reads the clients' acknowledgements, must run before the `replicate` systems
+    pub fn begin_system(mut server: UniqueViewMut<ReplicationServer>, network: UniqueView<Network>) {
+        let server = &mut *server;
+        server.tick += 1;
+        let tick = server.tick;
+
+        let connected = network.peers();
+        server.peers.retain(|peer, _| connected.contains(peer));
+        for peer in connected.into_iter() {
+            server.peers.entry(peer).or_insert_with(|| PeerState {
+                acked: None,
+                connected: tick,
+                records: vec![],
+            });
+        }
+
+        for (peer, data) in network.messages() {
+            if let (Some(state), Some(ack)) = (server.peers.get_mut(&peer), read_ack(data)) {
+                // Acks can arrive out of order, and can't be for a tick that hasn't been sent yet
+                if ack < tick && state.acked.map_or(true, |acked| ack > acked) {
+                    state.acked = Some(ack);
+                }
+            }
+        }
+
+        for state in server.peers.values_mut() {
+            state.records.clear();
+        }
+    }
+
+    /// Adds the changes to T each peer hasn't received yet to their snapshots
+    pub fn replicate<T: Replicate>(mut storage: ViewMut<T>, mut server: UniqueViewMut<ReplicationServer>) {
+        let server = &mut *server;
+        let tick = server.tick;
+        let history = server.history.entry(T::ID).or_insert_with(History::default);
+
+        for (id, _) in storage.inserted_or_modified().iter().with_id() {
+            history.changed.insert(id, tick);
+        }
+
+        for id in storage.take_removed().into_iter() {
+            history.changed.remove(&id);
+            history.removed.push((tick, id));
+        }
+        // Every replicated storage the entity had sends the deletion, the client only needs one of them
+        for (id, _) in storage.take_deleted().into_iter() {
+            history.changed.remove(&id);
+            history.deleted.push((tick, id));
+        }
+
+        storage.clear_inserted_and_modified();
+
+        for state in server.peers.values_mut() {
+            let baseline = state.baseline();
+
+            // Removals go first so that a component removed and added again ends up added
+            for (removed_tick, id) in history.removed.iter() {
+                if *removed_tick > baseline {
+                    state.records.push(removal_record(T::ID, *id));
+                }
+            }
+            for (deleted_tick, id) in history.deleted.iter() {
+                if *deleted_tick > baseline {
+                    state.records.push(deletion_record(T::ID, *id));
+                }
+            }
+
+            if state.acked.is_some() {
+                for (id, changed_tick) in history.changed.iter() {
+                    if *changed_tick > baseline {
+                        if let Ok(component) = (&storage).get(*id) {
+                            state.records.push(update_record(*id, component));
+                        }
+                    }
+                }
+            } else {
+                for (id, component) in (&storage).iter().with_id() {
+                    state.records.push(update_record(id, component));
+                }
+            }
+        }
+
+        // Removals and deletions every peer has received are no longer needed
+        let oldest = server.peers.values().map(PeerState::baseline).min();
+        history.removed.retain(|(removed_tick, _)| oldest.map_or(false, |oldest| *removed_tick > oldest));
+        history.deleted.retain(|(deleted_tick, _)| oldest.map_or(false, |oldest| *deleted_tick > oldest));
+    }
+
+    /// Sends the snapshots, must run after the `replicate` systems
+    pub fn send_system(server: UniqueView<ReplicationServer>, mut network: UniqueViewMut<Network>) {
+        for (peer, state) in server.peers.iter() {
+            for packet in split_snapshot(server.tick, &state.records, server.max_packet_size).iter() {
+                if let Err(error) = network.send(*peer, packet) {
+                    // The peer won't acknowledge this tick so the changes are sent again in the next snapshot
+                    network.last_error = Some(error);
+                    break;
+                }
+            }
+        }
+    }
+}
+
+impl Default for ReplicationServer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+//
+// Client
+
+/// A component value received from the server, `None` if it was removed
+struct Update {
+    tick: u64,
+    entity: NetEntity,
+    data: Option<Vec<u8>>,
+}
+
+/// One packet of a snapshot, only built once the whole packet has been read successfully
+struct Snapshot {
+    tick: u64,
+    part: u16,
+    parts: u16,
+    updates: Vec<(u16, Update)>,
+    deleted: Vec<NetEntity>,
+}
+
+fn read_snapshot(data: &[u8]) -> Option<Snapshot> {
+    let mut reader = ByteReader::new(data);
+    if reader.u8()? != REPLICATION_MAGIC || reader.u8()? != PACKET_SNAPSHOT {
+        return None;
+    }
+
+    let tick = reader.u64()?;
+    let part = reader.u16()?;
+    let parts = reader.u16()?;
+    if part >= parts {
+        return None;
+    }
+
+    let mut updates = vec![];
+    let mut deleted = vec![];
+    while !reader.is_empty() {
+        let component = reader.u16()?;
+        let kind = reader.u8()?;
+        let entity = (reader.u64()?, reader.u64()?);
+        let data = match kind {
+            RECORD_UPDATE => {
+                let len = reader.u32()? as usize;
+                Some(reader.bytes(len)?.to_vec())
+            },
+            RECORD_REMOVED => None,
+            RECORD_DELETED => {
+                deleted.push(entity);
+                continue;
+            },
+            _ => return None,
+        };
+        updates.push((component, Update { tick, entity, data }));
+    }
+
+    Some(Snapshot {
+        tick,
+        part,
+        parts,
+        updates,
+        deleted,
+    })
+}
+
+/// Receives snapshots from the server, acknowledges them and maps the server's entities to local ones
+pub struct ReplicationClient {
+    /// The newest tick received from the server
+    pub latest_tick: u64,
+    /// The tick interpolated components are currently showing, trails latest_tick by delay
+    pub render_tick: f64,
+    /// How many ticks behind the server interpolation runs, larger values hide more packet loss
+    pub delay: f64,
+    /// The newest tick every part of the snapshot has been received for
+    pub acked_tick: Option<u64>,
+
+    entities: HashMap<NetEntity, EntityId>,
+    pending: HashMap<u16, Vec<Update>>,
+    // The tick of the newest update applied to each component of each entity, parts of a newer snapshot
+    // can be applied before an older snapshot arrives
+    applied: HashMap<(u16, NetEntity), u64>,
+    // Server entities that were deleted and the tick they were deleted on, kept until a snapshot from after the
+    // deletion is acknowledged so that late updates can't bring them back
+    deleted: HashMap<NetEntity, u64>,
+    // Which parts of the incomplete snapshots have arrived
+    parts: HashMap<u64, Vec<bool>>,
+}
+
+impl ReplicationClient {
+    /// Incomplete snapshots beyond this many are given up on, oldest first
+    const MAX_PARTIAL_SNAPSHOTS: usize = 32;
+
+    pub fn new(delay: f64) -> Self {
+        ReplicationClient {
+            latest_tick: 0,
+            render_tick: 0.0,
+            delay,
+            acked_tick: None,
+
+            entities: HashMap::new(),
+            pending: HashMap::new(),
+            applied: HashMap::new(),
+            deleted: HashMap::new(),
+            parts: HashMap::new(),
+        }
+    }
+
+    /// The local entity for a server entity, if it has been replicated
+    pub fn local_entity(&self, index: u64, gen: u64) -> Option<EntityId> {
+        self.entities.get(&(index, gen)).copied()
+    }
+
+    /// Queues the snapshot's updates, returns whether it completed a newer tick that should be acknowledged
+    fn receive_snapshot(&mut self, snapshot: Snapshot) -> bool {
+        // Anything from before a complete snapshot is already out of date
+        if self.acked_tick.map_or(false, |acked| snapshot.tick <= acked) {
+            return false;
+        }
+
+        let parts = snapshot.parts as usize;
+        let received = self.parts.entry(snapshot.tick).or_insert_with(|| vec![false; parts]);
+        if received.len() != parts || received[snapshot.part as usize] {
+            return false;
+        }
+        received[snapshot.part as usize] = true;
+        let complete = received.iter().all(|r| *r);
+
+        if snapshot.tick > self.latest_tick {
+            self.latest_tick = snapshot.tick;
+        }
+        for (component, update) in snapshot.updates.into_iter() {
+            self.pending.entry(component).or_insert_with(Vec::new).push(update);
+        }
+        for entity in snapshot.deleted.into_iter() {
+            let tick = self.deleted.entry(entity).or_insert(snapshot.tick);
+            *tick = (*tick).max(snapshot.tick);
+        }
+
+        if complete {
+            self.acked_tick = Some(snapshot.tick);
+            self.parts.retain(|tick, _| *tick > snapshot.tick);
+        } else if self.parts.len() > Self::MAX_PARTIAL_SNAPSHOTS {
+            let oldest = *self.parts.keys().min().unwrap();
+            self.parts.remove(&oldest);
+        }
+        complete
+    }
+
+    fn take_pending(&mut self, component: u16) -> Vec<Update> {
+        self.pending.remove(&component).unwrap_or_default()
+    }
+
+    /// Records the update as applied, returns false if a newer update to the same component has already been applied
+    fn is_newest(&mut self, component: u16, update: &Update) -> bool {
+        let newest = self.applied.entry((component, update.entity)).or_insert(update.tick);
+        if update.tick < *newest {
+            return false;
+        }
+        *newest = update.tick;
+        true
+    }
+
+    /// Forgets the server entities that were deleted, returning the local entities to delete
+    fn take_deleted(&mut self) -> Vec<EntityId> {
+        if self.deleted.is_empty() {
+            return vec![];
+        }
+
+        let mut ids = vec![];
+        for entity in self.deleted.keys() {
+            if let Some(id) = self.entities.remove(entity) {
+                ids.push(id);
+            }
+        }
+        let deleted = &self.deleted;
+        self.applied.retain(|(_, entity), _| !deleted.contains_key(entity));
+
+        let acked = self.acked_tick;
+        self.deleted.retain(|_, tick| acked.map_or(true, |acked| *tick > acked));
+        ids
+    }
+
+    fn local_or_create(&mut self, entities: &mut EntitiesViewMut, entity: NetEntity) -> EntityId {
+        *self.entities.entry(entity).or_insert_with(|| entities.add_entity((), ()))
+    }
+
+    /// Reads the snapshots received this frame and acknowledges the newest complete one,
+    /// must run after the network is polled and before the `apply` systems
+    pub fn receive_system(mut client: UniqueViewMut<ReplicationClient>, mut network: UniqueViewMut<Network>) {
+        let client = &mut *client;
+
+        // The apply systems take their updates every frame, anything left over is for a component this client doesn't replicate
+        if !client.pending.is_empty() {
+            log::debug!(target: logging::NET, "Dropping updates for unreplicated components {:?}", client.pending.keys().collect::<Vec<_>>());
+            client.pending.clear();
+        }
+
+        let mut ack_to = None;
+        for (peer, data) in network.messages() {
+            // Malformed packets are dropped
+            if let Some(snapshot) = read_snapshot(data) {
+                if client.receive_snapshot(snapshot) {
+                    ack_to = Some(peer);
+                }
+            }
+        }
+
+        if let (Some(peer), Some(tick)) = (ack_to, client.acked_tick) {
+            let mut packet = vec![REPLICATION_MAGIC, PACKET_ACK];
+            packet.extend_from_slice(&tick.to_le_bytes());
+            if let Err(error) = network.send(peer, &packet) {
+                // The server keeps sending the changes since the last ack it got, the next ack makes up for this one
+                network.last_error = Some(error);
+            }
+        }
+
+        // Advance the interpolation clock, snapping if it has drifted too far from the server
+        let target = client.latest_tick as f64 - client.delay;
+        client.render_tick += 1.0;
+        if (client.render_tick - target).abs() > client.delay.max(1.0) * 2.0 {
+            client.render_tick = target;
+        }
+    }
+
+    /// Writes the received values of T onto the local entities
+    pub fn apply<T: Replicate>(
+        mut entities: EntitiesViewMut,
+        mut storage: ViewMut<T>,
+        mut client: UniqueViewMut<ReplicationClient>,
+    ) {
+        for update in client.take_pending(T::ID).into_iter() {
+            if client.deleted.contains_key(&update.entity) || !client.is_newest(T::ID, &update) {
+                continue;
+            }
+
+            match update.data {
+                Some(data) => {
+                    if let Some(component) = T::decode(&mut ByteReader::new(&data)) {
+                        let id = client.local_or_create(&mut entities, update.entity);
+                        entities.add_component(&mut storage, component, id);
+                    }
+                },
+                None => {
+                    if let Some(id) = client.entities.get(&update.entity) {
+                        storage.remove(*id);
+                    }
+                },
+            }
+        }
+    }
+
+    /// Buffers the received values of T so that `interpolate` can smooth between them
+    pub fn apply_interpolated<T: Replicate + Interpolate>(
+        mut entities: EntitiesViewMut,
+        mut storage: ViewMut<T>,
+        mut buffers: ViewMut<InterpolationBuffer<T>>,
+        mut client: UniqueViewMut<ReplicationClient>,
+    ) {
+        for update in client.take_pending(T::ID).into_iter() {
+            if client.deleted.contains_key(&update.entity) {
+                continue;
+            }
+            let newest = client.is_newest(T::ID, &update);
+
+            match update.data {
+                Some(data) => {
+                    let component = match T::decode(&mut ByteReader::new(&data)) {
+                        Some(component) => component,
+                        None => continue,
+                    };
+
+                    let id = client.local_or_create(&mut entities, update.entity);
+                    if let Ok(buffer) = (&mut buffers).get(id) {
+                        // The buffer is sorted by tick so older values can still be interpolated from
+                        buffer.push(update.tick, component);
+                    } else if newest {
+                        let mut buffer = InterpolationBuffer::new();
+                        buffer.push(update.tick, component);
+                        entities.add_component(&mut buffers, buffer, id);
+                    }
+                },
+                None if newest => {
+                    if let Some(id) = client.entities.get(&update.entity) {
+                        storage.remove(*id);
+                        buffers.remove(*id);
+                    }
+                },
+                None => {},
+            }
+        }
+    }
+
+    /// Deletes the local entities of server entities that were deleted, must run after the `apply` systems
+    pub fn delete_system(mut all_storages: AllStoragesViewMut) {
+        let deleted = all_storages.borrow::<UniqueViewMut<ReplicationClient>>().take_deleted();
+        for id in deleted.into_iter() {
+            all_storages.delete(id);
+        }
+    }
+
+    /// Sets T from its InterpolationBuffer at the current render tick
+    pub fn interpolate<T: Replicate + Interpolate>(
+        mut entities: EntitiesViewMut,
+        mut storage: ViewMut<T>,
+        buffers: View<InterpolationBuffer<T>>,
+        client: UniqueView<ReplicationClient>,
+    ) {
+        for (id, buffer) in buffers.iter().with_id() {
+            if let Some(value) = buffer.sample(client.render_tick) {
+                entities.add_component(&mut storage, value, id);
+            }
+        }
+    }
+}
+
+/// Components that can be smoothly blended between two snapshots
+pub trait Interpolate: Clone {
+    fn interpolate(&self, other: &Self, t: f64) -> Self;
+}
+
+impl Interpolate for Transform {
+    fn interpolate(&self, other: &Self, t: f64) -> Self {
+        Transform::new(
+            self.x + (other.x - self.x) * t,
+            self.y + (other.y - self.y) * t,
+        )
+    }
+}
+
+/// The recent values of a component received from the server
+pub struct InterpolationBuffer<T> {
+    samples: VecDeque<(u64, T)>,
+}
+
+impl<T: Interpolate> InterpolationBuffer<T> {
+    const MAX_SAMPLES: usize = 32;
+
+    pub fn new() -> Self {
+        InterpolationBuffer {
+            samples: VecDeque::new(),
+        }
+    }
+
+    pub fn push(&mut self, tick: u64, value: T) {
+        // Snapshots can arrive out of order
+        let index = self.samples.iter().position(|(t, _)| *t > tick).unwrap_or(self.samples.len());
+        if index > 0 && self.samples[index - 1].0 == tick {
+            self.samples[index - 1].1 = value;
+        } else {
+            self.samples.insert(index, (tick, value));
+        }
+
+        while self.samples.len() > Self::MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+    }
+
+    /// The value at the tick, held at the first or last sample outside of the buffered range
+    pub fn sample(&self, tick: f64) -> Option<T> {
+        let after = self.samples.iter().position(|(t, _)| *t as f64 >= tick);
+        match after {
+            None => self.samples.back().map(|(_, value)| value.clone()),
+            Some(0) => self.samples.front().map(|(_, value)| value.clone()),
+            Some(index) => {
+                let (t0, v0) = &self.samples[index - 1];
+                let (t1, v1) = &self.samples[index];
+                let t = (tick - *t0 as f64) / (*t1 as f64 - *t0 as f64);
+                Some(v0.interpolate(v1, t))
+            },
+        }
+    }
+}
+
+impl<T: Interpolate> Default for InterpolationBuffer<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Dummy trait to allow adding a method to World
+pub trait ReplicationCreator {
+    fn add_replication_server(&mut self);
+    fn add_replication_client(&mut self, delay: f64);
+    /// Enables change tracking on the component so the server can send only what changed
+    fn track_replicated<T: Replicate>(&mut self);
+}
+
+impl ReplicationCreator for World {
+    fn add_replication_server(&mut self) {
+        self.add_unique(ReplicationServer::new());
+    }
+
+    fn add_replication_client(&mut self, delay: f64) {
+        self.add_unique(ReplicationClient::new(delay));
+    }
+
+    fn track_replicated<T: Replicate>(&mut self) {
+        self.borrow::<ViewMut<T>>().update_pack();
+    }
+}
+
+/// Dummy trait to allow adding a method to WorkloadBuilder. Server workloads add
+/// `with_server_replication_start`, then `with_server_replicated::<T>` for every component,
+/// then `with_server_replication_end`. Client workloads do the same with the client methods.
+pub trait ReplicationWorkloadSystems<'a> {
+    fn with_server_replication_start(self) -> WorkloadBuilder<'a>;
+    fn with_server_replicated<T: Replicate>(self) -> WorkloadBuilder<'a>;
+    fn with_server_replication_end(self) -> WorkloadBuilder<'a>;
+
+    fn with_client_replication_start(self) -> WorkloadBuilder<'a>;
+    fn with_client_replicated<T: Replicate>(self) -> WorkloadBuilder<'a>;
+    fn with_client_interpolated<T: Replicate + Interpolate>(self) -> WorkloadBuilder<'a>;
+    fn with_client_replication_end(self) -> WorkloadBuilder<'a>;
+}
+
+impl<'a> ReplicationWorkloadSystems<'a> for WorkloadBuilder<'a> {
+    fn with_server_replication_start(self) -> WorkloadBuilder<'a> {
+        self.with_system(system!(ReplicationServer::begin_system))
+    }
+
+    fn with_server_replicated<T: Replicate>(self) -> WorkloadBuilder<'a> {
+        self.with_system(system!(ReplicationServer::replicate::<T>))
+    }
+
+    fn with_server_replication_end(self) -> WorkloadBuilder<'a> {
+        self.with_system(system!(ReplicationServer::send_system))
+    }
+
+    fn with_client_replication_start(self) -> WorkloadBuilder<'a> {
+        self.with_system(system!(ReplicationClient::receive_system))
+    }
+
+    fn with_client_replicated<T: Replicate>(self) -> WorkloadBuilder<'a> {
+        self.with_system(system!(ReplicationClient::apply::<T>))
+    }
+
+    fn with_client_interpolated<T: Replicate + Interpolate>(self) -> WorkloadBuilder<'a> {
+        self
+            .with_system(system!(ReplicationClient::apply_interpolated::<T>))
+            .with_system(system!(ReplicationClient::interpolate::<T>))
+    }
+
+    fn with_client_replication_end(self) -> WorkloadBuilder<'a> {
+        self.with_system(system!(ReplicationClient::delete_system))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::SocketAddr,
+        sync::{
+            Arc,
+            Mutex,
+        },
+    };
+    use serde::Deserialize;
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+    struct Health {
+        current: u32,
+        name: String,
+    }
+
+    impl SerdeReplicate for Health {
+        const ID: u16 = 16;
+    }
+
+    /// Packets in flight between the two ends of a loopback
+    #[derive(Default)]
+    struct Link {
+        to_client: Vec<Vec<u8>>,
+        to_server: Vec<Vec<u8>>,
+        /// Everything the server sends is lost while this is set
+        drop_to_client: bool,
+    }
+
+    /// One end of a loopback connection, the other end is always PeerId(0)
+    struct Loopback {
+        link: Arc<Mutex<Link>>,
+        server: bool,
+        connected: bool,
+    }
+
+    impl Transport for Loopback {
+        fn connect(&mut self, _: SocketAddr) -> io::Result<PeerId> {
+            Ok(PeerId(0))
+        }
+
+        fn disconnect(&mut self, _: PeerId) {}
+
+        fn send(&mut self, _: PeerId, data: &[u8]) -> io::Result<()> {
+            let mut link = self.link.lock().unwrap();
+            if !self.server {
+                link.to_server.push(data.to_vec());
+            } else if !link.drop_to_client {
+                link.to_client.push(data.to_vec());
+            }
+            Ok(())
+        }
+
+        fn poll(&mut self, events: &mut Vec<NetEvent>) -> io::Result<()> {
+            if !self.connected {
+                self.connected = true;
+                events.push(NetEvent::Connected(PeerId(0)));
+            }
+
+            let mut link = self.link.lock().unwrap();
+            let inbox = if self.server { &mut link.to_server } else { &mut link.to_client };
+            events.extend(inbox.drain(..).map(|data| NetEvent::Message(PeerId(0), data)));
+            Ok(())
+        }
+
+        fn peers(&self) -> Vec<PeerId> {
+            vec![PeerId(0)]
+        }
+    }
+
+    /// A server and a client World connected by a loopback, the client only applies Health if `apply_health` is set
+    fn connected_pair(max_packet_size: usize, apply_health: bool) -> (World, World, Arc<Mutex<Link>>) {
+        let link = Arc::new(Mutex::new(Link::default()));
+
+        let mut server = World::new();
+        server.add_unique(Network::new(Loopback { link: link.clone(), server: true, connected: false }));
+        server.add_replication_server();
+        server.run(|mut replication: UniqueViewMut<ReplicationServer>| replication.max_packet_size = max_packet_size);
+        server.track_replicated::<Transform>();
+        server.track_replicated::<Health>();
+        server
+            .add_workload("Replication")
+            .with_network_systems()
+            .with_server_replication_start()
+            .with_server_replicated::<Transform>()
+            .with_server_replicated::<Health>()
+            .with_server_replication_end()
+            .build();
+
+        let mut client = World::new();
+        client.add_unique(Network::new(Loopback { link: link.clone(), server: false, connected: false }));
+        client.add_replication_client(0.0);
+        let workload = client
+            .add_workload("Replication")
+            .with_network_systems()
+            .with_client_replication_start()
+            .with_client_replicated::<Transform>();
+        let workload = if apply_health {
+            workload.with_client_replicated::<Health>()
+        } else {
+            workload
+        };
+        workload.with_client_replication_end().build();
+
+        (server, client, link)
+    }
+
+    fn tick(server: &World, client: &World) {
+        server.run_workload("Replication");
+        client.run_workload("Replication");
+    }
+
+    fn spawn(server: &World, transform: Transform) -> EntityId {
+        server.run(|mut entities: EntitiesViewMut, mut transforms: ViewMut<Transform>| {
+            entities.add_entity(&mut transforms, transform)
+        })
+    }
+
+    fn move_to(server: &World, id: EntityId, x: f64) {
+        server.run(|mut transforms: ViewMut<Transform>| {
+            (&mut transforms).get(id).unwrap().x = x;
+        });
+    }
+
+    fn client_positions(client: &World) -> Vec<(f64, f64)> {
+        let mut positions = client.run(|transforms: View<Transform>| {
+            transforms.iter().map(|t| (t.x, t.y)).collect::<Vec<_>>()
+        });
+        positions.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        positions
+    }
+
+    #[test]
+    fn components_round_trip() {
+        let (server, client, _) = connected_pair(super::super::udp::MAX_PAYLOAD_SIZE, true);
+
+        let a = spawn(&server, Transform::new(1.0, 2.0));
+        let b = spawn(&server, Transform::new(3.0, 4.0));
+        server.run(|mut entities: EntitiesViewMut, mut healths: ViewMut<Health>| {
+            entities.add_component(&mut healths, Health { current: 7, name: "crab".into() }, a);
+        });
+        tick(&server, &client);
+
+        assert_eq!(client_positions(&client), vec![(1.0, 2.0), (3.0, 4.0)]);
+        let healths = client.run(|healths: View<Health>| healths.iter().cloned().collect::<Vec<_>>());
+        assert_eq!(healths, vec![Health { current: 7, name: "crab".into() }]);
+
+        move_to(&server, a, 10.0);
+        server.run(|mut transforms: ViewMut<Transform>| { transforms.remove(b); });
+        tick(&server, &client);
+
+        assert_eq!(client_positions(&client), vec![(10.0, 2.0)]);
+    }
+
+    #[test]
+    fn deleted_entities_are_deleted_on_the_client() {
+        let (server, client, _) = connected_pair(super::super::udp::MAX_PAYLOAD_SIZE, true);
+
+        let a = spawn(&server, Transform::new(1.0, 2.0));
+        let b = spawn(&server, Transform::new(3.0, 4.0));
+        server.run(|mut entities: EntitiesViewMut, mut healths: ViewMut<Health>| {
+            entities.add_component(&mut healths, Health { current: 7, name: "crab".into() }, a);
+        });
+        tick(&server, &client);
+
+        let local = |id: EntityId| {
+            client.run(|replication: UniqueView<ReplicationClient>| replication.local_entity(id.uindex() as u64, id.gen() as u64))
+        };
+        let local_a = local(a).unwrap();
+
+        server.run(|mut all_storages: AllStoragesViewMut| { all_storages.delete(a); });
+        tick(&server, &client);
+
+        assert_eq!(client_positions(&client), vec![(3.0, 4.0)]);
+        assert_eq!(client.run(|healths: View<Health>| healths.iter().count()), 0);
+        assert_eq!(local(a), None);
+        assert!(local(b).is_some());
+        assert!(!client.run(|entities: EntitiesView| entities.is_alive(local_a)));
+        client.run(|replication: UniqueView<ReplicationClient>| {
+            assert_eq!(replication.entities.len(), 1);
+            assert!(replication.applied.keys().all(|(_, entity)| *entity != (a.uindex() as u64, a.gen() as u64)));
+        });
+
+        // Once a snapshot from after the deletion is acknowledged there's nothing left to remember it by
+        tick(&server, &client);
+        tick(&server, &client);
+        assert!(client.run(|replication: UniqueView<ReplicationClient>| replication.deleted.is_empty()));
+    }
+
+    #[test]
+    fn lost_snapshots_are_resent_until_acked() {
+        let (server, client, link) = connected_pair(super::super::udp::MAX_PAYLOAD_SIZE, true);
+
+        // The full state keeps being sent until the client acknowledges one
+        let a = spawn(&server, Transform::new(1.0, 0.0));
+        link.lock().unwrap().drop_to_client = true;
+        tick(&server, &client);
+        tick(&server, &client);
+        assert!(client_positions(&client).is_empty());
+
+        link.lock().unwrap().drop_to_client = false;
+        tick(&server, &client);
+        assert_eq!(client_positions(&client), vec![(1.0, 0.0)]);
+
+        // A lost change is sent again even though the component doesn't change again
+        tick(&server, &client);
+        move_to(&server, a, 5.0);
+        link.lock().unwrap().drop_to_client = true;
+        tick(&server, &client);
+        link.lock().unwrap().drop_to_client = false;
+        tick(&server, &client);
+        assert_eq!(client_positions(&client), vec![(5.0, 0.0)]);
+    }
+
+    #[test]
+    fn older_snapshots_dont_overwrite_newer_values() {
+        let (server, client, link) = connected_pair(super::super::udp::MAX_PAYLOAD_SIZE, true);
+        let a = spawn(&server, Transform::new(0.0, 0.0));
+        let b = spawn(&server, Transform::new(0.0, 0.0));
+
+        // Tick 11 is split over two packets, only the first arrives before the whole of tick 10
+        let newer = update_record(a, &Transform::new(2.0, 0.0));
+        let newer = split_snapshot(11, &[newer.clone(), update_record(b, &Transform::new(3.0, 0.0))], SNAPSHOT_HEADER_SIZE + newer.len());
+        assert_eq!(newer.len(), 2);
+        link.lock().unwrap().to_client.push(newer[0].clone());
+        client.run_workload("Replication");
+        assert_eq!(client_positions(&client), vec![(2.0, 0.0)]);
+
+        let older = split_snapshot(10, &[update_record(a, &Transform::new(1.0, 0.0))], 1000);
+        link.lock().unwrap().to_client.extend(older);
+        client.run_workload("Replication");
+        assert_eq!(client_positions(&client), vec![(2.0, 0.0)]);
+        assert_eq!(client.run(|replication: UniqueView<ReplicationClient>| replication.acked_tick), Some(10));
+    }
+
+    #[test]
+    fn large_snapshots_are_split() {
+        let (server, client, link) = connected_pair(100, true);
+        for i in 0..20 {
+            spawn(&server, Transform::new(i as f64, 0.0));
+        }
+
+        server.run_workload("Replication");
+        {
+            let link = link.lock().unwrap();
+            assert!(link.to_client.len() > 1);
+            assert!(link.to_client.iter().all(|packet| packet.len() <= 100));
+        }
+
+        client.run_workload("Replication");
+        assert_eq!(client_positions(&client).len(), 20);
+        assert!(client.run(|replication: UniqueView<ReplicationClient>| replication.acked_tick).is_some());
+    }
+
+    #[test]
+    fn malformed_snapshots_queue_nothing() {
+        let mut record = update_record(EntityId::dead(), &Transform::new(1.0, 2.0));
+        let mut packet = split_snapshot(1, &[record.clone()], 1000).remove(0);
+        assert_eq!(read_snapshot(&packet).map(|snapshot| snapshot.updates.len()), Some(1));
+
+        // A second record cut short invalidates the whole packet, including the first record
+        record.truncate(record.len() - 1);
+        packet.extend_from_slice(&record);
+        assert!(read_snapshot(&packet).is_none());
+
+        let mut client = ReplicationClient::new(0.0);
+        if let Some(snapshot) = read_snapshot(&packet) {
+            client.receive_snapshot(snapshot);
+        }
+        assert!(client.pending.is_empty());
+        assert_eq!(client.acked_tick, None);
+    }
+
+    #[test]
+    fn unknown_components_are_dropped() {
+        let (server, client, _) = connected_pair(super::super::udp::MAX_PAYLOAD_SIZE, false);
+        let a = spawn(&server, Transform::new(0.0, 0.0));
+        server.run(|mut entities: EntitiesViewMut, mut healths: ViewMut<Health>| {
+            entities.add_component(&mut healths, Health { current: 0, name: String::new() }, a);
+        });
+
+        for i in 0..5 {
+            server.run(|mut healths: ViewMut<Health>| (&mut healths).get(a).unwrap().current = i);
+            tick(&server, &client);
+        }
+
+        let queued = client.run(|replication: UniqueView<ReplicationClient>| {
+            replication.pending.get(&<Health as SerdeReplicate>::ID).map_or(0, Vec::len)
+        });
+        assert!(queued <= 1);
+    }
+}
@@ -0,0 +1,218 @@
+use std::{
+    collections::HashMap,
+    io,
+    net::{
+        SocketAddr,
+        ToSocketAddrs,
+        UdpSocket,
+    },
+    time::{
+        Duration,
+        Instant,
+    },
+};
+use crate::logging;
+use super::*;
+
+// Every packet starts with one of these
+const PACKET_HELLO: u8 = 0;
+const PACKET_DATA: u8 = 1;
+const PACKET_GOODBYE: u8 = 2;
+const PACKET_HEARTBEAT: u8 = 3;
+
+const MAX_PACKET_SIZE: usize = 1200;
+
+/// The largest `data` that `UdpTransport::send` accepts
+pub const MAX_PAYLOAD_SIZE: usize = MAX_PACKET_SIZE - 1;
+
+struct Peer {
+    addr: SocketAddr,
+    last_received: Instant,
+    last_sent: Instant,
+    connected: bool,
+}
+
+/// Unreliable, unordered transport over a single non-blocking UDP socket. Peers are connected
+/// when they send their first packet and dropped when nothing is heard from them for `timeout`.
+pub struct UdpTransport {
+    socket: UdpSocket,
+    peers: HashMap<PeerId, Peer>,
+    next_peer: u32,
+
+    /// How long to wait without hearing from a peer before disconnecting it
+    pub timeout: Duration,
+    /// How long to go without sending to a peer before sending a heartbeat
+    pub heartbeat: Duration,
+}
+
+impl UdpTransport {
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        let socket = UdpSocket::bind(addr)?;
+        socket.set_nonblocking(true)?;
+
+        Ok(UdpTransport {
+            socket,
+            peers: HashMap::new(),
+            next_peer: 0,
+
+            timeout: Duration::from_secs(5),
+            heartbeat: Duration::from_secs(1),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    fn peer_from_addr(&self, addr: SocketAddr) -> Option<PeerId> {
+        self.peers.iter()
+            .find(|(_, peer)| peer.addr == addr)
+            .map(|(id, _)| *id)
+    }
+
+    fn add_peer(&mut self, addr: SocketAddr, connected: bool) -> PeerId {
+        let id = PeerId(self.next_peer);
+        self.next_peer += 1;
+
+        let now = Instant::now();
+        self.peers.insert(id, Peer {
+            addr,
+            last_received: now,
+            last_sent: now,
+            connected,
+        });
+        id
+    }
+
+    fn send_packet(&mut self, peer: PeerId, kind: u8, data: &[u8]) -> io::Result<()> {
+        let peer = match self.peers.get_mut(&peer) {
+            Some(peer) => peer,
+            None => return Err(io::Error::new(io::ErrorKind::NotConnected, "Unknown peer")),
+        };
+
+        let mut packet = Vec::with_capacity(data.len() + 1);
+        packet.push(kind);
+        packet.extend_from_slice(data);
+
+        self.socket.send_to(&packet, peer.addr)?;
+        peer.last_sent = Instant::now();
+        Ok(())
+    }
+}
+
+impl Transport for UdpTransport {
+    fn connect(&mut self, addr: SocketAddr) -> io::Result<PeerId> {
+        if let Some(peer) = self.peer_from_addr(addr) {
+            return Ok(peer);
+        }
+
+        let peer = self.add_peer(addr, false);
+        self.send_packet(peer, PACKET_HELLO, &[])?;
+        Ok(peer)
+    }
+
+    fn disconnect(&mut self, peer: PeerId) {
+        // The goodbye is best effort, the other side times out if it gets lost
+        let _ = self.send_packet(peer, PACKET_GOODBYE, &[]);
+        self.peers.remove(&peer);
+    }
+
+    fn send(&mut self, peer: PeerId, data: &[u8]) -> io::Result<()> {
+        if data.len() > MAX_PAYLOAD_SIZE {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "Packet too large"));
+        }
+        self.send_packet(peer, PACKET_DATA, data)
+    }
+
+    /// Socket errors are logged rather than returned so that one bad packet can't stop
+    /// timeouts and heartbeats from being handled
+    fn poll(&mut self, events: &mut Vec<NetEvent>) -> io::Result<()> {
+        let mut buffer = [0; MAX_PACKET_SIZE];
+
+        loop {
+            let (len, addr) = match self.socket.recv_from(&mut buffer) {
+                Ok(received) => received,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                // An ICMP port unreachable from a peer that went away shows up as a reset on the
+                // next receive on some platforms, the rest of the queue is still readable
+                Err(e) if e.kind() == io::ErrorKind::ConnectionReset => {
+                    log::warn!(target: logging::NET, "Receive failed: {}", e);
+                    continue;
+                },
+                // Anything else may keep failing, stop reading for this poll but still handle timeouts
+                Err(e) => {
+                    log::error!(target: logging::NET, "Receive failed: {}", e);
+                    break;
+                },
+            };
+            if len == 0 {
+                continue;
+            }
+
+            let kind = buffer[0];
+            let id = match self.peer_from_addr(addr) {
+                Some(id) => id,
+                None if kind == PACKET_GOODBYE => continue,
+                None => {
+                    let id = self.add_peer(addr, false);
+                    // Answer so the other side knows we heard them, the heartbeat resends it if this fails
+                    if let Err(e) = self.send_packet(id, PACKET_HELLO, &[]) {
+                        log::warn!(target: logging::NET, "Hello to {} failed: {}", addr, e);
+                    }
+                    id
+                },
+            };
+
+            if kind == PACKET_GOODBYE {
+                if self.peers.remove(&id).map_or(false, |peer| peer.connected) {
+                    events.push(NetEvent::Disconnected(id));
+                }
+                continue;
+            }
+
+            let peer = self.peers.get_mut(&id).unwrap();
+            peer.last_received = Instant::now();
+            if !peer.connected {
+                peer.connected = true;
+                events.push(NetEvent::Connected(id));
+            }
+
+            if kind == PACKET_DATA {
+                events.push(NetEvent::Message(id, buffer[1..len].to_vec()));
+            }
+        }
+
+        let now = Instant::now();
+
+        let timed_out: Vec<PeerId> = self.peers.iter()
+            .filter(|(_, peer)| now.duration_since(peer.last_received) > self.timeout)
+            .map(|(id, _)| *id)
+            .collect();
+        for id in timed_out.into_iter() {
+            if self.peers.remove(&id).map_or(false, |peer| peer.connected) {
+                events.push(NetEvent::Disconnected(id));
+            }
+        }
+
+        let quiet: Vec<PeerId> = self.peers.iter()
+            .filter(|(_, peer)| now.duration_since(peer.last_sent) > self.heartbeat)
+            .map(|(id, _)| *id)
+            .collect();
+        for id in quiet.into_iter() {
+            // Peers that haven't answered yet get the hello resent instead
+            let kind = if self.peers[&id].connected { PACKET_HEARTBEAT } else { PACKET_HELLO };
+            if let Err(e) = self.send_packet(id, kind, &[]) {
+                log::warn!(target: logging::NET, "Heartbeat to {:?} failed: {}", id, e);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn peers(&self) -> Vec<PeerId> {
+        self.peers.iter()
+            .filter(|(_, peer)| peer.connected)
+            .map(|(id, _)| *id)
+            .collect()
+    }
+}
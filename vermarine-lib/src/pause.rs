@@ -0,0 +1,106 @@
+use shipyard::*;
+use tetra::{
+    input::{
+        self,
+        Key,
+    },
+    Context,
+};
+use crate::{
+    text_input::TextInputBuffer,
+    time::Time,
+};
+
+/// Whether gameplay is paused. Pausing sets `Time::time_scale` to 0.0 and resuming restores the scale it had,
+/// and `Schedule::run_pausable` skips the stages that `Stage::pauses` while paused so physics and gameplay stop
+/// while input, late update and rendering workloads keep running for menus.
+#[derive(Clone, Debug)]
+pub struct Paused {
+    paused: bool,
+    resume_scale: f64,
+
+    /// Toggles the pause when pressed, see `handle_pause_key`. Defaults to `Some(Key::Escape)`.
+    pub key: Option<Key>,
+}
+
+impl Default for Paused {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Paused {
+    pub fn new() -> Self {
+        Paused {
+            paused: false,
+            resume_scale: 1.0,
+            key: Some(Key::Escape),
+        }
+    }
+
+    /// Sets the key that toggles the pause, `None` leaves pausing to the game.
+    pub fn with_key(mut self, key: Option<Key>) -> Self {
+        self.key = key;
+        self
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn pause(&mut self, time: &mut Time) {
+        if self.paused {
+            return;
+        }
+
+        self.paused = true;
+        self.resume_scale = time.time_scale;
+        time.time_scale = 0.0;
+    }
+
+    pub fn resume(&mut self, time: &mut Time) {
+        if !self.paused {
+            return;
+        }
+
+        self.paused = false;
+        time.time_scale = self.resume_scale;
+    }
+
+    /// Pauses or resumes and returns whether it is now paused
+    pub fn toggle(&mut self, time: &mut Time) -> bool {
+        if self.paused {
+            self.resume(time);
+        } else {
+            self.pause(time);
+        }
+        self.paused
+    }
+
+    /// Toggles the pause when the key is pressed, run in an input workload that isn't paused.
+    /// Skipped while the TextInputBuffer has the keyboard, so Escape cancelling text entry doesn't also pause.
+    pub fn handle_pause_key(
+        ctx: &mut Context,
+        mut paused: UniqueViewMut<Paused>,
+        mut time: UniqueViewMut<Time>,
+        text_input: UniqueView<TextInputBuffer>,
+    ) {
+        if let Some(key) = paused.key {
+            if input::is_key_pressed(ctx, key) && !text_input.blocks_key(ctx, key) {
+                paused.toggle(&mut time);
+            }
+        }
+    }
+}
+
+/// Dummy trait to allow adding a method to World
+pub trait PausedCreator {
+    fn add_paused(&mut self);
+}
+
+impl PausedCreator for World {
+    /// Time has to be added too, by `add_physics_workload` or `add_time`, and a TextInputBuffer by `add_text_input`
+    fn add_paused(&mut self) {
+        self.add_unique(Paused::new());
+    }
+}
@@ -0,0 +1,261 @@
+use std::{
+    collections::VecDeque,
+    thread,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+use shipyard::*;
+use tetra::{
+    graphics::{
+        self,
+        text::{
+            Font,
+            Text,
+        },
+        Color,
+        DrawParams,
+    },
+    math::{
+        Mat4,
+        Vec2,
+    },
+    Context,
+    Event,
+};
+use crate::debug::{
+    DebugFlag,
+    DebugFlags,
+};
+
+/// Measured frames and fixed updates per second, averaged over the last second.
+/// Run `PerfStats::update_system` in the fixed update workload and call `record_draw` once per draw.
+#[derive(Clone, Debug)]
+pub struct PerfStats {
+    updates: VecDeque<Instant>,
+    draws: VecDeque<Instant>,
+    /// How far back the rates are averaged over. Defaults to 1 second.
+    pub window: Duration,
+}
+
+impl Default for PerfStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PerfStats {
+    pub fn new() -> Self {
+        PerfStats {
+            updates: VecDeque::new(),
+            draws: VecDeque::new(),
+            window: Duration::from_secs(1),
+        }
+    }
+
+    fn record(times: &mut VecDeque<Instant>, now: Instant, window: Duration) {
+        times.push_back(now);
+        while times.front().map_or(false, |time| now.duration_since(*time) > window) {
+            times.pop_front();
+        }
+    }
+
+    fn rate(&self, times: &VecDeque<Instant>) -> f64 {
+        match (times.front(), times.back()) {
+            (Some(first), Some(last)) if times.len() > 1 => {
+                let span = last.duration_since(*first).as_secs_f64();
+                if span > 0.0 { (times.len() - 1) as f64 / span } else { 0.0 }
+            },
+            _ => 0.0,
+        }
+    }
+
+    pub fn record_update(&mut self) {
+        let window = self.window;
+        Self::record(&mut self.updates, Instant::now(), window);
+    }
+
+    pub fn record_draw(&mut self) {
+        let window = self.window;
+        Self::record(&mut self.draws, Instant::now(), window);
+    }
+
+    /// Frames drawn per second
+    pub fn fps(&self) -> f64 {
+        self.rate(&self.draws)
+    }
+
+    /// Fixed updates run per second
+    pub fn ups(&self) -> f64 {
+        self.rate(&self.updates)
+    }
+
+    /// Average milliseconds between frames, 0.0 until two frames have been drawn
+    pub fn frame_time_ms(&self) -> f64 {
+        let fps = self.fps();
+        if fps > 0.0 { 1000.0 / fps } else { 0.0 }
+    }
+
+    pub fn update_system(mut stats: UniqueViewMut<PerfStats>) {
+        stats.record_update();
+    }
+
+    /// Draws the frame rate, update rate and frame time in the top left of the screen while `DebugFlag::FpsOverlay` is on.
+    /// Run after the DrawBuffer has been flushed, the font is the game's as the engine doesn't ship one.
+    pub fn draw_fps_overlay(ctx: &mut Context, font: &Font, stats: UniqueView<PerfStats>, flags: UniqueView<DebugFlags>) {
+        if !flags.get(DebugFlag::FpsOverlay) {
+            return;
+        }
+
+        graphics::set_transform_matrix(ctx, Mat4::identity());
+        let mut text = Text::new(
+            format!("{:.0} fps  {:.0} ups  {:.1} ms", stats.fps(), stats.ups(), stats.frame_time_ms()),
+            font.clone(),
+        );
+        text.draw(ctx, DrawParams::new()
+            .position(Vec2::new(4.0, 4.0))
+            .color(Color::WHITE)
+        );
+    }
+}
+
+/// Caps the frame rate and skips drawing while the window is unfocused or minimized, for long running simulations
+/// that shouldn't keep a core busy in the background. Call `handle_event` from `State::event`, and at the start
+/// of `State::draw` return early if `begin_draw` is false.
+///
+/// While the window is inactive the frame rate is also capped to `background_fps`, as skipped draws don't present
+/// and so don't wait for vsync, and a minimized window may not wait for it even when drawing.
+#[derive(Clone, Debug)]
+pub struct FrameLimiter {
+    /// Defaults to `None`, which doesn't cap the frame rate
+    pub max_fps: Option<u32>,
+    /// Defaults to false
+    pub skip_unfocused: bool,
+    /// The frame rate cap while the window is unfocused or minimized, `None` leaves it at `max_fps`. Defaults to `Some(10)`.
+    pub background_fps: Option<u32>,
+
+    focused: bool,
+    minimized: bool,
+    last_frame: Option<Instant>,
+}
+
+impl Default for FrameLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameLimiter {
+    pub fn new() -> Self {
+        FrameLimiter {
+            max_fps: None,
+            skip_unfocused: false,
+            background_fps: Some(10),
+
+            focused: true,
+            minimized: false,
+            last_frame: None,
+        }
+    }
+
+    /// Sets the frame rate cap.
+    pub fn with_max_fps(mut self, max_fps: u32) -> Self {
+        self.max_fps = Some(max_fps);
+        self
+    }
+
+    /// Sets whether drawing is skipped while the window is unfocused or minimized.
+    pub fn with_skip_unfocused(mut self, skip_unfocused: bool) -> Self {
+        self.skip_unfocused = skip_unfocused;
+        self
+    }
+
+    /// Sets the frame rate cap while the window is inactive.
+    pub fn with_background_fps(mut self, background_fps: Option<u32>) -> Self {
+        self.background_fps = background_fps;
+        self
+    }
+
+    pub fn handle_event(&mut self, event: &Event) {
+        match event {
+            Event::FocusGained => self.focused = true,
+            Event::FocusLost => self.focused = false,
+            Event::Minimized => self.minimized = true,
+            Event::Restored | Event::Maximized => self.minimized = false,
+            _ => {},
+        }
+    }
+
+    /// Whether the window is focused and not minimized, as far as the events passed to `handle_event` tell
+    pub fn is_active(&self) -> bool {
+        self.focused && !self.minimized
+    }
+
+    /// The frame rate `begin_draw` currently caps to, the lower of `max_fps` and `background_fps` while inactive
+    pub fn frame_rate_cap(&self) -> Option<u32> {
+        if self.is_active() {
+            return self.max_fps;
+        }
+
+        match (self.max_fps, self.background_fps) {
+            (Some(max_fps), Some(background_fps)) => Some(max_fps.min(background_fps)),
+            (max_fps, background_fps) => max_fps.or(background_fps),
+        }
+    }
+
+    /// Sleeps until the next frame is due if there is a cap, then returns whether the frame should be drawn
+    pub fn begin_draw(&mut self) -> bool {
+        if let (Some(max_fps), Some(last_frame)) = (self.frame_rate_cap(), self.last_frame) {
+            let frame_time = Duration::from_secs_f64(1.0 / max_fps.max(1) as f64);
+            let elapsed = last_frame.elapsed();
+            if elapsed < frame_time {
+                thread::sleep(frame_time - elapsed);
+            }
+        }
+        self.last_frame = Some(Instant::now());
+
+        !self.skip_unfocused || self.is_active()
+    }
+}
+
+/// Dummy trait to allow adding a method to World
+pub trait PerfStatsCreator {
+    fn add_perf_stats(&mut self);
+}
+
+impl PerfStatsCreator for World {
+    fn add_perf_stats(&mut self) {
+        self.add_unique(PerfStats::new());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inactive_windows_are_capped_to_the_background_rate() {
+        let mut limiter = FrameLimiter::new().with_skip_unfocused(true);
+        assert_eq!(limiter.frame_rate_cap(), None);
+        assert!(limiter.begin_draw());
+
+        limiter.handle_event(&Event::Minimized);
+        assert_eq!(limiter.frame_rate_cap(), Some(10));
+        assert!(!limiter.begin_draw());
+
+        limiter.max_fps = Some(5);
+        assert_eq!(limiter.frame_rate_cap(), Some(5));
+        limiter.max_fps = Some(60);
+        assert_eq!(limiter.frame_rate_cap(), Some(10));
+
+        limiter.handle_event(&Event::Restored);
+        limiter.handle_event(&Event::FocusLost);
+        limiter.background_fps = None;
+        assert_eq!(limiter.frame_rate_cap(), Some(60));
+
+        limiter.handle_event(&Event::FocusGained);
+        assert!(limiter.is_active());
+        assert_eq!(limiter.frame_rate_cap(), Some(60));
+    }
+}
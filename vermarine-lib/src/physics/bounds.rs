@@ -0,0 +1,82 @@
+use super::*;
+use crate::events::Events;
+
+/// What happens to a body that moves outside of the world bounds
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BoundsMode {
+    /// The body is pushed back to the nearest point inside the bounds
+    Clamp,
+    /// The body comes out on the opposite side, for toroidal worlds
+    Wrap,
+    /// The body is left where it is and an OutOfBounds event is sent so the game can despawn it
+    Report,
+}
+
+/// The area bodies are kept in, checked against each body's position whenever it moves
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct WorldBounds {
+    pub min: Vec2<f64>,
+    pub max: Vec2<f64>,
+    /// Used for bodies that don't set their own `bounds_mode`. Defaults to `BoundsMode::Report`.
+    pub default_mode: BoundsMode,
+}
+
+impl WorldBounds {
+    pub fn new(min: Vec2<f64>, max: Vec2<f64>) -> Self {
+        WorldBounds {
+            min,
+            max,
+            default_mode: BoundsMode::Report,
+        }
+    }
+
+    /// Sets the mode used for bodies that don't set their own.
+    pub fn with_default_mode(mut self, default_mode: BoundsMode) -> Self {
+        self.default_mode = default_mode;
+        self
+    }
+
+    /// The body's own mode, or the default mode if it doesn't have one
+    pub fn mode_for(&self, body: &CollisionBody) -> BoundsMode {
+        body.bounds_mode.unwrap_or(self.default_mode)
+    }
+
+    pub fn contains(&self, position: Vec2<f64>) -> bool {
+        position.x >= self.min.x && position.x <= self.max.x
+            && position.y >= self.min.y && position.y <= self.max.y
+    }
+
+    /// Where the mode moves a body at the position to, `None` if the body is left where it is
+    pub fn apply(&self, mode: BoundsMode, position: Vec2<f64>) -> Option<Vec2<f64>> {
+        if self.contains(position) {
+            return None;
+        }
+
+        let size = self.max - self.min;
+        match mode {
+            BoundsMode::Clamp => Some(Vec2::new(
+                position.x.max(self.min.x).min(self.max.x),
+                position.y.max(self.min.y).min(self.max.y),
+            )),
+            BoundsMode::Wrap => Some(Vec2::new(
+                if size.x > 0.0 { self.min.x + (position.x - self.min.x).rem_euclid(size.x) } else { self.min.x },
+                if size.y > 0.0 { self.min.y + (position.y - self.min.y).rem_euclid(size.y) } else { self.min.y },
+            )),
+            BoundsMode::Report => None,
+        }
+    }
+}
+
+/// Sent for bodies with `BoundsMode::Report` that have moved outside of the world bounds
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct OutOfBounds {
+    pub entity: EntityId,
+    pub position: Vec2<f64>,
+}
+
+/// Sends the OutOfBounds events recorded by the PhysicsWorld since this last ran
+pub fn send_out_of_bounds_events(mut world: UniqueViewMut<PhysicsWorld>, mut events: UniqueViewMut<Events<OutOfBounds>>) {
+    for event in world.take_out_of_bounds().into_iter() {
+        events.send(event);
+    }
+}
@@ -0,0 +1,260 @@
+use super::*;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+/// Finds the bodies that might be colliding so that the narrowphase only has to check those. The PhysicsWorld
+/// owns one, `SpatialBuckets` by default. Pick another with `add_physics_workload_with_broadphase`, e.g.
+/// `SweepAndPrune` for bodies spread far apart or bodies that would span many buckets.
+///
+/// Bodies are always inserted and removed with the transform and AABB they were last inserted with.
+pub trait Broadphase: Send + Sync {
+    fn insert(&mut self, id: EntityId, transform: &Transform, aabb: &AABB);
+
+    /// Inserts every body, implementations can override it to grow their storage once
+    fn insert_many(&mut self, items: &[(EntityId, &Transform, &AABB)]) {
+        for (id, transform, aabb) in items.iter() {
+            self.insert(*id, transform, aabb);
+        }
+    }
+
+    fn remove(&mut self, id: EntityId, transform: &Transform, aabb: &AABB);
+
+    /// Every body that might overlap the AABB at the transform, other than id. Allowed to grow the broadphase.
+    fn nearby(&mut self, id: EntityId, transform: &Transform, aabb: &AABB) -> Vec<EntityId>;
+
+    /// Every body that might overlap the AABB at the transform, without changing the broadphase
+    fn query(&self, transform: &Transform, aabb: &AABB) -> Vec<EntityId>;
+
+    /// Whether the body was inserted with this transform and AABB
+    fn contains(&self, id: EntityId, transform: &Transform, aabb: &AABB) -> bool;
+
+    /// Calls visit with groups of bodies, nearest to the point first, along with a distance that every body in a
+    /// later group is at least as far from the point as. Stops when visit returns false. Used by `nearest_body`.
+    fn search_outward(&self, point: Vec2<f64>, visit: &mut dyn FnMut(&[EntityId], f64) -> bool);
+
+    fn stats(&self, span_threshold: usize) -> BroadphaseStats;
+
+    /// The world space area of every occupied cell and how many bodies are in it, drawn by the spatial hash heatmap.
+    /// Broadphases without cells return nothing.
+    fn occupied_cells(&self) -> Vec<(AABB, usize)> {
+        vec![]
+    }
+
+    /// Empties the broadphase, the caller is expected to reinsert every body.
+    /// Grid based broadphases also change their cell size, others ignore it.
+    fn reset_with_cell_size(&mut self, cell_width: f64, cell_height: f64);
+}
+
+impl Broadphase for SpatialBuckets {
+    fn insert(&mut self, id: EntityId, transform: &Transform, aabb: &AABB) {
+        SpatialBuckets::insert(self, id, transform, aabb);
+    }
+
+    fn insert_many(&mut self, items: &[(EntityId, &Transform, &AABB)]) {
+        SpatialBuckets::insert_many(self, items.iter().copied());
+    }
+
+    fn remove(&mut self, id: EntityId, transform: &Transform, aabb: &AABB) {
+        SpatialBuckets::remove(self, id, transform, aabb);
+    }
+
+    fn nearby(&mut self, id: EntityId, transform: &Transform, aabb: &AABB) -> Vec<EntityId> {
+        SpatialBuckets::nearby(self, id, transform, aabb)
+    }
+
+    fn query(&self, transform: &Transform, aabb: &AABB) -> Vec<EntityId> {
+        SpatialBuckets::query(self, transform, aabb)
+    }
+
+    fn contains(&self, id: EntityId, transform: &Transform, aabb: &AABB) -> bool {
+        SpatialBuckets::contains(self, id, transform, aabb)
+    }
+
+    /// Visits the buckets in rings outward from the point's bucket
+    fn search_outward(&self, point: Vec2<f64>, visit: &mut dyn FnMut(&[EntityId], f64) -> bool) {
+        let (x, y) = self.point_to_cell(point.x, point.y);
+        let (bucket_width, bucket_height) = self.bucket_size();
+        let cell_size = bucket_width.min(bucket_height);
+
+        for radius in 0..=self.max_ring(x, y) {
+            // Anything in the remaining rings is at least this far away from the point
+            if !visit(&self.ring(x, y, radius), radius as f64 * cell_size) {
+                return;
+            }
+        }
+    }
+
+    fn stats(&self, span_threshold: usize) -> BroadphaseStats {
+        SpatialBuckets::stats(self, span_threshold)
+    }
+
+    fn occupied_cells(&self) -> Vec<(AABB, usize)> {
+        SpatialBuckets::occupied_cells(self)
+    }
+
+    fn reset_with_cell_size(&mut self, cell_width: f64, cell_height: f64) {
+        SpatialBuckets::reset_with_cell_size(self, cell_width, cell_height);
+    }
+}
+
+//
+// Sweep and prune
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct SweepEntry {
+    id: EntityId,
+    min: Vec2<f64>,
+    max: Vec2<f64>,
+}
+
+/// Sorts entries by their left edge, the entity's index breaks ties so every body has its own key
+type SweepKey = (u64, usize);
+
+/// Keeps the bodies sorted by the left edge of their AABB, queries sweep along x from the widest body's width left
+/// of the area until they pass its right edge. Unlike SpatialBuckets it never allocates space for empty areas and
+/// large bodies cost the same to insert as small ones, but a single very wide body makes every query sweep further.
+///
+/// Inserting and removing are logarithmic in the number of bodies, and queries are logarithmic plus the number of
+/// bodies whose left edge is within the swept range.
+#[derive(Clone, Debug, Default)]
+pub struct SweepAndPrune {
+    entries: BTreeMap<SweepKey, SweepEntry>,
+    /// How many bodies have each width, the last one is how far left of an area a query has to start
+    widths: BTreeMap<u64, usize>,
+}
+
+/// Maps a float to an integer with the same ordering, -0.0 is treated as 0.0 and NaNs come after infinity
+fn ordered_bits(value: f64) -> u64 {
+    if value.is_nan() {
+        return u64::MAX;
+    }
+
+    let bits = if value == 0.0 { 0.0f64.to_bits() } else { value.to_bits() };
+    if bits & 0x8000_0000_0000_0000 != 0 {
+        !bits
+    } else {
+        bits | 0x8000_0000_0000_0000
+    }
+}
+
+impl SweepAndPrune {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn bounds(transform: &Transform, aabb: &AABB) -> (Vec2<f64>, Vec2<f64>) {
+        let min = Vec2::new(transform.x + aabb.dx, transform.y + aabb.dy);
+        (min, min + Vec2::new(aabb.width, aabb.height))
+    }
+
+    fn key(id: EntityId, min: Vec2<f64>) -> SweepKey {
+        (ordered_bits(min.x), id.uindex())
+    }
+
+    fn max_width(&self) -> f64 {
+        self.widths.keys().next_back().map_or(0.0, |width| f64::from_bits(*width))
+    }
+
+    /// Every entry whose AABB overlaps the area, the sweep starts at the widest body's width left of the area
+    /// as nothing further left can reach it
+    fn overlapping(&self, min: Vec2<f64>, max: Vec2<f64>) -> impl Iterator<Item = &SweepEntry> + '_ {
+        let start = ordered_bits(min.x - self.max_width());
+        // Only an inverted area can end before it starts, nothing overlaps it but range would panic
+        let end = ordered_bits(max.x).max(start);
+
+        self.entries.range((start, 0)..=(end, usize::MAX))
+            .map(|(_, entry)| entry)
+            .filter(move |entry| entry.max.x >= min.x && entry.min.y <= max.y && entry.max.y >= min.y)
+    }
+}
+
+impl Broadphase for SweepAndPrune {
+    fn insert(&mut self, id: EntityId, transform: &Transform, aabb: &AABB) {
+        let (min, max) = Self::bounds(transform, aabb);
+        self.entries.insert(Self::key(id, min), SweepEntry { id, min, max });
+        // Widths are never negative so their bits sort the same as their values
+        *self.widths.entry(aabb.width.max(0.0).to_bits()).or_insert(0) += 1;
+    }
+
+    fn remove(&mut self, id: EntityId, transform: &Transform, aabb: &AABB) {
+        let (min, _) = Self::bounds(transform, aabb);
+        if self.entries.remove(&Self::key(id, min)).is_none() {
+            return;
+        }
+
+        let width = aabb.width.max(0.0).to_bits();
+        if let Some(count) = self.widths.get_mut(&width) {
+            *count -= 1;
+            if *count == 0 {
+                self.widths.remove(&width);
+            }
+        }
+    }
+
+    fn nearby(&mut self, id: EntityId, transform: &Transform, aabb: &AABB) -> Vec<EntityId> {
+        let mut nearby = self.query(transform, aabb);
+        nearby.retain(|other| *other != id);
+        nearby
+    }
+
+    fn query(&self, transform: &Transform, aabb: &AABB) -> Vec<EntityId> {
+        let (min, max) = Self::bounds(transform, aabb);
+        self.overlapping(min, max)
+            .map(|entry| entry.id)
+            .collect()
+    }
+
+    fn contains(&self, id: EntityId, transform: &Transform, aabb: &AABB) -> bool {
+        let (min, max) = Self::bounds(transform, aabb);
+        self.entries.get(&Self::key(id, min)).map_or(false, |entry| entry.id == id && entry.min == min && entry.max == max)
+    }
+
+    /// Searches squares around the point that double in size each time, visiting the bodies first reached by each
+    /// square one at a time ordered by how far their AABB is from the point
+    fn search_outward(&self, point: Vec2<f64>, visit: &mut dyn FnMut(&[EntityId], f64) -> bool) {
+        fn gap(value: f64, min: f64, max: f64) -> f64 {
+            (min - value).max(value - max).max(0.0)
+        }
+
+        let mut visited = 0;
+        let mut inner = -1.0;
+        let mut radius: f64 = 1.0;
+        while visited < self.entries.len() && radius.is_finite() {
+            let reach = Vec2::new(radius, radius);
+            let mut ring: Vec<(f64, EntityId)> = self.overlapping(point - reach, point + reach)
+                .map(|entry| (gap(point.x, entry.min.x, entry.max.x).max(gap(point.y, entry.min.y, entry.max.y)), entry.id))
+                .filter(|(distance, _)| *distance > inner && *distance <= radius)
+                .collect();
+            ring.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+            visited += ring.len();
+
+            // Anything in a later square is further than radius, so further than every body in this one
+            for (distance, id) in ring.into_iter() {
+                if !visit(&[id], distance) {
+                    return;
+                }
+            }
+
+            inner = radius;
+            radius *= 2.0;
+        }
+    }
+
+    /// The sorted list counts as a single bucket
+    fn stats(&self, _span_threshold: usize) -> BroadphaseStats {
+        let bodies = self.entries.len();
+        BroadphaseStats {
+            bucket_count: 1,
+            occupied_buckets: if bodies > 0 { 1 } else { 0 },
+            bodies,
+            average_occupancy: bodies as f64,
+            max_occupancy: bodies,
+            bodies_spanning_many: 0,
+        }
+    }
+
+    fn reset_with_cell_size(&mut self, _cell_width: f64, _cell_height: f64) {
+        self.entries.clear();
+        self.widths.clear();
+    }
+}
@@ -0,0 +1,250 @@
+use super::*;
+use std::collections::HashMap;
+
+/// Named collision layers and what each collides with by default, added as a unique by `add_physics_workload`
+/// so that every CollisionBodyBuilder in the World agrees on the bits.
+///
+/// ```ignore
+/// layers.define("player", 1 << 0, 1 << 1 | 1 << 2);
+/// layers.define("enemy", 1 << 1, 1 << 0 | 1 << 2);
+/// layers.define("wall", 1 << 2, 0);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct CollisionLayers {
+    layers: HashMap<&'static str, (u64, u64)>,
+    /// Used by colliders that don't name a layer
+    pub default_layer: Option<&'static str>,
+}
+
+impl CollisionLayers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Names a layer and sets the mask its colliders collide with unless the builder overrides it
+    pub fn define(&mut self, name: &'static str, layer: u64, collides_with: u64) {
+        self.layers.insert(name, (layer, collides_with));
+    }
+
+    /// Sets the layer used by colliders that don't name one.
+    pub fn with_default_layer(mut self, name: &'static str) -> Self {
+        self.default_layer = Some(name);
+        self
+    }
+
+    /// The layer bits and default collides_with mask of the named layer
+    pub fn get(&self, name: &str) -> Option<(u64, u64)> {
+        self.layers.get(name).copied()
+    }
+
+    /// The bits of all the named layers combined, for building collides_with masks
+    pub fn mask(&self, names: &[&'static str]) -> Result<u64, BodyBuildError> {
+        names.iter().try_fold(0, |mask, name| {
+            self.get(name).map(|(layer, _)| mask | layer).ok_or(BodyBuildError::UnknownLayer(*name))
+        })
+    }
+}
+
+/// Why a CollisionBodyBuilder couldn't build its body, indices count colliders then sensors in the order they were added
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BodyBuildError {
+    UnknownLayer(&'static str),
+    /// The collider has no layer and the CollisionLayers has no default
+    NoLayer(usize),
+    InvalidPolygon(usize, PolygonError),
+    /// The body has no colliders or sensors
+    Empty,
+    /// A collider property was set before any collider or sensor was started
+    NoPart,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum LayerRef {
+    Name(&'static str),
+    Bits(u64),
+}
+
+#[derive(Clone)]
+struct PartDesc {
+    shape: CollisionShape,
+    sensor: bool,
+    layer: Option<LayerRef>,
+    collides_with: Option<u64>,
+    collides_with_names: Vec<&'static str>,
+    offset: Vec2<f64>,
+    rotation: f64,
+    group_id: Option<NonZeroU64>,
+    material: Material,
+}
+
+/// Builds a CollisionBody one collider at a time. `collider` and `sensor` start a new part, the methods after
+/// them set up that part, `build` fails with `BodyBuildError::NoPart` if one is called before any part was started.
+/// Layers can be named, they are looked up in the CollisionLayers unique when building.
+///
+/// ```ignore
+/// let body = CollisionBodyBuilder::new()
+///     .collider(CollisionShape::Circle(8.0)).layer("enemy")
+///     .sensor(CollisionShape::Circle(64.0)).offset(0.0, -8.0).layer("enemy").collides_with_layers(&["player"])
+///     .mass(2.0)
+///     .build(&layers)?;
+/// ```
+#[derive(Clone, Default)]
+pub struct CollisionBodyBuilder {
+    parts: Vec<PartDesc>,
+    // The first misuse of the builder, returned by build
+    error: Option<BodyBuildError>,
+    mass: Option<f64>,
+    bounds_mode: Option<BoundsMode>,
+    carrier: Option<Carrier>,
+}
+
+impl CollisionBodyBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn part(mut self, shape: CollisionShape, sensor: bool) -> Self {
+        self.parts.push(PartDesc {
+            shape,
+            sensor,
+            layer: None,
+            collides_with: None,
+            collides_with_names: vec![],
+            offset: Vec2::zero(),
+            rotation: 0.0,
+            group_id: None,
+            material: Material::default(),
+        });
+        self
+    }
+
+    /// Changes the current part, or records that there isn't one yet for `build` to return
+    fn with_last_part(mut self, change: impl FnOnce(&mut PartDesc)) -> Self {
+        match self.parts.last_mut() {
+            Some(part) => change(part),
+            None => {
+                self.error.get_or_insert(BodyBuildError::NoPart);
+            },
+        }
+        self
+    }
+
+    /// Starts a new collider
+    pub fn collider(self, shape: CollisionShape) -> Self {
+        self.part(shape, false)
+    }
+
+    /// Starts a new sensor
+    pub fn sensor(self, shape: CollisionShape) -> Self {
+        self.part(shape, true)
+    }
+
+    /// Sets the position of the current collider relative to the body.
+    pub fn offset(self, x: f64, y: f64) -> Self {
+        self.with_last_part(|part| part.offset = Vec2::new(x, y))
+    }
+
+    /// Sets the rotation of the current collider around its offset, in radians.
+    pub fn rotation(self, rotation: f64) -> Self {
+        self.with_last_part(|part| part.rotation = rotation)
+    }
+
+    /// Puts the current collider on a named layer, it collides with the layer's default mask unless overridden.
+    pub fn layer(self, name: &'static str) -> Self {
+        self.with_last_part(|part| part.layer = Some(LayerRef::Name(name)))
+    }
+
+    /// Puts the current collider on raw layer bits.
+    pub fn layer_bits(self, layer: u64) -> Self {
+        self.with_last_part(|part| part.layer = Some(LayerRef::Bits(layer)))
+    }
+
+    /// Sets the mask of layers the current collider collides with.
+    pub fn collides_with(self, collides_with: u64) -> Self {
+        self.with_last_part(|part| part.collides_with = Some(collides_with))
+    }
+
+    /// Sets the layers the current collider collides with by name.
+    pub fn collides_with_layers(self, names: &[&'static str]) -> Self {
+        self.with_last_part(|part| part.collides_with_names = names.to_vec())
+    }
+
+    /// Sets the exclusion group of the current collider.
+    pub fn group(self, group_id: NonZeroU64) -> Self {
+        self.with_last_part(|part| part.group_id = Some(group_id))
+    }
+
+    /// Sets the material of the current collider.
+    pub fn material(self, material: Material) -> Self {
+        self.with_last_part(|part| part.material = material)
+    }
+
+    /// Sets the mass of the body.
+    pub fn mass(mut self, mass: f64) -> Self {
+        self.mass = Some(mass);
+        self
+    }
+
+    /// Sets what happens when the body leaves the world bounds.
+    pub fn bounds_mode(mut self, bounds_mode: BoundsMode) -> Self {
+        self.bounds_mode = Some(bounds_mode);
+        self
+    }
+
+    /// Makes the body carry the bodies standing on it.
+    pub fn carrier(mut self, carrier: Carrier) -> Self {
+        self.carrier = Some(carrier);
+        self
+    }
+
+    /// Resolves the layers and validates the polygons, which are rewound if needed. The body's AABB covers every part.
+    pub fn build(self, layers: &CollisionLayers) -> Result<CollisionBody, BodyBuildError> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        if self.parts.is_empty() {
+            return Err(BodyBuildError::Empty);
+        }
+
+        let mut colliders = vec![];
+        let mut sensors = vec![];
+
+        let (collider_parts, sensor_parts): (Vec<PartDesc>, Vec<PartDesc>) = self.parts.into_iter().partition(|part| !part.sensor);
+        for (index, part) in collider_parts.into_iter().chain(sensor_parts.into_iter()).enumerate() {
+            let layer = part.layer.or_else(|| layers.default_layer.map(LayerRef::Name)).ok_or(BodyBuildError::NoLayer(index))?;
+            let (layer, default_collides_with) = match layer {
+                LayerRef::Name(name) => layers.get(name).ok_or(BodyBuildError::UnknownLayer(name))?,
+                LayerRef::Bits(bits) => (bits, 0),
+            };
+
+            let collides_with = match part.collides_with {
+                Some(mask) => mask,
+                None if !part.collides_with_names.is_empty() => layers.mask(&part.collides_with_names)?,
+                None => default_collides_with,
+            };
+
+            let mut shape = part.shape;
+            if let CollisionShape::Polygon(vertices) = &mut shape {
+                convex::validate(vertices).map_err(|error| BodyBuildError::InvalidPolygon(index, error))?;
+            }
+
+            let mut collider = Collider::new(shape, layer, collides_with)
+                .with_offset(part.offset.x, part.offset.y)
+                .with_rotation(part.rotation)
+                .with_material(part.material);
+            collider.group_id = part.group_id;
+
+            if part.sensor {
+                sensors.push(collider);
+            } else {
+                colliders.push(collider);
+            }
+        }
+
+        let mut body = CollisionBody::from_parts(colliders, sensors);
+        body.mass = self.mass;
+        body.bounds_mode = self.bounds_mode;
+        body.carrier = self.carrier;
+        Ok(body)
+    }
+}
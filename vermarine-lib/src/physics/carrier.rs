@@ -0,0 +1,132 @@
+use super::*;
+
+/// Makes a body carry the bodies standing on it, e.g. a moving platform. Whenever the carrier is moved through
+/// the PhysicsWorld its riders are moved by the same delta straight afterwards, colliding as they go, so they
+/// don't slide off or jitter.
+///
+/// A body rides the carrier if one of its colliders collides with one of the carrier's colliders when the carrier
+/// is nudged `probe` towards `up`, and the contact normal is within `max_angle` of `up`. With `use_sensors` anything
+/// overlapping one of the carrier's sensors rides it as well, for platforms with a sensor covering their top.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Carrier {
+    /// The direction riders stand in relative to the carrier. Defaults to `(0.0, -1.0)`.
+    pub up: Vec2<f64>,
+    /// The steepest contact in radians away from up that still counts as standing on the carrier. Defaults to 45 degrees.
+    pub max_angle: f64,
+    /// How close to the carrier a body has to be to count as touching it. Defaults to 0.5.
+    pub probe: f64,
+    /// Defaults to false
+    pub use_sensors: bool,
+}
+
+impl Default for Carrier {
+    fn default() -> Self {
+        Carrier {
+            up: Vec2::new(0.0, -1.0),
+            max_angle: std::f64::consts::FRAC_PI_4,
+            probe: 0.5,
+            use_sensors: false,
+        }
+    }
+}
+
+impl Carrier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the direction riders stand in.
+    pub fn with_up(mut self, up: Vec2<f64>) -> Self {
+        self.up = up;
+        self
+    }
+
+    /// Sets the steepest contact that counts as standing on the carrier.
+    pub fn with_max_angle(mut self, max_angle: f64) -> Self {
+        self.max_angle = max_angle;
+        self
+    }
+
+    /// Sets how close a body has to be to count as touching.
+    pub fn with_probe(mut self, probe: f64) -> Self {
+        self.probe = probe;
+        self
+    }
+
+    /// Sets whether bodies overlapping the carrier's sensors ride it.
+    pub fn with_sensors(mut self, use_sensors: bool) -> Self {
+        self.use_sensors = use_sensors;
+        self
+    }
+}
+
+impl PhysicsWorld {
+    /// The bodies standing on the carrier, empty if the body isn't a carrier. Bodies that are currently being
+    /// carried further down the chain are left out so that two carriers can't carry each other forever.
+    pub fn riders(&self, carrier: EntityId) -> Vec<EntityId> {
+        let (transform, body) = self.parts(carrier);
+        let settings = match body.carrier {
+            Some(settings) if body.is_enabled() && settings.up.magnitude_squared() > 0.0 => settings,
+            _ => return vec![],
+        };
+
+        let up = settings.up.normalized();
+        let min_dot = settings.max_angle.cos();
+        let mut riders: Vec<EntityId> = vec![];
+
+        if settings.use_sensors {
+            for collision in body.sensors.iter().flat_map(|sensor| sensor.overlapping.iter()) {
+                if !riders.contains(&collision.entity2) {
+                    riders.push(collision.entity2);
+                }
+            }
+        }
+
+        // Moving the carrier towards its riders makes anything touching it overlap it
+        let probed = Transform::new(transform.x + up.x * settings.probe, transform.y + up.y * settings.probe);
+        for id in self.broadphase().query(&probed, body.aabb()).into_iter() {
+            if id == carrier || riders.contains(&id) {
+                continue;
+            }
+
+            let (rider_transform, rider_body) = self.parts(id);
+            if !rider_body.is_enabled() {
+                continue;
+            }
+
+            let standing = body.colliders.iter().any(|collider| {
+                rider_body.colliders.iter().any(|rider_collider| {
+                    if rider_collider.collides_with & collider.collision_layer == 0 || collider.is_excluded_from(rider_collider) {
+                        return false;
+                    }
+
+                    // The mtv pushes the carrier out of the rider, so it points away from the rider
+                    match sat::collider_test(&probed, collider, rider_transform, rider_collider) {
+                        (true, Some(mtv)) if mtv.magnitude_squared() > 0.0 => (-mtv).normalized().dot(up) >= min_dot,
+                        _ => false,
+                    }
+                })
+            });
+
+            if standing {
+                riders.push(id);
+            }
+        }
+
+        riders.retain(|id| !self.carrying.contains(id));
+        riders
+    }
+
+    /// Moves the riders found before the carrier moved by the delta the carrier moved by
+    pub(crate) fn carry_riders(&mut self, carrier: EntityId, riders: Vec<EntityId>, delta: Vec2<f64>) {
+        if riders.is_empty() || delta.magnitude_squared() == 0.0 {
+            return;
+        }
+
+        self.carrying.push(carrier);
+        for rider in riders.into_iter() {
+            self.move_body_and_collide(rider, delta);
+        }
+        self.carrying.pop();
+    }
+}
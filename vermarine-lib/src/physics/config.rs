@@ -0,0 +1,137 @@
+use super::*;
+
+/// Global physics tuning, added as a unique by `add_physics_workload` and read by the physics systems every tick
+/// so it can be changed at runtime from game systems.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PhysicsConfig {
+    /// Acceleration applied to bodies affected by gravity, in meters per tick squared. Defaults to `(0.0, 0.0)`.
+    pub gravity: Vec2<f64>,
+
+    /// Fraction of velocity kept each tick by character controllers that don't specify their own damping. Defaults to `1.0`.
+    pub default_damping: f64,
+
+    /// The tallest ledge that `move_and_slide` will step up onto instead of treating as a wall.
+    pub max_step_height: f64,
+
+    /// The number of times overlapping bodies are re-checked and pushed apart per movement.
+    pub solver_iterations: usize,
+
+    /// The longest distance a single collision can push a body per solver iteration. Defaults to infinity.
+    pub max_correction: f64,
+
+    /// How many world units make up a meter, gravity is multiplied by it. Defaults to `1.0`.
+    pub units_per_meter: f64,
+
+    /// Whether movement is checked for non-finite or absurd values before it reaches the broadphase.
+    /// Defaults to true in debug builds.
+    pub validate: bool,
+
+    /// The longest single movement allowed when validating, longer movements are clamped. Defaults to `10_000.0`.
+    pub max_delta: f64,
+
+    /// Positions further than this from the origin on either axis are rejected when validating. Defaults to `1.0e7`.
+    pub max_coordinate: f64,
+
+    /// The area bodies are kept in. Defaults to `None`, which means unbounded.
+    pub bounds: Option<WorldBounds>,
+}
+
+impl Default for PhysicsConfig {
+    fn default() -> Self {
+        PhysicsConfig {
+            gravity: Vec2::zero(),
+            default_damping: 1.0,
+            max_step_height: 0.0,
+            solver_iterations: 4,
+            max_correction: f64::INFINITY,
+            units_per_meter: 1.0,
+
+            validate: cfg!(debug_assertions),
+            max_delta: 10_000.0,
+            max_coordinate: 1.0e7,
+
+            bounds: None,
+        }
+    }
+}
+
+impl PhysicsConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the gravity vector
+    pub fn gravity(mut self, gravity: Vec2<f64>) -> Self {
+        self.gravity = gravity;
+        self
+    }
+
+    /// Sets the default damping
+    pub fn default_damping(mut self, default_damping: f64) -> Self {
+        self.default_damping = default_damping;
+        self
+    }
+
+    /// Sets the max step height
+    pub fn max_step_height(mut self, max_step_height: f64) -> Self {
+        self.max_step_height = max_step_height;
+        self
+    }
+
+    /// Sets the number of solver iterations
+    pub fn solver_iterations(mut self, solver_iterations: usize) -> Self {
+        self.solver_iterations = solver_iterations;
+        self
+    }
+
+    /// Sets the max correction per solver iteration
+    pub fn max_correction(mut self, max_correction: f64) -> Self {
+        self.max_correction = max_correction;
+        self
+    }
+
+    /// Sets the number of world units per meter
+    pub fn units_per_meter(mut self, units_per_meter: f64) -> Self {
+        self.units_per_meter = units_per_meter;
+        self
+    }
+
+    /// Sets whether movement is validated
+    pub fn validate(mut self, validate: bool) -> Self {
+        self.validate = validate;
+        self
+    }
+
+    /// Sets the longest movement allowed when validating
+    pub fn max_delta(mut self, max_delta: f64) -> Self {
+        self.max_delta = max_delta;
+        self
+    }
+
+    /// Sets the furthest coordinate allowed when validating
+    pub fn max_coordinate(mut self, max_coordinate: f64) -> Self {
+        self.max_coordinate = max_coordinate;
+        self
+    }
+
+    /// Sets the world bounds
+    pub fn bounds(mut self, bounds: WorldBounds) -> Self {
+        self.bounds = Some(bounds);
+        self
+    }
+
+    /// Gravity in world units per tick squared
+    pub fn gravity_units(&self) -> Vec2<f64> {
+        self.gravity * self.units_per_meter
+    }
+
+    /// Converts a distance in meters to world units
+    pub fn meters_to_units(&self, meters: f64) -> f64 {
+        meters * self.units_per_meter
+    }
+
+    /// Converts a distance in world units to meters
+    pub fn units_to_meters(&self, units: f64) -> f64 {
+        units / self.units_per_meter
+    }
+}
@@ -0,0 +1,84 @@
+use std::collections::HashSet;
+use super::*;
+
+/// The pairs of bodies that were touching when `track_contact_pairs` last ran and the time before that.
+/// A pair is recorded in the order the first body's collider or sensor stored the overlap, queries check both orders.
+#[derive(Clone, Debug, Default)]
+pub struct ContactPairs {
+    current: HashSet<(EntityId, EntityId)>,
+    previous: HashSet<(EntityId, EntityId)>,
+}
+
+impl ContactPairs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn contains(&self, a: EntityId, b: EntityId) -> bool {
+        self.current.contains(&(a, b)) || self.current.contains(&(b, a))
+    }
+
+    pub fn contained(&self, a: EntityId, b: EntityId) -> bool {
+        self.previous.contains(&(a, b)) || self.previous.contains(&(b, a))
+    }
+
+    /// Replaces the current pairs, keeping the old ones as the previous pairs
+    pub fn snapshot<I: IntoIterator<Item = (EntityId, EntityId)>>(&mut self, pairs: I) {
+        std::mem::swap(&mut self.current, &mut self.previous);
+        self.current.clear();
+        self.current.extend(pairs);
+    }
+
+    pub fn clear(&mut self) {
+        self.current.clear();
+        self.previous.clear();
+    }
+}
+
+impl PhysicsWorld {
+    /// Whether the bodies were touching when `track_contact_pairs` last ran, sensor overlaps included
+    pub fn are_colliding(&self, a: EntityId, b: EntityId) -> bool {
+        self.contact_pairs.contains(a, b)
+    }
+
+    /// Whether the bodies were touching the time before `track_contact_pairs` last ran
+    pub fn were_colliding(&self, a: EntityId, b: EntityId) -> bool {
+        self.contact_pairs.contained(a, b)
+    }
+
+    /// Whether the bodies started touching during the last step
+    pub fn started_colliding(&self, a: EntityId, b: EntityId) -> bool {
+        self.are_colliding(a, b) && !self.were_colliding(a, b)
+    }
+
+    /// Whether the bodies stopped touching during the last step
+    pub fn stopped_colliding(&self, a: EntityId, b: EntityId) -> bool {
+        !self.are_colliding(a, b) && self.were_colliding(a, b)
+    }
+
+    /// Every collision currently recorded on the body's colliders and sensors, panics if the entity has no body
+    pub fn contacts_of(&self, entity: EntityId) -> impl Iterator<Item = &Collision> {
+        let body = self.collider(entity);
+        body.colliders.iter()
+            .chain(body.sensors.iter())
+            .flat_map(|collider| collider.overlapping.iter())
+    }
+
+    /// Records which bodies are touching right now, see `track_contact_pairs`
+    pub fn update_contact_pairs(&mut self) {
+        let pairs: Vec<(EntityId, EntityId)> = self.iter()
+            .flat_map(|(id, _, body)| {
+                body.colliders.iter()
+                    .chain(body.sensors.iter())
+                    .flat_map(|collider| collider.overlapping.iter())
+                    .map(move |collision| (id, collision.entity2))
+            })
+            .collect();
+        self.contact_pairs.snapshot(pairs);
+    }
+}
+
+/// Snapshots the touching pairs once per step so that `are_colliding`, `were_colliding` and friends are cheap lookups
+pub fn track_contact_pairs(mut world: UniqueViewMut<PhysicsWorld>) {
+    world.update_contact_pairs();
+}
@@ -0,0 +1,147 @@
+use super::*;
+
+/// Moves a body towards `desired_velocity` every tick using `PhysicsWorld::move_and_slide`.
+/// Game code sets `desired_velocity` from input and reads the grounded/wall flags for jumping and animation.
+#[derive(Clone, Debug)]
+pub struct CharacterController {
+    /// The velocity the character wants to move at, clamped to max_speed
+    pub desired_velocity: Vec2<f64>,
+    /// The velocity the character moved at last tick
+    pub velocity: Vec2<f64>,
+
+    pub max_speed: f64,
+    /// How much the velocity can change by per tick
+    pub acceleration: f64,
+    /// If true, the character falls with `PhysicsConfig::gravity` and only controls its horizontal movement
+    pub use_gravity: bool,
+    /// The steepest slope in radians that counts as floor
+    pub max_slope: f64,
+    /// The tallest ledge the character steps onto, `None` uses `PhysicsConfig::max_step_height`
+    pub max_step: Option<f64>,
+    /// Fraction of the velocity kept each tick before accelerating, `None` uses `PhysicsConfig::default_damping`
+    pub damping: Option<f64>,
+
+    is_grounded: bool,
+    is_on_wall: bool,
+    is_on_ceiling: bool,
+}
+
+impl CharacterController {
+    pub fn new(max_speed: f64, acceleration: f64) -> Self {
+        CharacterController {
+            desired_velocity: Vec2::zero(),
+            velocity: Vec2::zero(),
+
+            max_speed,
+            acceleration,
+            use_gravity: false,
+            max_slope: std::f64::consts::FRAC_PI_4,
+            max_step: None,
+            damping: None,
+
+            is_grounded: false,
+            is_on_wall: false,
+            is_on_ceiling: false,
+        }
+    }
+
+    /// Sets whether the character is affected by gravity
+    pub fn with_gravity(mut self, use_gravity: bool) -> Self {
+        self.use_gravity = use_gravity;
+        self
+    }
+
+    /// Sets the steepest walkable slope in radians
+    pub fn with_max_slope(mut self, max_slope: f64) -> Self {
+        self.max_slope = max_slope;
+        self
+    }
+
+    /// Sets the tallest ledge the character steps onto
+    pub fn with_max_step(mut self, max_step: f64) -> Self {
+        self.max_step = Some(max_step);
+        self
+    }
+
+    /// Sets the fraction of the velocity kept each tick
+    pub fn with_damping(mut self, damping: f64) -> Self {
+        self.damping = Some(damping);
+        self
+    }
+
+    /// True if the character was standing on a floor after its last move
+    pub fn is_grounded(&self) -> bool {
+        self.is_grounded
+    }
+
+    /// True if the character was pushed against a wall during its last move
+    pub fn is_on_wall(&self) -> bool {
+        self.is_on_wall
+    }
+
+    pub fn is_on_ceiling(&self) -> bool {
+        self.is_on_ceiling
+    }
+
+    /// Damps the velocity then changes it by one tick's worth of acceleration and gravity, multiplied by scale
+    pub(crate) fn accelerate(&mut self, gravity: Vec2<f64>, damping: f64, scale: f64) {
+        let gravity = gravity * scale;
+        self.velocity *= damping.max(0.0).powf(scale);
+
+        let mut target = self.desired_velocity;
+        if target.magnitude() > self.max_speed {
+            target = target.normalized() * self.max_speed;
+        }
+
+        if self.use_gravity {
+            // Only the axis perpendicular to gravity is controlled
+            let down = if gravity.magnitude_squared() > 0.0 { gravity.normalized() } else { Vec2::new(0.0, 1.0) };
+            let fall = self.velocity.dot(down);
+            let lateral = self.velocity - down * fall;
+            let target = target - down * target.dot(down);
+
+            self.velocity = approach(lateral, target, self.acceleration * scale) + down * fall + gravity;
+        } else {
+            self.velocity = approach(self.velocity, target, self.acceleration * scale);
+        }
+    }
+}
+
+fn approach(from: Vec2<f64>, to: Vec2<f64>, max_change: f64) -> Vec2<f64> {
+    let diff = to - from;
+    if diff.magnitude() <= max_change {
+        to
+    } else {
+        from + diff.normalized() * max_change
+    }
+}
+
+/// Accelerates every CharacterController and moves its body with `move_and_slide`
+pub fn update_character_controllers(
+    mut controllers: ViewMut<CharacterController>, 
+    bodies: View<PhysicsBody>, 
+    mut world: UniqueViewMut<PhysicsWorld>, 
+    config: UniqueView<PhysicsConfig>,
+    time: UniqueView<Time>,
+) {
+    if time.is_paused() {
+        return;
+    }
+    let scale = time.scale();
+
+    let gravity = config.gravity_units();
+    let up = if gravity.magnitude_squared() > 0.0 { -gravity.normalized() } else { Vec2::new(0.0, -1.0) };
+
+    for (id, (controller, _)) in (&mut controllers, &bodies).iter().with_id() {
+        let damping = controller.damping.unwrap_or(config.default_damping);
+        controller.accelerate(gravity, damping, scale);
+
+        let max_step = controller.max_step.unwrap_or(config.max_step_height);
+        let result = world.move_and_slide(id, controller.velocity * scale, up, controller.max_slope, max_step);
+
+        controller.velocity = result.velocity / scale;
+        controller.is_grounded = result.on_floor;
+        controller.is_on_wall = result.on_wall;
+        controller.is_on_ceiling = result.on_ceiling;
+    }
+}
@@ -0,0 +1,193 @@
+use super::*;
+
+/// Cross products smaller than this are treated as collinear
+const EPSILON: f64 = 1e-9;
+
+/// Why a vertex list can't be used as a polygon collider
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PolygonError {
+    TooFewVertices,
+    /// All of the vertices are on a line so the polygon has no area
+    Degenerate,
+    /// SAT only works on convex polygons, use `decompose` to split the polygon into convex parts
+    Concave,
+}
+
+fn cross(a: Vec2<f64>, b: Vec2<f64>) -> f64 {
+    a.x * b.y - a.y * b.x
+}
+
+/// Positive if the vertices are counter clockwise in y up coordinates, which is the winding used by `half_extents`
+pub fn signed_area(vertices: &[Vec2<f64>]) -> f64 {
+    let mut area = 0.0;
+    for (i, a) in vertices.iter().enumerate() {
+        let b = vertices[(i + 1) % vertices.len()];
+        area += cross(*a, b);
+    }
+    area / 2.0
+}
+
+/// Reverses the vertices if they are wound the opposite way to `half_extents`
+pub fn fix_winding(vertices: &mut Vec<Vec2<f64>>) {
+    if signed_area(vertices) < 0.0 {
+        vertices.reverse();
+    }
+}
+
+/// Whether every corner of the polygon turns the same way, collinear corners are allowed
+pub fn is_convex(vertices: &[Vec2<f64>]) -> bool {
+    let mut sign = 0.0;
+    for i in 0..vertices.len() {
+        let a = vertices[i];
+        let b = vertices[(i + 1) % vertices.len()];
+        let c = vertices[(i + 2) % vertices.len()];
+
+        let turn = cross(b - a, c - b);
+        if turn.abs() <= EPSILON {
+            continue;
+        }
+        if sign == 0.0 {
+            sign = turn.signum();
+        } else if turn.signum() != sign {
+            return false;
+        }
+    }
+    true
+}
+
+/// Fixes the winding of the vertices and checks that they form a convex polygon with an area
+pub fn validate(vertices: &mut Vec<Vec2<f64>>) -> Result<(), PolygonError> {
+    if vertices.len() < 3 {
+        return Err(PolygonError::TooFewVertices);
+    }
+    if signed_area(vertices).abs() <= EPSILON {
+        return Err(PolygonError::Degenerate);
+    }
+
+    fix_winding(vertices);
+    if !is_convex(vertices) {
+        return Err(PolygonError::Concave);
+    }
+    Ok(())
+}
+
+/// Splits a simple polygon, convex or concave, into convex polygons wound the same way as `half_extents`.
+/// The polygon is triangulated by ear clipping then triangles sharing an edge are merged while the result stays convex.
+/// Self intersecting polygons give a best effort result that may not cover the whole polygon.
+pub fn decompose(vertices: &[Vec2<f64>]) -> Result<Vec<Vec<Vec2<f64>>>, PolygonError> {
+    if vertices.len() < 3 {
+        return Err(PolygonError::TooFewVertices);
+    }
+    if signed_area(vertices).abs() <= EPSILON {
+        return Err(PolygonError::Degenerate);
+    }
+
+    let mut points = vertices.to_vec();
+    fix_winding(&mut points);
+    if is_convex(&points) {
+        return Ok(vec![points]);
+    }
+
+    let mut parts = triangulate(&points);
+
+    // Hertel-Mehlhorn, remove shared edges whenever the merged polygon is still convex
+    let mut merged = true;
+    while merged {
+        merged = false;
+        'search: for i in 0..parts.len() {
+            for j in (i + 1)..parts.len() {
+                if let Some(part) = merge(&parts[i], &parts[j]) {
+                    if is_convex(&part.iter().map(|index| points[*index]).collect::<Vec<_>>()) {
+                        parts[i] = part;
+                        parts.swap_remove(j);
+                        merged = true;
+                        break 'search;
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(parts.into_iter()
+        .map(|part| part.into_iter().map(|index| points[index]).collect())
+        .collect())
+}
+
+/// Ear clipping, the vertices have to be counter clockwise
+fn triangulate(points: &[Vec2<f64>]) -> Vec<Vec<usize>> {
+    let mut remaining: Vec<usize> = (0..points.len()).collect();
+    let mut triangles = vec![];
+
+    while remaining.len() > 3 {
+        let mut clipped = false;
+
+        for i in 0..remaining.len() {
+            let prev = remaining[(i + remaining.len() - 1) % remaining.len()];
+            let current = remaining[i];
+            let next = remaining[(i + 1) % remaining.len()];
+            let (a, b, c) = (points[prev], points[current], points[next]);
+
+            let turn = cross(b - a, c - b);
+            if turn.abs() <= EPSILON {
+                // Collinear vertices don't add anything to the shape
+                remaining.remove(i);
+                clipped = true;
+                break;
+            }
+            if turn < 0.0 {
+                continue;
+            }
+
+            let contains_other = remaining.iter()
+                .filter(|index| **index != prev && **index != current && **index != next)
+                .any(|index| triangle_contains(a, b, c, points[*index]));
+            if contains_other {
+                continue;
+            }
+
+            triangles.push(vec![prev, current, next]);
+            remaining.remove(i);
+            clipped = true;
+            break;
+        }
+
+        if !clipped {
+            break;
+        }
+    }
+
+    if remaining.len() == 3 {
+        triangles.push(remaining);
+    }
+    triangles
+}
+
+fn triangle_contains(a: Vec2<f64>, b: Vec2<f64>, c: Vec2<f64>, point: Vec2<f64>) -> bool {
+    cross(b - a, point - a) >= -EPSILON
+        && cross(c - b, point - b) >= -EPSILON
+        && cross(a - c, point - c) >= -EPSILON
+}
+
+/// Joins two counter clockwise polygons along an edge they share, `None` if they don't share one
+fn merge(first: &[usize], second: &[usize]) -> Option<Vec<usize>> {
+    for i in 0..first.len() {
+        let a = first[i];
+        let b = first[(i + 1) % first.len()];
+
+        // The shared edge runs the other way around the second polygon
+        let j = match (0..second.len()).find(|j| second[*j] == b && second[(j + 1) % second.len()] == a) {
+            Some(j) => j,
+            None => continue,
+        };
+
+        let mut part = vec![];
+        for k in 0..first.len() {
+            part.push(first[(i + 1 + k) % first.len()]);
+        }
+        for k in 2..second.len() {
+            part.push(second[(j + k) % second.len()]);
+        }
+        return Some(part);
+    }
+    None
+}
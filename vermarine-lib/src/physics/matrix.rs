@@ -0,0 +1,157 @@
+use super::*;
+use std::{
+    collections::HashMap,
+    sync::atomic::{
+        AtomicU64,
+        Ordering,
+    },
+};
+
+// Every change to any matrix gets a new version, so two matrices with the same version have the same rules
+static NEXT_VERSION: AtomicU64 = AtomicU64::new(1);
+
+/// What happens when colliders on a pair of layers overlap
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PairResponse {
+    /// Record the collision and push the bodies apart
+    Resolve,
+    /// Record the collision without pushing the bodies apart
+    Report,
+    /// Act as if the colliders don't overlap
+    Ignore,
+}
+
+/// An overlap between two layers that have a callback registered, `entity1` is always on the first layer of the rule
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PairContact {
+    pub entity1: EntityId,
+    pub entity2: EntityId,
+    pub layer1: u64,
+    pub layer2: u64,
+    /// Points away from entity2
+    pub normal: Vec2<f64>,
+    pub depth: f64,
+}
+
+/// Called by `run_collision_callbacks`, e.g. to send a `HitEvent` through `all_storages.borrow::<UniqueViewMut<Events<HitEvent>>>()`
+pub type PairCallback = fn(&PairContact, &AllStorages);
+
+#[derive(Copy, Clone)]
+pub struct PairRule {
+    /// `None` keeps the usual behaviour of resolving collisions between colliders
+    pub response: Option<PairResponse>,
+    pub callback: Option<PairCallback>,
+}
+
+/// Per layer pair collision responses and callbacks, added as a unique by `add_physics_workload`
+/// and copied into the PhysicsWorld by `apply_physics_config` whenever it changes.
+///
+/// Rules only apply to colliders whose `collides_with` masks already let them collide, and are keyed by the
+/// exact `collision_layer` of each collider. A rule for `(a, b)` also applies to `(b, a)`.
+#[derive(Clone, Default)]
+pub struct CollisionMatrix {
+    rules: HashMap<(u64, u64), PairRule>,
+    version: u64,
+}
+
+impl CollisionMatrix {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how overlaps between the two layers are handled
+    pub fn set_response(&mut self, layer1: u64, layer2: u64, response: PairResponse) {
+        self.rule_mut(layer1, layer2).response = Some(response);
+    }
+
+    /// Registers a callback for overlaps between the two layers, replacing any previous callback
+    pub fn on_contact(&mut self, layer1: u64, layer2: u64, callback: PairCallback) {
+        self.rule_mut(layer1, layer2).callback = Some(callback);
+    }
+
+    /// Removes the rule for the two layers, in either order
+    pub fn remove(&mut self, layer1: u64, layer2: u64) {
+        self.changed();
+        if self.rules.remove(&(layer1, layer2)).is_none() {
+            self.rules.remove(&(layer2, layer1));
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.changed();
+        self.rules.clear();
+    }
+
+    /// The rule for the two layers and whether the layers are swapped relative to how it was registered
+    pub fn rule(&self, layer1: u64, layer2: u64) -> Option<(&PairRule, bool)> {
+        self.rules.get(&(layer1, layer2)).map(|rule| (rule, false))
+            .or_else(|| self.rules.get(&(layer2, layer1)).map(|rule| (rule, true)))
+    }
+
+    pub fn response(&self, layer1: u64, layer2: u64) -> Option<PairResponse> {
+        self.rule(layer1, layer2).and_then(|(rule, _)| rule.response)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Changes whenever a rule is added, changed or removed
+    pub(crate) fn version(&self) -> u64 {
+        self.version
+    }
+
+    fn changed(&mut self) {
+        self.version = NEXT_VERSION.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn rule_mut(&mut self, layer1: u64, layer2: u64) -> &mut PairRule {
+        self.changed();
+        let key = if !self.rules.contains_key(&(layer1, layer2)) && self.rules.contains_key(&(layer2, layer1)) {
+            (layer2, layer1)
+        } else {
+            (layer1, layer2)
+        };
+
+        self.rules.entry(key).or_insert(PairRule {
+            response: None,
+            callback: None,
+        })
+    }
+
+    /// Records a contact for the rule's callback, if it has one
+    pub(crate) fn report(&self, reports: &mut Vec<PairContact>, c1: &Collider, e1: EntityId, c2: &Collider, e2: EntityId, mtv: Vec2<f64>) {
+        let swapped = match self.rule(c1.collision_layer, c2.collision_layer) {
+            Some((PairRule { callback: Some(_), .. }, swapped)) => swapped,
+            _ => return,
+        };
+
+        let normal = PhysicsWorld::collision_normal(mtv);
+        let contact = if swapped {
+            PairContact { entity1: e2, entity2: e1, layer1: c2.collision_layer, layer2: c1.collision_layer, normal: -normal, depth: mtv.magnitude() }
+        } else {
+            PairContact { entity1: e1, entity2: e2, layer1: c1.collision_layer, layer2: c2.collision_layer, normal, depth: mtv.magnitude() }
+        };
+        reports.push(contact);
+    }
+}
+
+/// Runs the CollisionMatrix callback for every contact recorded since the last time this ran.
+/// Callbacks run one at a time with access to all storages, so they can borrow any unique or component.
+pub fn run_collision_callbacks(all_storages: AllStoragesViewMut) {
+    let calls: Vec<(PairCallback, PairContact)> = {
+        let (mut world, matrix) = all_storages.borrow::<(UniqueViewMut<PhysicsWorld>, UniqueView<CollisionMatrix>)>();
+        world.take_pair_contacts()
+            .into_iter()
+            .filter_map(|contact| {
+                matrix.rule(contact.layer1, contact.layer2)
+                    .and_then(|(rule, _)| rule.callback)
+                    .map(|callback| (callback, contact))
+            })
+            .collect()
+    };
+
+    for (callback, contact) in calls.iter() {
+        callback(contact, &all_storages);
+    }
+}
@@ -1,21 +1,61 @@
 pub mod world;
 pub mod spatialhash;
+pub mod broadphase;
 pub mod sat;
+pub mod config;
+pub mod controller;
+pub mod matrix;
+pub mod bounds;
+pub mod convex;
+pub mod builder;
+pub mod carrier;
+pub mod contacts;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
 
 use crate::components::Transform;
+use crate::time::Time;
+use crate::logging;
+use crate::pool::sync_inactive_bodies;
+use crate::events::{
+    Events,
+    EventWorkloadSystems,
+};
 use shipyard::*;
 use tetra::math::Vec2;
+use std::borrow::Cow;
+use std::num::NonZeroU64;
 use world::*;
 use spatialhash::*;
+use broadphase::Broadphase;
+use config::*;
+use controller::*;
+use matrix::*;
+use bounds::*;
+use convex::PolygonError;
+use builder::CollisionLayers;
+use carrier::Carrier;
+use contacts::*;
 
 /// Dummy trait to allow adding a method to World
 pub trait PhysicsWorkloadCreator {
     fn add_physics_workload(&mut self, bucket_width: f64, bucket_height: f64) -> WorkloadBuilder;
+    fn add_physics_workload_with_broadphase(&mut self, broadphase: Box<dyn Broadphase>) -> WorkloadBuilder;
 }
 
 impl PhysicsWorkloadCreator for shipyard::World {
+    /// Uses SpatialBuckets of the given size as the broadphase
     fn add_physics_workload(&mut self, bucket_width: f64, bucket_height: f64) -> WorkloadBuilder {
-        self.add_unique(PhysicsWorld::new(bucket_width, bucket_height));
+        self.add_physics_workload_with_broadphase(Box::new(SpatialBuckets::new(bucket_width, bucket_height)))
+    }
+
+    fn add_physics_workload_with_broadphase(&mut self, broadphase: Box<dyn Broadphase>) -> WorkloadBuilder {
+        self.add_unique(PhysicsWorld::with_broadphase(broadphase));
+        self.add_unique(PhysicsConfig::new());
+        self.add_unique(CollisionMatrix::new());
+        self.add_unique(CollisionLayers::new());
+        self.add_unique(Events::<OutOfBounds>::new());
+        self.add_unique(Time::new());
         self.borrow::<ViewMut<PhysicsBody>>().update_pack();
         self.add_workload("Physics")
     }
@@ -28,19 +68,95 @@ pub trait PhysicsWorkloadSystems<'a> {
 
 impl<'a> PhysicsWorkloadSystems<'a> for WorkloadBuilder<'a> {
     fn with_physics_systems(self) -> WorkloadBuilder<'a> {
-        self.with_system(system!(sync_transforms))
+        self
+            .with_system(system!(apply_physics_config))
+            .with_system(system!(sync_inactive_bodies))
+            .with_system(system!(update_character_controllers))
+            .with_system(system!(write_back_transforms))
+            .with_system(system!(run_collision_callbacks))
+            .with_system(system!(track_contact_pairs))
+            .with_event::<OutOfBounds>()
+            .with_system(system!(send_out_of_bounds_events))
+    }
+}
+
+/// Copies PhysicsConfig and the CollisionMatrix into the PhysicsWorld when they have changed since the last tick
+pub fn apply_physics_config(config: UniqueView<PhysicsConfig>, matrix: UniqueView<CollisionMatrix>, mut world: UniqueViewMut<PhysicsWorld>) {
+    if world.config() != &*config {
+        world.set_config(&config);
+    }
+    if world.collision_matrix().version() != matrix.version() {
+        world.set_collision_matrix(&matrix);
     }
 }
 
-pub fn sync_transforms(mut transforms: ViewMut<Transform>, bodies: View<PhysicsBody>, world: UniqueView<PhysicsWorld>) {
-    for (id, (transform, _)) in (&mut transforms, &bodies).iter().with_id() {
-        let new_t = *world.transform(id);
-        *transform = new_t;
+/// Copies the transform of every body in the PhysicsWorld into its entity's Transform component
+pub fn write_back_transforms(mut transforms: ViewMut<Transform>, world: UniqueView<PhysicsWorld>) {
+    for (id, transform, _) in world.iter() {
+        if let Ok(component) = (&mut transforms).get(id) {
+            *component = *transform;
+        }
     }
 }
 
-#[derive(Default)]
-pub struct PhysicsBody;
+#[deprecated(note = "renamed to write_back_transforms")]
+pub fn sync_transforms(transforms: ViewMut<Transform>, _bodies: View<PhysicsBody>, world: UniqueView<PhysicsWorld>) {
+    write_back_transforms(transforms, world)
+}
+
+/// Marks an entity as having a body in the PhysicsWorld. Stores the entity's id so that systems iterating a view
+/// can drive the body directly, e.g. `body.move_body(&mut physics_world, delta)`, without passing ids around.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PhysicsBody(pub(crate) EntityId);
+
+impl PhysicsBody {
+    /// The entity that owns the body
+    pub fn id(&self) -> EntityId {
+        self.0
+    }
+
+    pub fn transform<'a>(&self, world: &'a PhysicsWorld) -> &'a Transform {
+        world.transform(self.0)
+    }
+
+    pub fn position(&self, world: &PhysicsWorld) -> Vec2<f64> {
+        let transform = world.transform(self.0);
+        Vec2::new(transform.x, transform.y)
+    }
+
+    pub fn collider<'a>(&self, world: &'a PhysicsWorld) -> &'a CollisionBody {
+        world.collider(self.0)
+    }
+
+    pub fn collider_mut<'a>(&self, world: &'a mut PhysicsWorld) -> CollisionBodyMut<'a> {
+        world.collider_mut(self.0)
+    }
+
+    /// Iterates over the collisions currently recorded on the body's colliders
+    pub fn collisions<'a>(&self, world: &'a PhysicsWorld) -> impl Iterator<Item = &'a Collision> {
+        world.collider(self.0).colliders.iter().flat_map(|collider| collider.overlapping.iter())
+    }
+
+    pub fn move_body(&self, world: &mut PhysicsWorld, delta: Vec2<f64>) {
+        world.move_body(self.0, delta);
+    }
+
+    pub fn move_body_and_collide(&self, world: &mut PhysicsWorld, delta: Vec2<f64>) -> Vec<Collision> {
+        world.move_body_and_collide(self.0, delta)
+    }
+
+    pub fn move_body_to(&self, world: &mut PhysicsWorld, position: Vec2<f64>) {
+        world.move_body_to(self.0, position);
+    }
+
+    pub fn move_and_slide(&self, world: &mut PhysicsWorld, velocity: Vec2<f64>, up: Vec2<f64>, max_slope: f64, max_step: f64) -> SlideResult {
+        world.move_and_slide(self.0, velocity, up, max_slope, max_step)
+    }
+
+    pub fn distance_to(&self, world: &PhysicsWorld, other: &PhysicsBody) -> f64 {
+        world.distance_between(self.0, other.0)
+    }
+}
 
 #[derive(Clone)]
 pub struct Collision {
@@ -56,6 +172,11 @@ pub struct Collision {
     pub entity2: EntityId,
 
     pub normal: Vec2<f64>,
+    /// How far the shapes were overlapping when the collision was found
+    pub depth: f64,
+
+    pub material1: Material,
+    pub material2: Material,
 }
 
 impl Collision {
@@ -75,14 +196,85 @@ impl Collision {
             entity2,
 
             normal,
+            depth: 0.0,
+
+            material1: Material::default(),
+            material2: Material::default(),
+        }
+    }
+
+    /// Sets the penetration depth
+    pub fn with_depth(mut self, depth: f64) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Sets the materials of the two colliders
+    pub fn with_materials(mut self, material1: Material, material2: Material) -> Self {
+        self.material1 = material1;
+        self.material2 = material2;
+        self
+    }
+
+    /// The friction and restitution used to respond to this collision
+    pub fn combined_material(&self) -> Material {
+        self.material1.combine(&self.material2)
+    }
+}
+
+/// Surface properties of a collider. `tag` isn't used by the physics, it's there for gameplay code
+/// to tell surfaces apart, e.g. to play footstep sounds or make ice slippery.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Material {
+    /// Fraction of the velocity along the surface removed on contact, from 0.0 to 1.0. Defaults to 0.0.
+    pub friction: f64,
+    /// Fraction of the velocity into the surface that is bounced back, from 0.0 to 1.0. Defaults to 0.0.
+    pub restitution: f64,
+    pub tag: u32,
+}
+
+impl Material {
+    pub fn new(friction: f64, restitution: f64) -> Self {
+        Material {
+            friction,
+            restitution,
+            tag: 0,
+        }
+    }
+
+    /// Sets the user tag
+    pub fn with_tag(mut self, tag: u32) -> Self {
+        self.tag = tag;
+        self
+    }
+
+    /// The properties used when two materials touch, the stickier and bouncier of the two win.
+    /// The tag is kept from self.
+    pub fn combine(&self, other: &Material) -> Material {
+        Material {
+            friction: self.friction.max(other.friction),
+            restitution: self.restitution.max(other.restitution),
+            tag: self.tag,
         }
     }
 }
 
+/// The colliders and sensors of a body. They can only be added or removed through methods that keep the AABB
+/// up to date, use `PhysicsWorld::collider_mut` for bodies that are already in the world so the broadphase is updated too.
 #[derive(Clone, Default)]
 pub struct CollisionBody {
-    pub colliders: Vec<Collider>,
-    pub sensors: Vec<Collider>,
+    pub(crate) colliders: Vec<Collider>,
+    pub(crate) sensors: Vec<Collider>,
+    /// Bodies without a mass are static, colliding with them moves only the other body.
+    /// When two bodies with mass collide the MTV is split between them, the lighter body moving further.
+    /// The body that gets pushed is moved like any other body, so it can't be pushed through static bodies.
+    pub mass: Option<f64>,
+    /// What happens when the body leaves the PhysicsWorld's bounds. Defaults to `None`, which uses the bounds' default mode.
+    pub bounds_mode: Option<BoundsMode>,
+    /// Makes the body carry the bodies standing on it when it moves. Defaults to `None`.
+    pub carrier: Option<Carrier>,
+    /// Disabled bodies stay in the broadphase but never collide, see `PhysicsWorld::set_body_enabled`
+    pub(crate) disabled: bool,
     aabb: AABB,
 }
 
@@ -92,50 +284,38 @@ impl CollisionBody {
     }
 
     pub fn from_collider(collider: Collider) -> Self {
-        let aabb = AABB::from_collider(&collider);
-        CollisionBody {
-            colliders: vec![collider],
-            sensors: vec![],
-            aabb,
-        }
+        Self::from_parts(vec![collider], vec![])
     }
 
     pub fn from_colliders(colliders: Vec<Collider>) -> Self {
-        let aabb = AABB::from_colliders(&colliders);
-        CollisionBody {
-            colliders,
-            sensors: vec![],
-            aabb,
-        }
+        Self::from_parts(colliders, vec![])
     }
 
     pub fn from_sensor(sensor: Collider) -> Self {
-        let aabb = AABB::from_collider(&sensor);
-        CollisionBody {
-            colliders: vec![],
-            sensors: vec![sensor],
-            aabb,
-        }
+        Self::from_parts(vec![], vec![sensor])
     }
 
     pub fn from_sensors(sensors: Vec<Collider>) -> Self {
-        let aabb = AABB::from_colliders(&sensors);
-        CollisionBody {
-            colliders: vec![],
-            sensors,
-            aabb,
-        }
+        Self::from_parts(vec![], sensors)
+    }
+
+    /// A body made of the convex parts of a concave polygon, see `Collider::convex_parts`
+    pub fn from_concave_polygon(vertices: &[Vec2<f64>], collision_layer: u64, collides_with: u64) -> Result<Self, PolygonError> {
+        Ok(Self::from_colliders(Collider::convex_parts(vertices, collision_layer, collides_with)?))
     }
 
     pub fn from_parts(colliders: Vec<Collider>, sensors: Vec<Collider>) -> Self {
-        let mut joined = colliders.clone();
-        joined.append(&mut sensors.clone());
-        let aabb = AABB::from_colliders(&joined);
-        CollisionBody {
+        let mut body = CollisionBody {
             colliders,
             sensors,
-            aabb,
-        }
+            mass: None,
+            bounds_mode: None,
+            carrier: None,
+            disabled: false,
+            aabb: AABB::default(),
+        };
+        body.aabb = AABB::from_body(&body);
+        body
     }
 
     pub fn from_body(body: &CollisionBody) -> Self {
@@ -153,9 +333,138 @@ impl CollisionBody {
             );
         }
 
+        new_body.mass = body.mass;
+        new_body.bounds_mode = body.bounds_mode;
+        new_body.carrier = body.carrier;
+        new_body.aabb = AABB::from_body(&new_body);
         new_body
     }
 
+    /// Sets the mass of the body
+    pub fn with_mass(mut self, mass: f64) -> Self {
+        self.mass = Some(mass);
+        self
+    }
+
+    /// Sets what happens when the body leaves the world bounds
+    pub fn with_bounds_mode(mut self, bounds_mode: BoundsMode) -> Self {
+        self.bounds_mode = Some(bounds_mode);
+        self
+    }
+
+    /// Makes the body carry the bodies standing on it
+    pub fn with_carrier(mut self, carrier: Carrier) -> Self {
+        self.carrier = Some(carrier);
+        self
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.disabled
+    }
+
+    /// The area weighted centre of the body's colliders, relative to the body's transform
+    pub fn center_of_mass(&self) -> Vec2<f64> {
+        let mut total_area = 0.0;
+        let mut center = Vec2::zero();
+
+        for collider in self.colliders.iter() {
+            let shape = collider.local_shape();
+            let area = shape.area();
+            total_area += area;
+            center += (shape.centroid() + collider.offset) * area;
+        }
+
+        if total_area > 0.0 {
+            center / total_area
+        } else {
+            Vec2::zero()
+        }
+    }
+
+    /// The body's resistance to rotating about its center of mass, with its mass spread evenly over the colliders' area.
+    /// `None` for bodies without a mass.
+    pub fn moment_of_inertia(&self) -> Option<f64> {
+        let mass = self.mass?;
+        let center = self.center_of_mass();
+        let mut total_area = 0.0;
+        let mut moment = 0.0;
+
+        for collider in self.colliders.iter() {
+            let shape = collider.local_shape();
+            let area = shape.area();
+            total_area += area;
+            moment += shape.polar_moment() + area * (shape.centroid() + collider.offset - center).magnitude_squared();
+        }
+
+        if total_area > 0.0 {
+            Some(mass * moment / total_area)
+        } else {
+            Some(0.0)
+        }
+    }
+
+    /// The fraction of the MTV between this body and the other that is applied to this body
+    pub(crate) fn resolution_share(&self, other: &CollisionBody) -> f64 {
+        match (self.mass, other.mass) {
+            (Some(m1), Some(m2)) if m1 > 0.0 && m2 > 0.0 => m2 / (m1 + m2),
+            _ => 1.0,
+        }
+    }
+
+    /// The AABB used for this body in the broadphase, relative to the body's transform
+    pub fn aabb(&self) -> &AABB {
+        &self.aabb
+    }
+
+    pub fn colliders(&self) -> &[Collider] {
+        &self.colliders
+    }
+
+    pub fn sensors(&self) -> &[Collider] {
+        &self.sensors
+    }
+
+    /// Changes to the shapes or offsets of the colliders are picked up when the body is created,
+    /// or when the guard from `PhysicsWorld::collider_mut` is dropped
+    pub fn colliders_mut(&mut self) -> &mut [Collider] {
+        &mut self.colliders
+    }
+
+    /// Same as `colliders_mut` for the sensors
+    pub fn sensors_mut(&mut self) -> &mut [Collider] {
+        &mut self.sensors
+    }
+
+    /// Adds a collider and recomputes the AABB
+    pub fn add_collider(&mut self, collider: Collider) {
+        self.colliders.push(collider);
+        self.update_aabb();
+    }
+
+    /// Adds a sensor and recomputes the AABB
+    pub fn add_sensor(&mut self, sensor: Collider) {
+        self.sensors.push(sensor);
+        self.update_aabb();
+    }
+
+    /// Removes the collider at the index and recomputes the AABB, panics if the index is out of bounds
+    pub fn remove_collider(&mut self, index: usize) -> Collider {
+        let collider = self.colliders.remove(index);
+        self.update_aabb();
+        collider
+    }
+
+    /// Removes the sensor at the index and recomputes the AABB, panics if the index is out of bounds
+    pub fn remove_sensor(&mut self, index: usize) -> Collider {
+        let sensor = self.sensors.remove(index);
+        self.update_aabb();
+        sensor
+    }
+
+    pub(crate) fn update_aabb(&mut self) {
+        self.aabb = AABB::from_body(self);
+    }
+
     pub(crate) fn remove_collision(&mut self, entity: EntityId) {
         for collider in self.colliders.iter_mut() {
             let mut counter = 0;
@@ -209,20 +518,66 @@ pub struct Collider {
     pub collision_layer: u64,
     pub collides_with: u64,
 
+    /// Position of the collider relative to the body's transform
+    pub offset: Vec2<f64>,
+    /// Rotation of the collider around its offset, in radians
+    pub rotation: f64,
+
+    /// Colliders sharing a group never collide with each other, regardless of their layers
+    pub group_id: Option<NonZeroU64>,
+
+    pub material: Material,
+
     pub overlapping: Vec<Collision>,
 }
 
 impl Collider {
-    pub fn circle(radius: f64, collision_layer: u64, collides_with: u64) -> Self {
+    pub fn new(shape: CollisionShape, collision_layer: u64, collides_with: u64) -> Self {
         Collider {
-            shape: CollisionShape::Circle(radius),
+            shape,
             collides_with,
             collision_layer,
 
+            offset: Vec2::zero(),
+            rotation: 0.0,
+
+            group_id: None,
+
+            material: Material::default(),
+
             overlapping: vec![],
         }
     }
 
+    pub fn circle(radius: f64, collision_layer: u64, collides_with: u64) -> Self {
+        Self::new(CollisionShape::Circle(radius), collision_layer, collides_with)
+    }
+
+    /// The vertices are reversed if they are wound the opposite way to `half_extents`. SAT gives wrong results for
+    /// concave polygons, they are logged as warnings. Use `try_polygon` to reject them or `convex_parts` to split them.
+    pub fn polygon(vertices: Vec<Vec2<f64>>, collision_layer: u64, collides_with: u64) -> Self {
+        let mut vertices = vertices;
+        if let Err(error) = convex::validate(&mut vertices) {
+            log::warn!(target: logging::PHYSICS, "Invalid polygon collider {:?}: {:?}", vertices, error);
+        }
+        Self::new(CollisionShape::Polygon(vertices), collision_layer, collides_with)
+    }
+
+    /// Same as `polygon` but returns an error for concave or degenerate polygons
+    pub fn try_polygon(vertices: Vec<Vec2<f64>>, collision_layer: u64, collides_with: u64) -> Result<Self, PolygonError> {
+        let mut vertices = vertices;
+        convex::validate(&mut vertices)?;
+        Ok(Self::new(CollisionShape::Polygon(vertices), collision_layer, collides_with))
+    }
+
+    /// Splits a concave polygon into convex colliders that together cover the same area
+    pub fn convex_parts(vertices: &[Vec2<f64>], collision_layer: u64, collides_with: u64) -> Result<Vec<Self>, PolygonError> {
+        Ok(convex::decompose(vertices)?
+            .into_iter()
+            .map(|part| Self::new(CollisionShape::Polygon(part), collision_layer, collides_with))
+            .collect())
+    }
+
     pub fn half_extents(width: f64, height: f64, collision_layer: u64, collides_with: u64) -> Self {
         let vertices = vec![
             Vec2::new(-width, -height),
@@ -231,13 +586,7 @@ impl Collider {
             Vec2::new(-width, height),
         ];
 
-        Collider {
-            shape: CollisionShape::Polygon(vertices),
-            collides_with,
-            collision_layer,
-
-            overlapping: vec![],
-        }
+        Self::polygon(vertices, collision_layer, collides_with)
     }
 
     pub fn from_collider(collider: &Collider) -> Self {
@@ -246,12 +595,74 @@ impl Collider {
             collision_layer: collider.collision_layer,
             collides_with: collider.collides_with,
 
+            offset: collider.offset,
+            rotation: collider.rotation,
+
+            group_id: collider.group_id,
+
+            material: collider.material,
+
             overlapping: vec![],
         }
     }
+
+    /// Sets the position of the collider relative to the body
+    pub fn with_offset(mut self, x: f64, y: f64) -> Self {
+        self.offset = Vec2::new(x, y);
+        self
+    }
+
+    /// Sets the rotation of the collider around its offset, in radians
+    pub fn with_rotation(mut self, rotation: f64) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Sets the exclusion group of the collider
+    pub fn with_group(mut self, group_id: NonZeroU64) -> Self {
+        self.group_id = Some(group_id);
+        self
+    }
+
+    /// Sets the surface material of the collider
+    pub fn with_material(mut self, material: Material) -> Self {
+        self.material = material;
+        self
+    }
+
+    /// Returns true if both colliders are in the same exclusion group and so should never collide
+    pub fn is_excluded_from(&self, other: &Collider) -> bool {
+        self.group_id.is_some() && self.group_id == other.group_id
+    }
+
+    /// The transform the collider's shape is positioned at for a body at the passed in transform
+    pub fn world_transform(&self, body: &Transform) -> Transform {
+        Transform::new(body.x + self.offset.x, body.y + self.offset.y)
+    }
+
+    /// The collider's shape with its rotation applied
+    pub fn local_shape(&self) -> Cow<CollisionShape> {
+        match &self.shape {
+            CollisionShape::Polygon(vertices) if self.rotation != 0.0 => {
+                let (sin, cos) = self.rotation.sin_cos();
+                Cow::Owned(CollisionShape::Polygon(
+                    vertices.iter()
+                        .map(|v| Vec2::new(v.x * cos - v.y * sin, v.x * sin + v.y * cos))
+                        .collect()
+                ))
+            },
+            _ => Cow::Borrowed(&self.shape),
+        }
+    }
+
+    /// Returns the (min, max) corners of the collider's bounding box relative to the body
+    pub fn bounds(&self) -> (Vec2<f64>, Vec2<f64>) {
+        let (min, max) = self.local_shape().bounds();
+        (min + self.offset, max + self.offset)
+    }
 }
 
-#[derive(Clone, Default, Debug)]
+#[derive(Clone, Default, Debug, PartialEq)]
 pub struct AABB {
     pub dx: f64,
     pub dy: f64,
@@ -270,63 +681,52 @@ impl AABB {
     }
 
     pub fn from_collider(collider: &Collider) -> Self {
-        Self::from_colliders(&[collider.clone()])
+        Self::from_bounds(std::iter::once(collider.bounds()))
+    }
+
+    /// Whether this AABB at the transform overlaps the other AABB at its transform, touching counts as overlapping
+    pub fn overlaps(&self, transform: &Transform, other: &AABB, other_transform: &Transform) -> bool {
+        let (x1, y1) = (transform.x + self.dx, transform.y + self.dy);
+        let (x2, y2) = (other_transform.x + other.dx, other_transform.y + other.dy);
+
+        x1 <= x2 + other.width && x2 <= x1 + self.width
+            && y1 <= y2 + other.height && y2 <= y1 + self.height
     }
 
     pub fn from_colliders(colliders: &[Collider]) -> Self {
-        let mut xmin = None;
-        let mut xmax = None;
-        let mut ymin = None;
-        let mut ymax = None;
+        Self::from_bounds(colliders.iter().map(|c| c.bounds()))
+    }
 
-        use CollisionShape::*;
-        
-        for collider in colliders.iter() {
-            match &collider.shape {
-                Polygon(vertices) => { 
-                    for vertex in vertices.iter() {
-                        if xmin.is_none() || vertex.x < xmin.unwrap() {
-                            xmin = Some(vertex.x);
-                        }
-                        if xmax.is_none() || vertex.x > xmax.unwrap() {
-                            xmax = Some(vertex.x);
-                        }
-                        if ymin.is_none() || vertex.y < ymin.unwrap() {
-                            ymin = Some(vertex.y);
-                        }
-                        if ymax.is_none() || vertex.y > ymin.unwrap() {
-                            ymax = Some(vertex.y);
-                        }
-                    }
-                },
-                Circle(r) => { 
-                    let r = *r;
-                    if xmin.is_none() || -r < xmin.unwrap() {
-                        xmin = Some(-r);
-                    }
-                    if xmax.is_none() || r > xmax.unwrap() {
-                        xmax = Some(r);
-                    }
-                    if ymin.is_none() || -r < ymin.unwrap() {
-                        ymin = Some(-r);
-                    }
-                    if ymax.is_none() || r > ymin.unwrap() {
-                        ymax = Some(r);
-                    }
-                },
-            };
-        }
+    pub fn from_body(body: &CollisionBody) -> Self {
+        Self::from_bounds(
+            body.colliders.iter()
+                .chain(body.sensors.iter())
+                .map(|c| c.bounds())
+        )
+    }
 
-        let xmin = xmin.unwrap();
-        let xmax = xmax.unwrap();
-        let ymin = ymin.unwrap();
-        let ymax = ymax.unwrap();
+    /// Creates the smallest AABB containing all of the passed in (min, max) pairs, an empty iterator gives a zero sized AABB
+    pub fn from_bounds<I: Iterator<Item = (Vec2<f64>, Vec2<f64>)>>(bounds: I) -> Self {
+        let mut result: Option<(Vec2<f64>, Vec2<f64>)> = None;
+
+        for (min, max) in bounds {
+            result = Some(match result {
+                Some((rmin, rmax)) => (
+                    Vec2::new(rmin.x.min(min.x), rmin.y.min(min.y)),
+                    Vec2::new(rmax.x.max(max.x), rmax.y.max(max.y)),
+                ),
+                None => (min, max),
+            });
+        }
 
-        AABB {
-            dx: if xmin < 0.0 { xmin } else { 0.0 },
-            dy: if ymin < 0.0 { ymin } else { 0.0 },
-            width: xmax - xmin,
-            height: ymax - ymin,
+        match result {
+            Some((min, max)) => AABB {
+                dx: min.x,
+                dy: min.y,
+                width: max.x - min.x,
+                height: max.y - min.y,
+            },
+            None => AABB::default(),
         }
     }
 }
@@ -345,6 +745,78 @@ impl CollisionShape {
         }
     }
 
+    /// Returns the (min, max) corners of the shape's bounding box
+    pub fn bounds(&self) -> (Vec2<f64>, Vec2<f64>) {
+        match self {
+            Self::Circle(r) => (Vec2::new(-r, -r), Vec2::new(*r, *r)),
+            Self::Polygon(vertices) => {
+                let (mut min, mut max) = match vertices.first() {
+                    Some(first) => (*first, *first),
+                    None => return (Vec2::zero(), Vec2::zero()),
+                };
+
+                for vertex in vertices.iter() {
+                    min.x = min.x.min(vertex.x);
+                    min.y = min.y.min(vertex.y);
+                    max.x = max.x.max(vertex.x);
+                    max.y = max.y.max(vertex.y);
+                }
+
+                (min, max)
+            },
+        }
+    }
+
+    pub fn area(&self) -> f64 {
+        match self {
+            Self::Circle(r) => std::f64::consts::PI * r * r,
+            Self::Polygon(vertices) => convex::signed_area(vertices).abs(),
+        }
+    }
+
+    /// The polar second moment of the shape's area about its centroid, multiply by density to get the moment of inertia
+    pub fn polar_moment(&self) -> f64 {
+        match self {
+            Self::Circle(r) => std::f64::consts::PI * r.powi(4) / 2.0,
+            Self::Polygon(vertices) => {
+                let area = convex::signed_area(vertices);
+                if area == 0.0 {
+                    return 0.0;
+                }
+
+                // About the origin, then moved to the centroid with the parallel axis theorem
+                let mut moment = 0.0;
+                for (i, a) in vertices.iter().enumerate() {
+                    let b = vertices[(i + 1) % vertices.len()];
+                    let cross = a.x * b.y - b.x * a.y;
+                    moment += cross * (a.dot(*a) + a.dot(b) + b.dot(b));
+                }
+                moment / 12.0 * area.signum() - area.abs() * self.centroid().magnitude_squared()
+            },
+        }
+    }
+
+    /// The centre of the shape's area
+    pub fn centroid(&self) -> Vec2<f64> {
+        match self {
+            Self::Circle(_) => Vec2::zero(),
+            Self::Polygon(vertices) => {
+                let area = convex::signed_area(vertices);
+                if area == 0.0 {
+                    return vertices.iter().fold(Vec2::zero(), |sum, v| sum + *v) / vertices.len() as f64;
+                }
+
+                let mut centroid = Vec2::zero();
+                for (i, a) in vertices.iter().enumerate() {
+                    let b = vertices[(i + 1) % vertices.len()];
+                    let cross = a.x * b.y - b.x * a.y;
+                    centroid += (*a + b) * cross;
+                }
+                centroid / (6.0 * area)
+            },
+        }
+    }
+
     pub fn get_width(&self) -> f64 {
         match self {
             Self::Circle(r) => r * 2.0,
@@ -411,12 +883,14 @@ mod tests {
         let e1 = world.run(|
             mut entities: EntitiesViewMut,
             mut bodies: ViewMut<PhysicsBody>,
+            mut transforms: ViewMut<Transform>,
             mut physics_world: UniqueViewMut<PhysicsWorld>| { 
                 let e1 = entities.add_entity((), ());
                 physics_world.create_body(
                     &mut entities, 
                     &mut bodies, 
                     e1, 
+                    &mut transforms,
                     Transform::new(10.0, 10.0), 
                     CollisionBody::from_collider(Collider::half_extents(2.0, 2.0, 1, 2)),
                 );
@@ -426,6 +900,7 @@ mod tests {
                     &mut entities, 
                     &mut bodies, 
                     e2, 
+                    &mut transforms,
                     Transform::new(0.0, 0.0), 
                     CollisionBody::from_collider(Collider::half_extents(2.0, 2.0, 2, 1)),
                 );
@@ -487,12 +962,14 @@ mod tests {
         let e1 = world.run(|
             mut entities: EntitiesViewMut,
             mut bodies: ViewMut<PhysicsBody>,
+            mut transforms: ViewMut<Transform>,
             mut physics_world: UniqueViewMut<PhysicsWorld>| { 
                 let e1 = entities.add_entity((), ());
                 physics_world.create_body(
                     &mut entities, 
                     &mut bodies, 
                     e1, 
+                    &mut transforms,
                     Transform::new(10.0, 10.0), 
                     CollisionBody::from_sensor(Collider::half_extents(2.0, 2.0, 1, 2)),
                 );
@@ -504,6 +981,7 @@ mod tests {
                     &mut entities, 
                     &mut bodies, 
                     e2, 
+                    &mut transforms,
                     Transform::new(0.0, 0.0), 
                     CollisionBody::from_collider(Collider::half_extents(2.0, 2.0, 2, 1)),
                 );
@@ -552,4 +1030,968 @@ mod tests {
                 assert_eq!(world.collider(e1).sensors[0].overlapping.len(), 0);
         });
     }
-}
\ No newline at end of file
+
+    /// Small deterministic generator so the AABB tests don't need an rng dependency
+    fn lcg(seed: &mut u64) -> f64 {
+        *seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        ((*seed >> 33) as f64 / (1u64 << 31) as f64) * 200.0 - 100.0
+    }
+
+    fn brute_force_bounds(colliders: &[Collider]) -> (f64, f64, f64, f64) {
+        let mut xs = vec![];
+        let mut ys = vec![];
+        for collider in colliders.iter() {
+            let offset = collider.offset;
+            match &collider.shape {
+                CollisionShape::Polygon(vertices) => {
+                    xs.extend(vertices.iter().map(|v| v.x + offset.x));
+                    ys.extend(vertices.iter().map(|v| v.y + offset.y));
+                },
+                CollisionShape::Circle(r) => {
+                    xs.push(offset.x - r);
+                    xs.push(offset.x + r);
+                    ys.push(offset.y - r);
+                    ys.push(offset.y + r);
+                },
+            }
+        }
+
+        let min = |v: &[f64]| v.iter().cloned().fold(f64::INFINITY, f64::min);
+        let max = |v: &[f64]| v.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        (min(&xs), min(&ys), max(&xs), max(&ys))
+    }
+
+    #[test]
+    fn aabb_asymmetric_polygon() {
+        let collider = Collider::polygon(
+            vec![
+                Vec2::new(2.0, 1.0),
+                Vec2::new(10.0, 3.0),
+                Vec2::new(4.0, 7.0),
+            ],
+            1,
+            1,
+        );
+
+        let aabb = AABB::from_collider(&collider);
+        assert_eq!(aabb, AABB::new(2.0, 1.0, 8.0, 6.0));
+    }
+
+    #[test]
+    fn aabb_rotated_offset_collider() {
+        let collider = Collider::half_extents(1.0, 1.0, 1, 1)
+            .with_offset(10.0, 0.0)
+            .with_rotation(std::f64::consts::FRAC_PI_4);
+
+        let aabb = AABB::from_collider(&collider);
+        let half = std::f64::consts::SQRT_2;
+        assert!((aabb.dx - (10.0 - half)).abs() < 1e-9);
+        assert!((aabb.dy + half).abs() < 1e-9);
+        assert!((aabb.width - half * 2.0).abs() < 1e-9);
+        assert!((aabb.height - half * 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn aabb_empty_body() {
+        assert_eq!(AABB::from_colliders(&[]), AABB::default());
+        assert_eq!(CollisionBody::new().aabb(), &AABB::default());
+    }
+
+    #[test]
+    fn aabb_matches_brute_force() {
+        let mut seed = 0x5eed;
+
+        for _ in 0..200 {
+            let mut colliders = vec![];
+            let count = 1 + (lcg(&mut seed).abs() as usize % 4);
+            for _ in 0..count {
+                let collider = if lcg(&mut seed) > 50.0 {
+                    Collider::circle(lcg(&mut seed).abs(), 1, 1)
+                } else {
+                    let vertex_count = 3 + (lcg(&mut seed).abs() as usize % 5);
+                    let vertices = (0..vertex_count)
+                        .map(|_| Vec2::new(lcg(&mut seed), lcg(&mut seed)))
+                        .collect();
+                    Collider::polygon(vertices, 1, 1)
+                };
+                colliders.push(collider.with_offset(lcg(&mut seed), lcg(&mut seed)));
+            }
+
+            let (xmin, ymin, xmax, ymax) = brute_force_bounds(&colliders);
+            let aabb = AABB::from_colliders(&colliders);
+            assert_eq!(aabb, AABB::new(xmin, ymin, xmax - xmin, ymax - ymin));
+
+            let (sensors, colliders) = colliders.split_at(colliders.len() / 2);
+            let body = CollisionBody::from_parts(colliders.to_vec(), sensors.to_vec());
+            assert_eq!(body.aabb(), &aabb);
+            assert_eq!(CollisionBody::from_body(&body).aabb(), &aabb);
+        }
+    }
+
+    #[test]
+    fn collider_mut_recomputes_aabb() {
+        let mut world = World::new();
+
+        world
+            .add_physics_workload(50.0, 50.0)
+            .with_physics_systems()
+            .build();
+
+        world.run(|
+            mut entities: EntitiesViewMut,
+            mut bodies: ViewMut<PhysicsBody>,
+            mut transforms: ViewMut<Transform>,
+            mut physics_world: UniqueViewMut<PhysicsWorld>| {
+                let e1 = entities.add_entity((), ());
+                physics_world.create_body(
+                    &mut entities,
+                    &mut bodies,
+                    e1,
+                    &mut transforms,
+                    Transform::new(0.0, 0.0),
+                    CollisionBody::from_collider(Collider::half_extents(2.0, 2.0, 1, 1)),
+                );
+
+                let e2 = entities.add_entity((), ());
+                physics_world.create_body(
+                    &mut entities,
+                    &mut bodies,
+                    e2,
+                    &mut transforms,
+                    Transform::new(70.0, 0.0),
+                    CollisionBody::from_collider(Collider::half_extents(2.0, 2.0, 1, 1)),
+                );
+
+                // e2 is alone in bucket 1 until e1's AABB grows into it
+                physics_world.collider_mut(e1).colliders.push(Collider::circle(80.0, 1, 1));
+                assert_eq!(physics_world.collider(e1).aabb(), &AABB::new(-80.0, -80.0, 160.0, 160.0));
+
+                // The broadphase must know about the grown AABB for e2 to find e1
+                physics_world.move_body(e2, Vec2::new(1.0, 0.0));
+                assert_eq!(physics_world.collider(e2).colliders[0].overlapping.len(), 1);
+        });
+    }
+
+    #[test]
+    fn controller_damping_and_units() {
+        let config = PhysicsConfig::new().gravity(Vec2::new(0.0, 1.0)).units_per_meter(16.0);
+        assert_eq!(config.gravity_units(), Vec2::new(0.0, 16.0));
+
+        let mut controller = CharacterController::new(10.0, 100.0).with_gravity(true).with_damping(0.5);
+        controller.velocity = Vec2::new(0.0, 8.0);
+        controller.accelerate(config.gravity_units(), 0.5, 1.0);
+
+        // Half of the fall speed is kept, then a tick of gravity is added
+        assert_eq!(controller.velocity, Vec2::new(0.0, 20.0));
+    }
+
+    #[test]
+    fn empty_polygon_bounds() {
+        assert_eq!(CollisionShape::Polygon(vec![]).bounds(), (Vec2::zero(), Vec2::zero()));
+    }
+
+    #[test]
+    fn nearest_body_and_distance() {
+        let mut world = World::new();
+
+        world
+            .add_physics_workload(10.0, 10.0)
+            .with_physics_systems()
+            .build();
+
+        world.run(|
+            mut entities: EntitiesViewMut,
+            mut bodies: ViewMut<PhysicsBody>,
+            mut transforms: ViewMut<Transform>,
+            mut physics_world: UniqueViewMut<PhysicsWorld>| {
+                let near = entities.add_entity((), ());
+                physics_world.create_body(
+                    &mut entities,
+                    &mut bodies,
+                    near,
+                    &mut transforms,
+                    Transform::new(30.0, 0.0),
+                    CollisionBody::from_collider(Collider::half_extents(5.0, 5.0, 1, 0)),
+                );
+
+                let far = entities.add_entity((), ());
+                physics_world.create_body(
+                    &mut entities,
+                    &mut bodies,
+                    far,
+                    &mut transforms,
+                    Transform::new(-80.0, 0.0),
+                    CollisionBody::from_collider(Collider::circle(5.0, 2, 0)),
+                );
+
+                let (id, distance) = physics_world.nearest_body(Vec2::zero(), 1 | 2).unwrap();
+                assert_eq!(id, near);
+                assert!((distance - 25.0).abs() < 1e-9);
+
+                let (id, distance) = physics_world.nearest_body(Vec2::zero(), 2).unwrap();
+                assert_eq!(id, far);
+                assert!((distance - 75.0).abs() < 1e-9);
+
+                assert!(physics_world.nearest_body(Vec2::zero(), 4).is_none());
+
+                assert!((physics_world.distance_between(near, far) - 100.0).abs() < 1e-9);
+        });
+    }
+
+    #[test]
+    fn shared_group_never_collides() {
+        let group = NonZeroU64::new(7).unwrap();
+        let mut t1 = Transform::new(0.0, 0.0);
+        let mut t2 = Transform::new(1.0, 0.0);
+        let mut b1 = CollisionBody::from_collider(Collider::circle(2.0, 1, 1).with_group(group));
+        let mut b2 = CollisionBody::from_collider(Collider::circle(2.0, 1, 1).with_group(group));
+
+        let collisions = PhysicsWorld::update_overlapping_partial(&mut t1, &mut b1, EntityId::dead(), &mut t2, &mut b2, EntityId::dead(), false);
+        assert!(collisions.is_empty());
+        assert!(b1.colliders[0].overlapping.is_empty());
+        assert!(b2.colliders[0].overlapping.is_empty());
+
+        let mut b3 = CollisionBody::from_collider(Collider::circle(2.0, 1, 1));
+        let collisions = PhysicsWorld::update_overlapping_partial(&mut t1, &mut b1, EntityId::dead(), &mut t2, &mut b3, EntityId::dead(), false);
+        assert_eq!(collisions.len(), 1);
+    }
+
+    /// Collides the two bodies and checks that each side's normal points away from the other body
+    fn assert_opposing_normals(p1: Vec2<f64>, c1: Collider, p2: Vec2<f64>, c2: Collider) {
+        let mut t1 = Transform::new(p1.x, p1.y);
+        let mut t2 = Transform::new(p2.x, p2.y);
+        let mut b1 = CollisionBody::from_collider(c1);
+        let mut b2 = CollisionBody::from_collider(c2);
+
+        let collisions = PhysicsWorld::update_overlapping_partial(&mut t1, &mut b1, EntityId::dead(), &mut t2, &mut b2, EntityId::dead(), false);
+        assert_eq!(collisions.len(), 1);
+
+        let n1 = b1.colliders[0].overlapping[0].normal;
+        let n2 = b2.colliders[0].overlapping[0].normal;
+        assert!(n1.dot(p1 - p2) > 0.0);
+        assert!(n2.dot(p2 - p1) > 0.0);
+        assert!((n1 + n2).magnitude() < 1e-9);
+        assert!((n1.magnitude() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn normals_circle_polygon() {
+        assert_opposing_normals(
+            Vec2::new(0.0, 0.0), Collider::circle(2.0, 1, 1),
+            Vec2::new(3.0, 0.5), Collider::half_extents(2.0, 2.0, 1, 1),
+        );
+        assert_opposing_normals(
+            Vec2::new(3.0, 0.5), Collider::half_extents(2.0, 2.0, 1, 1),
+            Vec2::new(0.0, 0.0), Collider::circle(2.0, 1, 1),
+        );
+    }
+
+    #[test]
+    fn normals_polygon_polygon() {
+        assert_opposing_normals(
+            Vec2::new(0.0, 0.0), Collider::half_extents(2.0, 2.0, 1, 1),
+            Vec2::new(1.0, 3.0), Collider::half_extents(2.0, 2.0, 1, 1),
+        );
+        assert_opposing_normals(
+            Vec2::new(1.0, 3.0), Collider::half_extents(2.0, 2.0, 1, 1),
+            Vec2::new(0.0, 0.0), Collider::half_extents(2.0, 2.0, 1, 1),
+        );
+    }
+
+    #[test]
+    fn sensor_records_normal_away_from_collider() {
+        let mut t1 = Transform::new(0.0, 0.0);
+        let mut t2 = Transform::new(0.0, 3.0);
+        let mut sensor_body = CollisionBody::from_sensor(Collider::circle(2.0, 1, 1));
+        let mut collider_body = CollisionBody::from_collider(Collider::circle(2.0, 1, 1));
+
+        PhysicsWorld::update_overlapping_partial(&mut t1, &mut sensor_body, EntityId::dead(), &mut t2, &mut collider_body, EntityId::dead(), false);
+
+        assert_eq!(collider_body.colliders[0].overlapping.len(), 0);
+        let normal = sensor_body.sensors[0].overlapping[0].normal;
+        assert!((normal - Vec2::new(0.0, -1.0)).magnitude() < 1e-9);
+    }
+
+    #[test]
+    fn materials_combine_and_are_recorded() {
+        let ice = Material::new(0.1, 0.0).with_tag(1);
+        let rubber = Material::new(0.8, 0.6).with_tag(2);
+
+        let combined = ice.combine(&rubber);
+        assert_eq!(combined, Material::new(0.8, 0.6).with_tag(1));
+        assert_eq!(rubber.combine(&ice).tag, 2);
+
+        let mut t1 = Transform::new(0.0, 0.0);
+        let mut t2 = Transform::new(3.0, 0.0);
+        let mut b1 = CollisionBody::from_collider(Collider::circle(2.0, 1, 1).with_material(ice));
+        let mut b2 = CollisionBody::from_collider(Collider::circle(2.0, 1, 1).with_material(rubber));
+
+        PhysicsWorld::update_overlapping_partial(&mut t1, &mut b1, EntityId::dead(), &mut t2, &mut b2, EntityId::dead(), false);
+
+        // Each side records its own material first
+        let collision = &b1.colliders[0].overlapping[0];
+        assert_eq!((collision.material1, collision.material2), (ice, rubber));
+        assert_eq!(collision.combined_material(), combined);
+
+        let collision = &b2.colliders[0].overlapping[0];
+        assert_eq!((collision.material1, collision.material2), (rubber, ice));
+        assert_eq!(collision.combined_material().tag, 2);
+    }
+
+    #[test]
+    fn mtv_split_by_mass() {
+        let mut t1 = Transform::new(0.0, 0.0);
+        let mut t2 = Transform::new(15.0, 0.0);
+        let mut b1 = CollisionBody::from_collider(Collider::circle(10.0, 1, 1)).with_mass(1.0);
+        let mut b2 = CollisionBody::from_collider(Collider::circle(10.0, 1, 1)).with_mass(1.0);
+
+        PhysicsWorld::update_overlapping_partial(&mut t1, &mut b1, EntityId::dead(), &mut t2, &mut b2, EntityId::dead(), true);
+        assert!((t1.x - -2.5).abs() < 1e-9);
+        assert!((t2.x - 17.5).abs() < 1e-9);
+
+        let mut t1 = Transform::new(0.0, 0.0);
+        let mut t2 = Transform::new(15.0, 0.0);
+        let mut b2 = CollisionBody::from_collider(Collider::circle(10.0, 1, 1)).with_mass(3.0);
+
+        PhysicsWorld::update_overlapping_partial(&mut t1, &mut b1, EntityId::dead(), &mut t2, &mut b2, EntityId::dead(), true);
+        assert!((t1.x - -3.75).abs() < 1e-9);
+        assert!((t2.x - 16.25).abs() < 1e-9);
+
+        // Static bodies keep the old behaviour of the mover taking the whole MTV
+        let mut t1 = Transform::new(0.0, 0.0);
+        let mut t2 = Transform::new(15.0, 0.0);
+        let mut b3 = CollisionBody::from_collider(Collider::circle(10.0, 1, 1));
+
+        PhysicsWorld::update_overlapping_partial(&mut t1, &mut b1, EntityId::dead(), &mut t2, &mut b3, EntityId::dead(), true);
+        assert!((t1.x - -5.0).abs() < 1e-9);
+        assert!((t2.x - 15.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn collision_matrix_responses() {
+        let mut matrix = CollisionMatrix::new();
+        matrix.set_response(1, 2, PairResponse::Report);
+        matrix.set_response(1, 4, PairResponse::Ignore);
+        matrix.on_contact(2, 1, |_, _| {});
+
+        // Reported pairs are recorded but not pushed apart, the contact is ordered as the callback was registered
+        let mut t1 = Transform::new(0.0, 0.0);
+        let mut t2 = Transform::new(15.0, 0.0);
+        let mut b1 = CollisionBody::from_collider(Collider::circle(10.0, 1, 6));
+        let mut b2 = CollisionBody::from_collider(Collider::circle(10.0, 2, 1));
+        let mut reports = vec![];
+
+        let collisions = PhysicsWorld::update_overlapping_partial_limited(&mut t1, &mut b1, EntityId::dead(), &mut t2, &mut b2, EntityId::dead(), Some(f64::INFINITY), true, &matrix, &mut reports);
+        assert_eq!(collisions.len(), 1);
+        assert_eq!(t1.x, 0.0);
+        assert_eq!(reports.len(), 1);
+        assert_eq!((reports[0].layer1, reports[0].layer2), (2, 1));
+        assert!((reports[0].normal - Vec2::new(1.0, 0.0)).magnitude() < 1e-9);
+
+        // Ignored pairs aren't recorded at all
+        let mut b3 = CollisionBody::from_collider(Collider::circle(10.0, 4, 1));
+        let collisions = PhysicsWorld::update_overlapping_partial_limited(&mut t1, &mut b1, EntityId::dead(), &mut t2, &mut b3, EntityId::dead(), Some(f64::INFINITY), true, &matrix, &mut reports);
+        assert!(collisions.is_empty());
+        assert!(b3.colliders[0].overlapping.is_empty());
+        assert_eq!(reports.len(), 1);
+    }
+
+    #[test]
+    fn world_bounds_modes() {
+        let bounds = WorldBounds::new(Vec2::new(-10.0, 0.0), Vec2::new(10.0, 20.0));
+
+        assert_eq!(bounds.apply(BoundsMode::Clamp, Vec2::new(5.0, 5.0)), None);
+        assert_eq!(bounds.apply(BoundsMode::Clamp, Vec2::new(15.0, -5.0)), Some(Vec2::new(10.0, 0.0)));
+        assert_eq!(bounds.apply(BoundsMode::Wrap, Vec2::new(12.0, -5.0)), Some(Vec2::new(-8.0, 15.0)));
+        assert_eq!(bounds.apply(BoundsMode::Wrap, Vec2::new(-35.0, 45.0)), Some(Vec2::new(5.0, 5.0)));
+        assert_eq!(bounds.apply(BoundsMode::Report, Vec2::new(15.0, 5.0)), None);
+    }
+
+    #[test]
+    fn physics_config_is_applied_on_change() {
+        let (world, _) = PhysicsWorldBuilder::new().build();
+
+        world.run(|mut config: UniqueViewMut<PhysicsConfig>, mut matrix: UniqueViewMut<CollisionMatrix>| {
+            config.max_delta = 5.0;
+            config.solver_iterations = 2;
+            matrix.set_response(1, 2, PairResponse::Ignore);
+        });
+        world.run(apply_physics_config);
+
+        world.run(|physics_world: UniqueView<PhysicsWorld>| {
+            assert_eq!(physics_world.config().max_delta, 5.0);
+            assert_eq!(physics_world.config().solver_iterations, 2);
+            assert_eq!(physics_world.collision_matrix().response(2, 1), Some(PairResponse::Ignore));
+        });
+
+        // Removing the rule changes the version even though the matrix is back to how it started
+        world.run(|mut matrix: UniqueViewMut<CollisionMatrix>| matrix.remove(1, 2));
+        world.run(apply_physics_config);
+        world.run(|physics_world: UniqueView<PhysicsWorld>| {
+            assert_eq!(physics_world.collision_matrix().response(1, 2), None);
+        });
+    }
+
+    #[test]
+    fn movement_is_validated() {
+        let (world, bodies) = PhysicsWorldBuilder::new()
+            .body(BodyDesc::circle(0.0, 0.0, 5.0))
+            .build();
+        let body = bodies[0];
+
+        world.run(|mut physics_world: UniqueViewMut<PhysicsWorld>| {
+            physics_world.set_config(&PhysicsConfig::new().validate(true).max_delta(100.0).max_coordinate(1000.0));
+            let position = |physics_world: &PhysicsWorld| {
+                let transform = physics_world.transform(body);
+                Vec2::new(transform.x, transform.y)
+            };
+
+            physics_world.move_body(body, Vec2::new(f64::NAN, 0.0));
+            physics_world.move_body_to(body, Vec2::new(0.0, f64::INFINITY));
+            assert_eq!(position(&physics_world), Vec2::new(0.0, 0.0));
+
+            // Long movements are clamped rather than rejected
+            physics_world.move_body(body, Vec2::new(0.0, 500.0));
+            assert!((position(&physics_world) - Vec2::new(0.0, 100.0)).magnitude() < 1e-9);
+
+            physics_world.move_body_to(body, Vec2::new(2000.0, 0.0));
+            physics_world.move_body_to_x(body, -1001.0);
+            assert!((position(&physics_world) - Vec2::new(0.0, 100.0)).magnitude() < 1e-9);
+            assert!(physics_world.move_body_and_collide(body, Vec2::new(f64::NAN, f64::NAN)).is_empty());
+
+            physics_world.set_config(&PhysicsConfig::new().validate(false).max_delta(100.0).max_coordinate(1000.0));
+            physics_world.move_body_to(body, Vec2::new(2000.0, 0.0));
+            assert_eq!(position(&physics_world), Vec2::new(2000.0, 0.0));
+        });
+    }
+
+    #[test]
+    fn pushed_body_is_stopped_by_wall() {
+        let (world, bodies) = PhysicsWorldBuilder::new()
+            .body(BodyDesc::new(Transform::new(-7.0, 0.0), CollisionBody::from_collider(Collider::circle(10.0, 1, 1)).with_mass(1.0)))
+            .body(BodyDesc::new(Transform::new(14.0, 0.0), CollisionBody::from_collider(Collider::circle(10.0, 1, 1)).with_mass(1.0)))
+            .body(BodyDesc::rect(30.0, 0.0, 5.0, 50.0))
+            .build();
+
+        world.run(|mut physics_world: UniqueViewMut<PhysicsWorld>| {
+            // Half of the 9 unit overlap pushes the second circle, which can only move 1 before hitting the wall.
+            // The rest is taken back from the first circle, leaving all three touching.
+            physics_world.move_body_and_collide(bodies[0], Vec2::new(10.0, 0.0));
+            assert!((physics_world.transform(bodies[1]).x - 15.0).abs() < 1e-6);
+            assert!((physics_world.transform(bodies[0]).x - -5.0).abs() < 1e-6);
+            check_invariants(&physics_world, &bodies).unwrap();
+
+            // The pushed body still knows what pushed it
+            let pushed = &physics_world.collider(bodies[1]).colliders()[0].overlapping;
+            assert!(pushed.iter().any(|collision| collision.entity2 == bodies[0]));
+        });
+    }
+
+    #[test]
+    fn moment_of_inertia() {
+        let circle = CollisionBody::from_collider(Collider::circle(2.0, 1, 1)).with_mass(3.0);
+        assert!((circle.moment_of_inertia().unwrap() - 6.0).abs() < 1e-9);
+
+        let rect = CollisionBody::from_collider(Collider::half_extents(1.0, 2.0, 1, 1)).with_mass(12.0);
+        assert!((rect.moment_of_inertia().unwrap() - 20.0).abs() < 1e-9);
+
+        // Two 2x2 squares 6 apart, each square's own moment plus its area times 3 squared
+        let pair = CollisionBody::from_colliders(vec![
+            Collider::half_extents(1.0, 1.0, 1, 1).with_offset(-3.0, 0.0),
+            Collider::half_extents(1.0, 1.0, 1, 1).with_offset(3.0, 0.0),
+        ]).with_mass(2.0);
+        let expected = 2.0 * 2.0 * (8.0 / 3.0 + 36.0) / 8.0;
+        assert!((pair.moment_of_inertia().unwrap() - expected).abs() < 1e-9);
+
+        assert_eq!(CollisionBody::from_collider(Collider::circle(1.0, 1, 1)).moment_of_inertia(), None);
+    }
+
+    #[test]
+    fn center_of_mass_weights_by_area() {
+        let body = CollisionBody::from_colliders(vec![
+            Collider::half_extents(1.0, 1.0, 1, 1).with_offset(-3.0, 0.0),
+            Collider::half_extents(1.0, 1.0, 1, 1).with_offset(3.0, 0.0),
+            Collider::half_extents(1.0, 1.0, 1, 1).with_offset(3.0, 0.0),
+        ]);
+        assert!((body.center_of_mass() - Vec2::new(1.0, 0.0)).magnitude() < 1e-9);
+    }
+
+    #[test]
+    fn solver_resolves_corner() {
+        let mut world = World::new();
+
+        world
+            .add_physics_workload(64.0, 64.0)
+            .with_physics_systems()
+            .build();
+
+        world.run(|
+            mut entities: EntitiesViewMut,
+            mut bodies: ViewMut<PhysicsBody>,
+            mut transforms: ViewMut<Transform>,
+            mut physics_world: UniqueViewMut<PhysicsWorld>| {
+                // A floor and a wall meeting in a corner
+                let floor = entities.add_entity((), ());
+                physics_world.create_body(
+                    &mut entities,
+                    &mut bodies,
+                    floor,
+                    &mut transforms,
+                    Transform::new(0.0, 20.0),
+                    CollisionBody::from_collider(Collider::half_extents(40.0, 10.0, 1, 1)),
+                );
+
+                let wall = entities.add_entity((), ());
+                physics_world.create_body(
+                    &mut entities,
+                    &mut bodies,
+                    wall,
+                    &mut transforms,
+                    Transform::new(30.0, 0.0),
+                    CollisionBody::from_collider(Collider::half_extents(10.0, 30.0, 1, 1)),
+                );
+
+                let mover = entities.add_entity((), ());
+                physics_world.create_body(
+                    &mut entities,
+                    &mut bodies,
+                    mover,
+                    &mut transforms,
+                    Transform::new(0.0, 0.0),
+                    CollisionBody::from_collider(Collider::half_extents(5.0, 5.0, 1, 1)),
+                );
+
+                // Pushed 3 units into both the floor and the wall
+                let collisions = physics_world.move_body_and_collide(mover, Vec2::new(18.0, 8.0));
+                assert!(!collisions.is_empty());
+
+                let transform = physics_world.transform(mover);
+                assert!(transform.x <= 15.0 + 1e-9);
+                assert!(transform.y <= 5.0 + 1e-9);
+        });
+    }
+
+    #[test]
+    fn add_collider_updates_broadphase() {
+        use test_utils::*;
+
+        let (world, bodies) = PhysicsWorldBuilder::new()
+            .body(BodyDesc::circle(0.0, 0.0, 5.0))
+            .body(BodyDesc::circle(100.0, 0.0, 5.0))
+            .build();
+
+        world.run(|mut physics_world: UniqueViewMut<PhysicsWorld>| {
+            physics_world.add_collider(bodies[0], Collider::circle(5.0, 1, 1).with_offset(90.0, 0.0));
+            check_invariants(&physics_world, &bodies).unwrap();
+            assert!((physics_world.collider(bodies[0]).aabb().width - 100.0).abs() < 1e-9);
+
+            // The new collider now overlaps the second body
+            let collisions = physics_world.move_body_and_collide(bodies[0], Vec2::new(1.0, 0.0));
+            assert!(collisions.iter().any(|collision| collision.entity2 == bodies[1]));
+
+            physics_world.remove_collider(bodies[0], 1);
+            check_invariants(&physics_world, &bodies).unwrap();
+            assert!((physics_world.collider(bodies[0]).aabb().width - 10.0).abs() < 1e-9);
+        });
+    }
+
+    #[test]
+    fn sat_uses_closing_edge() {
+        let triangle = CollisionShape::Polygon(vec![Vec2::new(0.0, 0.0), Vec2::new(10.0, 0.0), Vec2::new(0.0, 10.0)]);
+        let quad = CollisionShape::Polygon(vec![Vec2::new(0.0, 0.0), Vec2::new(10.0, 0.0), Vec2::new(12.0, 8.0), Vec2::new(-4.0, 6.0)]);
+        let circle = CollisionShape::Circle(2.0);
+        let origin = Transform::new(0.0, 0.0);
+
+        // Only separated along the normal of the edge from the last vertex back to the first
+        let beside_triangle = Transform::new(-3.0, 5.0);
+        assert!(!sat::separating_axis_test(&origin, &triangle, &beside_triangle, &circle).0);
+        assert!(!sat::separating_axis_test(&beside_triangle, &circle, &origin, &triangle).0);
+
+        let small_circle = CollisionShape::Circle(1.0);
+        let beside_quad = Transform::new(-4.5, 1.34);
+        assert!(!sat::separating_axis_test(&origin, &quad, &beside_quad, &small_circle).0);
+        assert!(!sat::separating_axis_test(&beside_quad, &small_circle, &origin, &quad).0);
+
+        let inside_quad = Transform::new(-1.0, 3.0);
+        assert!(sat::separating_axis_test(&origin, &quad, &inside_quad, &small_circle).0);
+
+        // Boxes have two pairs of parallel edges
+        let square = Collider::half_extents(5.0, 5.0, 1, 1);
+        assert_eq!(sat::get_axes(&square.shape).len(), 2);
+        assert_eq!(sat::get_axes(&triangle).len(), 3);
+    }
+
+    #[test]
+    fn polygon_validation_and_decomposition() {
+        let clockwise = vec![Vec2::new(0.0, 0.0), Vec2::new(0.0, 10.0), Vec2::new(10.0, 10.0), Vec2::new(10.0, 0.0)];
+        let collider = Collider::try_polygon(clockwise, 1, 1).unwrap();
+        if let CollisionShape::Polygon(vertices) = &collider.shape {
+            assert!(convex::signed_area(vertices) > 0.0);
+        }
+
+        let l_shape = vec![
+            Vec2::new(0.0, 0.0),
+            Vec2::new(20.0, 0.0),
+            Vec2::new(20.0, 10.0),
+            Vec2::new(10.0, 10.0),
+            Vec2::new(10.0, 20.0),
+            Vec2::new(0.0, 20.0),
+        ];
+        assert_eq!(Collider::try_polygon(l_shape.clone(), 1, 1).err(), Some(PolygonError::Concave));
+        assert_eq!(Collider::try_polygon(vec![Vec2::zero(), Vec2::new(1.0, 1.0), Vec2::new(2.0, 2.0)], 1, 1).err(), Some(PolygonError::Degenerate));
+
+        let body = CollisionBody::from_concave_polygon(&l_shape, 1, 1).unwrap();
+        assert!(body.colliders().len() >= 2);
+        let mut area = 0.0;
+        for collider in body.colliders().iter() {
+            if let CollisionShape::Polygon(vertices) = &collider.shape {
+                assert!(convex::is_convex(vertices));
+                assert!(convex::signed_area(vertices) > 0.0);
+            }
+            area += collider.shape.area();
+        }
+        assert!((area - 300.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn body_builder_resolves_layers() {
+        use builder::*;
+
+        let mut layers = CollisionLayers::new().with_default_layer("wall");
+        layers.define("player", 1, 2 | 4);
+        layers.define("enemy", 2, 1 | 4);
+        layers.define("wall", 4, 0);
+
+        let body = CollisionBodyBuilder::new()
+            .collider(CollisionShape::Circle(8.0)).layer("enemy")
+            .sensor(CollisionShape::Circle(20.0)).offset(30.0, 0.0).layer("enemy").collides_with_layers(&["player"])
+            .collider(CollisionShape::Polygon(vec![Vec2::new(0.0, 0.0), Vec2::new(0.0, 4.0), Vec2::new(4.0, 4.0), Vec2::new(4.0, 0.0)]))
+            .mass(2.0)
+            .build(&layers)
+            .unwrap();
+
+        assert_eq!(body.colliders().len(), 2);
+        assert_eq!(body.sensors().len(), 1);
+        assert_eq!((body.colliders()[0].collision_layer, body.colliders()[0].collides_with), (2, 5));
+        assert_eq!((body.colliders()[1].collision_layer, body.colliders()[1].collides_with), (4, 0));
+        assert_eq!(body.sensors()[0].collides_with, 1);
+        assert_eq!(body.mass, Some(2.0));
+        // The AABB spans from the left of the circle to the right of the offset sensor
+        assert!((body.aabb().width - 58.0).abs() < 1e-9);
+
+        let unknown = CollisionBodyBuilder::new().collider(CollisionShape::Circle(1.0)).layer("ghost").build(&layers);
+        assert_eq!(unknown.err(), Some(BodyBuildError::UnknownLayer("ghost")));
+        assert_eq!(CollisionBodyBuilder::new().build(&layers).err(), Some(BodyBuildError::Empty));
+    }
+
+    #[test]
+    fn body_builder_reports_properties_without_a_part() {
+        use builder::*;
+
+        let layers = CollisionLayers::new().with_default_layer("wall");
+        let early = CollisionBodyBuilder::new()
+            .offset(4.0, 0.0)
+            .material(Material::default())
+            .collider(CollisionShape::Circle(8.0))
+            .build(&layers);
+        assert_eq!(early.err(), Some(BodyBuildError::NoPart));
+
+        // Body properties don't need a part
+        let mass_first = CollisionBodyBuilder::new().mass(2.0).collider(CollisionShape::Circle(8.0)).layer_bits(1).build(&layers);
+        assert!(mass_first.is_ok());
+    }
+
+    #[test]
+    fn create_bodies_in_bulk() {
+        use test_utils::*;
+
+        let (world, _) = PhysicsWorldBuilder::new().bucket_size(20.0, 20.0).build();
+
+        let ids = world.run(|
+            mut entities: EntitiesViewMut,
+            mut bodies: ViewMut<PhysicsBody>,
+            mut transforms: ViewMut<Transform>,
+            mut physics_world: UniqueViewMut<PhysicsWorld>| {
+                let tiles: Vec<(EntityId, Transform, CollisionBody)> = (0..100)
+                    .map(|i| (
+                        entities.add_entity((), ()),
+                        Transform::new((i % 10) as f64 * 16.0 - 80.0, (i / 10) as f64 * 16.0 - 80.0),
+                        CollisionBody::from_collider(Collider::half_extents(8.0, 8.0, 1, 1)),
+                    ))
+                    .collect();
+                let ids: Vec<EntityId> = tiles.iter().map(|(id, _, _)| *id).collect();
+
+                physics_world.create_bodies(&mut entities, &mut bodies, &mut transforms, tiles);
+                check_invariants(&physics_world, &ids).unwrap();
+                ids
+        });
+
+        world.run(|bodies: View<PhysicsBody>, physics_world: UniqueView<PhysicsWorld>| {
+            assert!(ids.iter().all(|id| bodies.get(*id).is_ok()));
+            assert_eq!(physics_world.nearby_point(Vec2::new(-80.0, -80.0)), vec![ids[0]]);
+        });
+    }
+
+    #[test]
+    fn free_standing_queries() {
+        use test_utils::*;
+
+        let (world, bodies) = PhysicsWorldBuilder::new()
+            .body(BodyDesc::circle(0.0, 0.0, 5.0))
+            .body(BodyDesc::circle(100.0, 0.0, 5.0))
+            .build();
+
+        world.run(|physics_world: UniqueView<PhysicsWorld>| {
+            assert_eq!(physics_world.nearby_point(Vec2::new(3.0, 3.0)), vec![bodies[0]]);
+            assert!(physics_world.nearby_point(Vec2::new(50.0, 0.0)).is_empty());
+
+            let area = AABB::new(-10.0, -10.0, 20.0, 20.0);
+            assert_eq!(physics_world.nearby_aabb(&area, &Transform::new(95.0, 0.0)), vec![bodies[1]]);
+            assert!(physics_world.nearby_aabb(&area, &Transform::new(50.0, 50.0)).is_empty());
+        });
+    }
+
+    #[test]
+    fn disabled_bodies_dont_collide() {
+        use test_utils::*;
+
+        let (world, bodies) = PhysicsWorldBuilder::new()
+            .body(BodyDesc::circle(0.0, 0.0, 5.0))
+            .body(BodyDesc::circle(20.0, 0.0, 5.0))
+            .build();
+
+        world.run(|mut physics_world: UniqueViewMut<PhysicsWorld>| {
+            physics_world.set_body_enabled(bodies[1], false);
+            let collisions = physics_world.move_body_and_collide(bodies[0], Vec2::new(15.0, 0.0));
+            assert!(collisions.is_empty());
+            assert_eq!(physics_world.nearest_body(Vec2::new(20.0, 0.0), u64::MAX).map(|(id, _)| id), Some(bodies[0]));
+
+            // Re-enabling picks up the overlap without moving either body
+            physics_world.set_body_enabled(bodies[1], true);
+            assert!(physics_world.collider(bodies[1]).colliders()[0].overlapping.iter().any(|c| c.entity2 == bodies[0]));
+            check_invariants(&physics_world, &bodies).unwrap();
+        });
+    }
+
+    #[test]
+    fn carriers_move_riders() {
+        use test_utils::*;
+
+        let platform = CollisionBody::from_collider(Collider::half_extents(20.0, 5.0, 1, 1)).with_carrier(Carrier::new());
+        let (world, bodies) = PhysicsWorldBuilder::new()
+            .body(BodyDesc::new(Transform::new(0.0, 0.0), platform))
+            .body(BodyDesc::rect(0.0, -10.0, 5.0, 5.0))
+            .body(BodyDesc::rect(0.0, 10.0, 5.0, 5.0))
+            .body(BodyDesc::rect(100.0, -10.0, 5.0, 5.0))
+            .build();
+
+        world.run(|mut physics_world: UniqueViewMut<PhysicsWorld>| {
+            // Only the body on top rides, not the one touching the underside
+            assert_eq!(physics_world.riders(bodies[0]), vec![bodies[1]]);
+
+            physics_world.move_body(bodies[0], Vec2::new(10.0, 0.0));
+            assert_eq!(*physics_world.transform(bodies[1]), Transform::new(10.0, -10.0));
+            assert_eq!(*physics_world.transform(bodies[2]), Transform::new(0.0, 10.0));
+            assert_eq!(*physics_world.transform(bodies[3]), Transform::new(100.0, -10.0));
+
+            physics_world.move_body_to(bodies[0], Vec2::new(10.0, -3.0));
+            assert_eq!(*physics_world.transform(bodies[1]), Transform::new(10.0, -13.0));
+            check_invariants(&physics_world, &bodies).unwrap();
+        });
+    }
+
+    #[test]
+    fn contact_pair_queries() {
+        use test_utils::*;
+
+        let (world, bodies) = PhysicsWorldBuilder::new()
+            .body(BodyDesc::rect(0.0, 0.0, 5.0, 5.0))
+            .body(BodyDesc::rect(20.0, 0.0, 5.0, 5.0))
+            .body(BodyDesc::rect(100.0, 0.0, 5.0, 5.0))
+            .build();
+
+        world.run(|mut physics_world: UniqueViewMut<PhysicsWorld>| {
+            physics_world.move_body(bodies[1], Vec2::new(-12.0, 0.0));
+            physics_world.update_contact_pairs();
+            assert!(physics_world.are_colliding(bodies[0], bodies[1]));
+            assert!(physics_world.started_colliding(bodies[1], bodies[0]));
+            assert!(!physics_world.are_colliding(bodies[0], bodies[2]));
+            assert_eq!(physics_world.contacts_of(bodies[1]).map(|collision| collision.entity2).collect::<Vec<_>>(), vec![bodies[0]]);
+
+            physics_world.update_contact_pairs();
+            assert!(physics_world.were_colliding(bodies[0], bodies[1]));
+            assert!(!physics_world.started_colliding(bodies[0], bodies[1]));
+
+            physics_world.move_body(bodies[1], Vec2::new(50.0, 0.0));
+            physics_world.update_contact_pairs();
+            assert!(physics_world.stopped_colliding(bodies[0], bodies[1]));
+            assert_eq!(physics_world.contacts_of(bodies[1]).count(), 0);
+        });
+    }
+
+    #[test]
+    fn sweep_and_prune_broadphase() {
+        use crate::random::Random;
+        use test_utils::*;
+
+        let mut random = Random::new(4658);
+        let (mut world, mut bodies) = PhysicsWorldBuilder::new()
+            .sweep_and_prune()
+            .body(BodyDesc::circle(0.0, 0.0, 5.0))
+            .body(BodyDesc::circle(1000.0, 0.0, 5.0))
+            .scattered(30, &mut random, 100.0)
+            .build();
+
+        world.run(|physics_world: UniqueView<PhysicsWorld>| {
+            check_invariants(&physics_world, &bodies).unwrap();
+            assert_eq!(physics_world.nearby_point(Vec2::new(1002.0, 0.0)), vec![bodies[1]]);
+            assert_eq!(physics_world.nearest_body(Vec2::new(1020.0, 0.0), u64::MAX).map(|(id, _)| id), Some(bodies[1]));
+        });
+
+        Fuzzer::new(4658).run(&mut world, &mut bodies, 500);
+    }
+
+    #[test]
+    fn sweep_and_prune_matches_brute_force() {
+        use crate::random::Random;
+        use broadphase::SweepAndPrune;
+
+        let mut random = Random::new(4658);
+        let world = World::new();
+        let mut sweep = SweepAndPrune::new();
+        let mut bodies: Vec<(EntityId, Transform, AABB)> = (0..200)
+            .map(|index| {
+                let id = world.run(|mut entities: EntitiesViewMut| entities.add_entity((), ()));
+                let transform = Transform::new(random.range_f64(-500.0, 500.0), random.range_f64(-500.0, 500.0));
+                // One very wide body so queries have to start well left of the area
+                let width = if index == 0 { 800.0 } else { random.range_f64(1.0, 20.0) };
+                (id, transform, AABB::new(-width / 2.0, -5.0, width, 10.0))
+            })
+            .collect();
+        for (id, transform, aabb) in bodies.iter() {
+            sweep.insert(*id, transform, aabb);
+        }
+
+        let mut check = |sweep: &SweepAndPrune, bodies: &[(EntityId, Transform, AABB)]| {
+            for _ in 0..50 {
+                let transform = Transform::new(random.range_f64(-600.0, 600.0), random.range_f64(-600.0, 600.0));
+                let area = AABB::new(0.0, 0.0, random.range_f64(1.0, 100.0), random.range_f64(1.0, 100.0));
+
+                let mut found = sweep.query(&transform, &area);
+                let mut expected: Vec<EntityId> = bodies.iter()
+                    .filter(|(_, other_transform, aabb)| area.overlaps(&transform, aabb, other_transform))
+                    .map(|(id, _, _)| *id)
+                    .collect();
+                found.sort_by_key(|id| id.uindex());
+                expected.sort_by_key(|id| id.uindex());
+                assert_eq!(found, expected);
+            }
+
+            for (id, transform, aabb) in bodies.iter() {
+                assert!(sweep.contains(*id, transform, aabb));
+            }
+
+            // Every body is visited once, nearest first
+            let mut visited = vec![];
+            let mut last = 0.0;
+            sweep.search_outward(Vec2::new(random.range_f64(-600.0, 600.0), 0.0), &mut |ids, distance| {
+                assert!(distance >= last);
+                last = distance;
+                visited.extend_from_slice(ids);
+                true
+            });
+            assert_eq!(visited.len(), bodies.len());
+        };
+
+        check(&sweep, &bodies);
+
+        // Removing the wide body shrinks how far queries reach back
+        for (id, transform, aabb) in bodies.drain(..100) {
+            sweep.remove(id, &transform, &aabb);
+            assert!(!sweep.contains(id, &transform, &aabb));
+        }
+        check(&sweep, &bodies);
+    }
+
+    #[test]
+    fn fuzz_invariants() {
+        use crate::random::Random;
+        use test_utils::*;
+
+        let mut random = Random::new(4606);
+        let (mut world, mut bodies) = PhysicsWorldBuilder::new()
+            .bucket_size(20.0, 20.0)
+            .scattered(30, &mut random, 100.0)
+            .body(BodyDesc::circle(0.0, 0.0, 15.0).sensor())
+            .bodies(15, |i| {
+                // Crowds of pushable bodies, some much heavier than others
+                let mass = if i % 5 == 0 { 1000.0 } else { 1.0 + i as f64 };
+                BodyDesc::circle(-60.0 + (i % 5) as f64 * 12.0, 60.0 + (i / 5) as f64 * 12.0, 5.0).with_mass(mass)
+            })
+            .build();
+
+        world.run(|physics_world: UniqueView<PhysicsWorld>| {
+            check_invariants(&physics_world, &bodies).unwrap();
+        });
+
+        Fuzzer::new(4606).run(&mut world, &mut bodies, 500);
+    }
+
+    /// A circle of radius 5 at the origin and a static box with the material, returns the slide result of moving the circle
+    fn slide_into_box(box_position: Vec2<f64>, half_extents: Vec2<f64>, material: Material, velocity: Vec2<f64>) -> (SlideResult, Transform) {
+        use test_utils::*;
+
+        let wall = CollisionBody::from_collider(Collider::half_extents(half_extents.x, half_extents.y, 1, 1).with_material(material));
+        let (world, bodies) = PhysicsWorldBuilder::new()
+            .body(BodyDesc::circle(0.0, 0.0, 5.0))
+            .body(BodyDesc::new(Transform::new(box_position.x, box_position.y), wall))
+            .build();
+
+        world.run(|mut physics_world: UniqueViewMut<PhysicsWorld>| {
+            let result = physics_world.move_and_slide(bodies[0], velocity, Vec2::new(0.0, -1.0), std::f64::consts::FRAC_PI_4, 0.0);
+            (result, *physics_world.transform(bodies[0]))
+        })
+    }
+
+    #[test]
+    fn move_and_slide_along_wall() {
+        let (result, transform) = slide_into_box(Vec2::new(12.0, 0.0), Vec2::new(5.0, 50.0), Material::default(), Vec2::new(4.0, 5.0));
+
+        assert!(result.on_wall);
+        assert!(!result.on_floor && !result.on_ceiling);
+        assert!((transform.x - 2.0).abs() < 1e-9);
+        assert!((transform.y - 5.0).abs() < 1e-9);
+
+        // Only the motion into the wall is removed
+        assert!(result.velocity.x.abs() < 1e-9);
+        assert!((result.velocity.y - 5.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn move_and_slide_onto_floor() {
+        let (result, transform) = slide_into_box(Vec2::new(0.0, 12.0), Vec2::new(50.0, 5.0), Material::default(), Vec2::new(3.0, 4.0));
+
+        assert!(result.on_floor);
+        assert!(!result.on_wall);
+        assert!((transform.y - 2.0).abs() < 1e-9);
+        assert!((result.velocity.x - 3.0).abs() < 1e-9);
+        assert!(result.velocity.y.abs() < 1e-9);
+    }
+
+    #[test]
+    fn move_and_slide_friction() {
+        let (result, _) = slide_into_box(Vec2::new(12.0, 0.0), Vec2::new(5.0, 50.0), Material::new(0.5, 0.0), Vec2::new(4.0, 5.0));
+
+        assert!(result.velocity.x.abs() < 1e-9);
+        assert!((result.velocity.y - 2.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn move_and_slide_restitution() {
+        let (result, _) = slide_into_box(Vec2::new(12.0, 0.0), Vec2::new(5.0, 50.0), Material::new(0.0, 1.0), Vec2::new(4.0, 5.0));
+
+        // Bounced straight back off the wall
+        assert!((result.velocity.x + 4.0).abs() < 1e-9);
+        assert!((result.velocity.y - 5.0).abs() < 1e-9);
+    }
+}
@@ -1,5 +1,6 @@
 use super::*;
 
+/// The normals of every edge of the polygon, including the closing edge. Parallel edges only add one axis.
 pub fn get_axes(shape: &CollisionShape) -> Vec<Vec2<f64>> {
     use CollisionShape::Polygon;
     use CollisionShape::Circle;
@@ -7,13 +8,19 @@ pub fn get_axes(shape: &CollisionShape) -> Vec<Vec2<f64>> {
     match shape {
         Polygon(vertices) => {
             // Get the normals of each edge of the polygon
-            let mut axes1 = vec![];
-            for i in 0..(vertices.len() - 1) {
-                let p1 = vertices[i];
-                let p2 = vertices[i + 1];
+            let mut axes1: Vec<Vec2<f64>> = vec![];
+            for (p1, p2) in edges(vertices) {
                 let edge = p1 - p2;
-                let normal = Vec2::new(edge.y, -edge.x);
-                axes1.push(normal.normalized());
+                if edge.magnitude_squared() == 0.0 {
+                    continue;
+                }
+
+                let normal = Vec2::new(edge.y, -edge.x).normalized();
+                // Projections onto parallel axes are the same, opposite edges of boxes would otherwise be tested twice
+                if axes1.iter().any(|axis| (axis.x * normal.y - axis.y * normal.x).abs() < 1e-9) {
+                    continue;
+                }
+                axes1.push(normal);
             }
             axes1
         },
@@ -138,7 +145,23 @@ pub fn project_shape(shape: &CollisionShape, transform: &Transform, axis: &Vec2<
     }
 }
 
-pub fn seperating_axis_test(t1: &Transform, c1: &CollisionShape, t2: &Transform, c2: &CollisionShape) -> (bool, Option<Vec2<f64>>) {                
+/// Runs the separating axis test between two colliders, taking their offset and rotation relative to the body into account
+pub fn collider_test(t1: &Transform, c1: &Collider, t2: &Transform, c2: &Collider) -> (bool, Option<Vec2<f64>>) {
+    separating_axis_test(
+        &c1.world_transform(t1), 
+        &c1.local_shape(), 
+        &c2.world_transform(t2), 
+        &c2.local_shape(),
+    )
+}
+
+#[deprecated(note = "renamed to separating_axis_test")]
+pub fn seperating_axis_test(t1: &Transform, c1: &CollisionShape, t2: &Transform, c2: &CollisionShape) -> (bool, Option<Vec2<f64>>) {
+    separating_axis_test(t1, c1, t2, c2)
+}
+
+/// Whether the two shapes overlap, and if they do the minimum translation vector that pushes c1 out of c2
+pub fn separating_axis_test(t1: &Transform, c1: &CollisionShape, t2: &Transform, c2: &CollisionShape) -> (bool, Option<Vec2<f64>>) {
     use CollisionShape::Circle;
     
     // Get separating axes
@@ -200,4 +223,107 @@ pub fn seperating_axis_test(t1: &Transform, c1: &CollisionShape, t2: &Transform,
     }
     
     (true, Some(mtv))
+}
+
+//
+// Closest point queries
+
+/// Iterates over every edge of the polygon, including the closing edge from the last vertex to the first
+pub fn edges(vertices: &[Vec2<f64>]) -> impl Iterator<Item = (Vec2<f64>, Vec2<f64>)> + '_ {
+    vertices.iter()
+        .zip(vertices.iter().cycle().skip(1))
+        .map(|(a, b)| (*a, *b))
+}
+
+/// Distance from the point to the closest point on the segment a-b
+pub fn segment_distance(point: Vec2<f64>, a: Vec2<f64>, b: Vec2<f64>) -> f64 {
+    let ab = b - a;
+    let length_squared = ab.magnitude_squared();
+    if length_squared == 0.0 {
+        return (point - a).magnitude();
+    }
+
+    let t = ((point - a).dot(ab) / length_squared).max(0.0).min(1.0);
+    (point - (a + ab * t)).magnitude()
+}
+
+/// Returns true if the point lies inside the convex polygon
+pub fn polygon_contains(vertices: &[Vec2<f64>], point: Vec2<f64>) -> bool {
+    let mut sign = 0.0;
+    for (a, b) in edges(vertices) {
+        let edge = b - a;
+        let to_point = point - a;
+        let cross = edge.x * to_point.y - edge.y * to_point.x;
+
+        if cross != 0.0 {
+            if sign != 0.0 && cross.signum() != sign {
+                return false;
+            }
+            sign = cross.signum();
+        }
+    }
+    true
+}
+
+/// Distance from the point to the closest point of the shape, 0.0 if the point is inside the shape
+pub fn point_distance(point: Vec2<f64>, shape: &CollisionShape, transform: &Transform) -> f64 {
+    use CollisionShape::Polygon;
+    use CollisionShape::Circle;
+
+    let local = point - Vec2::new(transform.x, transform.y);
+
+    match shape {
+        Circle(r) => (local.magnitude() - r).max(0.0),
+        Polygon(vertices) => {
+            if polygon_contains(vertices, local) {
+                return 0.0;
+            }
+
+            edges(vertices)
+                .map(|(a, b)| segment_distance(local, a, b))
+                .fold(f64::INFINITY, f64::min)
+        },
+    }
+}
+
+/// Distance between the closest points of the two shapes, 0.0 if they overlap
+pub fn shape_distance(t1: &Transform, c1: &CollisionShape, t2: &Transform, c2: &CollisionShape) -> f64 {
+    use CollisionShape::Polygon;
+    use CollisionShape::Circle;
+
+    if separating_axis_test(t1, c1, t2, c2).0 {
+        return 0.0;
+    }
+
+    match (c1, c2) {
+        (Circle(r), _) => (point_distance(Vec2::new(t1.x, t1.y), c2, t2) - r).max(0.0),
+        (_, Circle(r)) => (point_distance(Vec2::new(t2.x, t2.y), c1, t1) - r).max(0.0),
+        (Polygon(vertices1), Polygon(vertices2)) => {
+            let pos1 = Vec2::new(t1.x, t1.y);
+            let pos2 = Vec2::new(t2.x, t2.y);
+
+            let one_way = |from: &[Vec2<f64>], from_pos: Vec2<f64>, to: &[Vec2<f64>], to_pos: Vec2<f64>| {
+                from.iter()
+                    .map(|v| {
+                        let v = *v + from_pos - to_pos;
+                        edges(to)
+                            .map(|(a, b)| segment_distance(v, a, b))
+                            .fold(f64::INFINITY, f64::min)
+                    })
+                    .fold(f64::INFINITY, f64::min)
+            };
+
+            one_way(vertices1, pos1, vertices2, pos2).min(one_way(vertices2, pos2, vertices1, pos1))
+        },
+    }
+}
+
+/// Distance between the closest points of two colliders, taking their offset and rotation into account
+pub fn collider_distance(t1: &Transform, c1: &Collider, t2: &Transform, c2: &Collider) -> f64 {
+    shape_distance(
+        &c1.world_transform(t1), 
+        &c1.local_shape(), 
+        &c2.world_transform(t2), 
+        &c2.local_shape(),
+    )
 }
\ No newline at end of file
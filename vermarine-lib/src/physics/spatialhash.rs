@@ -1,4 +1,19 @@
 use super::*;
+use std::collections::HashMap;
+
+/// A summary of how bodies are spread over the buckets, used to tune the bucket size
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct BroadphaseStats {
+    /// Allocated buckets, including empty ones
+    pub bucket_count: usize,
+    pub occupied_buckets: usize,
+    pub bodies: usize,
+    /// Average number of bodies in the occupied buckets
+    pub average_occupancy: f64,
+    pub max_occupancy: usize,
+    /// Bodies that are in more buckets than the threshold passed to `stats`, a lot of these means the buckets are too small
+    pub bodies_spanning_many: usize,
+}
 
 pub struct SpatialBuckets {
     buckets: Vec<Vec<EntityId>>,
@@ -44,6 +59,33 @@ impl SpatialBuckets {
         }
     }
 
+    /// Inserts every entity, growing the grid once for the area they cover instead of once per entity
+    pub fn insert_many<'a, I: Iterator<Item = (EntityId, &'a Transform, &'a AABB)> + Clone>(&mut self, items: I) {
+        let mut cells: Option<(isize, isize, isize, isize)> = None;
+        for (_, transform, aabb) in items.clone() {
+            let (xmin, ymin) = self.point_to_cell(transform.x + aabb.dx, transform.y + aabb.dy);
+            let (xmax, ymax) = self.point_to_cell(transform.x + aabb.dx + aabb.width, transform.y + aabb.dy + aabb.height);
+            cells = Some(match cells {
+                Some((x0, y0, x1, y1)) => (x0.min(xmin), y0.min(ymin), x1.max(xmax), y1.max(ymax)),
+                None => (xmin, ymin, xmax, ymax),
+            });
+        }
+
+        if let Some((xmin, ymin, xmax, ymax)) = cells {
+            while 
+                self.wrap_point(xmin) >= self.width || 
+                self.wrap_point(xmax) >= self.width || 
+                self.wrap_point(ymin) >= self.height || 
+                self.wrap_point(ymax) >= self.height {
+                self.resize();
+            }
+        }
+
+        for (id, transform, aabb) in items {
+            self.insert(id, transform, aabb);
+        }
+    }
+
     pub fn remove(&mut self, id: EntityId, transform: &Transform, aabb: &AABB) {
         let xmin = transform.x + aabb.dx;
         let ymin = transform.y + aabb.dy;
@@ -100,6 +142,151 @@ impl SpatialBuckets {
         nearby
     }
 
+    /// Returns all entities in the cells whose chebyshev distance from (x, y) is exactly radius.
+    /// Cells outside of the allocated grid are skipped as they can't contain anything.
+    pub fn ring(&self, x: isize, y: isize, radius: isize) -> Vec<EntityId> {
+        let mut found = vec![];
+        let mut add_cell = |cx: isize, cy: isize| {
+            let (wx, wy) = self.wrap_cell(cx, cy);
+            if wx >= self.width || wy >= self.height {
+                return;
+            }
+            for e in self.buckets[wy * self.width + wx].iter() {
+                if !found.contains(e) {
+                    found.push(*e);
+                }
+            }
+        };
+
+        if radius == 0 {
+            add_cell(x, y);
+            return found;
+        }
+
+        for cx in (x - radius)..=(x + radius) {
+            add_cell(cx, y - radius);
+            add_cell(cx, y + radius);
+        }
+        for cy in (y - radius + 1)..(y + radius) {
+            add_cell(x - radius, cy);
+            add_cell(x + radius, cy);
+        }
+        found
+    }
+
+    /// The largest ring radius around (x, y) that still contains allocated cells
+    pub fn max_ring(&self, x: isize, y: isize) -> isize {
+        let (width, height) = (self.width as isize, self.height as isize);
+        let (xmin, xmax) = (-(width / 2), (width - 1) / 2);
+        let (ymin, ymax) = (-(height / 2), (height - 1) / 2);
+
+        (x - xmin).abs()
+            .max((x - xmax).abs())
+            .max((y - ymin).abs())
+            .max((y - ymax).abs())
+    }
+
+    /// Returns true if the id is in every bucket the AABB overlaps, without growing the grid
+    pub fn contains(&self, id: EntityId, transform: &Transform, aabb: &AABB) -> bool {
+        let xmin = transform.x + aabb.dx;
+        let ymin = transform.y + aabb.dy;
+        let xmax = xmin + aabb.width;
+        let ymax = ymin + aabb.height;
+
+        let (xmin, ymin) = self.point_to_cell(xmin, ymin);
+        let (xmax, ymax) = self.point_to_cell(xmax, ymax);
+
+        for x in xmin..=xmax {
+            for y in ymin..=ymax {
+                let (x, y) = self.wrap_cell(x, y);
+                if x >= self.width || y >= self.height || !self.buckets[y * self.width + x].contains(&id) {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    /// Returns every entity in the buckets the AABB overlaps without growing the grid, unlike `nearby`.
+    /// Buckets outside of the allocated grid are skipped as they can't contain anything.
+    pub fn query(&self, transform: &Transform, aabb: &AABB) -> Vec<EntityId> {
+        let xmin = transform.x + aabb.dx;
+        let ymin = transform.y + aabb.dy;
+        let xmax = xmin + aabb.width;
+        let ymax = ymin + aabb.height;
+
+        let (xmin, ymin) = self.point_to_cell(xmin, ymin);
+        let (xmax, ymax) = self.point_to_cell(xmax, ymax);
+
+        let mut found = vec![];
+        for x in xmin..=xmax {
+            for y in ymin..=ymax {
+                let (x, y) = self.wrap_cell(x, y);
+                if x >= self.width || y >= self.height {
+                    continue;
+                }
+                for e in self.buckets[y * self.width + x].iter() {
+                    if !found.contains(e) {
+                        found.push(*e);
+                    }
+                }
+            }
+        }
+        found
+    }
+
+    pub fn bucket_size(&self) -> (f64, f64) {
+        (self.bucket_width, self.bucket_height)
+    }
+
+    /// Gathers occupancy statistics, bodies in more than span_threshold buckets are counted in `bodies_spanning_many`
+    pub fn stats(&self, span_threshold: usize) -> BroadphaseStats {
+        let mut spans: HashMap<EntityId, usize> = HashMap::new();
+        let mut occupied_buckets = 0;
+        let mut total_occupancy = 0;
+        let mut max_occupancy = 0;
+
+        for bucket in self.buckets.iter() {
+            if bucket.is_empty() {
+                continue;
+            }
+
+            occupied_buckets += 1;
+            total_occupancy += bucket.len();
+            max_occupancy = max_occupancy.max(bucket.len());
+            for id in bucket.iter() {
+                *spans.entry(*id).or_insert(0) += 1;
+            }
+        }
+
+        BroadphaseStats {
+            bucket_count: self.buckets.len(),
+            occupied_buckets,
+            bodies: spans.len(),
+            average_occupancy: if occupied_buckets > 0 { total_occupancy as f64 / occupied_buckets as f64 } else { 0.0 },
+            max_occupancy,
+            bodies_spanning_many: spans.values().filter(|span| **span > span_threshold).count(),
+        }
+    }
+
+    /// The world space area of every bucket with bodies in it and how many bodies it holds
+    pub fn occupied_cells(&self) -> Vec<(AABB, usize)> {
+        self.buckets.iter()
+            .enumerate()
+            .filter(|(_, bucket)| !bucket.is_empty())
+            .map(|(index, bucket)| {
+                let x = self.unwrap_point(index % self.width) as f64;
+                let y = self.unwrap_point(index / self.width) as f64;
+                (AABB::new(x * self.bucket_width, y * self.bucket_height, self.bucket_width, self.bucket_height), bucket.len())
+            })
+            .collect()
+    }
+
+    /// Empties the grid and changes the bucket size, the caller is expected to reinsert every body
+    pub fn reset_with_cell_size(&mut self, bucket_width: f64, bucket_height: f64) {
+        *self = SpatialBuckets::new(bucket_width, bucket_height);
+    }
+
     pub fn resize(&mut self) {
         let mut insert_idx = self.width;
         for _ in 0..self.height {
@@ -127,6 +314,15 @@ impl SpatialBuckets {
         point as usize
     }
 
+    /// Turns a wrapped index back into the cell coordinate it came from
+    pub fn unwrap_point(&self, point: usize) -> isize {
+        if point % 2 == 0 {
+            (point / 2) as isize
+        } else {
+            -(((point + 1) / 2) as isize)
+        }
+    }
+
     pub fn wrap_cell(&self, x: isize, y: isize) -> (usize, usize) {
         (self.wrap_point(x), self.wrap_point(y))
     }
@@ -393,4 +589,48 @@ mod tests {
         let nearby = buckets.nearby(id1, &t1, &aabb1);
         assert_eq!(nearby.len(), 0);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn stats() {
+        use crate::physics::*;
+
+        let world = World::new();
+        let (id1, id2) = world.run(|mut entities: EntitiesViewMut| {
+            (entities.add_entity((), ()), entities.add_entity((), ()))
+        });
+
+        let mut buckets = SpatialBuckets::new(10.0, 10.0);
+        buckets.insert(id1, &Transform::new(5.0, 5.0), &AABB::new(0.0, 0.0, 10.0, 10.0));
+        buckets.insert(id2, &Transform::new(1.0, 1.0), &AABB::new(0.0, 0.0, 1.0, 1.0));
+
+        let stats = buckets.stats(1);
+        assert_eq!(stats.bucket_count, 16);
+        assert_eq!(stats.occupied_buckets, 4);
+        assert_eq!(stats.bodies, 2);
+        assert_eq!(stats.max_occupancy, 2);
+        assert!((stats.average_occupancy - 1.25).abs() < 1e-9);
+        assert_eq!(stats.bodies_spanning_many, 1);
+
+        buckets.reset_with_cell_size(20.0, 20.0);
+        assert_eq!(buckets.stats(1), BroadphaseStats { bucket_count: 1, ..Default::default() });
+    }
+
+    #[test]
+    fn occupied_cells() {
+        use crate::physics::*;
+
+        let world = World::new();
+        let id = world.run(|mut entities: EntitiesViewMut| {
+            entities.add_entity((), ())
+        });
+
+        let mut buckets = SpatialBuckets::new(10.0, 10.0);
+        buckets.insert(id, &Transform::new(0.0, 0.0), &AABB::new(-5.0, -5.0, 10.0, 10.0));
+
+        let mut cells: Vec<(f64, f64, usize)> = buckets.occupied_cells().iter()
+            .map(|(cell, count)| (cell.dx, cell.dy, *count))
+            .collect();
+        cells.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(cells, vec![(-10.0, -10.0, 1), (-10.0, 0.0, 1), (0.0, -10.0, 1), (0.0, 0.0, 1)]);
+    }
+}
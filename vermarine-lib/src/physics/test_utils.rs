@@ -0,0 +1,280 @@
+use super::*;
+use super::broadphase::SweepAndPrune;
+use crate::random::Random;
+
+/// A body to be spawned by a PhysicsWorldBuilder
+#[derive(Clone)]
+pub struct BodyDesc {
+    pub transform: Transform,
+    pub body: CollisionBody,
+}
+
+impl BodyDesc {
+    pub fn new(transform: Transform, body: CollisionBody) -> Self {
+        BodyDesc {
+            transform,
+            body,
+        }
+    }
+
+    /// A box on layer 1 that collides with layer 1
+    pub fn rect(x: f64, y: f64, half_width: f64, half_height: f64) -> Self {
+        Self::new(Transform::new(x, y), CollisionBody::from_collider(Collider::half_extents(half_width, half_height, 1, 1)))
+    }
+
+    /// A circle on layer 1 that collides with layer 1
+    pub fn circle(x: f64, y: f64, radius: f64) -> Self {
+        Self::new(Transform::new(x, y), CollisionBody::from_collider(Collider::circle(radius, 1, 1)))
+    }
+
+    /// Turns all of the body's colliders into sensors
+    pub fn sensor(mut self) -> Self {
+        let mut colliders = std::mem::take(&mut self.body.colliders);
+        colliders.append(&mut self.body.sensors);
+        self.body = CollisionBody::from_sensors(colliders);
+        self
+    }
+
+    /// Gives the body a mass so that it can be pushed
+    pub fn with_mass(mut self, mass: f64) -> Self {
+        self.body.mass = Some(mass);
+        self
+    }
+}
+
+/// Sets up a World with the physics workload and a set of bodies, for tests
+pub struct PhysicsWorldBuilder {
+    bucket_width: f64,
+    bucket_height: f64,
+    sweep_and_prune: bool,
+    bodies: Vec<BodyDesc>,
+}
+
+impl Default for PhysicsWorldBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PhysicsWorldBuilder {
+    pub fn new() -> Self {
+        PhysicsWorldBuilder {
+            bucket_width: 50.0,
+            bucket_height: 50.0,
+            sweep_and_prune: false,
+            bodies: vec![],
+        }
+    }
+
+    /// Sets the size of the broadphase buckets. Defaults to 50x50.
+    pub fn bucket_size(mut self, bucket_width: f64, bucket_height: f64) -> Self {
+        self.bucket_width = bucket_width;
+        self.bucket_height = bucket_height;
+        self
+    }
+
+    /// Uses SweepAndPrune as the broadphase instead of SpatialBuckets
+    pub fn sweep_and_prune(mut self) -> Self {
+        self.sweep_and_prune = true;
+        self
+    }
+
+    pub fn body(mut self, body: BodyDesc) -> Self {
+        self.bodies.push(body);
+        self
+    }
+
+    /// Adds `count` bodies, the closure is called with the index of each body
+    pub fn bodies(mut self, count: usize, mut body: impl FnMut(usize) -> BodyDesc) -> Self {
+        self.bodies.extend((0..count).map(|index| body(index)));
+        self
+    }
+
+    /// Adds `count` boxes of random sizes spread over a square of half size `extent` around the origin
+    pub fn scattered(self, count: usize, random: &mut Random, extent: f64) -> Self {
+        self.bodies(count, |_| {
+            BodyDesc::rect(
+                random.range_f64(-extent, extent),
+                random.range_f64(-extent, extent),
+                random.range_f64(1.0, 10.0),
+                random.range_f64(1.0, 10.0),
+            )
+        })
+    }
+
+    /// Creates the World and spawns the bodies, the returned ids are in the order the bodies were added
+    pub fn build(self) -> (World, Vec<EntityId>) {
+        let mut world = World::new();
+
+        let broadphase: Box<dyn Broadphase> = if self.sweep_and_prune {
+            Box::new(SweepAndPrune::new())
+        } else {
+            Box::new(SpatialBuckets::new(self.bucket_width, self.bucket_height))
+        };
+
+        world
+            .add_physics_workload_with_broadphase(broadphase)
+            .with_physics_systems()
+            .build();
+
+        let descs = &self.bodies;
+        let ids = world.run(|
+            mut entities: EntitiesViewMut,
+            mut bodies: ViewMut<PhysicsBody>,
+            mut transforms: ViewMut<Transform>,
+            mut physics_world: UniqueViewMut<PhysicsWorld>| {
+                descs.iter().map(|desc| {
+                    let id = entities.add_entity((), ());
+                    physics_world.create_body(
+                        &mut entities,
+                        &mut bodies,
+                        id,
+                        &mut transforms,
+                        desc.transform,
+                        desc.body.clone(),
+                    );
+                    id
+                }).collect::<Vec<_>>()
+        });
+
+        (world, ids)
+    }
+}
+
+//
+//
+
+/// Checks the internal consistency of the PhysicsWorld, `alive` should be every entity that is expected to have a body
+pub fn check_invariants(world: &PhysicsWorld, alive: &[EntityId]) -> Result<(), String> {
+    let (transforms, colliders, owners, sparse) = world.all_parts();
+
+    if transforms.len() != owners.len() || colliders.len() != owners.len() {
+        return Err(format!(
+            "body vecs have different lengths: {} transforms, {} colliders, {} owners",
+            transforms.len(), colliders.len(), owners.len(),
+        ));
+    }
+
+    if owners.len() != alive.len() {
+        return Err(format!("expected {} bodies but the world has {}", alive.len(), owners.len()));
+    }
+
+    for (index, owner) in owners.iter().enumerate() {
+        if sparse.get(owner.uindex()).copied().flatten() != Some(index) {
+            return Err(format!("body {} owned by {:?} isn't pointed to by the sparse array", index, owner));
+        }
+
+        let body = &colliders[index];
+        if body.aabb != AABB::from_body(body) {
+            return Err(format!("body {} owned by {:?} has an outdated AABB", index, owner));
+        }
+
+        if !world.broadphase().contains(*owner, &transforms[index], &body.aabb) {
+            return Err(format!("body {} owned by {:?} is missing from the broadphase", index, owner));
+        }
+
+        for collider in body.colliders.iter().chain(body.sensors.iter()) {
+            for collision in collider.overlapping.iter() {
+                if !owners.contains(&collision.entity2) {
+                    return Err(format!("body {} owned by {:?} is overlapping removed entity {:?}", index, owner, collision.entity2));
+                }
+            }
+        }
+    }
+
+    for (sparse_index, body) in sparse.iter().enumerate() {
+        if let Some(body) = body {
+            if owners.get(*body).map(|owner| owner.uindex()) != Some(sparse_index) {
+                return Err(format!("sparse entry {} points to body {} which it doesn't own", sparse_index, body));
+            }
+        }
+    }
+
+    for id in alive.iter() {
+        if sparse.get(id.uindex()).copied().flatten().map(|body| owners[body]) != Some(*id) {
+            return Err(format!("{:?} is alive but doesn't have a body", id));
+        }
+    }
+
+    let stats = world.broadphase().stats(usize::MAX);
+    if stats.bodies != owners.len() {
+        return Err(format!("broadphase has {} bodies but the world has {}", stats.bodies, owners.len()));
+    }
+
+    Ok(())
+}
+
+/// Randomly moves with and without collisions, teleports and deletes bodies, checking the PhysicsWorld invariants after every step
+pub struct Fuzzer {
+    pub random: Random,
+    /// The largest distance a body is moved in a single step on each axis
+    pub max_step: f64,
+    /// Bodies are teleported to a random point in a square of this half size around the origin
+    pub extent: f64,
+    pub teleport_chance: f64,
+    pub delete_chance: f64,
+    /// The chance of a move resolving collisions, pushing bodies with mass, instead of passing through
+    pub collide_chance: f64,
+}
+
+impl Fuzzer {
+    pub fn new(seed: u64) -> Self {
+        Fuzzer {
+            random: Random::new(seed),
+            max_step: 10.0,
+            extent: 200.0,
+            teleport_chance: 0.05,
+            delete_chance: 0.02,
+            collide_chance: 0.5,
+        }
+    }
+
+    /// Runs the given number of steps, deleted bodies are removed from `bodies`.
+    /// Panics with the seed and step if an invariant is broken.
+    pub fn run(&mut self, world: &mut World, bodies: &mut Vec<EntityId>, steps: usize) {
+        for step in 0..steps {
+            if bodies.is_empty() {
+                return;
+            }
+
+            let index = self.random.range(0, bodies.len() as i64) as usize;
+            let id = bodies[index];
+
+            if self.random.chance(self.delete_chance) {
+                bodies.swap_remove(index);
+                world.run(|mut all_storages: AllStoragesViewMut| {
+                    all_storages.delete(id);
+                    let (mut physics_world, mut bodies) = all_storages.borrow::<(UniqueViewMut<PhysicsWorld>, ViewMut<PhysicsBody>)>();
+                    physics_world.sync(&mut bodies);
+                });
+            } else if self.random.chance(self.teleport_chance) {
+                let position = Vec2::new(
+                    self.random.range_f64(-self.extent, self.extent),
+                    self.random.range_f64(-self.extent, self.extent),
+                );
+                world.run(|mut physics_world: UniqueViewMut<PhysicsWorld>| {
+                    physics_world.move_body_to(id, position);
+                });
+            } else {
+                let delta = Vec2::new(
+                    self.random.range_f64(-self.max_step, self.max_step),
+                    self.random.range_f64(-self.max_step, self.max_step),
+                );
+                let collide = self.random.chance(self.collide_chance);
+                world.run(|mut physics_world: UniqueViewMut<PhysicsWorld>| {
+                    if collide {
+                        physics_world.move_body_and_collide(id, delta);
+                    } else {
+                        physics_world.move_body(id, delta);
+                    }
+                });
+            }
+
+            let alive = &bodies[..];
+            let result = world.run(|physics_world: UniqueView<PhysicsWorld>| check_invariants(&physics_world, alive));
+            if let Err(error) = result {
+                panic!("Physics invariant broken at step {} with seed {}: {}", step, self.random.seed(), error);
+            }
+        }
+    }
+}
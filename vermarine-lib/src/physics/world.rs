@@ -9,11 +9,46 @@ pub struct PhysicsWorld {
     // Lookup of EntityId to BodyId
     sparse: Vec<Option<usize>>,
 
-    broadphase: SpatialBuckets,
+    broadphase: Box<dyn Broadphase>,
+
+    /// The solver, validation and bounds settings, copied from the PhysicsConfig unique by `apply_physics_config`
+    config: PhysicsConfig,
+
+    /// Per layer pair responses, copied from the CollisionMatrix unique by `apply_physics_config`
+    pub(crate) collision_matrix: CollisionMatrix,
+    /// Contacts waiting for their CollisionMatrix callback to be run
+    pair_contacts: Vec<PairContact>,
+
+    /// Bodies that have left the bounds since `send_out_of_bounds_events` last ran
+    out_of_bounds: Vec<OutOfBounds>,
+
+    /// The carriers whose riders are being moved, innermost last
+    pub(crate) carrying: Vec<EntityId>,
+    /// Set while a body pushed by a collision is being moved, it is pushed out of everything it hits instead of pushing them too
+    pushing: bool,
+
+    /// Touching pairs as of the last two calls to `update_contact_pairs`
+    pub(crate) contact_pairs: ContactPairs,
 }
 
+/// How a collision between two colliders should be resolved
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct Resolution {
+    /// The fraction of the MTV applied to the first body, the rest is applied to the second
+    pub share: f64,
+    pub max_correction: f64,
+}
+
+/// Penetration depths at or below this are treated as touching rather than overlapping by the solver
+pub(crate) const PENETRATION_SLOP: f64 = 1e-9;
+
 impl PhysicsWorld {
+    /// Creates a world using SpatialBuckets of the given size as its broadphase
     pub fn new(bucket_width: f64, bucket_height: f64) -> Self {
+        Self::with_broadphase(Box::new(SpatialBuckets::new(bucket_width, bucket_height)))
+    }
+
+    pub fn with_broadphase(broadphase: Box<dyn Broadphase>) -> Self {
         PhysicsWorld {
             transforms: vec![],
             colliders: vec![],
@@ -21,10 +56,52 @@ impl PhysicsWorld {
 
             sparse: vec![],
 
-            broadphase: SpatialBuckets::new(bucket_height, bucket_width),
+            broadphase,
+
+            config: PhysicsConfig::new(),
+
+            collision_matrix: CollisionMatrix::new(),
+            pair_contacts: vec![],
+
+            out_of_bounds: vec![],
+
+            carrying: vec![],
+            pushing: false,
+
+            contact_pairs: ContactPairs::new(),
         }
     }
 
+    /// The settings the world is using, change them through the PhysicsConfig unique
+    pub fn config(&self) -> &PhysicsConfig {
+        &self.config
+    }
+
+    /// Replaces the world's settings, only needed for worlds that aren't updated by `apply_physics_config`
+    pub fn set_config(&mut self, config: &PhysicsConfig) {
+        self.config.clone_from(config);
+    }
+
+    /// The collision responses the world is using, change them through the CollisionMatrix unique
+    pub fn collision_matrix(&self) -> &CollisionMatrix {
+        &self.collision_matrix
+    }
+
+    /// Replaces the world's collision responses, only needed for worlds that aren't updated by `apply_physics_config`
+    pub fn set_collision_matrix(&mut self, matrix: &CollisionMatrix) {
+        self.collision_matrix.clone_from(matrix);
+    }
+
+    /// Takes the contacts recorded for CollisionMatrix callbacks, done by `run_collision_callbacks`
+    pub fn take_pair_contacts(&mut self) -> Vec<PairContact> {
+        std::mem::take(&mut self.pair_contacts)
+    }
+
+    /// Takes the bodies that have left the bounds, done by `send_out_of_bounds_events`
+    pub fn take_out_of_bounds(&mut self) -> Vec<OutOfBounds> {
+        std::mem::take(&mut self.out_of_bounds)
+    }
+
     pub fn sync(&mut self, bodies: &mut ViewMut<PhysicsBody>) {
         // Adding bodies is done via add_body not with events
 
@@ -42,6 +119,7 @@ impl PhysicsWorld {
 
     pub(crate) fn remove_body(&mut self, id: EntityId) {
         self.remove_overlapping(id);
+        self.out_of_bounds.retain(|event| event.entity != id);
 
         {
             let transform = &self.transform(id).clone();
@@ -85,10 +163,82 @@ impl PhysicsWorld {
         transform: Transform, 
         collider: CollisionBody
     ) {
-        let sparse_index = id.uindex();
+        let (transform, collider) = match self.prepare_body(id, transform, collider) {
+            Some(prepared) => prepared,
+            None => return,
+        };
 
         self.broadphase.insert(id, &transform, &collider.aabb);
 
+        if self.store_body(id, transform, collider) {
+            entities.add_component(bodies, PhysicsBody(id), id);
+            entities.add_component(transforms, transform, id);
+        }
+    }
+
+    /// Same as calling `create_body` for every body, but the storage is reserved and the sparse vec padded once,
+    /// and the broadphase only grows once for the area covered by all of the bodies
+    pub fn create_bodies<I: IntoIterator<Item = (EntityId, Transform, CollisionBody)>>(
+        &mut self,
+        entities: &mut EntitiesViewMut,
+        bodies: &mut ViewMut<PhysicsBody>,
+        transforms: &mut ViewMut<Transform>,
+        new_bodies: I,
+    ) {
+        let prepared: Vec<(EntityId, Transform, CollisionBody)> = new_bodies.into_iter()
+            .filter_map(|(id, transform, collider)| {
+                self.prepare_body(id, transform, collider).map(|(transform, collider)| (id, transform, collider))
+            })
+            .collect();
+
+        self.transforms.reserve(prepared.len());
+        self.colliders.reserve(prepared.len());
+        self.owners.reserve(prepared.len());
+        if let Some(highest) = prepared.iter().map(|(id, _, _)| id.uindex()).max() {
+            if highest >= self.sparse.len() {
+                self.sparse.resize(highest + 1, None);
+            }
+        }
+
+        let items: Vec<(EntityId, &Transform, &AABB)> = prepared.iter().map(|(id, transform, collider)| (*id, transform, &collider.aabb)).collect();
+        self.broadphase.insert_many(&items);
+
+        let mut created = Vec::with_capacity(prepared.len());
+        for (id, transform, collider) in prepared.into_iter() {
+            if self.store_body(id, transform, collider) {
+                created.push((id, transform));
+            }
+        }
+
+        for (id, transform) in created.into_iter() {
+            entities.add_component(&mut *bodies, PhysicsBody(id), id);
+            entities.add_component(&mut *transforms, transform, id);
+        }
+    }
+
+    /// Validates the body's position and applies the bounds, `None` if the body shouldn't be created
+    fn prepare_body(&mut self, id: EntityId, transform: Transform, collider: CollisionBody) -> Option<(Transform, CollisionBody)> {
+        if !self.validate_position(id, Vec2::new(transform.x, transform.y)) {
+            return None;
+        }
+
+        let mut collider = collider;
+        collider.update_aabb();
+
+        let mut transform = transform;
+        let mode = self.config.bounds.map(|bounds| bounds.mode_for(&collider));
+        if let Some(position) = mode.and_then(|mode| self.bounded_position(id, mode, Vec2::new(transform.x, transform.y))) {
+            transform.x = position.x;
+            transform.y = position.y;
+        }
+
+        Some((transform, collider))
+    }
+
+    /// Stores the body data, returns true if a new body was created rather than an existing one replaced
+    fn store_body(&mut self, id: EntityId, transform: Transform, collider: CollisionBody) -> bool {
+        let sparse_index = id.uindex();
+
         // Padding 
         if sparse_index >= self.sparse.len() {
             // Pad sparse vec so that we can directly index sparse with entity id
@@ -99,15 +249,13 @@ impl PhysicsWorld {
         // Dont replace body if it's a higher generation than the passed in id
         if let Some(body) = &mut self.sparse[sparse_index] {
             let body = *body;
-            if self.owners[body].gen() > id.gen() {
-                return;
-            } else {
+            if self.owners[body].gen() <= id.gen() {
                 // Replace current body with passed in body
                 self.owners[body] = id;
                 self.transforms[body] = transform;
                 self.colliders[body] = collider;
-                return;
             }
+            false
         } else {
             // Create new body
             let body = self.transforms.len();            
@@ -116,10 +264,8 @@ impl PhysicsWorld {
             self.owners.push(id);
             self.transforms.push(transform);
             self.colliders.push(collider);
+            true
         }
-
-        entities.add_component(bodies, PhysicsBody, id);
-        entities.add_component(transforms, transform, id);
     }
 
     //
@@ -127,16 +273,30 @@ impl PhysicsWorld {
 
     /// Popping the returned vec of collisions will give you the most recent collision
     pub fn move_body_and_collide(&mut self, body: EntityId, delta: Vec2<f64>) -> Vec<Collision> {
+        let delta = match self.validate_delta(body, delta) {
+            Some(delta) => delta,
+            None => return vec![],
+        };
+
+        let riders = self.riders(body);
         self.handle_pre_movement(body);
 
         let transform = self.transform_mut(body);
         transform.x += delta.x;
         transform.y += delta.y;
 
-        self.handle_movement(body, true)
+        let collisions = self.handle_movement(body, true);
+        self.carry_riders(body, riders, delta);
+        collisions
     }
 
     pub fn move_body(&mut self, body: EntityId, delta: Vec2<f64>) {
+        let delta = match self.validate_delta(body, delta) {
+            Some(delta) => delta,
+            None => return,
+        };
+
+        let riders = self.riders(body);
         self.handle_pre_movement(body);
 
         let transform = self.transform_mut(body);
@@ -144,39 +304,164 @@ impl PhysicsWorld {
         transform.y += delta.y;
         
         self.handle_movement(body, false);
+        self.carry_riders(body, riders, delta);
     }
 
     pub fn move_body_to(&mut self, body: EntityId, position: Vec2<f64>) {
+        if !self.validate_position(body, position) {
+            return;
+        }
+
+        let riders = self.riders(body);
         self.handle_pre_movement(body);
 
         let transform = self.transform_mut(body);
+        let delta = Vec2::new(position.x - transform.x, position.y - transform.y);
         transform.x = position.x;
         transform.y = position.y;
 
         self.handle_movement(body, false);
+        self.carry_riders(body, riders, delta);
     }
 
     pub fn move_body_to_x(&mut self, body: EntityId, x: f64) {
+        if !self.validate_position(body, Vec2::new(x, self.transform(body).y)) {
+            return;
+        }
+
+        let riders = self.riders(body);
         self.handle_pre_movement(body);
 
         let transform = self.transform_mut(body);
+        let delta = Vec2::new(x - transform.x, 0.0);
         transform.x = x;
     
         self.handle_movement(body, false);
+        self.carry_riders(body, riders, delta);
     }
 
     pub fn move_body_to_y(&mut self, body: EntityId, y: f64) {
+        if !self.validate_position(body, Vec2::new(self.transform(body).x, y)) {
+            return;
+        }
+
+        let riders = self.riders(body);
         self.handle_pre_movement(body);
         
         let transform = self.transform_mut(body);
+        let delta = Vec2::new(0.0, y - transform.y);
         transform.y = y;
 
         self.handle_movement(body, false);
+        self.carry_riders(body, riders, delta);
+    }
+
+    /// Disabled bodies keep their position and colliders but are skipped by collision checks and queries,
+    /// e.g. for pooled entities that are waiting to be reused. Re-enabling a body updates its overlaps.
+    pub fn set_body_enabled(&mut self, body: EntityId, enabled: bool) {
+        if self.collider(body).is_enabled() == enabled {
+            return;
+        }
+
+        if enabled {
+            self.body_mut(body).disabled = false;
+            self.update_overlapping(body, false);
+        } else {
+            self.remove_overlapping(body);
+            self.body_mut(body).disabled = true;
+        }
+    }
+
+    /// Moves the body, resolving any collisions, then removes the part of the velocity that points into the surfaces that were hit.
+    /// Surfaces within max_slope radians of up count as floors, walls no taller than max_step are stepped up onto.
+    pub fn move_and_slide(&mut self, body: EntityId, velocity: Vec2<f64>, up: Vec2<f64>, max_slope: f64, max_step: f64) -> SlideResult {
+        let velocity = match self.validate_delta(body, velocity) {
+            Some(velocity) => velocity,
+            None => return SlideResult::new(Vec2::zero()),
+        };
+        let start = *self.transform(body);
+        let collisions = self.move_body_and_collide(body, velocity);
+
+        let mut result = SlideResult::new(velocity);
+        for collision in collisions.into_iter() {
+            result.add_collision(collision, up, max_slope);
+        }
+
+        let lateral = velocity - up * velocity.dot(up);
+        if !result.on_wall || max_step <= 0.0 || lateral.magnitude_squared() == 0.0 {
+            return result;
+        }
+
+        // Retry the move from a max_step higher, if nothing blocks it and there is floor to land on the body steps up
+        let blocked = *self.transform(body);
+        self.move_body_to(body, Vec2::new(start.x, start.y));
+
+        let raised = self.move_body_and_collide(body, up * max_step);
+        let moved = self.move_body_and_collide(body, lateral);
+        let lowered = self.move_body_and_collide(body, -up * max_step);
+
+        let mut stepped = SlideResult::new(velocity);
+        for collision in lowered.into_iter() {
+            stepped.add_collision(collision, up, max_slope);
+        }
+
+        if raised.is_empty() && moved.is_empty() && stepped.on_floor {
+            stepped.collisions.extend(result.collisions.into_iter().filter(|c| !SlideResult::is_wall(c, up, max_slope)));
+            stepped
+        } else {
+            self.move_body_to(body, Vec2::new(blocked.x, blocked.y));
+            result
+        }
     }
 
     //
     //
 
+    /// Returns the delta to move by, or None if the movement should be rejected
+    pub(crate) fn validate_delta(&self, body: EntityId, delta: Vec2<f64>) -> Option<Vec2<f64>> {
+        if !self.config.validate {
+            return Some(delta);
+        }
+
+        if !delta.x.is_finite() || !delta.y.is_finite() {
+            log::warn!(target: logging::PHYSICS, "Rejected non-finite movement {:?} of {:?}", delta, body);
+            return None;
+        }
+
+        let mut delta = delta;
+        let length = delta.magnitude();
+        if length > self.config.max_delta {
+            log::warn!(target: logging::PHYSICS, "Clamped movement of length {} of {:?} to {}", length, body, self.config.max_delta);
+            delta *= self.config.max_delta / length;
+        }
+
+        let transform = self.transform(body);
+        if !self.validate_position(body, Vec2::new(transform.x + delta.x, transform.y + delta.y)) {
+            return None;
+        }
+
+        Some(delta)
+    }
+
+    /// Returns false if the body shouldn't be placed at the position
+    pub(crate) fn validate_position(&self, body: EntityId, position: Vec2<f64>) -> bool {
+        if !self.config.validate {
+            return true;
+        }
+
+        if !position.x.is_finite() || !position.y.is_finite() {
+            log::warn!(target: logging::PHYSICS, "Rejected non-finite position {:?} for {:?}", position, body);
+            return false;
+        }
+
+        if position.x.abs() > self.config.max_coordinate || position.y.abs() > self.config.max_coordinate {
+            log::warn!(target: logging::PHYSICS, "Rejected position {:?} for {:?}, further than {} from the origin", position, body, self.config.max_coordinate);
+            return false;
+        }
+
+        true
+    }
+
     pub(crate) fn handle_pre_movement(&mut self, id: EntityId) {
         self.remove_overlapping(id);
 
@@ -188,7 +473,13 @@ impl PhysicsWorld {
     }
 
     pub(crate) fn handle_movement(&mut self, id: EntityId, resolve_collisions: bool) -> Vec<Collision> {
-        let collisions = self.update_overlapping(id, resolve_collisions);
+        self.apply_bounds(id);
+
+        let collisions = if resolve_collisions {
+            self.solve_overlapping(id)
+        } else {
+            self.update_overlapping(id, false)
+        };
 
         {
             let transform = &self.transform(id).clone();
@@ -199,27 +490,103 @@ impl PhysicsWorld {
         collisions
     }
 
+    /// Moves the body back inside the bounds or reports it, depending on its bounds mode
+    pub(crate) fn apply_bounds(&mut self, id: EntityId) {
+        let bounds = match self.config.bounds {
+            Some(bounds) => bounds,
+            None => return,
+        };
+
+        let (transform, body) = self.parts(id);
+        let position = Vec2::new(transform.x, transform.y);
+        let mode = bounds.mode_for(body);
+        if let Some(position) = self.bounded_position(id, mode, position) {
+            let transform = self.transform_mut(id);
+            transform.x = position.x;
+            transform.y = position.y;
+        }
+    }
+
+    /// Where a body at the position should be moved to, recording an OutOfBounds event if it's reported instead
+    fn bounded_position(&mut self, id: EntityId, mode: BoundsMode, position: Vec2<f64>) -> Option<Vec2<f64>> {
+        let bounds = self.config.bounds?;
+        if bounds.contains(position) {
+            return None;
+        }
+
+        if mode == BoundsMode::Report {
+            let event = OutOfBounds { entity: id, position };
+            match self.out_of_bounds.iter_mut().find(|event| event.entity == id) {
+                Some(existing) => *existing = event,
+                None => self.out_of_bounds.push(event),
+            }
+        }
+
+        bounds.apply(mode, position)
+    }
+
     /// Clears all stored overlapping data on the passed in body, also removes any overlapping data on other bodies regarding the passed in body
     pub(crate) fn remove_overlapping(&mut self, to_remove: EntityId) {
 
         let transform = &self.transform(to_remove).clone();
         let aabb = &self.collider(to_remove).aabb.clone();
         for id in self.broadphase.nearby(to_remove, transform, aabb).into_iter() {
-            let body = self.collider_mut(id);
+            let body = self.body_mut(id);
             body.remove_collision(to_remove);
         }
 
-        let c_body = self.collider_mut(to_remove);
+        let c_body = self.body_mut(to_remove);
         c_body.remove_all_collisions();
     }
 
+    /// Pushes the body out of everything it overlaps, re-running the narrowphase after each round of corrections so
+    /// that being pushed out of one body into another gets resolved too. Stops once a round finds no penetration or
+    /// after solver_iterations rounds. Returns every contact found, later rounds replacing earlier contacts with the same body.
+    pub(crate) fn solve_overlapping(&mut self, body: EntityId) -> Vec<Collision> {
+        let mut contacts: Vec<Collision> = vec![];
+
+        for iteration in 0..self.config.solver_iterations.max(1) {
+            if iteration > 0 {
+                self.remove_overlapping(body);
+            }
+
+            let round = self.update_overlapping(body, true);
+            let penetrating = round.iter().any(|collision| collision.depth > PENETRATION_SLOP);
+
+            contacts.retain(|contact| round.iter().all(|collision| collision.entity2 != contact.entity2));
+            contacts.extend(round.into_iter());
+
+            if !penetrating {
+                break;
+            }
+        }
+
+        contacts
+    }
+
     /// Finds all overlapping bodies and adds collisions to them all
     pub(crate) fn update_overlapping(&mut self, body: EntityId, resolve_collisions: bool) -> Vec<Collision> {
         let mut collisions = vec![];
+        if !self.collider(body).is_enabled() {
+            return collisions;
+        }
+
         let transform = &self.transform(body).clone();
         let aabb = &self.collider(body).aabb.clone();
         let nearby = self.broadphase.nearby(body, transform, aabb);
+        let max_correction = self.config.max_correction;
+
+        // Taken out for the loop as the body data is borrowed mutably alongside it
+        let matrix = std::mem::take(&mut self.collision_matrix);
+        let mut reports: Vec<PairContact> = vec![];
+        let mut pushes: Vec<(EntityId, Vec2<f64>)> = vec![];
+        let share_mtv = !self.pushing;
+
         for id in nearby.into_iter() {
+            if !self.collider(id).is_enabled() {
+                continue;
+            }
+
             let body1 = self.sparse[body.uindex()].unwrap();
             let body2 = self.sparse[id.uindex()].unwrap();
 
@@ -248,41 +615,133 @@ impl PhysicsWorld {
                 )
             };
 
+            let before = *t2;
             collisions.append(
-                &mut Self::update_overlapping_partial(t1, c1, body, t2, c2, id, resolve_collisions)
+                &mut Self::update_overlapping_partial_limited(t1, c1, body, t2, c2, id, if resolve_collisions { Some(max_correction) } else { None }, share_mtv, &matrix, &mut reports)
             );
+
+            // The other body's share of the MTV is applied afterwards by moving it normally, so that it can't be pushed into static bodies
+            let pushed = Vec2::new(t2.x - before.x, t2.y - before.y);
+            if pushed.magnitude_squared() > 0.0 {
+                *t2 = before;
+                match pushes.iter_mut().find(|(other, _)| *other == id) {
+                    Some((_, delta)) => *delta += pushed,
+                    None => pushes.push((id, pushed)),
+                }
+            }
+        }
+
+        self.collision_matrix = matrix;
+        // The solver re-checks the same pairs, only the latest contact for each pair is kept until the callbacks run
+        for report in reports.into_iter() {
+            let existing = self.pair_contacts.iter_mut().find(|contact| {
+                contact.entity1 == report.entity1 && contact.entity2 == report.entity2
+                    && contact.layer1 == report.layer1 && contact.layer2 == report.layer2
+            });
+            match existing {
+                Some(contact) => *contact = report,
+                None => self.pair_contacts.push(report),
+            }
+        }
+
+        for (id, delta) in pushes.into_iter() {
+            self.push_body(body, id, delta);
         }
         collisions
     }
 
+    /// Moves a body pushed by the body being moved, as if it had moved itself but without pushing anything else.
+    /// Whatever part of the push it is blocked from making, e.g. by a wall behind it, is taken back from the pusher.
+    fn push_body(&mut self, pusher: EntityId, body: EntityId, delta: Vec2<f64>) {
+        let start = *self.transform(body);
+
+        // Moving clears the body's contacts, and the pusher is out of the broadphase so they wouldn't be found again
+        let pushed_by = |colliders: &[Collider]| -> Vec<Vec<Collision>> {
+            colliders.iter()
+                .map(|collider| collider.overlapping.iter().filter(|c| c.entity2 == pusher).cloned().collect())
+                .collect()
+        };
+        let kept_colliders = pushed_by(&self.collider(body).colliders);
+        let kept_sensors = pushed_by(&self.collider(body).sensors);
+
+        let pushing = std::mem::replace(&mut self.pushing, true);
+        self.move_body_and_collide(body, delta);
+        self.pushing = pushing;
+
+        let pushed = self.body_mut(body);
+        for (collider, kept) in pushed.colliders.iter_mut().zip(kept_colliders.into_iter()) {
+            collider.overlapping.extend(kept.into_iter());
+        }
+        for (sensor, kept) in pushed.sensors.iter_mut().zip(kept_sensors.into_iter()) {
+            sensor.overlapping.extend(kept.into_iter());
+        }
+
+        let end = *self.transform(body);
+        let moved = Vec2::new(end.x - start.x, end.y - start.y);
+        let direction = delta.normalized();
+        let blocked = delta.magnitude() - moved.dot(direction);
+        if blocked > 0.0 {
+            // The pusher is out of the broadphase while it moves, so it can be moved directly
+            let transform = self.transform_mut(pusher);
+            transform.x -= direction.x * blocked;
+            transform.y -= direction.y * blocked;
+        }
+    }
+
     /// Checks all colliders from c_body1 against all colliders from the provided slice
     pub(crate) fn update_overlapping_partial(t1: &mut Transform, c_body1: &mut CollisionBody, entity1: EntityId, t2: &mut Transform, c_body2: &mut CollisionBody, entity2: EntityId, resolve_collisions: bool) -> Vec<Collision> {
+        let max_correction = if resolve_collisions { Some(f64::INFINITY) } else { None };
+        Self::update_overlapping_partial_limited(t1, c_body1, entity1, t2, c_body2, entity2, max_correction, true, &CollisionMatrix::new(), &mut vec![])
+    }
+
+    /// Same as update_overlapping_partial, collisions are only resolved if max_correction is Some and the matrix allows it.
+    /// The MTV is only split with the second body if share_mtv is set, otherwise the first body takes all of it.
+    /// Contacts for pairs with a matrix callback are pushed to reports.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn update_overlapping_partial_limited(t1: &mut Transform, c_body1: &mut CollisionBody, entity1: EntityId, t2: &mut Transform, c_body2: &mut CollisionBody, entity2: EntityId, max_correction: Option<f64>, share_mtv: bool, matrix: &CollisionMatrix, reports: &mut Vec<PairContact>) -> Vec<Collision> {
         let mut collisions = vec![];
+        let resolution = max_correction.map(|max_correction| Resolution {
+            share: if share_mtv { c_body1.resolution_share(c_body2) } else { 1.0 },
+            max_correction,
+        });
+
         // Sensor x Sensor
         for sensor1 in c_body1.sensors.iter_mut() {
             for sensor2 in c_body2.sensors.iter_mut() {
-                Self::update_overlapping_single(t1, sensor1, entity1, t2, sensor2, entity2, true, false);
+                if sensor1.is_excluded_from(sensor2) {
+                    continue;
+                }
+                Self::update_overlapping_single(t1, sensor1, entity1, t2, sensor2, entity2, true, None, matrix, reports);
             }
         }
 
         // Sensor1 x Collider2
         for sensor1 in c_body1.sensors.iter_mut() {
             for collider2 in c_body2.colliders.iter_mut() {
-                Self::update_overlapping_single(t1, sensor1, entity1, t2, collider2, entity2, false, false);
+                if sensor1.is_excluded_from(collider2) {
+                    continue;
+                }
+                Self::update_overlapping_single(t1, sensor1, entity1, t2, collider2, entity2, false, None, matrix, reports);
             }
         }
 
         // Sensor2 x Collider1
         for sensor2 in c_body2.sensors.iter_mut() {
             for collider1 in c_body1.colliders.iter_mut() {
-                Self::update_overlapping_single(t2, sensor2, entity2, t1, collider1, entity1, false, false);
+                if sensor2.is_excluded_from(collider1) {
+                    continue;
+                }
+                Self::update_overlapping_single(t2, sensor2, entity2, t1, collider1, entity1, false, None, matrix, reports);
             }
         }
 
         // Collider1 x Collider2
         for collider1 in c_body1.colliders.iter_mut() {
             for collider2 in c_body2.colliders.iter_mut() {
-                if let Some(collision) = Self::update_overlapping_single(t1, collider1, entity1, t2, collider2, entity2, true, resolve_collisions) {
+                if collider1.is_excluded_from(collider2) {
+                    continue;
+                }
+                if let Some(collision) = Self::update_overlapping_single(t1, collider1, entity1, t2, collider2, entity2, true, resolution, matrix, reports) {
                     collisions.push(collision);
                 }
             }
@@ -290,47 +749,209 @@ impl PhysicsWorld {
         collisions
     }
 
+    /// Checks the two colliders against each other and records a Collision on each collider whose collides_with contains the other's layer.
+    /// Each side's Collision has a normal pointing away from the other body. Only the collision recorded on c1 is returned.
+    /// When resolving, the MTV is clamped to max_correction then split between t1 and t2 by the resolution's share.
+    /// The matrix can ignore the pair or only report it, and a contact is pushed to reports if the pair has a callback.
     #[allow(clippy::too_many_arguments)]
-    pub(crate) fn update_overlapping_single(t1: &mut Transform, c1: &mut Collider, e1: EntityId, t2: &mut Transform, c2: &mut Collider, e2: EntityId, check_both: bool, resolve_collisions: bool) -> Option<Collision>{
-        let mut result: Option<(bool, Option<Vec2<f64>>)> = None;
-        let mut collision = None;
-
-        if c1.collides_with & c2.collision_layer > 0 {
-            result = Some(sat::seperating_axis_test(t1, &c1.shape, t2, &c2.shape));
-            let (collided, mtv) = result.unwrap();
-            if collided {
-                collision = Some(
-                    Self::handle_collision(t1, c1, t2, c2, e2, mtv, resolve_collisions)
-                )
-            }
+    pub(crate) fn update_overlapping_single(t1: &mut Transform, c1: &mut Collider, e1: EntityId, t2: &mut Transform, c2: &mut Collider, e2: EntityId, check_both: bool, resolution: Option<Resolution>, matrix: &CollisionMatrix, reports: &mut Vec<PairContact>) -> Option<Collision> {
+        let first_sees_second = c1.collides_with & c2.collision_layer > 0;
+        let second_sees_first = check_both && c2.collides_with & c1.collision_layer > 0;
+
+        // Avoid running SAT when neither side would record the collision
+        if !first_sees_second && !second_sees_first {
+            return None;
         }
 
-        if c2.collides_with & c1.collision_layer > 0 && check_both {
-            if result.is_none() {
-                result = Some(sat::seperating_axis_test(t1, &c1.shape, t2, &c2.shape));
-            }
-            let (collided, mtv) = result.unwrap();
-            
-            if collided {
-                Self::handle_collision(t2, c2, t1, c1, e1, Some(-mtv.unwrap()), false);
+        let resolution = match matrix.response(c1.collision_layer, c2.collision_layer) {
+            Some(PairResponse::Ignore) => return None,
+            Some(PairResponse::Report) => None,
+            _ => resolution,
+        };
+
+        // The mtv pushes c1 out of c2
+        let mtv = match sat::collider_test(t1, c1, t2, c2) {
+            (true, Some(mtv)) => mtv,
+            _ => return None,
+        };
+
+        matrix.report(reports, c1, e1, c2, e2, mtv);
+
+        // Record c2's side first so that both collisions describe the transforms from before any resolution
+        if second_sees_first {
+            Self::handle_collision(t2, c2, t1, c1, e1, -mtv);
+        }
+
+        if first_sees_second {
+            let collision = Self::handle_collision(t1, c1, t2, c2, e2, mtv);
+
+            if let Some(Resolution { share, max_correction }) = resolution {
+                let depth = mtv.magnitude();
+                let correction = if depth > max_correction { mtv * (max_correction / depth) } else { mtv };
+
+                t1.x += correction.x * share;
+                t1.y += correction.y * share;
+                t2.x -= correction.x * (1.0 - share);
+                t2.y -= correction.y * (1.0 - share);
             }
+
+            Some(collision)
+        } else {
+            None
         }
-        collision
     }
 
-    pub(crate) fn handle_collision(t1: &mut Transform, c1: &mut Collider, t2: &Transform, c2: &Collider, e2: EntityId, mtv: Option<Vec2<f64>>, resolve_collisions: bool) -> Collision {
+    pub(crate) fn handle_collision(t1: &Transform, c1: &mut Collider, t2: &Transform, c2: &Collider, e2: EntityId, mtv: Vec2<f64>) -> Collision {
         let collision_data = Collision::new(*t1, c1.shape.clone(), c1.collides_with, c1.collision_layer,
-            *t2, c2.shape.clone(), c2.collides_with, c2.collision_layer, e2, mtv.unwrap().normalized());
+            *t2, c2.shape.clone(), c2.collides_with, c2.collision_layer, e2, Self::collision_normal(mtv))
+            .with_depth(mtv.magnitude())
+            .with_materials(c1.material, c2.material);
 
         c1.overlapping.push(collision_data.clone());
 
-        if resolve_collisions {
-            let mtv = mtv.unwrap();
-            t1.x += mtv.x;
-            t1.y += mtv.y;
+        collision_data
+    }
+
+    /// Normalizes the mtv, shapes that are only touching have a zero length mtv and so get a zero normal
+    pub(crate) fn collision_normal(mtv: Vec2<f64>) -> Vec2<f64> {
+        if mtv.magnitude_squared() > 0.0 {
+            mtv.normalized()
+        } else {
+            Vec2::zero()
         }
+    }
 
-        collision_data
+    /// Finds the body closest to the point that has a collider or sensor on one of the layers in layer_mask.
+    /// Searches the broadphase outward from the point so that far away bodies are never checked.
+    pub fn nearest_body(&self, point: Vec2<f64>, layer_mask: u64) -> Option<(EntityId, f64)> {
+        let mut checked = vec![];
+        let mut nearest: Option<(EntityId, f64)> = None;
+
+        self.broadphase.search_outward(point, &mut |candidates, min_distance| {
+            for id in candidates.iter().copied() {
+                if checked.contains(&id) {
+                    continue;
+                }
+                checked.push(id);
+
+                let (transform, body) = self.parts(id);
+                if !body.is_enabled() {
+                    continue;
+                }
+
+                let distance = body.colliders.iter()
+                    .chain(body.sensors.iter())
+                    .filter(|c| c.collision_layer & layer_mask > 0)
+                    .map(|c| sat::point_distance(point, &c.local_shape(), &c.world_transform(transform)))
+                    .fold(None, |min: Option<f64>, d| Some(min.map_or(d, |min| min.min(d))));
+
+                if let Some(distance) = distance {
+                    if nearest.is_none() || distance < nearest.unwrap().1 {
+                        nearest = Some((id, distance));
+                    }
+                }
+            }
+
+            // Keep searching unless everything left is further away than the nearest body so far
+            !matches!(nearest, Some((_, distance)) if distance <= min_distance)
+        });
+
+        nearest
+    }
+
+    /// Every enabled body whose AABB overlaps the AABB at the transform, e.g. to check an area is free before spawning.
+    /// Only the AABBs are compared, run a narrowphase check on the result if the exact shapes matter.
+    pub fn nearby_aabb(&self, aabb: &AABB, transform: &Transform) -> Vec<EntityId> {
+        self.broadphase.query(transform, aabb)
+            .into_iter()
+            .filter(|id| {
+                let (body_transform, body) = self.parts(*id);
+                body.is_enabled() && body.aabb.overlaps(body_transform, aabb, transform)
+            })
+            .collect()
+    }
+
+    /// Every enabled body whose AABB contains the point
+    pub fn nearby_point(&self, point: Vec2<f64>) -> Vec<EntityId> {
+        self.nearby_aabb(&AABB::default(), &Transform::new(point.x, point.y))
+    }
+
+    /// Distance between the closest points of the two bodies' shapes, 0.0 if they overlap
+    pub fn distance_between(&self, entity_a: EntityId, entity_b: EntityId) -> f64 {
+        let (t1, b1) = self.parts(entity_a);
+        let (t2, b2) = self.parts(entity_b);
+
+        let mut distance = f64::INFINITY;
+        for c1 in b1.colliders.iter().chain(b1.sensors.iter()) {
+            for c2 in b2.colliders.iter().chain(b2.sensors.iter()) {
+                distance = distance.min(sat::collider_distance(t1, c1, t2, c2));
+            }
+        }
+        distance
+    }
+
+    /// Occupancy statistics for the broadphase, see `SpatialBuckets::stats`
+    pub fn broadphase_stats(&self, span_threshold: usize) -> BroadphaseStats {
+        self.broadphase.stats(span_threshold)
+    }
+
+    /// Every occupied broadphase cell and how many bodies are in it, see `Broadphase::occupied_cells`
+    pub fn broadphase_cells(&self) -> Vec<(AABB, usize)> {
+        self.broadphase.occupied_cells()
+    }
+
+    /// Changes the broadphase bucket size and rehashes every body, e.g. after loading a level with differently sized bodies
+    pub fn rebuild_with_cell_size(&mut self, bucket_width: f64, bucket_height: f64) {
+        self.broadphase.reset_with_cell_size(bucket_width, bucket_height);
+        self.reinsert_all();
+    }
+
+    /// Swaps the broadphase for another one and inserts every body into it
+    pub fn set_broadphase(&mut self, broadphase: Box<dyn Broadphase>) {
+        self.broadphase = broadphase;
+        self.reinsert_all();
+    }
+
+    fn reinsert_all(&mut self) {
+        let (transforms, colliders) = (&self.transforms, &self.colliders);
+        let items: Vec<(EntityId, &Transform, &AABB)> = self.owners.iter().enumerate()
+            .map(|(index, id)| (*id, &transforms[index], &colliders[index].aabb))
+            .collect();
+        self.broadphase.insert_many(&items);
+    }
+
+    /// Recomputes the AABB of the body and reinserts it into the broadphase if it changed from `old`,
+    /// the AABB the body was last inserted with
+    pub(crate) fn refresh_aabb(&mut self, id: EntityId, old: &AABB) {
+        let transform = *self.transform(id);
+        let new = AABB::from_body(self.collider(id));
+
+        if *old != new {
+            self.broadphase.remove(id, &transform, old);
+            self.broadphase.insert(id, &transform, &new);
+        }
+        self.body_mut(id).aabb = new;
+    }
+
+    /// Iterates over every body along with the entity that owns it
+    pub fn iter(&self) -> impl Iterator<Item = (EntityId, &Transform, &CollisionBody)> {
+        self.owners.iter().copied()
+            .zip(self.transforms.iter())
+            .zip(self.colliders.iter())
+            .map(|((id, transform), body)| (id, transform, body))
+    }
+
+    /// Mutable access to every body, the returned guard updates the broadphase for any bodies that were moved or
+    /// had their colliders changed when dropped. Overlaps aren't updated until the bodies are next moved.
+    pub fn iter_mut(&mut self) -> BodiesMut {
+        let previous = self.transforms.iter().copied()
+            .zip(self.colliders.iter().map(|body| body.aabb.clone()))
+            .collect();
+
+        BodiesMut {
+            world: self,
+            previous,
+        }
     }
 
     //
@@ -357,13 +978,32 @@ impl PhysicsWorld {
     pub fn collider(&self, body: EntityId) -> &CollisionBody {
         &self.colliders[self.sparse[body.uindex()].unwrap()]
     } 
-    pub fn collider_mut(&mut self, body: EntityId) -> &mut CollisionBody {
+    /// The returned guard recomputes the body's AABB and updates the broadphase when dropped
+    pub fn collider_mut(&mut self, body: EntityId) -> CollisionBodyMut {
+        let previous = self.collider(body).aabb.clone();
+        CollisionBodyMut {
+            world: self,
+            body,
+            previous,
+        }
+    } 
+    /// Adds a collider to a body that is already in the world, updating the broadphase
+    pub fn add_collider(&mut self, body: EntityId, collider: Collider) {
+        self.collider_mut(body).add_collider(collider);
+    }
+    /// Removes a collider from a body that is already in the world, updating the broadphase
+    pub fn remove_collider(&mut self, body: EntityId, index: usize) -> Collider {
+        self.collider_mut(body).remove_collider(index)
+    }
+    pub(crate) fn body_mut(&mut self, body: EntityId) -> &mut CollisionBody {
         &mut self.colliders[self.sparse[body.uindex()].unwrap()]
     } 
     pub fn index_from_body(&self, body: EntityId) -> usize {
         self.sparse[body.uindex()].unwrap()
     }
-    pub fn parts_mut(&mut self, body: EntityId) -> (&Transform, &mut CollisionBody) {
+    /// Changing the colliders through this leaves the broadphase stale, outside of the crate use `collider_mut`
+    #[allow(dead_code)]
+    pub(crate) fn parts_mut(&mut self, body: EntityId) -> (&Transform, &mut CollisionBody) {
         let index = self.index_from_body(body);
         (self.transforms.get(index).unwrap(), self.colliders.get_mut(index).unwrap())
     } 
@@ -376,6 +1016,10 @@ impl PhysicsWorld {
         let index = self.index_from_body(body);
         (self.transforms.get(index).unwrap(), self.colliders.get(index).unwrap())
     }
+    #[allow(dead_code)]
+    pub(crate) fn broadphase(&self) -> &dyn Broadphase {
+        self.broadphase.as_ref()
+    }
     #[allow(clippy::type_complexity)]
     pub(crate) fn all_parts_mut(&mut self) -> (&mut [Transform], &mut [CollisionBody], &mut [EntityId], &mut [Option<usize>]) {
         (&mut self.transforms, &mut self.colliders, &mut self.owners, &mut self.sparse)
@@ -384,4 +1028,117 @@ impl PhysicsWorld {
     pub(crate) fn all_parts(&self) -> (&[Transform], &[CollisionBody], &[EntityId], &[Option<usize>]) {
         (&self.transforms, &self.colliders, &self.owners, &self.sparse,)
     }
-}
\ No newline at end of file
+}
+
+/// Mutable access to all bodies returned by `PhysicsWorld::iter_mut`, keeps the broadphase in sync with any changes
+pub struct BodiesMut<'a> {
+    world: &'a mut PhysicsWorld,
+    previous: Vec<(Transform, AABB)>,
+}
+
+impl<'a> BodiesMut<'a> {
+    pub fn iter(&mut self) -> impl Iterator<Item = (EntityId, &mut Transform, &mut CollisionBody)> {
+        let world = &mut *self.world;
+        world.owners.iter().copied()
+            .zip(world.transforms.iter_mut())
+            .zip(world.colliders.iter_mut())
+            .map(|((id, transform), body)| (id, transform, body))
+    }
+}
+
+impl<'a> Drop for BodiesMut<'a> {
+    fn drop(&mut self) {
+        let world = &mut *self.world;
+        for (index, (transform, aabb)) in self.previous.iter().enumerate() {
+            let id = world.owners[index];
+            let new_aabb = AABB::from_body(&world.colliders[index]);
+
+            if world.transforms[index] != *transform || new_aabb != *aabb {
+                world.broadphase.remove(id, transform, aabb);
+                world.broadphase.insert(id, &world.transforms[index], &new_aabb);
+                world.colliders[index].aabb = new_aabb;
+            }
+        }
+    }
+}
+
+/// Mutable access to a body's CollisionBody, keeps the AABB and broadphase in sync with any changes made to the colliders
+pub struct CollisionBodyMut<'a> {
+    world: &'a mut PhysicsWorld,
+    body: EntityId,
+    /// The AABB the body is in the broadphase with
+    previous: AABB,
+}
+
+impl<'a> std::ops::Deref for CollisionBodyMut<'a> {
+    type Target = CollisionBody;
+
+    fn deref(&self) -> &CollisionBody {
+        self.world.collider(self.body)
+    }
+}
+
+impl<'a> std::ops::DerefMut for CollisionBodyMut<'a> {
+    fn deref_mut(&mut self) -> &mut CollisionBody {
+        self.world.body_mut(self.body)
+    }
+}
+
+impl<'a> Drop for CollisionBodyMut<'a> {
+    fn drop(&mut self) {
+        self.world.refresh_aabb(self.body, &self.previous);
+    }
+}
+
+/// The outcome of `PhysicsWorld::move_and_slide`
+#[derive(Clone)]
+pub struct SlideResult {
+    /// The velocity with any motion into the surfaces that were hit removed, or bounced back by their restitution,
+    /// and the motion along them reduced by their friction
+    pub velocity: Vec2<f64>,
+    pub collisions: Vec<Collision>,
+
+    pub on_floor: bool,
+    pub on_wall: bool,
+    pub on_ceiling: bool,
+}
+
+impl SlideResult {
+    pub(crate) fn new(velocity: Vec2<f64>) -> Self {
+        SlideResult {
+            velocity,
+            collisions: vec![],
+            on_floor: false,
+            on_wall: false,
+            on_ceiling: false,
+        }
+    }
+
+    pub(crate) fn add_collision(&mut self, collision: Collision, up: Vec2<f64>, max_slope: f64) {
+        let normal = collision.normal;
+        let min_dot = max_slope.cos();
+        let dot = normal.dot(up);
+
+        if dot >= min_dot {
+            self.on_floor = true;
+        } else if dot <= -min_dot {
+            self.on_ceiling = true;
+        } else if normal.magnitude_squared() > 0.0 {
+            self.on_wall = true;
+        }
+
+        let into = self.velocity.dot(normal);
+        if into < 0.0 {
+            let material = collision.combined_material();
+            let along = self.velocity - normal * into;
+            self.velocity = along * (1.0 - material.friction.min(1.0).max(0.0)) - normal * into * material.restitution;
+        }
+
+        self.collisions.push(collision);
+    }
+
+    pub(crate) fn is_wall(collision: &Collision, up: Vec2<f64>, max_slope: f64) -> bool {
+        let dot = collision.normal.dot(up);
+        dot.abs() < max_slope.cos() && collision.normal.magnitude_squared() > 0.0
+    }
+}
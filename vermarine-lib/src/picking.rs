@@ -0,0 +1,196 @@
+use shipyard::*;
+use tetra::{
+    graphics::Camera,
+    input::{
+        self,
+        MouseButton,
+    },
+    math::{
+        Vec2,
+        Vec4,
+    },
+    Context,
+};
+use crate::{
+    components::Transform,
+    hexmap::{
+        Axial,
+        HexMap,
+        maps::HexMaps,
+    },
+    pool::Inactive,
+    rendering::screen::Letterbox,
+};
+
+/// The mouse position in screen, world and hex space. Added by `add_rendering_workload`,
+/// update it once per frame with `Picking::update` and optionally `Picking::update_hex`
+/// so that game systems can just read it.
+#[derive(Clone, Debug, Default)]
+pub struct Picking {
+    /// In the Letterbox's virtual resolution when it has one
+    pub mouse_screen: Vec2<f32>,
+    pub mouse_world: Vec2<f32>,
+    /// Only set by `update_hex`, `None` if the mouse isn't over a tile
+    pub mouse_hex: Option<Axial>,
+}
+
+impl Picking {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads the mouse position from tetra, undoes the Letterbox's scale and offset and converts it to world
+    /// space through the Camera unique
+    pub fn update(ctx: &mut Context, mut picking: UniqueViewMut<Picking>, camera: UniqueView<Camera>, letterbox: UniqueView<Letterbox>) {
+        let window_size = Vec2::new(tetra::window::get_width(ctx) as f32, tetra::window::get_height(ctx) as f32);
+        picking.mouse_screen = letterbox.window_to_virtual(window_size, input::get_mouse_position(ctx));
+        picking.mouse_world = screen_to_world(&camera, picking.mouse_screen);
+    }
+
+    /// Finds the hex under the mouse for a HexMap unique, must be run after `update`
+    pub fn update_hex<T: 'static + Send + Sync>(mut picking: UniqueViewMut<Picking>, map: UniqueView<HexMap<T>>) {
+        picking.mouse_hex = map.pixel_to_hex(picking.mouse_world);
+    }
+
+    /// Finds the hex under the mouse for the active map in a HexMaps unique, must be run after `update`
+    pub fn update_hex_maps<T: 'static + Send + Sync>(mut picking: UniqueViewMut<Picking>, maps: UniqueView<HexMaps<T>>) {
+        let mouse_world = picking.mouse_world;
+        picking.mouse_hex = maps.active().and_then(|map| map.pixel_to_hex(mouse_world));
+    }
+}
+
+/// Finds the hex at any screen position, e.g. for touch input or a second cursor. `Picking::mouse_hex` already
+/// has the hex under the mouse.
+pub fn pick_hex<T, const W: usize, const H: usize>(map: &HexMap<T, W, H>, camera: &Camera, screen_pos: Vec2<f32>) -> Option<Axial> {
+    map.pick(screen_pos, camera)
+}
+
+/// Converts a position on the screen to a position in the world using the inverse of the camera's matrix
+pub fn screen_to_world(camera: &Camera, screen: Vec2<f32>) -> Vec2<f32> {
+    let world = camera.as_matrix().inverted() * Vec4::new(screen.x, screen.y, 0.0, 1.0);
+    Vec2::new(world.x, world.y)
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum HitShape {
+    /// Starts at the position and extends right and down
+    Rect { width: f32, height: f32 },
+    /// Centered on the position
+    Circle(f32),
+}
+
+/// Whether a HitArea is tested against `Picking::mouse_screen` or `Picking::mouse_world`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum HitSpace {
+    Screen,
+    World,
+}
+
+/// Makes an entity hoverable and clickable, for UI elements and world space interactables like units or hexes alike.
+/// The area is placed at the entity's Transform plus the offset, or at just the offset if it has no Transform.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct HitArea {
+    pub shape: HitShape,
+    pub space: HitSpace,
+    pub offset: Vec2<f32>,
+    /// When areas overlap the highest priority one is hovered. Defaults to 0, screen space areas are
+    /// always tested before world space ones so UI blocks the world under it.
+    pub priority: i32,
+}
+
+impl HitArea {
+    pub fn rect(space: HitSpace, width: f32, height: f32) -> Self {
+        HitArea {
+            shape: HitShape::Rect { width, height },
+            space,
+            offset: Vec2::zero(),
+            priority: 0,
+        }
+    }
+
+    pub fn circle(space: HitSpace, radius: f32) -> Self {
+        HitArea {
+            shape: HitShape::Circle(radius),
+            space,
+            offset: Vec2::zero(),
+            priority: 0,
+        }
+    }
+
+    /// Sets the offset of the area from the entity's Transform.
+    pub fn with_offset(mut self, offset: Vec2<f32>) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Sets the priority of the area.
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Whether the point is inside the area when it is placed at position
+    pub fn contains(&self, position: Vec2<f32>, point: Vec2<f32>) -> bool {
+        let local = point - position - self.offset;
+        match self.shape {
+            HitShape::Rect { width, height } => local.x >= 0.0 && local.y >= 0.0 && local.x <= width && local.y <= height,
+            HitShape::Circle(radius) => local.magnitude_squared() <= radius * radius,
+        }
+    }
+}
+
+/// The entities with a HitArea under the mouse this frame. Added by `add_rendering_workload`,
+/// update it with `Hits::update` after `Picking::update`.
+#[derive(Clone, Debug, Default)]
+pub struct Hits {
+    /// Every area under the mouse, topmost first
+    pub under_mouse: Vec<EntityId>,
+    pub hovered: Option<EntityId>,
+    /// The hovered entity if the left mouse button was pressed this frame
+    pub clicked: Option<EntityId>,
+    /// The hovered entity if the right mouse button was pressed this frame
+    pub right_clicked: Option<EntityId>,
+}
+
+impl Hits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_hovered(&self, id: EntityId) -> bool {
+        self.hovered == Some(id)
+    }
+
+    pub fn is_clicked(&self, id: EntityId) -> bool {
+        self.clicked == Some(id)
+    }
+
+    /// Tests every active HitArea against the mouse and records which entities are hovered and clicked
+    pub fn update(
+        ctx: &mut Context,
+        mut hits: UniqueViewMut<Hits>,
+        picking: UniqueView<Picking>,
+        areas: View<HitArea>,
+        transforms: View<Transform>,
+        inactive: View<Inactive>,
+    ) {
+        let mut under_mouse: Vec<(EntityId, HitArea)> = (&areas).iter().with_id()
+            .filter(|(id, _)| inactive.get(*id).is_err())
+            .filter_map(|(id, area)| {
+                let position = transforms.get(id).map_or(Vec2::zero(), |t| Vec2::new(t.x as f32, t.y as f32));
+                let point = match area.space {
+                    HitSpace::Screen => picking.mouse_screen,
+                    HitSpace::World => picking.mouse_world,
+                };
+                if area.contains(position, point) { Some((id, *area)) } else { None }
+            })
+            .collect();
+
+        under_mouse.sort_by_key(|(_, area)| (area.space != HitSpace::Screen, std::cmp::Reverse(area.priority)));
+
+        hits.under_mouse = under_mouse.into_iter().map(|(id, _)| id).collect();
+        hits.hovered = hits.under_mouse.first().copied();
+        hits.clicked = hits.hovered.filter(|_| input::is_mouse_button_pressed(ctx, MouseButton::Left));
+        hits.right_clicked = hits.hovered.filter(|_| input::is_mouse_button_pressed(ctx, MouseButton::Right));
+    }
+}
@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+use shipyard::*;
+use crate::physics::{
+    PhysicsBody,
+    world::PhysicsWorld,
+};
+
+/// Creates a new entity for a pool, borrowing whatever storages it needs from `all_storages`
+pub type PoolSpawn = fn(&AllStorages) -> EntityId;
+/// Puts a pooled entity back into its starting state when it is acquired, e.g. resetting health or velocity
+pub type PoolReset = fn(EntityId, &AllStorages);
+
+/// Added to every entity spawned by an EntityPool
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Pooled {
+    pub pool: &'static str,
+}
+
+/// Added to pooled entities that are waiting to be acquired, sprite drawing and physics skip these
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Inactive;
+
+struct Pool {
+    free: Vec<EntityId>,
+    spawn: PoolSpawn,
+    reset: Option<PoolReset>,
+}
+
+/// Named pools of pre-spawned entities that are reused instead of being deleted and spawned again,
+/// for things like bullets and particles that would otherwise churn the storages and the PhysicsWorld.
+///
+/// ```ignore
+/// world.add_entity_pool();
+/// world.run(|mut pools: UniqueViewMut<EntityPool>| pools.register("bullets", spawn_bullet, Some(reset_bullet)));
+/// world.run(|all_storages: AllStoragesViewMut| EntityPool::fill(&all_storages, "bullets", 200));
+///
+/// // In a system with `all_storages: AllStoragesViewMut`
+/// let bullet = EntityPool::acquire(&all_storages, "bullets");
+/// EntityPool::release(&all_storages, bullet);
+/// ```
+#[derive(Default)]
+pub struct EntityPool {
+    pools: HashMap<&'static str, Pool>,
+}
+
+impl EntityPool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an empty pool, replacing any pool with the same name
+    pub fn register(&mut self, name: &'static str, spawn: PoolSpawn, reset: Option<PoolReset>) {
+        self.pools.insert(name, Pool {
+            free: vec![],
+            spawn,
+            reset,
+        });
+    }
+
+    /// How many entities in the pool are waiting to be acquired
+    pub fn free_count(&self, name: &'static str) -> usize {
+        self.pools.get(name).map_or(0, |pool| pool.free.len())
+    }
+
+    fn pool_mut(&mut self, name: &'static str) -> &mut Pool {
+        self.pools.get_mut(name).unwrap_or_else(|| panic!("No entity pool named \"{}\"", name))
+    }
+
+    /// Spawns `count` inactive entities into the pool. Panics if the pool hasn't been registered.
+    pub fn fill(all_storages: &AllStorages, name: &'static str, count: usize) {
+        for _ in 0..count {
+            let id = Self::spawn(all_storages, name);
+            all_storages.borrow::<UniqueViewMut<EntityPool>>().pool_mut(name).free.push(id);
+        }
+    }
+
+    /// Activates a free entity from the pool and runs the pool's reset on it, spawning a new entity if none are free.
+    /// Panics if the pool hasn't been registered.
+    pub fn acquire(all_storages: &AllStorages, name: &'static str) -> EntityId {
+        let (id, reset) = {
+            let mut pools = all_storages.borrow::<UniqueViewMut<EntityPool>>();
+            let pool = pools.pool_mut(name);
+            (pool.free.pop(), pool.reset)
+        };
+        let id = id.unwrap_or_else(|| Self::spawn(all_storages, name));
+
+        all_storages.borrow::<ViewMut<Inactive>>().remove(id);
+        if let Some(reset) = reset {
+            reset(id, all_storages);
+        }
+        id
+    }
+
+    /// Deactivates a pooled entity and returns it to its pool.
+    /// Does nothing for entities that aren't pooled or have already been released.
+    pub fn release(all_storages: &AllStorages, id: EntityId) {
+        let (mut entities, pooled, mut inactive, mut pools) = all_storages.borrow::<(
+            EntitiesViewMut,
+            View<Pooled>,
+            ViewMut<Inactive>,
+            UniqueViewMut<EntityPool>,
+        )>();
+
+        let pool = match pooled.get(id) {
+            Ok(pooled) => pooled.pool,
+            Err(_) => return,
+        };
+        if inactive.get(id).is_ok() {
+            return;
+        }
+
+        entities.add_component(&mut inactive, Inactive, id);
+        pools.pool_mut(pool).free.push(id);
+    }
+
+    fn spawn(all_storages: &AllStorages, name: &'static str) -> EntityId {
+        let spawn = all_storages.borrow::<UniqueViewMut<EntityPool>>().pool_mut(name).spawn;
+        let id = spawn(all_storages);
+
+        let (mut entities, mut pooled, mut inactive) = all_storages.borrow::<(EntitiesViewMut, ViewMut<Pooled>, ViewMut<Inactive>)>();
+        entities.add_component((&mut pooled, &mut inactive), (Pooled { pool: name }, Inactive), id);
+        id
+    }
+}
+
+/// Disables the bodies of inactive entities and re-enables them once they are acquired again
+pub fn sync_inactive_bodies(bodies: View<PhysicsBody>, inactive: View<Inactive>, mut world: UniqueViewMut<PhysicsWorld>) {
+    for (id, _) in bodies.iter().with_id() {
+        let enabled = inactive.get(id).is_err();
+        if world.collider(id).is_enabled() != enabled {
+            world.set_body_enabled(id, enabled);
+        }
+    }
+}
+
+/// Dummy trait to allow adding a method to World
+pub trait EntityPoolCreator {
+    fn add_entity_pool(&mut self);
+}
+
+impl EntityPoolCreator for World {
+    fn add_entity_pool(&mut self) {
+        self.add_unique(EntityPool::new());
+    }
+}
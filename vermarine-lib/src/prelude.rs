@@ -0,0 +1,130 @@
+//! Re-exports the types and dummy traits most games need, so that they can start with `use vermarine_lib::prelude::*;`
+
+pub use shipyard::*;
+pub use tetra::{
+    graphics::{
+        Camera,
+        Color,
+        Texture,
+    },
+    math::{
+        Mat4,
+        Vec2,
+        Vec3,
+    },
+    Context,
+    State,
+};
+
+pub use crate::{
+    components::Transform,
+    events::{
+        EventReader,
+        Events,
+        EventsCreator,
+        EventWorkloadSystems,
+    },
+    gizmos::{
+        Gizmos,
+        GizmosCreator,
+    },
+    hexmap::{
+        Axial,
+        Hex,
+        HexDirection,
+        HexMap,
+        maps::{
+            HexMaps,
+            HexMapsCreator,
+        },
+    },
+    physics::{
+        Collider,
+        CollisionBody,
+        PhysicsBody,
+        PhysicsWorkloadCreator,
+        PhysicsWorkloadSystems,
+        broadphase::{
+            Broadphase,
+            SweepAndPrune,
+        },
+        builder::{
+            CollisionBodyBuilder,
+            CollisionLayers,
+        },
+        carrier::Carrier,
+        config::PhysicsConfig,
+        world::PhysicsWorld,
+    },
+    pause::{
+        Paused,
+        PausedCreator,
+    },
+    perf::{
+        FrameLimiter,
+        PerfStats,
+        PerfStatsCreator,
+    },
+    picking::{
+        HitArea,
+        HitShape,
+        HitSpace,
+        Hits,
+        Picking,
+    },
+    pool::{
+        EntityPool,
+        EntityPoolCreator,
+    },
+    pushdown_automaton_state::{
+        PDAState,
+        PushdownAutomaton,
+        Trans,
+    },
+    random::{
+        Random,
+        RandomCreator,
+    },
+    rendering::{
+        Drawables,
+        RenderingWorkloadCreator,
+        RenderingWorkloadSystems,
+        Sprite,
+        SpriteName,
+        cursor::{
+            CursorConfig,
+            CursorCreator,
+            CursorStyle,
+        },
+        animation::{
+            AnimationCreator,
+            AnimationWorkloadSystems,
+        },
+        draw_buffer::{
+            DrawBuffer,
+            DrawCommand,
+            DrawQueue,
+            PassId,
+        },
+        layers::DrawLayer,
+        tint::{
+            Flash,
+            Tint,
+        },
+    },
+    text_input::{
+        TextInputBuffer,
+        TextInputCreator,
+        forward_text_input,
+    },
+    time::{
+        Time,
+        TimeCreator,
+    },
+    window_events::{
+        WindowEvent,
+        WindowEvents,
+        WindowEventsCreator,
+        forward_window_event,
+    },
+};
@@ -0,0 +1,335 @@
+use shipyard::*;
+use tetra::math::Vec2;
+use crate::{
+    components::Transform,
+    events::Events,
+    time::Time,
+    physics::{
+        world::PhysicsWorld,
+        CollisionBody,
+        Collider,
+        PhysicsBody,
+    },
+};
+
+/// A body that flies in a straight line, hitting anything on its layer mask until it runs out of pierce or lifetime.
+/// The body is a single sensor so projectiles never push anything around.
+#[derive(Clone, Debug)]
+pub struct Projectile {
+    /// Movement per tick
+    pub velocity: Vec2<f64>,
+    /// Remaining ticks the projectile moves for before it despawns, paused ticks aren't counted.
+    /// A projectile always moves for at least one tick.
+    pub lifetime: u32,
+    /// The entity that fired the projectile, never hit by it
+    pub owner: Option<EntityId>,
+    /// How many more bodies the projectile can pass through after the first hit
+    pub pierce: u32,
+    /// The collision layers the projectile hits
+    pub layer_mask: u64,
+
+    hit: Vec<EntityId>,
+    expired: bool,
+}
+
+impl Projectile {
+    pub fn new(velocity: Vec2<f64>, lifetime: u32, layer_mask: u64) -> Self {
+        Projectile {
+            velocity,
+            lifetime,
+            owner: None,
+            pierce: 0,
+            layer_mask,
+
+            hit: vec![],
+            expired: false,
+        }
+    }
+
+    /// Sets the entity that fired the projectile
+    pub fn with_owner(mut self, owner: EntityId) -> Self {
+        self.owner = Some(owner);
+        self
+    }
+
+    /// Sets how many bodies the projectile passes through
+    pub fn with_pierce(mut self, pierce: u32) -> Self {
+        self.pierce = pierce;
+        self
+    }
+
+    /// The bodies this projectile has already hit
+    pub fn hit(&self) -> &[EntityId] {
+        &self.hit
+    }
+
+    /// True once the projectile has run out of lifetime or pierce, it is deleted by `despawn_projectiles`
+    pub fn is_expired(&self) -> bool {
+        self.expired
+    }
+}
+
+/// Sent through `Events<ProjectileHit>` whenever a projectile hits a body
+#[derive(Copy, Clone, Debug)]
+pub struct ProjectileHit {
+    pub projectile: EntityId,
+    pub target: EntityId,
+    pub owner: Option<EntityId>,
+    pub position: Vec2<f64>,
+    /// Points away from the target
+    pub normal: Vec2<f64>,
+}
+
+/// Creates an entity with the Projectile and a circle sensor body of the given radius
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_projectile(
+    entities: &mut EntitiesViewMut,
+    physics_world: &mut PhysicsWorld,
+    bodies: &mut ViewMut<PhysicsBody>,
+    transforms: &mut ViewMut<Transform>,
+    projectiles: &mut ViewMut<Projectile>,
+    position: Vec2<f64>,
+    radius: f64,
+    projectile: Projectile,
+) -> EntityId {
+    let sensor = Collider::circle(radius, 0, projectile.layer_mask);
+    let id = entities.add_entity(&mut *projectiles, projectile);
+
+    physics_world.create_body(
+        entities,
+        bodies,
+        id,
+        transforms,
+        Transform::new(position.x, position.y),
+        CollisionBody::from_sensor(sensor),
+    );
+
+    id
+}
+
+/// Moves projectiles in steps no longer than their own size so they can't pass through thin bodies,
+/// sends a ProjectileHit for each new body they touch and counts down their lifetime
+pub fn update_projectiles(
+    mut projectiles: ViewMut<Projectile>,
+    bodies: View<PhysicsBody>,
+    mut world: UniqueViewMut<PhysicsWorld>,
+    mut hits: UniqueViewMut<Events<ProjectileHit>>,
+    time: UniqueView<Time>,
+) {
+    if time.is_paused() {
+        return;
+    }
+
+    for (id, (projectile, _)) in (&mut projectiles, &bodies).iter().with_id() {
+        if projectile.expired {
+            continue;
+        }
+
+        let aabb = world.collider(id).aabb();
+        let step_length = f64::max(aabb.width.min(aabb.height) / 2.0, f64::EPSILON);
+        let velocity = projectile.velocity * time.scale();
+        let steps = (velocity.magnitude() / step_length).ceil().max(1.0) as usize;
+        let delta = velocity / steps as f64;
+
+        for _ in 0..steps {
+            world.move_body(id, delta);
+
+            let position = {
+                let transform = world.transform(id);
+                Vec2::new(transform.x, transform.y)
+            };
+            let touching: Vec<(EntityId, Vec2<f64>)> = world.collider(id).sensors.iter()
+                .flat_map(|sensor| sensor.overlapping.iter())
+                .map(|collision| (collision.entity2, collision.normal))
+                .collect();
+
+            for (target, normal) in touching.into_iter() {
+                if Some(target) == projectile.owner || projectile.hit.contains(&target) {
+                    continue;
+                }
+                projectile.hit.push(target);
+
+                hits.send(ProjectileHit {
+                    projectile: id,
+                    target,
+                    owner: projectile.owner,
+                    position,
+                    normal,
+                });
+
+                if projectile.pierce == 0 {
+                    projectile.expired = true;
+                    break;
+                }
+                projectile.pierce -= 1;
+            }
+
+            if projectile.expired {
+                break;
+            }
+        }
+
+        projectile.lifetime = projectile.lifetime.saturating_sub(1);
+        if projectile.lifetime == 0 {
+            projectile.expired = true;
+        }
+    }
+}
+
+/// Deletes every expired projectile, the PhysicsWorld removes their bodies on its next sync
+pub fn despawn_projectiles(mut all_storages: AllStoragesViewMut) {
+    let expired: Vec<EntityId> = {
+        let projectiles = all_storages.borrow::<View<Projectile>>();
+        projectiles.iter().with_id()
+            .filter(|(_, projectile)| projectile.expired)
+            .map(|(id, _)| id)
+            .collect()
+    };
+
+    for id in expired.into_iter() {
+        all_storages.delete(id);
+    }
+}
+
+/// Dummy trait to allow adding a method to WorkloadBuilder
+pub trait ProjectileWorkloadSystems<'a> {
+    fn with_projectile_systems(self) -> WorkloadBuilder<'a>;
+}
+
+impl<'a> ProjectileWorkloadSystems<'a> for WorkloadBuilder<'a> {
+    /// Requires the `Events<ProjectileHit>` unique, added with `World::add_events::<ProjectileHit>()`
+    fn with_projectile_systems(self) -> WorkloadBuilder<'a> {
+        self
+            .with_system(system!(update_projectiles))
+            .with_system(system!(despawn_projectiles))
+    }
+}
+
+//
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::EventsCreator;
+    use crate::physics::{
+        PhysicsWorkloadCreator,
+        PhysicsWorkloadSystems,
+    };
+
+    /// A World with the physics workload and a thin wall on layer 1 at each x
+    fn world_with_walls(xs: &[f64]) -> (World, Vec<EntityId>) {
+        let mut world = World::new();
+        world
+            .add_physics_workload(50.0, 50.0)
+            .with_physics_systems()
+            .build();
+        world.add_events::<ProjectileHit>();
+
+        let walls = world.run(|
+            mut entities: EntitiesViewMut,
+            mut bodies: ViewMut<PhysicsBody>,
+            mut transforms: ViewMut<Transform>,
+            mut physics_world: UniqueViewMut<PhysicsWorld>| {
+                xs.iter().map(|x| {
+                    let id = entities.add_entity((), ());
+                    physics_world.create_body(
+                        &mut entities,
+                        &mut bodies,
+                        id,
+                        &mut transforms,
+                        Transform::new(*x, 0.0),
+                        CollisionBody::from_collider(Collider::half_extents(0.5, 10.0, 1, 1)),
+                    );
+                    id
+                }).collect::<Vec<_>>()
+        });
+
+        (world, walls)
+    }
+
+    fn spawn(world: &World, projectile: Projectile) -> EntityId {
+        world.run(|
+            mut entities: EntitiesViewMut,
+            mut physics_world: UniqueViewMut<PhysicsWorld>,
+            mut bodies: ViewMut<PhysicsBody>,
+            mut transforms: ViewMut<Transform>,
+            mut projectiles: ViewMut<Projectile>| {
+                spawn_projectile(&mut entities, &mut physics_world, &mut bodies, &mut transforms, &mut projectiles, Vec2::zero(), 1.0, projectile)
+        })
+    }
+
+    fn hit_targets(world: &World) -> Vec<EntityId> {
+        world.run(|hits: UniqueView<Events<ProjectileHit>>| {
+            hits.iter().map(|hit| hit.target).collect()
+        })
+    }
+
+    fn is_expired(world: &World, projectile: EntityId) -> bool {
+        world.run(|projectiles: View<Projectile>| projectiles.get(projectile).unwrap().is_expired())
+    }
+
+    #[test]
+    fn fast_projectile_hits_thin_wall() {
+        let (world, walls) = world_with_walls(&[15.0]);
+        let projectile = spawn(&world, Projectile::new(Vec2::new(30.0, 0.0), 10, 1));
+
+        world.run(update_projectiles);
+
+        // Moving 30 in one tick would jump over the wall without the sub steps
+        assert_eq!(hit_targets(&world), vec![walls[0]]);
+        assert!(is_expired(&world, projectile));
+    }
+
+    #[test]
+    fn pierce_passes_through_bodies() {
+        let (world, walls) = world_with_walls(&[10.0, 20.0, 30.0]);
+        let projectile = spawn(&world, Projectile::new(Vec2::new(40.0, 0.0), 10, 1).with_pierce(1));
+
+        world.run(update_projectiles);
+
+        assert_eq!(hit_targets(&world), vec![walls[0], walls[1]]);
+        assert!(is_expired(&world, projectile));
+    }
+
+    #[test]
+    fn owner_is_never_hit() {
+        let (world, walls) = world_with_walls(&[10.0, 20.0]);
+        spawn(&world, Projectile::new(Vec2::new(30.0, 0.0), 10, 1).with_owner(walls[0]));
+
+        world.run(update_projectiles);
+
+        assert_eq!(hit_targets(&world), vec![walls[1]]);
+    }
+
+    #[test]
+    fn lifetime_counts_moving_ticks() {
+        let (world, _) = world_with_walls(&[]);
+        let projectile = spawn(&world, Projectile::new(Vec2::new(1.0, 0.0), 3, 1));
+
+        world.run(update_projectiles);
+        world.run(update_projectiles);
+        assert!(!is_expired(&world, projectile));
+
+        world.run(update_projectiles);
+        assert!(is_expired(&world, projectile));
+
+        // Expired projectiles don't move even before they are despawned
+        world.run(update_projectiles);
+        world.run(|physics_world: UniqueView<PhysicsWorld>| {
+            assert!((physics_world.transform(projectile).x - 3.0).abs() < 1e-9);
+        });
+    }
+
+    #[test]
+    fn expired_projectiles_are_despawned() {
+        let (world, walls) = world_with_walls(&[10.0]);
+        let projectile = spawn(&world, Projectile::new(Vec2::new(20.0, 0.0), 10, 1));
+
+        world.run(update_projectiles);
+        world.run(despawn_projectiles);
+
+        assert_eq!(hit_targets(&world), vec![walls[0]]);
+        world.run(|projectiles: View<Projectile>| assert!(projectiles.get(projectile).is_err()));
+    }
+}
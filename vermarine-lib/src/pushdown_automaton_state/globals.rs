@@ -0,0 +1,101 @@
+use shipyard::*;
+
+/// The shared resource for a `PushdownAutomaton<Globals>` where every PDAState owns its own World, so pushed states
+/// such as pause menus or battle screens can't trample the uniques of the states below them.
+///
+/// Uniques every state needs go in `world`. Data that has to move between states goes through `handoff`: the state
+/// being left moves it in during `on_pop` or `on_cover`, the state being entered takes it out during `on_uncover` or `on_push`.
+///
+/// ```ignore
+/// fn on_pop(&mut self, _ctx: &mut Context, globals: &mut Globals) {
+///     move_unique::<BattleResult>(&self.world, &globals.handoff);
+///     EntityTransfer::new().with_component::<Unit>().transfer(&self.world, &self.survivors, &globals.handoff);
+/// }
+///
+/// fn on_uncover(&mut self, _ctx: &mut Context, globals: &mut Globals) {
+///     move_unique::<BattleResult>(&globals.handoff, &self.world);
+/// }
+/// ```
+pub struct Globals {
+    pub world: World,
+    pub handoff: World,
+}
+
+impl Globals {
+    pub fn new() -> Self {
+        Globals {
+            world: World::new(),
+            handoff: World::new(),
+        }
+    }
+
+    /// Moves every entity in the handoff World that has a `C` into `to`, along with the components added to the transfer
+    pub fn take_handoff_entities<C: 'static + Send + Sync>(&self, transfer: &EntityTransfer, to: &World) -> Vec<EntityId> {
+        let ids: Vec<EntityId> = self.handoff.borrow::<View<C>>().iter().with_id().map(|(id, _)| id).collect();
+        transfer.transfer(&self.handoff, &ids, to)
+    }
+}
+
+impl Default for Globals {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Removes the unique from `from` and adds it to `to`, replacing the unique in `to` if there already is one
+pub fn move_unique<U: 'static + Send + Sync>(from: &World, to: &World) {
+    let unique = from.remove_unique::<U>();
+    to.add_unique(unique);
+}
+
+/// Adds a clone of the unique in `from` to `to`
+pub fn copy_unique<U: 'static + Send + Sync + Clone>(from: &World, to: &World) {
+    let unique = (*from.borrow::<UniqueView<U>>()).clone();
+    to.add_unique(unique);
+}
+
+type ComponentMover = fn(&World, EntityId, &World, EntityId);
+
+/// Moves entities from one World to another. Entities get new ids in the World they are moved to and only the
+/// components added with `with_component` are moved, the rest are deleted with the original entity.
+#[derive(Clone, Default)]
+pub struct EntityTransfer {
+    movers: Vec<ComponentMover>,
+}
+
+impl EntityTransfer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a component type to move with each entity.
+    pub fn with_component<C: 'static + Send + Sync>(mut self) -> Self {
+        self.movers.push(move_component::<C>);
+        self
+    }
+
+    /// Moves the entities, returning their new ids in `to` in the same order as `ids`
+    pub fn transfer(&self, from: &World, ids: &[EntityId], to: &World) -> Vec<EntityId> {
+        let mut new_ids = vec![];
+
+        for id in ids.iter() {
+            let new_id = to.borrow::<EntitiesViewMut>().add_entity((), ());
+            for mover in self.movers.iter() {
+                mover(from, *id, to, new_id);
+            }
+
+            from.borrow::<AllStoragesViewMut>().delete(*id);
+            new_ids.push(new_id);
+        }
+
+        new_ids
+    }
+}
+
+fn move_component<C: 'static + Send + Sync>(from: &World, id: EntityId, to: &World, new_id: EntityId) {
+    let component = from.borrow::<ViewMut<C>>().remove(id);
+    if let Some(component) = component {
+        let (mut entities, mut storage) = to.borrow::<(EntitiesViewMut, ViewMut<C>)>();
+        entities.add_component(&mut storage, component, new_id);
+    }
+}
@@ -0,0 +1,145 @@
+use tetra::{
+    graphics::{
+        self,
+        Color,
+    },
+    math::{
+        Vec2,
+        Vec3,
+    },
+    window,
+    Context,
+    Result,
+};
+use crate::logging;
+use crate::rendering::{
+    Drawables,
+    draw_buffer::{
+        DrawBuffer,
+        DrawCommand,
+        PassId,
+        Primitive,
+    },
+    loader::TextureLoader,
+};
+use super::{
+    PDAState,
+    Trans,
+};
+
+type NextState<T> = Box<dyn FnOnce(&mut Context, &mut T, Drawables) -> Result<Box<dyn PDAState<T>>>>;
+
+/// Streams in every texture in a directory with a TextureLoader while drawing a progress bar, then switches
+/// to the state created by the factory, which is handed the finished Drawables to put in its World.
+///
+/// Textures that can't be loaded don't stop the game, they keep the loader's placeholder and loading still
+/// finishes. Each failure is logged as it happens and a summary of all of them is logged before switching.
+pub struct LoadingState<T> {
+    loader: TextureLoader,
+    drawables: Option<Drawables>,
+    next: Option<NextState<T>>,
+    draw_buffer: DrawBuffer,
+
+    /// How many textures are uploaded per update. Defaults to 8.
+    pub uploads_per_update: usize,
+
+    pub clear_color: Color,
+    pub bar_color: Color,
+    pub bar_background: Color,
+    /// The size of the progress bar in pixels, it's drawn in the center of the window
+    pub bar_size: Vec2<f32>,
+}
+
+impl<T> LoadingState<T> {
+    pub fn new<F>(ctx: &mut Context, dir: &str, next: F) -> Result<Self>
+        where
+        F: FnOnce(&mut Context, &mut T, Drawables) -> Result<Box<dyn PDAState<T>>> + 'static, {
+        let (drawables, loader) = TextureLoader::new(ctx, dir)?;
+
+        Ok(LoadingState {
+            loader,
+            drawables: Some(drawables),
+            next: Some(Box::new(next)),
+            draw_buffer: DrawBuffer::new(),
+
+            uploads_per_update: 8,
+
+            clear_color: Color::BLACK,
+            bar_color: Color::WHITE,
+            bar_background: Color::rgb(0.2, 0.2, 0.2),
+            bar_size: Vec2::new(200.0, 16.0),
+        })
+    }
+
+    /// Sets how many textures are uploaded per update.
+    pub fn with_uploads_per_update(mut self, uploads_per_update: usize) -> Self {
+        self.uploads_per_update = uploads_per_update;
+        self
+    }
+
+    /// Sets the colors of the progress bar.
+    pub fn with_bar_colors(mut self, bar_color: Color, bar_background: Color) -> Self {
+        self.bar_color = bar_color;
+        self.bar_background = bar_background;
+        self
+    }
+}
+
+impl<T> PDAState<T> for LoadingState<T> {
+    fn update(&mut self, ctx: &mut Context, resources: &mut T) -> Result<Trans<T>> {
+        let drawables = match self.drawables.as_mut() {
+            Some(drawables) => drawables,
+            None => return Ok(Trans::None),
+        };
+
+        let progress = self.loader.upload(ctx, drawables, self.uploads_per_update);
+        if !progress.is_done() {
+            return Ok(Trans::None);
+        }
+
+        if progress.failed > 0 {
+            let paths: Vec<_> = self.loader.failures().iter().map(|(path, _)| path).collect();
+            log::warn!(
+                target: logging::ASSETS,
+                "Finished loading with {} of {} textures missing, they will draw as placeholders: {:?}",
+                progress.failed, progress.total, paths,
+            );
+        }
+
+        match (self.next.take(), self.drawables.take()) {
+            (Some(next), Some(drawables)) => Ok(Trans::Switch(next(ctx, resources, drawables)?)),
+            _ => Ok(Trans::None),
+        }
+    }
+
+    fn draw(&mut self, ctx: &mut Context, _resources: &mut T) -> Result {
+        graphics::clear(ctx, self.clear_color);
+
+        let drawables = match &self.drawables {
+            Some(drawables) => drawables,
+            None => return Ok(()),
+        };
+
+        let window_size = Vec2::new(window::get_width(ctx) as f32, window::get_height(ctx) as f32);
+        let top_left = (window_size - self.bar_size) / 2.0;
+        let position = Vec3::new(top_left.x, top_left.y, 0.0);
+        let fraction = self.loader.progress().fraction();
+
+        self.draw_buffer.draw(
+            DrawCommand::from_primitive(Primitive::Rect { width: self.bar_size.x, height: self.bar_size.y })
+                .position(position)
+                .color(self.bar_background)
+                .pass(PassId::UI)
+        );
+        self.draw_buffer.draw(
+            DrawCommand::from_primitive(Primitive::Rect { width: self.bar_size.x * fraction, height: self.bar_size.y })
+                .position(position)
+                .draw_layer(1.0)
+                .color(self.bar_color)
+                .pass(PassId::UI)
+        );
+        self.draw_buffer.flush_to(ctx, drawables);
+
+        Ok(())
+    }
+}
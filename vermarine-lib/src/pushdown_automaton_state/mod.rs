@@ -1,3 +1,7 @@
+pub mod loading;
+pub mod globals;
+
+use std::any::Any;
 use tetra::{Context, Result, Event};
 
 /// An enum representing the transitions to apply to the pushdown automaton
@@ -11,6 +15,9 @@ pub enum Trans<T> {
     /// Pop the state off the top of the Pushdown Automaton
     Pop,
 
+    /// Pop the state off the top of the Pushdown Automaton and pass the payload to the uncovered state's `on_resume`
+    PopWith(Box<dyn Any>),
+
     /// Switches the state at the top of the Pushdown Automaton with the provided State
     Switch(Box<dyn PDAState<T>>),
 
@@ -23,7 +30,8 @@ pub enum Trans<T> {
     /// Executes a sequence of Trans
     Sequence(Vec<Trans<T>>),
 
-    /// Quit the engine
+    /// Calls `on_quit` then `on_pop` on every state from the top of the stack down and quits the engine,
+    /// any transitions after it are ignored
     Quit,
 }
 
@@ -31,6 +39,7 @@ pub enum Trans<T> {
 pub struct PushdownAutomaton<T> {
     pub(crate) states: Vec<Box<dyn PDAState<T>>>,
     pub(crate) resource: T,
+    quitting: bool,
 }
 
 impl<T> PushdownAutomaton<T> {
@@ -45,9 +54,31 @@ impl<T> PushdownAutomaton<T> {
         Ok(PushdownAutomaton {
             states: vec![state],
             resource,
+            quitting: false,
         })
     }
 
+    /// The number of states on the stack
+    pub fn stack_len(&self) -> usize {
+        self.states.len()
+    }
+
+    /// The name of the state depth states below the top of the stack, 0 being the top
+    pub fn peek_name(&self, depth: usize) -> Option<&str> {
+        let index = self.states.len().checked_sub(depth + 1)?;
+        Some(self.states[index].name())
+    }
+
+    /// The names of all states on the stack from the bottom to the top
+    pub fn stack_names(&self) -> Vec<&str> {
+        self.states.iter().map(|s| s.name()).collect()
+    }
+
+    /// Whether a state has returned `Trans::Quit`, the stack is empty once it has
+    pub fn is_quitting(&self) -> bool {
+        self.quitting
+    }
+
     pub(crate) fn push(&mut self, ctx: &mut Context, mut state: Box<dyn PDAState<T>>) {
         state.on_push(ctx, &mut self.resource);
         if let Some(s) = self.states.last_mut() {
@@ -63,6 +94,13 @@ impl<T> PushdownAutomaton<T> {
         }
     }
 
+    pub(crate) fn pop_with(&mut self, ctx: &mut Context, payload: Box<dyn Any>) {
+        self.pop(ctx);
+        if let Some(s) = self.states.last_mut() {
+            s.on_resume(ctx, &mut self.resource, payload);
+        }
+    }
+
     pub(crate) fn switch(&mut self, ctx: &mut Context, state: Box<dyn PDAState<T>>) {
         self.pop(ctx);
         self.push(ctx, state);
@@ -91,16 +129,36 @@ impl<T> PushdownAutomaton<T> {
         }
     }
 
+    pub(crate) fn quit(&mut self, ctx: &mut Context) {
+        self.quitting = true;
+
+        // Every state gets to save while the whole stack is still there
+        for s in self.states.iter_mut().rev() {
+            s.on_quit(ctx, &mut self.resource);
+        }
+
+        while let Some(mut s) = self.states.pop() {
+            s.on_pop(ctx, &mut self.resource);
+        }
+
+        tetra::window::quit(ctx);
+    }
+
     pub(crate) fn run_trans(&mut self, ctx: &mut Context, trans: Trans<T>) {
+        if self.quitting {
+            return;
+        }
+
         match trans {
             Trans::None => {},
             Trans::Push(state) => { self.push(ctx, state) },
             Trans::Pop => { self.pop(ctx) },
+            Trans::PopWith(payload) => { self.pop_with(ctx, payload) },
             Trans::Switch(state) => { self.switch(ctx, state) },
             Trans::Replace(state) => { self.replace(ctx, state) },
             Trans::NewStack(stack) => { self.new_stack(ctx, stack) },
             Trans::Sequence(sequence) => { self.sequence(ctx, sequence) },
-            Trans::Quit => {},
+            Trans::Quit => { self.quit(ctx) },
         }
     }
 }
@@ -112,7 +170,7 @@ impl<T> tetra::State for PushdownAutomaton<T> {
             trans = Some(s.update(ctx, &mut self.resource)?);
         }
 
-        let len = self.states.len() - 1;
+        let len = self.states.len().saturating_sub(1);
         for idx in (0..len).rev() {
             self.states[idx].shadow_update(ctx, &mut self.resource)?;
         }
@@ -125,7 +183,7 @@ impl<T> tetra::State for PushdownAutomaton<T> {
     }
 
     fn draw(&mut self, ctx: &mut Context) -> Result {
-        let len = self.states.len() - 1;
+        let len = self.states.len().saturating_sub(1);
         for idx in 0..len {
             self.states[idx].shadow_draw(ctx, &mut self.resource)?;
         }
@@ -169,6 +227,12 @@ pub trait PDAState<T> {
 
     }
 
+    /// Called on every state from the top of the stack down when a state returns `Trans::Quit`, before any of
+    /// them are popped. Use it to save the game before it closes.
+    fn on_quit(&mut self, ctx: &mut Context, resources: &mut T) {
+
+    }
+
     /// Called when the state has another state pushed ontop of it
     fn on_cover(&mut self, ctx: &mut Context, resources: &mut T) {
 
@@ -179,6 +243,16 @@ pub trait PDAState<T> {
 
     }
 
+    /// Called after `on_uncover` when the state above it was popped with `Trans::PopWith`
+    fn on_resume(&mut self, ctx: &mut Context, resources: &mut T, payload: Box<dyn Any>) {
+
+    }
+
+    /// The name shown when inspecting the stack, defaults to the type name
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+
     /// Called when it is time for the game to update and the state is on the top of the stack.
     fn update(&mut self, ctx: &mut Context, resources: &mut T) -> Result<Trans<T>> {
         Ok(Trans::None)
@@ -0,0 +1,167 @@
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use shipyard::*;
+
+/// Seedable random number generator for gameplay code. The whole state is two u64s so it can be
+/// stored alongside a replay, and `fork` gives each system its own stream so that adding a
+/// random call in one system doesn't change the numbers another system gets.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Random {
+    seed: u64,
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Random {
+            seed,
+            state: seed,
+        }
+    }
+
+    /// Seeds the generator from the system clock
+    pub fn from_time() -> Self {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|duration| duration.as_nanos() as u64)
+            .unwrap_or(0);
+        Self::new(nanos)
+    }
+
+    /// Restores a generator from a seed and state previously returned by `seed` and `state`
+    pub fn from_state(seed: u64, state: u64) -> Self {
+        Random {
+            seed,
+            state,
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+
+    /// Creates an independent generator for the named stream. The result only depends on the seed
+    /// and the name, not on how many numbers have been generated so far.
+    pub fn fork(&self, stream: &str) -> Random {
+        // FNV-1a, std's hashers aren't guaranteed to be stable between releases
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for byte in stream.bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+
+        let mut forked = Random::new(self.seed ^ hash);
+        forked.next_u64();
+        forked
+    }
+
+    // SplitMix64
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a value in the range [0.0, 1.0)
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Returns a value in the range [min, max), or min if the range is empty
+    pub fn range(&mut self, min: i64, max: i64) -> i64 {
+        if max <= min {
+            return min;
+        }
+
+        // Wrapping keeps the span right for ranges wider than i64::MAX, e.g. i64::MIN..i64::MAX
+        let span = max.wrapping_sub(min) as u64;
+        // Reject the values that would make the lower results more likely
+        let zone = u64::MAX - (u64::MAX % span);
+        loop {
+            let value = self.next_u64();
+            if value < zone {
+                return min.wrapping_add((value % span) as i64);
+            }
+        }
+    }
+
+    /// Returns a value in the range [min, max)
+    pub fn range_f64(&mut self, min: f64, max: f64) -> f64 {
+        min + self.next_f64() * (max - min)
+    }
+
+    /// Returns true with the given probability, from 0.0 to 1.0
+    pub fn chance(&mut self, probability: f64) -> bool {
+        self.next_f64() < probability
+    }
+
+    pub fn pick_from_slice<'a, T>(&mut self, slice: &'a [T]) -> Option<&'a T> {
+        if slice.is_empty() {
+            None
+        } else {
+            slice.get(self.range(0, slice.len() as i64) as usize)
+        }
+    }
+
+    pub fn shuffle<T>(&mut self, slice: &mut [T]) {
+        for i in (1..slice.len()).rev() {
+            let j = self.range(0, i as i64 + 1) as usize;
+            slice.swap(i, j);
+        }
+    }
+}
+
+/// Dummy trait to allow adding a method to World
+pub trait RandomCreator {
+    fn add_random(&mut self, seed: u64);
+}
+
+impl RandomCreator for World {
+    fn add_random(&mut self, seed: u64) {
+        self.add_unique(Random::new(seed));
+    }
+}
+
+//
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn range_handles_extreme_bounds() {
+        let mut random = Random::new(4591);
+        for _ in 0..1000 {
+            let value = random.range(i64::MIN, i64::MAX);
+            assert!(value < i64::MAX);
+
+            let value = random.range(-1, i64::MAX);
+            assert!((-1..i64::MAX).contains(&value));
+
+            assert_eq!(random.range(i64::MAX - 1, i64::MAX), i64::MAX - 1);
+        }
+    }
+
+    #[test]
+    fn serialized_state_replays_the_same_numbers() {
+        let mut random = Random::new(4591);
+        random.next_u64();
+
+        let saved = ron::to_string(&random).unwrap();
+        let mut restored: Random = ron::from_str(&saved).unwrap();
+
+        assert_eq!(restored, random);
+        for _ in 0..10 {
+            assert_eq!(restored.next_u64(), random.next_u64());
+        }
+    }
+}
@@ -0,0 +1,374 @@
+use std::{
+    collections::HashMap,
+    io,
+    path::Path,
+    sync::Arc,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use shipyard::*;
+use tetra::TetraError;
+use crate::{
+    rendering::{
+        Drawables,
+        Sprite,
+    },
+    time::Time,
+};
+
+/// A requirement on the Animator's parameters for a transition to be taken
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Condition {
+    /// The bool parameter equals the value, missing parameters are false
+    Bool(String, bool),
+    /// The float parameter is greater than the value, missing parameters are 0.0
+    Greater(String, f32),
+    /// The float parameter is less than the value, missing parameters are 0.0
+    Less(String, f32),
+    /// The current state has played to its last frame, or completed a loop if it's looping
+    Finished,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Transition {
+    pub to: String,
+    /// All of the conditions have to hold, no conditions means the transition is taken immediately
+    pub conditions: Vec<Condition>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AnimationState {
+    /// Names of the drawables shown by each frame
+    pub frames: Vec<String>,
+    /// How many ticks each frame is shown for
+    pub frame_ticks: u32,
+    pub looping: bool,
+    /// Checked in order every tick, the first one whose conditions hold is taken
+    pub transitions: Vec<Transition>,
+}
+
+/// Why an AnimationGraph couldn't be loaded
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AnimationGraphError {
+    /// The source isn't valid RON, with ron's description of the problem
+    Ron(String),
+    /// There is no state with the name given as `initial`
+    MissingInitial(String),
+    /// A transition out of `from` goes to a state that doesn't exist
+    MissingTransitionTarget { from: String, to: String },
+}
+
+impl std::fmt::Display for AnimationGraphError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            AnimationGraphError::Ron(error) => write!(f, "Invalid animation graph RON: {}", error),
+            AnimationGraphError::MissingInitial(name) => write!(f, "Initial animation state \"{}\" doesn't exist", name),
+            AnimationGraphError::MissingTransitionTarget { from, to } =>
+                write!(f, "Animation state \"{}\" transitions to \"{}\" which doesn't exist", from, to),
+        }
+    }
+}
+
+impl std::error::Error for AnimationGraphError {}
+
+/// The states and transitions of an animation, shared between every Animator that plays it.
+///
+/// ```ron
+/// (
+///     initial: "idle",
+///     states: {
+///         "idle": (frames: ["idle_0", "idle_1"], frame_ticks: 10, looping: true, transitions: [
+///             (to: "run", conditions: [Bool("running", true)]),
+///         ]),
+///         "run": (frames: ["run_0", "run_1", "run_2"], frame_ticks: 6, looping: true, transitions: [
+///             (to: "idle", conditions: [Bool("running", false)]),
+///         ]),
+///     },
+/// )
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AnimationGraph {
+    pub initial: String,
+    pub states: HashMap<String, AnimationState>,
+}
+
+impl AnimationGraph {
+    /// Parses the graph and checks that `initial` and every transition's `to` name a state
+    pub fn from_ron(source: &str) -> Result<Self, AnimationGraphError> {
+        let graph: AnimationGraph = ron::de::from_str(source)
+            .map_err(|error| AnimationGraphError::Ron(error.to_string()))?;
+        graph.validate()?;
+        Ok(graph)
+    }
+
+    /// Checks that `initial` and every transition's `to` name a state, so that Animators playing the graph
+    /// can't panic on a missing state. Graphs built in code should be checked with this before being played.
+    pub fn validate(&self) -> Result<(), AnimationGraphError> {
+        if !self.states.contains_key(&self.initial) {
+            return Err(AnimationGraphError::MissingInitial(self.initial.clone()));
+        }
+
+        // Sorted so the same graph always reports the same error
+        let mut names: Vec<&String> = self.states.keys().collect();
+        names.sort();
+        for from in names.into_iter() {
+            let missing = self.states[from].transitions
+                .iter()
+                .find(|transition| !self.states.contains_key(&transition.to));
+
+            if let Some(transition) = missing {
+                return Err(AnimationGraphError::MissingTransitionTarget {
+                    from: from.clone(),
+                    to: transition.to.clone(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> tetra::Result<Self> {
+        let path = path.as_ref();
+        let to_error = |reason| TetraError::FailedToLoadAsset { reason, path: path.to_path_buf() };
+
+        let source = std::fs::read_to_string(path).map_err(to_error)?;
+        Self::from_ron(&source)
+            .map_err(|error| to_error(io::Error::new(io::ErrorKind::InvalidData, error.to_string())))
+    }
+
+    /// Panics if there is no state with the name
+    pub fn state(&self, name: &str) -> &AnimationState {
+        self.states.get(name)
+            .unwrap_or_else(|| panic!("No animation state named \"{}\"", name))
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum AnimationParam {
+    Bool(bool),
+    Float(f32),
+}
+
+/// Plays an AnimationGraph, `update_animations` writes the current frame's drawable into the entity's Sprite.
+/// Entities with an Animator shouldn't also have a SpriteName as it would override the frame.
+#[derive(Clone, Debug)]
+pub struct Animator {
+    pub graph: Arc<AnimationGraph>,
+    params: HashMap<String, AnimationParam>,
+
+    state: String,
+    frame: usize,
+    ticks: f64,
+    finished: bool,
+}
+
+impl Animator {
+    pub fn new(graph: Arc<AnimationGraph>) -> Self {
+        let state = graph.initial.clone();
+        Animator {
+            graph,
+            params: HashMap::new(),
+
+            state,
+            frame: 0,
+            ticks: 0.0,
+            finished: false,
+        }
+    }
+
+    pub fn set_bool(&mut self, name: &str, value: bool) {
+        self.params.insert(name.to_string(), AnimationParam::Bool(value));
+    }
+
+    pub fn set_float(&mut self, name: &str, value: f32) {
+        self.params.insert(name.to_string(), AnimationParam::Float(value));
+    }
+
+    pub fn param(&self, name: &str) -> Option<AnimationParam> {
+        self.params.get(name).copied()
+    }
+
+    pub fn state(&self) -> &str {
+        &self.state
+    }
+
+    pub fn frame(&self) -> usize {
+        self.frame
+    }
+
+    /// Returns true once the current state has played to its last frame, or completed a loop if it's looping
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Switches to the state from its first frame, even if it's already the current state
+    pub fn play(&mut self, state: &str) {
+        self.graph.state(state);
+        self.state = state.to_string();
+        self.frame = 0;
+        self.ticks = 0.0;
+        self.finished = false;
+    }
+
+    /// The name of the drawable for the current frame
+    pub fn current_frame(&self) -> Option<&str> {
+        self.graph.state(&self.state).frames.get(self.frame).map(|frame| frame.as_str())
+    }
+
+    /// Advances the animation by `ticks` and then takes the first transition whose conditions hold
+    pub fn advance(&mut self, ticks: f64) {
+        let graph = self.graph.clone();
+        let state = graph.state(&self.state);
+        let frame_ticks = state.frame_ticks.max(1) as f64;
+
+        self.ticks += ticks;
+        while self.ticks >= frame_ticks && !state.frames.is_empty() {
+            self.ticks -= frame_ticks;
+
+            if self.frame + 1 < state.frames.len() {
+                self.frame += 1;
+            } else if state.looping {
+                self.frame = 0;
+                self.finished = true;
+            } else {
+                self.finished = true;
+                self.ticks = 0.0;
+                break;
+            }
+        }
+
+        let transition = state.transitions
+            .iter()
+            .find(|transition| transition.conditions.iter().all(|condition| self.check(condition)));
+
+        if let Some(transition) = transition {
+            self.play(&transition.to);
+        }
+    }
+
+    fn check(&self, condition: &Condition) -> bool {
+        let float = |name: &str| match self.param(name) {
+            Some(AnimationParam::Float(value)) => value,
+            _ => 0.0,
+        };
+
+        match condition {
+            Condition::Bool(name, expected) => match self.param(name) {
+                Some(AnimationParam::Bool(value)) => value == *expected,
+                _ => !*expected,
+            },
+            Condition::Greater(name, value) => float(name) > *value,
+            Condition::Less(name, value) => float(name) < *value,
+            Condition::Finished => self.finished,
+        }
+    }
+}
+
+/// Advances every Animator by one tick, scaled by `Time::time_scale`, and updates its Sprite
+pub fn update_animations(
+    mut animators: ViewMut<Animator>,
+    mut sprites: ViewMut<Sprite>,
+    drawables: NonSendSync<UniqueView<Drawables>>,
+    time: UniqueView<Time>,
+) {
+    if time.is_paused() {
+        return;
+    }
+
+    for (animator, sprite) in (&mut animators, &mut sprites).iter() {
+        animator.advance(time.scale());
+
+        if let Some(drawable) = animator.current_frame().and_then(|name| drawables.get_id(name)) {
+            sprite.0.drawable = drawable;
+        }
+    }
+}
+
+/// Dummy trait to allow adding a method to World
+pub trait AnimationCreator {
+    fn add_animation(&mut self);
+}
+
+impl AnimationCreator for World {
+    /// Adds the Time unique the animation system depends on, not needed if the physics workload was added
+    fn add_animation(&mut self) {
+        self.add_unique(Time::new());
+    }
+}
+
+/// Dummy trait to allow adding a method to WorkloadBuilder
+pub trait AnimationWorkloadSystems<'a> {
+    fn with_animation_systems(self) -> WorkloadBuilder<'a>;
+}
+
+impl<'a> AnimationWorkloadSystems<'a> for WorkloadBuilder<'a> {
+    /// Should run before `with_rendering_systems` so that sprites draw this tick's frame
+    fn with_animation_systems(self) -> WorkloadBuilder<'a> {
+        self
+            .with_system(system!(update_animations))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRAPH: &str = r#"(
+        initial: "idle",
+        states: {
+            "idle": (frames: ["idle_0", "idle_1"], frame_ticks: 2, looping: true, transitions: [
+                (to: "run", conditions: [Bool("running", true)]),
+            ]),
+            "run": (frames: ["run_0", "run_1"], frame_ticks: 2, looping: true, transitions: [
+                (to: "idle", conditions: [Bool("running", false)]),
+            ]),
+        },
+    )"#;
+
+    #[test]
+    fn valid_graph_loads() {
+        let graph = AnimationGraph::from_ron(GRAPH).unwrap();
+        assert_eq!(graph.initial, "idle");
+        assert_eq!(graph.states.len(), 2);
+    }
+
+    #[test]
+    fn missing_initial_is_an_error() {
+        let source = GRAPH.replace(r#"initial: "idle""#, r#"initial: "jump""#);
+        assert_eq!(
+            AnimationGraph::from_ron(&source),
+            Err(AnimationGraphError::MissingInitial("jump".to_string())),
+        );
+    }
+
+    #[test]
+    fn missing_transition_target_is_an_error() {
+        let source = GRAPH.replace(r#"(to: "idle""#, r#"(to: "idel""#);
+        assert_eq!(
+            AnimationGraph::from_ron(&source),
+            Err(AnimationGraphError::MissingTransitionTarget { from: "run".to_string(), to: "idel".to_string() }),
+        );
+    }
+
+    #[test]
+    fn invalid_ron_is_an_error() {
+        assert!(matches!(AnimationGraph::from_ron("(initial: "), Err(AnimationGraphError::Ron(_))));
+    }
+
+    #[test]
+    fn transitions_follow_params() {
+        let mut animator = Animator::new(Arc::new(AnimationGraph::from_ron(GRAPH).unwrap()));
+        animator.advance(1.0);
+        assert_eq!(animator.state(), "idle");
+
+        animator.set_bool("running", true);
+        animator.advance(1.0);
+        assert_eq!(animator.state(), "run");
+        assert_eq!(animator.current_frame(), Some("run_0"));
+
+        animator.advance(2.0);
+        assert_eq!(animator.current_frame(), Some("run_1"));
+    }
+}
@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+use shipyard::*;
+use tetra::{
+    math::{
+        Vec2,
+        Vec3,
+    },
+    window,
+    Context,
+};
+use super::{
+    draw_buffer::{
+        DrawBuffer,
+        DrawCommand,
+        PassId,
+    },
+    layers::DrawLayer,
+    Drawables,
+};
+use crate::picking::Picking;
+
+/// A sprite drawn in place of the OS cursor, the hotspot is the pixel of the sprite that sits on the mouse position
+#[derive(Clone, Debug, PartialEq)]
+pub struct CursorStyle {
+    pub drawable: u64,
+    pub hotspot: Vec2<f32>,
+    pub scale: Vec2<f32>,
+}
+
+impl CursorStyle {
+    pub fn new(drawable: u64, hotspot: Vec2<f32>) -> Self {
+        CursorStyle {
+            drawable,
+            hotspot,
+            scale: Vec2::one(),
+        }
+    }
+
+    /// Creates a style for the drawable with the passed in name, panics if there is no such drawable
+    pub fn from_name(drawables: &Drawables, name: &str, hotspot: Vec2<f32>) -> Self {
+        Self::new(crate::drawable_id!(drawables, name), hotspot)
+    }
+
+    /// Sets the scale of the sprite.
+    pub fn with_scale(mut self, scale: Vec2<f32>) -> Self {
+        self.scale = scale;
+        self
+    }
+}
+
+/// Named cursor styles and which one is active, added by `add_cursor` along with a "cursor" pass drawn above
+/// everything else in screen space. Game states switch cursors in `on_push`/`on_uncover`, e.g. a crosshair
+/// in gameplay and an arrow in menus. While no style is active the OS cursor is shown.
+#[derive(Clone, Debug, Default)]
+pub struct CursorConfig {
+    styles: HashMap<&'static str, CursorStyle>,
+    active: Option<&'static str>,
+    os_cursor_hidden: bool,
+}
+
+impl CursorConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, name: &'static str, style: CursorStyle) {
+        self.styles.insert(name, style);
+    }
+
+    /// Switches to the named style, unknown names fall back to the OS cursor
+    pub fn set_cursor(&mut self, name: &'static str) {
+        self.active = Some(name);
+    }
+
+    /// Goes back to the OS cursor
+    pub fn clear_cursor(&mut self) {
+        self.active = None;
+    }
+
+    pub fn active(&self) -> Option<&'static str> {
+        self.active
+    }
+
+    pub fn active_style(&self) -> Option<&CursorStyle> {
+        self.active.and_then(|name| self.styles.get(name))
+    }
+
+    /// The command drawing the active cursor at a screen position
+    pub fn command(&self, pass: PassId, mouse_screen: Vec2<f32>) -> Option<DrawCommand> {
+        self.active_style().map(|style| {
+            DrawCommand::new(style.drawable)
+                .position(Vec3::new(mouse_screen.x, mouse_screen.y, 0.0))
+                .origin(style.hotspot)
+                .scale(style.scale)
+                .draw_layer(DrawLayer::UI)
+                .pass(pass)
+        })
+    }
+
+    /// Hides the OS cursor while a custom one is active and draws the custom one at the mouse position,
+    /// run after `Picking::update` each frame
+    pub fn draw_cursor(
+        ctx: &mut Context,
+        mut config: UniqueViewMut<CursorConfig>,
+        picking: UniqueView<Picking>,
+        mut draw_buffer: UniqueViewMut<DrawBuffer>,
+    ) -> tetra::Result {
+        let hide = config.active_style().is_some();
+        if hide != config.os_cursor_hidden {
+            window::set_mouse_visible(ctx, !hide)?;
+            config.os_cursor_hidden = hide;
+        }
+
+        let pass = match draw_buffer.pass_id("cursor") {
+            Some(pass) => pass,
+            None => return Ok(()),
+        };
+
+        if let Some(command) = config.command(pass, picking.mouse_screen) {
+            draw_buffer.draw(command);
+        }
+
+        Ok(())
+    }
+}
+
+/// Dummy trait to allow adding a method to World
+pub trait CursorCreator {
+    fn add_cursor(&mut self);
+}
+
+impl CursorCreator for World {
+    /// Adds the CursorConfig unique and the screen space "cursor" pass, must be called after `add_rendering_workload`
+    fn add_cursor(&mut self) {
+        self.add_unique(CursorConfig::new());
+        self.run(|mut draw_buffer: UniqueViewMut<DrawBuffer>| {
+            draw_buffer.add_screen_pass("cursor", 30);
+        });
+    }
+}
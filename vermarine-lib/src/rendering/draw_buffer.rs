@@ -3,30 +3,126 @@ use tetra::{
         self,
         Drawable,
         Color,
+        FilterMode,
         Rectangle,
+        mesh::{
+            Mesh,
+            Vertex,
+            VertexBuffer,
+        },
     },
     Context,
     math::{
         Vec2,
         Vec3,
+        Vec4,
         Mat4,
     },
 };
 use super::{
     DrawParams,
     Drawables,
+    layers::DrawLayer,
 };
+use crate::logging;
 use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{
+    Hash,
+    Hasher,
+};
+use std::sync::Mutex;
 use shipyard::{
     self,
     *,
 };
 
+/// How commands in a DrawCommandPool are ordered. Sorting is stable and ties are broken by the command's sequence,
+/// so commands that compare equal keep the same order every frame.
+///
+/// Draw layers are compared as plain floats, the bands in `layers` reserve ranges of them so that e.g. anything
+/// in `LayerBand::Ui` sorts above anything in `LayerBand::Fx` regardless of the offsets used within each band.
+/// Bands only order commands that tie on every key compared before the draw layer: under `TopDown` z comes first,
+/// so a command with a higher z draws on top even if its band is lower. Keep z equal across bands (or put the
+/// ui in its own pass) when the band has to win. Under `Isometric` the draw layer is the primary key so bands always win.
+/// NaNs sort above every other value instead of comparing equal to everything.
+#[derive(Copy, Clone)]
+pub enum SortMode {
+    /// Sorts by z, then draw layer, then y, then x
+    TopDown,
+    /// Sorts by draw layer, then y, then z so taller things on the same row draw on top, then x
+    Isometric,
+    /// Sorts with the provided comparator, ties are still broken by sequence
+    Custom(fn(&DrawCommand, &DrawCommand) -> Ordering),
+}
+
+impl Default for SortMode {
+    fn default() -> Self {
+        SortMode::TopDown
+    }
+}
+
+impl SortMode {
+    pub fn compare(&self, a: &DrawCommand, b: &DrawCommand) -> Ordering {
+        match self {
+            SortMode::Custom(compare) => compare(a, b).then_with(|| a.sequence.cmp(&b.sequence)),
+            _ => self.key(a).cmp(&self.key(b)),
+        }
+    }
+
+    /// The key the command sorts by, `TopDown` and `Isometric` only ever compare these.
+    /// For `Custom` only the pass and sequence are filled in.
+    pub fn key(&self, command: &DrawCommand) -> SortKey {
+        let bits = |a: f32, b: f32, c: f32, d: f32| {
+            (ordered_bits(a) as u128) << 96 | (ordered_bits(b) as u128) << 64 | (ordered_bits(c) as u128) << 32 | ordered_bits(d) as u128
+        };
+
+        let position = command.position;
+        let order = match self {
+            SortMode::TopDown => bits(position.z, command.draw_layer, position.y, position.x),
+            SortMode::Isometric => bits(command.draw_layer, position.y, position.z, position.x),
+            SortMode::Custom(_) => 0,
+        };
+
+        SortKey {
+            pass: command.pass.map_or(0, |pass| pass.0 + 1),
+            order,
+            // Keeps None before every Some like Option's own ordering
+            sequence: command.sequence.map_or(0, |sequence| sequence.saturating_add(1)),
+        }
+    }
+}
+
+/// Every field a command is sorted by packed into integers, so that it is computed once per command
+/// instead of once per comparison and never has to compare floats
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct SortKey {
+    pass: usize,
+    order: u128,
+    sequence: u64,
+}
+
+/// Maps a float to an integer with the same ordering, -0.0 is treated as 0.0 and NaNs come after infinity
+fn ordered_bits(value: f32) -> u32 {
+    if value.is_nan() {
+        return u32::MAX;
+    }
+
+    let bits = if value == 0.0 { 0.0f32.to_bits() } else { value.to_bits() };
+    if bits & 0x8000_0000 != 0 {
+        !bits
+    } else {
+        bits | 0x8000_0000
+    }
+}
+
 #[derive(Default)]
 pub struct DrawCommandPool {
     pub commands: Vec<DrawCommand>,
     is_sorted: bool,
     finished: bool,
+    next_sequence: u64,
 }
 
 impl DrawCommandPool {
@@ -35,89 +131,363 @@ impl DrawCommandPool {
             commands: vec![],
             is_sorted: false,
             finished: false,
+            next_sequence: 0,
         }
     }
 
-    #[allow(clippy::float_cmp)]
+    /// Pushes a command, giving it the next insertion sequence if it doesn't have one
+    pub fn push(&mut self, mut command: DrawCommand) {
+        if command.sequence.is_none() {
+            command.sequence = Some(self.next_sequence);
+            self.next_sequence += 1;
+        }
+        self.commands.push(command);
+    }
+
     pub fn sort(&mut self) {
-        self.commands.sort_by(|a, b| {
-            if a.position.z == b.position.z {
-                if a.draw_layer == b.draw_layer {
-                    if a.position.y == b.position.y {
-                        if a.position.x == b.position.x {
-                            Ordering::Equal
-                        } else {
-                            a.position.x.partial_cmp(&b.position.x).unwrap()
-                        }
-                    } else {
-                        a.position.y.partial_cmp(&b.position.y).unwrap()
-                    }
-                } else {
-                    a.draw_layer.partial_cmp(&b.draw_layer).unwrap()
-                }
-            } else {
-                a.position.z.partial_cmp(&b.position.z).unwrap()
-            }
-        });
+        self.sort_with(SortMode::TopDown);
+    }
+
+    pub fn sort_with(&mut self, mode: SortMode) {
+        match mode {
+            SortMode::Custom(_) => self.commands.sort_by(|a, b| mode.compare(a, b)),
+            _ => self.commands.sort_by_cached_key(|command| mode.key(command)),
+        }
+    }
+}
+
+/// Identifies a named render pass in the DrawBuffer
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct PassId(usize);
+
+impl PassId {
+    pub const BACKGROUND: PassId = PassId(0);
+    pub const WORLD: PassId = PassId(1);
+    pub const FX: PassId = PassId(2);
+    pub const UI: PassId = PassId(3);
+}
+
+/// A named group of draw commands drawn together at a fixed point in the frame
+pub struct RenderPass {
+    pub name: &'static str,
+    /// Passes are drawn from the lowest order to the highest. Commands without a pass are drawn
+    /// as if they were in a pass of order 0, before any other pass of order 0.
+    pub order: i32,
+    /// Replaces the DrawBuffer's transform_mat for this pass, e.g. the identity matrix for UI
+    pub camera: Option<Mat4<f32>>,
+    /// Multiplied with the color of every command in the pass. Defaults to `Color::WHITE`.
+    pub color: Color,
+    /// Multiplied with the alpha of every command in the pass. Defaults to 1.0.
+    pub opacity: f32,
+    pool: DrawCommandPool,
+}
+
+impl RenderPass {
+    /// The color each command's color is multiplied by when the pass is flushed
+    pub fn modulation(&self) -> Color {
+        Color::rgba(self.color.r, self.color.g, self.color.b, self.color.a * self.opacity)
     }
 }
 
-#[derive(Default)]
 pub struct DrawBuffer {
     pub transform_mat: Mat4<f32>,
+    /// Applied on top of transform_mat and every pass camera, including screen space ones. Set from the
+    /// Letterbox by `apply_letterbox` so the game is scaled and centred in the window.
+    pub viewport: Mat4<f32>,
+    pub sort_mode: SortMode,
+    /// The outlines drawn by `Primitive::Polygon` commands
+    pub polygons: PolygonStore,
     buffers: Vec<DrawCommandPool>,
+    passes: Vec<RenderPass>,
+    transform_stack: Vec<Mat4<f32>>,
+}
+
+impl Default for DrawBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl DrawBuffer {
+    /// Creates a DrawBuffer with the standard passes "background" (-20), "world" (-10), "fx" (10) and "ui" (20).
+    /// The "ui" pass is drawn in screen space.
     pub fn new() -> Self {
-        DrawBuffer {
+        let mut draw_buffer = DrawBuffer {
             transform_mat: Mat4::identity(),
+            viewport: Mat4::identity(),
+            sort_mode: SortMode::TopDown,
+            polygons: PolygonStore::new(),
             buffers: vec![DrawCommandPool::new()],
-        }
+            passes: vec![],
+            transform_stack: vec![],
+        };
+
+        draw_buffer.add_pass("background", -20);
+        draw_buffer.add_pass("world", -10);
+        draw_buffer.add_pass("fx", 10);
+        draw_buffer.add_screen_pass("ui", 20);
+        draw_buffer
+    }
+
+    /// Adds a render pass drawn in screen space, ignoring transform_mat
+    pub fn add_screen_pass(&mut self, name: &'static str, order: i32) -> PassId {
+        let pass = self.add_pass(name, order);
+        self.set_pass_camera(pass, Some(Mat4::identity()));
+        pass
+    }
+
+    /// Adds a render pass, the name should be unique
+    pub fn add_pass(&mut self, name: &'static str, order: i32) -> PassId {
+        self.passes.push(RenderPass {
+            name,
+            order,
+            camera: None,
+            color: Color::WHITE,
+            opacity: 1.0,
+            pool: DrawCommandPool::new(),
+        });
+        PassId(self.passes.len() - 1)
+    }
+
+    pub fn pass_id(&self, name: &str) -> Option<PassId> {
+        self.passes.iter().position(|pass| pass.name == name).map(PassId)
+    }
+
+    pub fn pass(&self, pass: PassId) -> &RenderPass {
+        &self.passes[pass.0]
+    }
+
+    pub fn pass_mut(&mut self, pass: PassId) -> &mut RenderPass {
+        &mut self.passes[pass.0]
+    }
+
+    /// Sets the transform used for the pass instead of transform_mat, `None` goes back to transform_mat
+    pub fn set_pass_camera(&mut self, pass: PassId, camera: Option<Mat4<f32>>) {
+        self.passes[pass.0].camera = camera;
+    }
+
+    /// Sets the color every command in the pass is multiplied by, e.g. for tinting the world at night
+    pub fn set_pass_color(&mut self, pass: PassId, color: Color) {
+        self.passes[pass.0].color = color;
+    }
+
+    /// Sets the opacity of the whole pass, e.g. for fading the screen in and out
+    pub fn set_pass_opacity(&mut self, pass: PassId, opacity: f32) {
+        self.passes[pass.0].opacity = opacity;
+    }
+
+    /// Makes every command drawn until the matching `pop_transform` a child of the matrix, combined with any
+    /// transform already pushed. Lets a group of commands, e.g. the sprites making up a character, be moved,
+    /// rotated and scaled together when flushed without recomputing each command's position.
+    pub fn push_transform(&mut self, matrix: Mat4<f32>) {
+        let matrix = match self.transform_stack.last() {
+            Some(parent) => *parent * matrix,
+            None => matrix,
+        };
+        self.transform_stack.push(matrix);
+    }
+
+    pub fn pop_transform(&mut self) {
+        self.transform_stack.pop();
+    }
+
+    /// The combined matrix of every pushed transform, if any
+    pub fn current_transform(&self) -> Option<Mat4<f32>> {
+        self.transform_stack.last().copied()
+    }
+
+    /// Runs f with the matrix pushed, popping it afterwards
+    pub fn with_transform<F: FnOnce(&mut DrawBuffer)>(&mut self, matrix: Mat4<f32>, f: F) {
+        self.push_transform(matrix);
+        f(self);
+        self.pop_transform();
+    }
+
+    /// Issues all the buffered draw commands. Passes with a negative order are drawn first, then the
+    /// DrawCommandPools in the order they were created, then the remaining passes.
+    pub fn flush(ctx: &mut Context, mut draw_buffer: UniqueViewMut<DrawBuffer>, queue: UniqueView<DrawQueue>, drawables: NonSendSync<UniqueViewMut<Drawables>>) {
+        draw_buffer.merge_queue(&queue);
+        draw_buffer.flush_to(ctx, &drawables);
     }
 
-    /// Sequentially starting from the first DrawCommandPool issues all the buffered draw commands
-    pub fn flush(ctx: &mut Context, mut draw_buffer: UniqueViewMut<DrawBuffer>, drawables: NonSendSync<UniqueViewMut<Drawables>>) {
-        //camera.update();
-        //graphics::set_transform_matrix(ctx, camera.as_matrix());
-        graphics::set_transform_matrix(ctx, draw_buffer.transform_mat);
+    /// Issues all the buffered draw commands in the same order as `flush`, for DrawBuffers that aren't stored in a World
+    pub fn flush_to(&mut self, ctx: &mut Context, drawables: &Drawables) {
+        // Consecutive primitive commands are batched into a single mesh
+        let mut batch: Vec<Vertex> = vec![];
 
-        for buffer in draw_buffer.buffers.iter_mut() {
+        if !self.transform_stack.is_empty() {
+            log::warn!(target: logging::RENDER, "{} transforms were pushed without being popped before the flush", self.transform_stack.len());
+            self.transform_stack.clear();
+        }
+
+        let sort_mode = self.sort_mode;
+        let viewport = self.viewport;
+        let transform_mat = viewport * self.transform_mat;
+
+        let mut order: Vec<usize> = (0..self.passes.len()).collect();
+        order.sort_by_key(|index| self.passes[*index].order);
+        let split = order.iter().position(|index| self.passes[*index].order >= 0).unwrap_or(order.len());
+
+        for index in order[..split].iter() {
+            Self::flush_pass(ctx, &mut self.passes[*index], viewport, self.transform_mat, sort_mode, drawables, &self.polygons, &mut batch);
+        }
+
+        graphics::set_transform_matrix(ctx, transform_mat);
+        for buffer in self.buffers.iter_mut() {
             if !buffer.is_sorted {
-                buffer.sort();
+                buffer.sort_with(sort_mode);
             }
 
-            for cmd in buffer.commands.iter_mut() {
-                let drawable = drawables.lookup.get(cmd.drawable as usize)
-                    .expect("Invalid texture ID was issued to a draw command");
-    
-                let mut params = DrawParams::new()
-                    .position(Vec2::new(cmd.position.x, cmd.position.y))
-                    .scale(cmd.scale)
-                    .origin(cmd.origin)
-                    .rotation(cmd.rotation)
-                    .color(cmd.color);
-    
-                params.clip = cmd.clip;
-                
-                if cmd.draw_iso {
-                    params.position.y -= cmd.position.z;
+            Self::flush_pool(ctx, buffer, Color::WHITE, transform_mat, drawables, &self.polygons, &mut batch);
+        }
+        Self::flush_batch(ctx, &mut batch);
+
+        for index in order[split..].iter() {
+            Self::flush_pass(ctx, &mut self.passes[*index], viewport, self.transform_mat, sort_mode, drawables, &self.polygons, &mut batch);
+        }
+
+        graphics::set_transform_matrix(ctx, transform_mat);
+        self.buffers.clear();
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn flush_pass(
+        ctx: &mut Context,
+        pass: &mut RenderPass,
+        viewport: Mat4<f32>,
+        transform_mat: Mat4<f32>,
+        sort_mode: SortMode,
+        drawables: &Drawables,
+        polygons: &PolygonStore,
+        batch: &mut Vec<Vertex>,
+    ) {
+        if pass.pool.commands.is_empty() {
+            return;
+        }
+
+        let camera = viewport * pass.camera.unwrap_or(transform_mat);
+        graphics::set_transform_matrix(ctx, camera);
+        pass.pool.sort_with(sort_mode);
+        Self::flush_pool(ctx, &pass.pool, pass.modulation(), camera, drawables, polygons, batch);
+        Self::flush_batch(ctx, batch);
+
+        pass.pool = DrawCommandPool::new();
+    }
+
+    /// Commands with a parent are drawn with the parent applied on top of camera, primitives are transformed as they
+    /// are tessellated so that they still batch together
+    #[allow(clippy::too_many_arguments)]
+    fn flush_pool(
+        ctx: &mut Context,
+        pool: &DrawCommandPool,
+        modulation: Color,
+        camera: Mat4<f32>,
+        drawables: &Drawables,
+        polygons: &PolygonStore,
+        batch: &mut Vec<Vertex>,
+    ) {
+        let mut current_parent: Option<Mat4<f32>> = None;
+
+        for cmd in pool.commands.iter() {
+            let color = modulate(cmd.color, modulation);
+
+            if let Some(primitive) = &cmd.primitive {
+                // The batch is drawn with the camera alone
+                if current_parent.take().is_some() {
+                    graphics::set_transform_matrix(ctx, camera);
                 }
+                primitive.tessellate(cmd, color, polygons, batch);
+                continue;
+            }
+
+            Self::flush_batch(ctx, batch);
+
+            if cmd.parent != current_parent {
+                graphics::set_transform_matrix(ctx, match cmd.parent {
+                    Some(parent) => camera * parent,
+                    None => camera,
+                });
+                current_parent = cmd.parent;
+            }
+
+            let drawable = drawables.lookup.get(cmd.drawable as usize)
+                .expect("Invalid texture ID was issued to a draw command");
     
-                drawable.draw(ctx, params);
+            let size = match cmd.clip {
+                Some(clip) => Vec2::new(clip.width, clip.height),
+                None => Vec2::new(drawable.width() as f32, drawable.height() as f32),
+            };
+
+            let mut params = DrawParams::new()
+                .position(Vec2::new(cmd.position.x, cmd.position.y))
+                .scale(cmd.scale)
+                .origin(cmd.origin.resolve(Vec2::zero(), size))
+                .rotation(cmd.rotation)
+                .color(color);
+    
+            params.clip = cmd.clip;
+            
+            if cmd.draw_iso {
+                params.position.y -= cmd.position.z;
             }
+
+            match cmd.filter_mode {
+                Some(filter_mode) if filter_mode != drawable.filter_mode() => {
+                    // The filter mode belongs to the texture, so anything already batched with the old mode
+                    // has to be drawn first and the old mode put back afterwards
+                    let previous = drawable.filter_mode();
+                    let mut texture = drawable.clone();
+
+                    graphics::flush(ctx);
+                    texture.set_filter_mode(ctx, filter_mode);
+                    texture.draw(ctx, params);
+                    graphics::flush(ctx);
+                    texture.set_filter_mode(ctx, previous);
+                },
+                _ => drawable.draw(ctx, params),
+            }
+        }
+
+        if current_parent.is_some() {
+            graphics::set_transform_matrix(ctx, camera);
         }
-        
-        draw_buffer.buffers.clear();
     }
 
-    /// Pushes a draw command to the newest command pool
-    pub fn draw(&mut self, command: DrawCommand) {
+    /// Draws all the batched primitive vertices as one mesh
+    fn flush_batch(ctx: &mut Context, batch: &mut Vec<Vertex>) {
+        if batch.is_empty() {
+            return;
+        }
+
+        let vertex_buffer = VertexBuffer::new(ctx, &batch)
+            .expect("Failed to create vertex buffer for primitive draw commands");
+        Mesh::new(vertex_buffer).draw(ctx, DrawParams::new());
+        batch.clear();
+    }
+
+    /// Pushes a draw command to its pass, or the newest command pool if it doesn't have one.
+    /// The command becomes a child of the pushed transforms, if there are any.
+    pub fn draw(&mut self, mut command: DrawCommand) {
+        if let Some(transform) = self.transform_stack.last() {
+            command.parent = Some(match command.parent {
+                Some(parent) => *transform * parent,
+                None => *transform,
+            });
+        }
+
+        if let Some(pass) = command.pass {
+            self.passes.get_mut(pass.0)
+                .expect("Draw command was issued to a pass that doesn't exist")
+                .pool
+                .push(command);
+            return;
+        }
+
         if self.buffers.is_empty() || self.buffers.last().unwrap().finished {
             self.new_command_pool(false);
         }
 
-        self.buffers.last_mut().unwrap().commands.push(command);
+        self.buffers.last_mut().unwrap().push(command);
     }
 
     /// Creates a command pool
@@ -139,6 +509,281 @@ impl DrawBuffer {
         }
         buffer
     }
+
+    /// Moves all the commands recorded in the DrawQueue into the buffer, done automatically by `flush`
+    pub fn merge_queue(&mut self, queue: &DrawQueue) {
+        for command in queue.drain() {
+            self.draw(command);
+        }
+    }
+}
+
+const DRAW_QUEUE_SHARDS: usize = 16;
+
+/// Records draw commands through a shared borrow so that systems can submit commands in parallel,
+/// request it as `UniqueView<DrawQueue>` instead of `UniqueViewMut<DrawBuffer>`.
+///
+/// Commands are merged into the DrawBuffer at flush in no particular order across threads,
+/// so give them a sequence if ties in the sort order need to be stable between frames.
+pub struct DrawQueue {
+    shards: Vec<Mutex<Vec<DrawCommand>>>,
+}
+
+impl Default for DrawQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DrawQueue {
+    pub fn new() -> Self {
+        DrawQueue {
+            shards: (0..DRAW_QUEUE_SHARDS).map(|_| Mutex::new(vec![])).collect(),
+        }
+    }
+
+    pub fn draw(&self, command: DrawCommand) {
+        self.with_shard(|commands| commands.push(command));
+    }
+
+    /// Records several commands while only locking once
+    pub fn draw_many<I: IntoIterator<Item = DrawCommand>>(&self, commands: I) {
+        self.with_shard(|shard| shard.extend(commands));
+    }
+
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|shard| shard.lock().unwrap().len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Takes all the recorded commands, shard by shard
+    pub fn drain(&self) -> Vec<DrawCommand> {
+        let mut commands = vec![];
+        for shard in self.shards.iter() {
+            commands.append(&mut shard.lock().unwrap());
+        }
+        commands
+    }
+
+    /// Each thread starts at its own shard and takes the first one that isn't locked,
+    /// only blocking if every shard is in use
+    fn with_shard<F: FnOnce(&mut Vec<DrawCommand>)>(&self, f: F) {
+        let mut hasher = DefaultHasher::new();
+        std::thread::current().id().hash(&mut hasher);
+        let start = hasher.finish() as usize % self.shards.len();
+
+        for offset in 0..self.shards.len() {
+            let shard = &self.shards[(start + offset) % self.shards.len()];
+            if let Ok(mut commands) = shard.try_lock() {
+                f(&mut commands);
+                return;
+            }
+        }
+
+        f(&mut self.shards[start].lock().unwrap());
+    }
+}
+
+/// The point of a graphic that is placed at the command's position and that scaling and rotation are applied around.
+/// Presets are resolved against the size of the texture or primitive when the command is drawn.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Origin {
+    TopLeft,
+    Center,
+    BottomCenter,
+    /// An offset in pixels from the graphic's local origin
+    Custom(Vec2<f32>),
+}
+
+impl Default for Origin {
+    fn default() -> Self {
+        Origin::TopLeft
+    }
+}
+
+impl From<Vec2<f32>> for Origin {
+    fn from(origin: Vec2<f32>) -> Self {
+        Origin::Custom(origin)
+    }
+}
+
+impl Origin {
+    /// Returns the origin in pixels for a graphic whose bounds start at min and have the passed in size
+    pub fn resolve(&self, min: Vec2<f32>, size: Vec2<f32>) -> Vec2<f32> {
+        match self {
+            Origin::TopLeft => min,
+            Origin::Center => min + size / 2.0,
+            Origin::BottomCenter => min + Vec2::new(size.x / 2.0, size.y),
+            Origin::Custom(origin) => *origin,
+        }
+    }
+}
+
+/// Multiplies two colors component-wise
+fn modulate(a: Color, b: Color) -> Color {
+    Color::rgba(a.r * b.r, a.g * b.g, a.b * b.b, a.a * b.a)
+}
+
+/// Identifies an outline in a DrawBuffer's PolygonStore
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct PolygonId(usize);
+
+/// The outlines of polygon primitives, kept out of line so that DrawCommand stays Copy. Adding an outline that
+/// is already stored returns the existing id, so a shape can be added every frame without the store growing.
+///
+/// Outlines are kept until `clear` is called, so shapes that change every frame should be drawn some other way,
+/// e.g. as several lines, rather than adding a new outline each frame.
+#[derive(Clone, Debug, Default)]
+pub struct PolygonStore {
+    polygons: Vec<Vec<Vec2<f32>>>,
+    ids: HashMap<Vec<[u32; 2]>, PolygonId>,
+}
+
+impl PolygonStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores a convex outline, returning the id of an identical outline if there is one
+    pub fn add(&mut self, points: Vec<Vec2<f32>>) -> PolygonId {
+        let key: Vec<[u32; 2]> = points.iter().map(|point| [point.x.to_bits(), point.y.to_bits()]).collect();
+        if let Some(id) = self.ids.get(&key) {
+            return *id;
+        }
+
+        let id = PolygonId(self.polygons.len());
+        self.polygons.push(points);
+        self.ids.insert(key, id);
+        id
+    }
+
+    /// The outline's points, empty for ids that aren't from this store
+    pub fn get(&self, id: PolygonId) -> &[Vec2<f32>] {
+        self.polygons.get(id.0).map_or(&[], |points| points.as_slice())
+    }
+
+    pub fn len(&self) -> usize {
+        self.polygons.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.polygons.is_empty()
+    }
+
+    /// Forgets every outline, any command still holding an id from before draws nothing
+    pub fn clear(&mut self) {
+        self.polygons.clear();
+        self.ids.clear();
+    }
+}
+
+/// A shape that is drawn as a solid color mesh instead of a texture
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Primitive {
+    /// A rectangle with its top left at the origin
+    Rect { width: f32, height: f32 },
+    Circle { radius: f32 },
+    Line { a: Vec2<f32>, b: Vec2<f32>, thickness: f32 },
+    /// A convex polygon stored in the DrawBuffer's PolygonStore
+    Polygon(PolygonId),
+}
+
+impl Primitive {
+    const CIRCLE_SEGMENTS: usize = 32;
+
+    /// Returns the primitive as a list of triangles in local space, polygons are looked up in the store
+    pub fn triangles(&self, polygons: &PolygonStore) -> Vec<Vec2<f32>> {
+        match self {
+            Primitive::Rect { width, height } => {
+                let (w, h) = (*width, *height);
+                vec![
+                    Vec2::new(0.0, 0.0), Vec2::new(w, 0.0), Vec2::new(w, h),
+                    Vec2::new(0.0, 0.0), Vec2::new(w, h), Vec2::new(0.0, h),
+                ]
+            },
+            Primitive::Circle { radius } => {
+                let points: Vec<Vec2<f32>> = (0..Self::CIRCLE_SEGMENTS)
+                    .map(|i| {
+                        let angle = i as f32 / Self::CIRCLE_SEGMENTS as f32 * std::f32::consts::PI * 2.0;
+                        Vec2::new(angle.cos(), angle.sin()) * *radius
+                    })
+                    .collect();
+                Self::fan(&points)
+            },
+            Primitive::Line { a, b, thickness } => {
+                let direction = *b - *a;
+                if direction.magnitude_squared() == 0.0 {
+                    return vec![];
+                }
+                let normal = Vec2::new(-direction.y, direction.x).normalized() * (*thickness / 2.0);
+                vec![
+                    *a + normal, *b + normal, *b - normal,
+                    *a + normal, *b - normal, *a - normal,
+                ]
+            },
+            Primitive::Polygon(id) => Self::fan(polygons.get(*id)),
+        }
+    }
+
+    /// Returns the top left corner and size of the primitive's bounding box in local space
+    pub fn bounds(&self, polygons: &PolygonStore) -> (Vec2<f32>, Vec2<f32>) {
+        Self::triangle_bounds(&self.triangles(polygons))
+    }
+
+    fn triangle_bounds(triangles: &[Vec2<f32>]) -> (Vec2<f32>, Vec2<f32>) {
+        if triangles.is_empty() {
+            return (Vec2::zero(), Vec2::zero());
+        }
+
+        let mut min = triangles[0];
+        let mut max = triangles[0];
+        for point in triangles.iter() {
+            min = Vec2::new(min.x.min(point.x), min.y.min(point.y));
+            max = Vec2::new(max.x.max(point.x), max.y.max(point.y));
+        }
+        (min, max - min)
+    }
+
+    /// Triangulates a convex outline as a fan around the first point
+    fn fan(points: &[Vec2<f32>]) -> Vec<Vec2<f32>> {
+        let mut triangles = vec![];
+        for i in 1..points.len().saturating_sub(1) {
+            triangles.push(points[0]);
+            triangles.push(points[i]);
+            triangles.push(points[i + 1]);
+        }
+        triangles
+    }
+
+    /// Appends the primitive's triangles to the batch, transformed by the command's position, origin, scale and rotation
+    pub(crate) fn tessellate(&self, cmd: &DrawCommand, color: Color, polygons: &PolygonStore, batch: &mut Vec<Vertex>) {
+        let (sin, cos) = cmd.rotation.sin_cos();
+        let mut position = Vec2::new(cmd.position.x, cmd.position.y);
+        if cmd.draw_iso {
+            position.y -= cmd.position.z;
+        }
+
+        let triangles = self.triangles(polygons);
+        let (min, size) = Self::triangle_bounds(&triangles);
+        let origin = cmd.origin.resolve(min, size);
+
+        for point in triangles.into_iter() {
+            let point = (point - origin) * cmd.scale;
+            let point = Vec2::new(point.x * cos - point.y * sin, point.x * sin + point.y * cos);
+            let point = point + position;
+            let point = match cmd.parent {
+                Some(parent) => {
+                    let point = parent * Vec4::new(point.x, point.y, 0.0, 1.0);
+                    Vec2::new(point.x, point.y)
+                },
+                None => point,
+            };
+            batch.push(Vertex::new(point, Vec2::zero(), color));
+        }
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -147,13 +792,16 @@ pub struct DrawCommand {
     /// Can be obtained by using the Alias hashmap
     pub drawable: u64,
 
+    /// A shape to draw instead of the drawable. Defaults to `None`.
+    pub primitive: Option<Primitive>,
+
     /// The position that the drawable should be drawn at. Defaults to `(0.0, 0.0, 0.0)`.
     /// Z-axis is used for draw order sorting and in the case of isometric is subtracted from the y-axis when drawn
     /// 
     /// When used in a Sprite the xy of position is used is an offset to the entities Transform
     pub position: Vec3<f32>,
 
-    /// Used in draw order sorting
+    /// Used in draw order sorting, see `layers` for the reserved ranges. Defaults to 0.0, the bottom of `LayerBand::Entities`.
     pub draw_layer: f32,
 
     /// The scale that the graphic should be drawn at. Defaults to `(1.0, 1.0)`.
@@ -161,12 +809,12 @@ pub struct DrawCommand {
     /// This can be set to a negative value to flip the graphic around the origin.
     pub scale: Vec2<f32>,
 
-    /// The origin of the graphic. Defaults to `(0.0, 0.0)` (the top left).
+    /// The origin of the graphic. Defaults to `Origin::TopLeft` for textures and `Origin::Custom((0.0, 0.0))` for primitives.
     ///
     /// This offset is applied before scaling, rotation and positioning. For example, if you have
     /// a 16x16 image and set the origin to [8.0, 8.0], subsequent transformations will be performed
-    /// relative to the center of the image.
-    pub origin: Vec2<f32>,
+    /// relative to the center of the image. `Origin::Center` does the same without needing to know the image size.
+    pub origin: Origin,
 
     /// The rotation of the graphic, in radians. Defaults to `0.0`.
     pub rotation: f32,
@@ -181,20 +829,73 @@ pub struct DrawCommand {
     ///
     /// This is useful if you're using spritesheets (which you should be, if you want good performance!).
     pub clip: Option<Rectangle>,
+
+    /// Used to order commands that are otherwise equal when sorting. Defaults to `None`, which means the
+    /// command is given its insertion order in the command pool. `draw_sprites` sets this to the entity's index.
+    pub sequence: Option<u64>,
+
+    /// The render pass to draw in. Defaults to `None`, which means the command goes to the newest command pool.
+    pub pass: Option<PassId>,
+
+    /// Overrides the texture's filter mode for this command, e.g. `FilterMode::Nearest` for pixel art drawn
+    /// alongside smoothly filtered assets. Defaults to `None`, which uses the texture's own filter mode.
+    ///
+    /// Each override breaks up tetra's sprite batching, so prefer setting the mode on the texture when it's always the same.
+    pub filter_mode: Option<FilterMode>,
+
+    /// A matrix applied after the command's own position, scale and rotation, shared by a group of commands so that
+    /// they move together. Defaults to `None`, set by `DrawBuffer::push_transform` for commands drawn while it is pushed.
+    ///
+    /// Sorting only looks at the command's own position, so give the children positions that sort sensibly on their own.
+    pub parent: Option<Mat4<f32>>,
 }
 
 impl DrawCommand {
     pub fn new(drawable: u64) -> Self {
         DrawCommand {
             drawable,
+            primitive: None,
             position: Vec3::default(),
             draw_layer: 0.0,
             scale: Vec2::new(1.0, 1.0),
-            origin: Vec2::default(),
+            origin: Origin::TopLeft,
             rotation: 0.0,
             color: Color::WHITE,
             draw_iso: false,
             clip: None,
+            sequence: None,
+            pass: None,
+            filter_mode: None,
+            parent: None,
+        }
+    }
+
+    /// Creates a command that draws a filled rectangle with its top left at the command's position.
+    pub fn rect(width: f32, height: f32) -> Self {
+        Self::from_primitive(Primitive::Rect { width, height })
+    }
+
+    /// Creates a command that draws a filled circle centered on the command's position.
+    pub fn circle(radius: f32) -> Self {
+        Self::from_primitive(Primitive::Circle { radius })
+    }
+
+    /// Creates a command that draws a line between two points relative to the command's position.
+    pub fn line(a: Vec2<f32>, b: Vec2<f32>, thickness: f32) -> Self {
+        Self::from_primitive(Primitive::Line { a, b, thickness })
+    }
+
+    /// Creates a command that draws a filled convex polygon relative to the command's position,
+    /// the outline is added to the DrawBuffer with `draw_buffer.polygons.add(points)`.
+    pub fn polygon(polygon: PolygonId) -> Self {
+        Self::from_primitive(Primitive::Polygon(polygon))
+    }
+
+    pub fn from_primitive(primitive: Primitive) -> Self {
+        DrawCommand {
+            primitive: Some(primitive),
+            origin: Origin::Custom(Vec2::zero()),
+            ..DrawCommand::new(0)
         }
     }
 
@@ -204,9 +905,9 @@ impl DrawCommand {
         self
     }
 
-    /// Sets the draw layer
-    pub fn draw_layer(mut self, draw_layer: f32) -> DrawCommand {
-        self.draw_layer = draw_layer;
+    /// Sets the draw layer, either a raw float or a `DrawLayer` such as `DrawLayer::FX.with_offset(2.0)`
+    pub fn draw_layer<L: Into<DrawLayer>>(mut self, draw_layer: L) -> DrawCommand {
+        self.draw_layer = draw_layer.into().value();
         self
     }
 
@@ -216,9 +917,9 @@ impl DrawCommand {
         self
     }
 
-    /// Sets the origin of the graphic.
-    pub fn origin(mut self, origin: Vec2<f32>) -> DrawCommand {
-        self.origin = origin;
+    /// Sets the origin of the graphic, either a preset or an offset in pixels.
+    pub fn origin<O: Into<Origin>>(mut self, origin: O) -> DrawCommand {
+        self.origin = origin.into();
         self
     }
 
@@ -240,9 +941,33 @@ impl DrawCommand {
         self
     }
 
+    /// Sets the sequence used to break ties when sorting.
+    pub fn sequence(mut self, sequence: u64) -> DrawCommand {
+        self.sequence = Some(sequence);
+        self
+    }
+
+    /// Sets the render pass to draw in.
+    pub fn pass(mut self, pass: PassId) -> DrawCommand {
+        self.pass = Some(pass);
+        self
+    }
+
     /// Sets the region of the graphic to draw.
     pub fn clip(mut self, clip: Rectangle) -> DrawCommand {
         self.clip = Some(clip);
         self
     }
+
+    /// Sets the filter mode to draw the texture with.
+    pub fn filter_mode(mut self, filter_mode: FilterMode) -> DrawCommand {
+        self.filter_mode = Some(filter_mode);
+        self
+    }
+
+    /// Sets the parent transform of the command.
+    pub fn parent(mut self, parent: Mat4<f32>) -> DrawCommand {
+        self.parent = Some(parent);
+        self
+    }
 }
\ No newline at end of file
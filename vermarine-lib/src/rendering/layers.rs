@@ -0,0 +1,94 @@
+/// The width of the float range reserved for each band
+pub const BAND_WIDTH: f32 = 1000.0;
+
+/// Named ranges of draw layers, from back to front. The default draw layer of 0.0 is the bottom of `Entities`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum LayerBand {
+    /// [-2000, -1000)
+    Background,
+    /// [-1000, 0)
+    Terrain,
+    /// [0, 1000)
+    Entities,
+    /// [1000, 2000)
+    Fx,
+    /// [2000, 3000)
+    Ui,
+}
+
+impl LayerBand {
+    pub const ALL: [LayerBand; 5] = [
+        LayerBand::Background,
+        LayerBand::Terrain,
+        LayerBand::Entities,
+        LayerBand::Fx,
+        LayerBand::Ui,
+    ];
+
+    /// The lowest draw layer in the band
+    pub fn base(&self) -> f32 {
+        match self {
+            LayerBand::Background => -2.0 * BAND_WIDTH,
+            LayerBand::Terrain => -BAND_WIDTH,
+            LayerBand::Entities => 0.0,
+            LayerBand::Fx => BAND_WIDTH,
+            LayerBand::Ui => 2.0 * BAND_WIDTH,
+        }
+    }
+
+    pub fn range(&self) -> std::ops::Range<f32> {
+        self.base()..self.base() + BAND_WIDTH
+    }
+
+    /// Returns the band a draw layer falls in, if any
+    pub fn of(draw_layer: f32) -> Option<LayerBand> {
+        Self::ALL.iter().copied().find(|band| band.range().contains(&draw_layer))
+    }
+}
+
+/// A draw layer, built from a band and an offset inside of it or from a raw float.
+/// Accepted by `DrawCommand::draw_layer`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DrawLayer(f32);
+
+impl DrawLayer {
+    pub const BACKGROUND: DrawLayer = DrawLayer(-2.0 * BAND_WIDTH);
+    pub const TERRAIN: DrawLayer = DrawLayer(-BAND_WIDTH);
+    pub const ENTITIES: DrawLayer = DrawLayer(0.0);
+    pub const FX: DrawLayer = DrawLayer(BAND_WIDTH);
+    pub const UI: DrawLayer = DrawLayer(2.0 * BAND_WIDTH);
+
+    pub fn new(band: LayerBand) -> Self {
+        DrawLayer(band.base())
+    }
+
+    /// Moves the layer up within its band, panics in debug builds if this would leave the band
+    pub fn with_offset(self, offset: f32) -> Self {
+        let layer = DrawLayer(self.0 + offset);
+        debug_assert!(
+            self.band().is_none() || layer.band() == self.band(),
+            "Draw layer offset {} moves {} out of its {:?} band", offset, self.0, self.band().unwrap(),
+        );
+        layer
+    }
+
+    pub fn value(&self) -> f32 {
+        self.0
+    }
+
+    pub fn band(&self) -> Option<LayerBand> {
+        LayerBand::of(self.0)
+    }
+}
+
+impl From<f32> for DrawLayer {
+    fn from(draw_layer: f32) -> Self {
+        DrawLayer(draw_layer)
+    }
+}
+
+impl From<LayerBand> for DrawLayer {
+    fn from(band: LayerBand) -> Self {
+        DrawLayer::new(band)
+    }
+}
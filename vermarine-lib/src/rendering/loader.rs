@@ -0,0 +1,185 @@
+use std::{
+    path::{
+        Path,
+        PathBuf,
+    },
+    sync::mpsc::{
+        self,
+        Receiver,
+    },
+    thread,
+};
+use tetra::{
+    graphics::Texture,
+    Context,
+};
+use shipyard::*;
+use crate::logging;
+use super::{
+    Drawables,
+    build_aliases,
+    report_conflicts,
+    get_texture_paths,
+};
+
+/// How many of the queued textures have been uploaded, useful for drawing a loading bar
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct LoadingProgress {
+    pub loaded: usize,
+    /// Textures that couldn't be read, decoded or uploaded, they keep the placeholder
+    pub failed: usize,
+    pub total: usize,
+}
+
+impl LoadingProgress {
+    /// Returns a value from 0.0 to 1.0, failed textures count as finished
+    pub fn fraction(&self) -> f32 {
+        if self.total == 0 {
+            1.0
+        } else {
+            (self.loaded + self.failed) as f32 / self.total as f32
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.loaded + self.failed >= self.total
+    }
+}
+
+/// A texture decoded to RGBA8 on the worker thread, ready to be uploaded
+struct DecodedTexture {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+fn decode(path: &Path) -> Result<DecodedTexture, String> {
+    let data = std::fs::read(path).map_err(|error| format!("couldn't read the file: {}", error))?;
+    let image = image::load_from_memory(&data).map_err(|error| format!("couldn't decode the image: {}", error))?.into_rgba8();
+
+    Ok(DecodedTexture {
+        width: image.width(),
+        height: image.height(),
+        pixels: image.into_raw(),
+    })
+}
+
+/// Streams textures in over multiple frames. Files are read and decoded on a worker thread and every drawable
+/// is given a checkerboard placeholder until its texture has been uploaded on the main thread.
+///
+/// Textures that fail to load are logged and keep their placeholder, they count towards the progress so that
+/// loading still finishes.
+pub struct TextureLoader {
+    receiver: Receiver<(u64, PathBuf, Result<DecodedTexture, String>)>,
+    progress: LoadingProgress,
+    failures: Vec<(PathBuf, String)>,
+}
+
+impl TextureLoader {
+    /// Creates Drawables for every png in the directory with placeholder textures and starts reading the files
+    pub fn new(ctx: &mut Context, dir: &str) -> tetra::Result<(Drawables, TextureLoader)> {
+        let placeholder = placeholder_texture(ctx)?;
+        let paths = get_texture_paths(dir);
+
+        let keys: Vec<&'static str> = paths.iter().map(|(key, _)| *key).collect();
+        let (alias, conflicts) = build_aliases(&keys);
+        report_conflicts(&keys, &conflicts);
+        let drawables = Drawables {
+            alias,
+            lookup: vec![placeholder; paths.len()],
+            conflicts,
+        };
+        let queue: Vec<(u64, PathBuf)> = paths.into_iter()
+            .enumerate()
+            .map(|(id, (_, path))| (id as u64, path))
+            .collect();
+
+        let total = queue.len();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for (id, path) in queue.into_iter() {
+                let texture = decode(&path);
+                if sender.send((id, path, texture)).is_err() {
+                    // The loader was dropped so nobody wants the rest of the textures
+                    return;
+                }
+            }
+        });
+
+        Ok((
+            drawables,
+            TextureLoader {
+                receiver,
+                progress: LoadingProgress {
+                    loaded: 0,
+                    failed: 0,
+                    total,
+                },
+                failures: vec![],
+            },
+        ))
+    }
+
+    /// Uploads up to max_uploads textures that have finished decoding, replacing their placeholders
+    pub fn upload(&mut self, ctx: &mut Context, drawables: &mut Drawables, max_uploads: usize) -> LoadingProgress {
+        for (id, path, texture) in self.receiver.try_iter().take(max_uploads) {
+            let uploaded = texture.and_then(|texture| {
+                Texture::from_rgba(ctx, texture.width as i32, texture.height as i32, &texture.pixels)
+                    .map_err(|error| format!("couldn't upload the texture: {}", error))
+            });
+
+            match uploaded {
+                Ok(texture) => {
+                    drawables.lookup[id as usize] = texture;
+                    log::debug!(target: logging::ASSETS, "Loaded texture {:?}", path);
+                    self.progress.loaded += 1;
+                },
+                Err(reason) => {
+                    log::error!(target: logging::ASSETS, "Texture {:?} {}, keeping the placeholder", path, reason);
+                    self.failures.push((path, reason));
+                    self.progress.failed += 1;
+                },
+            }
+        }
+
+        self.progress
+    }
+
+    pub fn progress(&self) -> LoadingProgress {
+        self.progress
+    }
+
+    /// The path of every texture that failed to load and why
+    pub fn failures(&self) -> &[(PathBuf, String)] {
+        &self.failures
+    }
+
+    /// Uploads up to max_uploads textures and publishes the progress to the LoadingProgress unique
+    pub fn update(
+        ctx: &mut Context, 
+        max_uploads: usize,
+        mut loader: NonSendSync<UniqueViewMut<TextureLoader>>,
+        mut drawables: NonSendSync<UniqueViewMut<Drawables>>,
+        mut progress: UniqueViewMut<LoadingProgress>,
+    ) {
+        *progress = loader.upload(ctx, &mut drawables, max_uploads);
+    }
+}
+
+/// A magenta and black checkerboard used for textures that haven't loaded yet
+pub fn placeholder_texture(ctx: &mut Context) -> tetra::Result<Texture> {
+    const SIZE: i32 = 8;
+
+    let mut data = Vec::with_capacity((SIZE * SIZE * 4) as usize);
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            if (x / 4 + y / 4) % 2 == 0 {
+                data.extend_from_slice(&[255, 0, 255, 255]);
+            } else {
+                data.extend_from_slice(&[0, 0, 0, 255]);
+            }
+        }
+    }
+
+    Texture::from_rgba(ctx, SIZE, SIZE, &data)
+}
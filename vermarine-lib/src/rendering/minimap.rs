@@ -0,0 +1,259 @@
+use tetra::{
+    graphics::{
+        self,
+        Canvas,
+        Color,
+        DrawParams,
+        Drawable,
+        Rectangle,
+        mesh::{
+            Mesh,
+            Vertex,
+            VertexBuffer,
+        },
+    },
+    math::{
+        Mat4,
+        Vec2,
+        Vec3,
+    },
+    Context,
+};
+use shipyard::*;
+use crate::{
+    components::Transform,
+    hexmap::HexMap,
+    rendering::{
+        Drawables,
+        draw_buffer::{
+            DrawBuffer,
+            DrawCommand,
+            PassId,
+        },
+        layers::DrawLayer,
+    },
+};
+
+/// Shows the entity as a dot on the minimap
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct MinimapMarker {
+    pub color: Color,
+    /// The width and height of the dot in screen pixels
+    pub size: f32,
+}
+
+impl MinimapMarker {
+    pub fn new(color: Color) -> Self {
+        MinimapMarker {
+            color,
+            size: 3.0,
+        }
+    }
+
+    /// Sets the size of the dot.
+    pub fn with_size(mut self, size: f32) -> Self {
+        self.size = size;
+        self
+    }
+}
+
+/// A down-scaled view of a HexMap with one quad per tile, rendered into a Canvas that is only
+/// redrawn when the minimap is marked dirty. Markers are drawn on top of it every frame.
+///
+/// The canvas is registered as a drawable so the minimap is drawn through the DrawBuffer, in the screen space UI pass by default.
+pub struct Minimap<T> {
+    /// The top left of the minimap on screen
+    pub position: Vec2<f32>,
+    /// The size of the minimap on screen, the canvas is stretched to fit
+    pub size: Vec2<f32>,
+    pub background: Color,
+    /// The pass the minimap is drawn in, it should be drawn in screen space e.g. a pass from `DrawBuffer::add_screen_pass`
+    pub pass: PassId,
+
+    /// The color of a tile, `None` leaves the tile out
+    pub tile_color: fn(&T) -> Option<Color>,
+
+    canvas: Canvas,
+    drawable: u64,
+    /// The area of the world covered by the minimap, fit around the map's tiles when the canvas is redrawn
+    bounds: Rectangle,
+    dirty: bool,
+}
+
+impl<T> Minimap<T> {
+    /// Creates a minimap with a canvas of the given resolution, shown at that size in the top left of the screen
+    pub fn new(
+        ctx: &mut Context,
+        drawables: &mut Drawables,
+        width: i32,
+        height: i32,
+        tile_color: fn(&T) -> Option<Color>,
+    ) -> tetra::Result<Self> {
+        let canvas = Canvas::new(ctx, width, height)?;
+
+        drawables.lookup.push(canvas.texture().clone());
+        let drawable = (drawables.lookup.len() - 1) as u64;
+
+        Ok(Minimap {
+            position: Vec2::zero(),
+            size: Vec2::new(width as f32, height as f32),
+            background: Color::BLACK,
+            pass: PassId::UI,
+
+            tile_color,
+
+            canvas,
+            drawable,
+            bounds: Rectangle::new(0.0, 0.0, 1.0, 1.0),
+            dirty: true,
+        })
+    }
+
+    /// Sets the top left of the minimap on screen.
+    pub fn with_position(mut self, x: f32, y: f32) -> Self {
+        self.position = Vec2::new(x, y);
+        self
+    }
+
+    /// Sets the size of the minimap on screen.
+    pub fn with_size(mut self, width: f32, height: f32) -> Self {
+        self.size = Vec2::new(width, height);
+        self
+    }
+
+    /// Sets the color of the area not covered by tiles.
+    pub fn with_background(mut self, background: Color) -> Self {
+        self.background = background;
+        self.dirty = true;
+        self
+    }
+
+    /// Sets the pass the minimap is drawn in.
+    pub fn with_pass(mut self, pass: PassId) -> Self {
+        self.pass = pass;
+        self
+    }
+
+    /// The drawable ID of the minimap's canvas
+    pub fn drawable(&self) -> u64 {
+        self.drawable
+    }
+
+    /// The tiles are redrawn into the canvas the next time the minimap is drawn
+    pub fn mark_dirty(&mut self) {
+        self.dirty = true;
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Converts a world position to a position on screen, based on the tiles when the canvas was last redrawn
+    pub fn world_to_screen(&self, position: Vec2<f32>) -> Vec2<f32> {
+        let normalized = Vec2::new(
+            (position.x - self.bounds.x) / self.bounds.width,
+            (position.y - self.bounds.y) / self.bounds.height,
+        );
+        self.position + normalized * self.size
+    }
+
+    /// Redraws every tile of the map into the canvas and fits the minimap's bounds around them
+    pub fn rebuild(&mut self, ctx: &mut Context, map: &HexMap<T>) {
+        self.dirty = false;
+
+        let tile_size = Vec2::new(map.hex_width, map.hex_vert_step);
+        let mut tiles = vec![];
+        for chunk in map.chunks() {
+            for (hex, tile) in chunk.tiles() {
+                if let Some(color) = (self.tile_color)(tile) {
+                    tiles.push((map.axial_to_pixel(hex), color));
+                }
+            }
+        }
+
+        if let Some((first, _)) = tiles.first() {
+            let mut min = *first;
+            let mut max = *first + tile_size;
+            for (position, _) in tiles.iter() {
+                min = Vec2::new(min.x.min(position.x), min.y.min(position.y));
+                max = Vec2::new(max.x.max(position.x + tile_size.x), max.y.max(position.y + tile_size.y));
+            }
+            self.bounds = Rectangle::new(min.x, min.y, max.x - min.x, max.y - min.y);
+        }
+
+        let resolution = Vec2::new(self.canvas.width() as f32, self.canvas.height() as f32);
+        let scale = Vec2::new(resolution.x / self.bounds.width, resolution.y / self.bounds.height);
+        // Tiles are at least a pixel so small minimaps don't drop rows
+        let quad = Vec2::new((tile_size.x * scale.x).max(1.0), (tile_size.y * scale.y).max(1.0));
+
+        let mut vertices = vec![];
+        for (position, color) in tiles.into_iter() {
+            let min = (position - Vec2::new(self.bounds.x, self.bounds.y)) * scale;
+            let max = min + quad;
+            for (x, y) in [(min.x, min.y), (max.x, min.y), (max.x, max.y), (min.x, min.y), (max.x, max.y), (min.x, max.y)].iter() {
+                vertices.push(Vertex::new(Vec2::new(*x, *y), Vec2::zero(), color));
+            }
+        }
+
+        graphics::set_canvas(ctx, &self.canvas);
+        graphics::clear(ctx, self.background);
+        if !vertices.is_empty() {
+            graphics::set_transform_matrix(ctx, Mat4::identity());
+            let vertex_buffer = VertexBuffer::new(ctx, &vertices)
+                .expect("Failed to create vertex buffer for minimap tiles");
+            Mesh::new(vertex_buffer).draw(ctx, DrawParams::new());
+        }
+        graphics::reset_canvas(ctx);
+    }
+
+    /// Draws the canvas and a dot for each marker in the minimap's pass
+    pub fn draw<'a, I>(&self, draw_buffer: &mut DrawBuffer, markers: I)
+    where I: IntoIterator<Item = (Vec2<f32>, &'a MinimapMarker)> {
+        let resolution = Vec2::new(self.canvas.width() as f32, self.canvas.height() as f32);
+
+        draw_buffer.draw(
+            DrawCommand::new(self.drawable)
+                .position(Vec3::new(self.position.x, self.position.y, 0.0))
+                .scale(self.size / resolution)
+                .draw_layer(DrawLayer::UI)
+                .pass(self.pass)
+        );
+
+        for (position, marker) in markers.into_iter() {
+            let position = self.world_to_screen(position);
+            let inside = position.x >= self.position.x && position.y >= self.position.y
+                && position.x <= self.position.x + self.size.x && position.y <= self.position.y + self.size.y;
+            if !inside {
+                continue;
+            }
+
+            draw_buffer.draw(
+                DrawCommand::rect(marker.size, marker.size)
+                    .position(Vec3::new(position.x, position.y, 0.0))
+                    .origin(Vec2::new(marker.size / 2.0, marker.size / 2.0))
+                    .color(marker.color)
+                    .draw_layer(DrawLayer::UI.with_offset(1.0))
+                    .pass(self.pass)
+            );
+        }
+    }
+
+    /// Redraws the canvas if the minimap is dirty and then draws it along with every entity with a MinimapMarker
+    pub fn draw_minimap(
+        ctx: &mut Context,
+        map: UniqueView<HexMap<T>>,
+        mut minimap: NonSendSync<UniqueViewMut<Minimap<T>>>,
+        mut draw_buffer: UniqueViewMut<DrawBuffer>,
+        markers: View<MinimapMarker>,
+        transforms: View<Transform>,
+    ) where T: 'static + Send + Sync {
+        if minimap.is_dirty() {
+            minimap.rebuild(ctx, &map);
+        }
+
+        let markers = (&transforms, &markers)
+            .iter()
+            .map(|(transform, marker)| (Vec2::new(transform.x as f32, transform.y as f32), marker));
+        minimap.draw(&mut draw_buffer, markers);
+    }
+}
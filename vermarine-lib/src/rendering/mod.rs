@@ -1,5 +1,12 @@
 pub mod draw_buffer;
 pub mod systems;
+pub mod loader;
+pub mod screen;
+pub mod layers;
+pub mod animation;
+pub mod minimap;
+pub mod cursor;
+pub mod tint;
 
 use std::collections::HashMap;
 use tetra::{
@@ -13,9 +20,26 @@ use tetra::{
 use draw_buffer::{
     DrawCommand,
     DrawBuffer,
+    DrawQueue,
+    Origin,
+};
+use screen::{
+    ClearColor,
+    Letterbox,
 };
 use shipyard::*;
-use std::path::Path;
+use std::path::{
+    Path,
+    PathBuf,
+};
+use crate::{
+    debug::DebugFlags,
+    logging,
+    picking::{
+        Hits,
+        Picking,
+    },
+};
 
 /// Dummy trait to allow adding a method to World
 pub trait RenderingWorkloadCreator {
@@ -26,6 +50,12 @@ impl RenderingWorkloadCreator for World {
     fn add_rendering_workload(&mut self, ctx: &mut Context) -> WorkloadBuilder {
         self.add_unique(Camera::with_window_size(ctx));
         self.add_unique(DrawBuffer::new());
+        self.add_unique(DrawQueue::new());
+        self.add_unique(ClearColor::default());
+        self.add_unique(Letterbox::default());
+        self.add_unique(DebugFlags::new());
+        self.add_unique(Picking::new());
+        self.add_unique(Hits::new());
         self.add_workload("Rendering")
     }
 }
@@ -53,60 +83,270 @@ impl Sprite {
     pub fn from_command(draw_command: DrawCommand) -> Self {
         Sprite(draw_command)
     }
+
+    /// Sets the origin of the sprite, either a preset or an offset in pixels
+    pub fn with_origin<O: Into<Origin>>(mut self, origin: O) -> Self {
+        self.0.origin = origin.into();
+        self
+    }
+
+    /// Creates a sprite for the drawable with the passed in name, panics if there is no such drawable
+    pub fn from_name(drawables: &Drawables, name: &str) -> Self {
+        Self::try_from_name(drawables, name)
+            .unwrap_or_else(|| panic!("No drawable named \"{}\"", name))
+    }
+
+    pub fn try_from_name(drawables: &Drawables, name: &str) -> Option<Self> {
+        drawables.get_id(name).map(Sprite::new)
+    }
+
+    /// Creates a sprite along with a SpriteName so that `draw_sprites` keeps the drawable ID in sync with the name
+    pub fn named(drawables: &Drawables, name: &'static str) -> (Self, SpriteName) {
+        (Self::from_name(drawables, name), SpriteName(name))
+    }
 }
 
+/// Ties a Sprite to a drawable name, `draw_sprites` re-resolves the name every frame and skips
+/// drawing the sprite if the name no longer exists instead of issuing an invalid drawable ID
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SpriteName(pub &'static str);
+
+/// Looks up the drawable ID for a name, panicking with the name and call site if it doesn't exist
+#[macro_export]
+macro_rules! drawable_id {
+    ($drawables:expr, $name:expr) => {
+        $drawables.get_id($name).unwrap_or_else(|| match $drawables.conflict($name) {
+            Some(conflict) => panic!("No drawable named \"{}\" at {}:{}, {}", $name, file!(), line!(), conflict),
+            None => panic!("No drawable named \"{}\" at {}:{}", $name, file!(), line!()),
+        })
+    };
+}
+
+/// Every texture found in the assets directory. Textures are named by their path relative to the directory without
+/// the extension, e.g. `"player/idle"` for `assets/player/idle.png`, and also by their file stem (`"idle"`) as long as
+/// no other texture shares the stem, so that names from before textures were namespaced keep working. Stems that
+/// couldn't be given out are kept in `conflicts`.
 #[derive(Clone)]
 pub struct Drawables {
     pub alias: HashMap<&'static str, u64>,
     pub lookup: Vec<Texture>,
+    pub conflicts: Vec<AliasConflict>,
+}
+
+/// A name that more than one drawable wanted. `add_alias` leaves the name with `existing`, `build_aliases` only
+/// does when `existing` is the texture at the top of the assets directory and otherwise gives the name to neither.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct AliasConflict {
+    pub name: &'static str,
+    pub existing: u64,
+    pub rejected: u64,
 }
 
+impl std::fmt::Display for AliasConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "drawable name \"{}\" is wanted by both drawable {} and drawable {}", self.name, self.existing, self.rejected)
+    }
+}
+
+impl std::error::Error for AliasConflict {}
+
 impl Drawables {
     pub fn new(ctx: &mut Context) -> tetra::Result<Drawables> {
-        let mut alias = HashMap::<&'static str, u64>::new();
-        let mut lookup = vec![];
-
         let pngs = get_textures(ctx, "assets/")
             .expect("Couldn't find assets directory");
 
-        for (found, (key, value)) in pngs.into_iter().enumerate() {
-            alias.insert(key, found as u64);
-            lookup.push(value);
-        }
-
+        let keys: Vec<&'static str> = pngs.iter().map(|(key, _)| *key).collect();
+        let (alias, conflicts) = build_aliases(&keys);
+        report_conflicts(&keys, &conflicts);
         Ok(Drawables {
             alias,
-            lookup,
+            lookup: pngs.into_iter().map(|(_, texture)| texture).collect(),
+            conflicts,
         })
     }
+
+    /// Returns the drawable ID for the name, if there is one
+    pub fn get_id(&self, name: &str) -> Option<u64> {
+        self.alias.get(name).copied()
+    }
+
+    /// Returns why the name wasn't given out when the textures were found, if it was wanted by more than one
+    pub fn conflict(&self, name: &str) -> Option<&AliasConflict> {
+        self.conflicts.iter().find(|conflict| conflict.name == name)
+    }
+
+    pub fn is_valid_id(&self, id: u64) -> bool {
+        (id as usize) < self.lookup.len()
+    }
+
+    /// Gives the drawable another name. Fails if the name already belongs to a different drawable,
+    /// giving a drawable a name it already has is fine.
+    pub fn add_alias(&mut self, name: &'static str, id: u64) -> Result<(), AliasConflict> {
+        match self.alias.get(name) {
+            Some(existing) if *existing != id => Err(AliasConflict {
+                name,
+                existing: *existing,
+                rejected: id,
+            }),
+            _ => {
+                self.alias.insert(name, id);
+                Ok(())
+            },
+        }
+    }
+}
+
+/// Names every key by its index along with the last segment of its path, leaving out last segments that more than
+/// one key shares and returning a conflict for each key after the first that wanted one. A texture at the top of the
+/// assets directory keeps its name over nested textures with the same stem. Keys are expected to be unique, as the
+/// relative paths from `get_texture_paths` are.
+pub(crate) fn build_aliases(keys: &[&'static str]) -> (HashMap<&'static str, u64>, Vec<AliasConflict>) {
+    let mut alias = HashMap::new();
+    let mut short: HashMap<&'static str, Vec<u64>> = HashMap::new();
+
+    for (id, key) in keys.iter().enumerate() {
+        alias.insert(*key, id as u64);
+        if let Some((_, stem)) = key.rsplit_once('/') {
+            short.entry(stem).or_default().push(id as u64);
+        }
+    }
+
+    let mut conflicts = vec![];
+    for (stem, ids) in short.into_iter() {
+        let (existing, rejected) = match alias.get(stem) {
+            Some(top) => (*top, &ids[..]),
+            None if ids.len() > 1 => (ids[0], &ids[1..]),
+            None => {
+                alias.insert(stem, ids[0]);
+                continue;
+            },
+        };
+
+        conflicts.extend(rejected.iter().map(|id| AliasConflict {
+            name: stem,
+            existing,
+            rejected: *id,
+        }));
+    }
+
+    // HashMap order isn't stable between runs
+    conflicts.sort_by_key(|conflict| (conflict.name, conflict.rejected));
+    (alias, conflicts)
+}
+
+/// Logs every name that couldn't be given out along with the textures that wanted it
+pub(crate) fn report_conflicts(keys: &[&'static str], conflicts: &[AliasConflict]) {
+    for conflict in conflicts.iter() {
+        log::warn!(
+            target: logging::ASSETS,
+            "Texture name \"{}\" is ambiguous between \"{}\" and \"{}\", use the full path",
+            conflict.name,
+            keys[conflict.existing as usize],
+            keys[conflict.rejected as usize],
+        );
+    }
 }
 
 pub fn get_textures<P: AsRef<Path>>(ctx: &mut Context, dir: P) -> tetra::Result<Vec<(&'static str, Texture)>> {
+    let mut found = vec![];
+
+    for (key, path) in get_texture_paths(dir).into_iter() {
+        found.push((key, Texture::new(ctx, path)?));
+    }
+
+    Ok(found)
+}
+
+/// Recursively finds all png files in the directory, keyed by their path relative to the directory
+/// without the extension and with `/` between directories, e.g. `"player/idle"`. Sorted by key.
+pub fn get_texture_paths<P: AsRef<Path>>(dir: P) -> Vec<(&'static str, PathBuf)> {
+    fn to_str(string: String) -> &'static str {
+        Box::leak(string.into_boxed_str())
+    }
+
+    let mut found: Vec<(String, PathBuf)> = vec![];
+    find_texture_paths(dir.as_ref(), "", &mut found);
+    found.sort_by(|a, b| a.0.cmp(&b.0));
+
+    found.into_iter()
+        .map(|(key, path)| (to_str(key), path))
+        .collect()
+}
+
+fn find_texture_paths(dir: &Path, prefix: &str, found: &mut Vec<(String, PathBuf)>) {
     use std::fs::read_dir;
 
-    let mut found = vec![];
+    let entries = match read_dir(dir) {
+        Ok(entries) => entries,
+        Err(error) => {
+            log::error!(target: logging::ASSETS, "Couldn't read texture directory {:?}: {}", dir, error);
+            return;
+        },
+    };
+
+    for file in entries {
+        let (file, file_type) = match file.and_then(|file| file.file_type().map(|file_type| (file, file_type))) {
+            Ok(entry) => entry,
+            Err(error) => {
+                log::warn!(target: logging::ASSETS, "Skipped an entry in {:?}: {}", dir, error);
+                continue;
+            },
+        };
 
-    for file in read_dir(dir).unwrap() {
-        let file = file.unwrap();
-        if file.file_type().unwrap().is_file() {
-            let path = file.path();
+        let path = file.path();
+        if file_type.is_file() {
             if let Some(ext) = path.extension() {
                 if ext == "png" {
                     if let Some(stem) = path.file_stem() {
-                        let stem = stem.to_string_lossy().into_owned();
-                        let texture = Texture::new(ctx, path);
-
-                        fn to_str(string: String) -> &'static str {
-                            Box::leak(string.into_boxed_str())
-                        }
-                        found.push((to_str(stem), texture?));
+                        found.push((format!("{}{}", prefix, stem.to_string_lossy()), path));
                     }
                 }
             }
         } else {
-            found.append(&mut get_textures(ctx, file.path())?);
+            let name = file.file_name().to_string_lossy().into_owned();
+            find_texture_paths(&path, &format!("{}{}/", prefix, name), found);
         }
     }
+}
 
-    Ok(found)
-}
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nested_textures_keep_unique_stems() {
+        let keys = ["background", "enemies/bat", "player/run"];
+        let (alias, conflicts) = build_aliases(&keys);
+
+        assert!(conflicts.is_empty());
+        assert_eq!(alias.get("enemies/bat"), Some(&1));
+        assert_eq!(alias.get("bat"), Some(&1));
+        assert_eq!(alias.get("run"), Some(&2));
+        assert_eq!(alias.get("background"), Some(&0));
+        assert_eq!(alias.len(), 5);
+    }
+
+    #[test]
+    fn shared_stems_go_to_nobody() {
+        let keys = ["enemies/bat/idle", "enemies/slime/idle", "player/idle", "player/run"];
+        let (alias, conflicts) = build_aliases(&keys);
+
+        assert_eq!(alias.get("idle"), None);
+        assert_eq!(alias.get("player/idle"), Some(&2));
+        assert_eq!(alias.get("run"), Some(&3));
+        assert_eq!(conflicts, vec![
+            AliasConflict { name: "idle", existing: 0, rejected: 1 },
+            AliasConflict { name: "idle", existing: 0, rejected: 2 },
+        ]);
+    }
+
+    #[test]
+    fn top_level_textures_keep_their_name() {
+        let keys = ["idle", "player/idle"];
+        let (alias, conflicts) = build_aliases(&keys);
+
+        assert_eq!(alias.get("idle"), Some(&0));
+        assert_eq!(alias.get("player/idle"), Some(&1));
+        assert_eq!(conflicts, vec![AliasConflict { name: "idle", existing: 0, rejected: 1 }]);
+    }
+}
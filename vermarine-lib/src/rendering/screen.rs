@@ -0,0 +1,179 @@
+use tetra::{
+    graphics::{
+        self,
+        Color,
+        DrawParams,
+        Drawable,
+        Rectangle,
+        mesh::{
+            Mesh,
+            Vertex,
+            VertexBuffer,
+        },
+    },
+    math::{
+        Mat4,
+        Vec2,
+        Vec3,
+    },
+    window,
+    Context,
+};
+use shipyard::*;
+use super::draw_buffer::DrawBuffer;
+
+/// The color the screen is cleared to at the start of each frame
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ClearColor(pub Color);
+
+impl Default for ClearColor {
+    fn default() -> Self {
+        ClearColor(Color::rgb(0.392, 0.584, 0.929))
+    }
+}
+
+/// When a virtual resolution is set the game is scaled to fit the window while keeping its
+/// aspect ratio, and the space left over on either side is covered with bars. Everything is drawn
+/// as if the window were the virtual size, so the Camera's viewport should be set to it too.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Letterbox {
+    pub virtual_size: Option<Vec2<f32>>,
+    pub bar_color: Color,
+}
+
+impl Default for Letterbox {
+    fn default() -> Self {
+        Letterbox {
+            virtual_size: None,
+            bar_color: Color::BLACK,
+        }
+    }
+}
+
+impl Letterbox {
+    pub fn new(virtual_width: f32, virtual_height: f32) -> Self {
+        Letterbox {
+            virtual_size: Some(Vec2::new(virtual_width, virtual_height)),
+            ..Self::default()
+        }
+    }
+
+    /// Sets the color of the bars.
+    pub fn with_bar_color(mut self, color: Color) -> Self {
+        self.bar_color = color;
+        self
+    }
+
+    /// The area of the window the game is drawn to, the whole window if there's no virtual resolution
+    pub fn viewport(&self, window_size: Vec2<f32>) -> Rectangle {
+        let virtual_size = match self.virtual_size {
+            Some(size) => size,
+            None => return Rectangle::new(0.0, 0.0, window_size.x, window_size.y),
+        };
+
+        let scale = f32::min(window_size.x / virtual_size.x, window_size.y / virtual_size.y);
+        let size = virtual_size * scale;
+        let offset = (window_size - size) / 2.0;
+
+        Rectangle::new(offset.x, offset.y, size.x, size.y)
+    }
+
+    /// The scale from the virtual resolution to the window, 1.0 if there's no virtual resolution
+    pub fn scale(&self, window_size: Vec2<f32>) -> f32 {
+        match self.virtual_size {
+            Some(size) => self.viewport(window_size).width / size.x,
+            None => 1.0,
+        }
+    }
+
+    /// Scales and offsets from the virtual resolution into the viewport
+    pub fn transform(&self, window_size: Vec2<f32>) -> Mat4<f32> {
+        let viewport = self.viewport(window_size);
+        let scale = self.scale(window_size);
+        Mat4::translation_2d(Vec2::new(viewport.x, viewport.y)) * Mat4::scaling_3d(Vec3::new(scale, scale, 1.0))
+    }
+
+    /// Converts a position in the window, e.g. the mouse position, to the virtual resolution
+    pub fn window_to_virtual(&self, window_size: Vec2<f32>, position: Vec2<f32>) -> Vec2<f32> {
+        let viewport = self.viewport(window_size);
+        (position - Vec2::new(viewport.x, viewport.y)) / self.scale(window_size)
+    }
+
+    /// The areas of the window outside of the viewport
+    pub fn bars(&self, window_size: Vec2<f32>) -> Vec<Rectangle> {
+        let viewport = self.viewport(window_size);
+        let mut bars = vec![];
+
+        if viewport.x > 0.0 {
+            bars.push(Rectangle::new(0.0, 0.0, viewport.x, window_size.y));
+            bars.push(Rectangle::new(viewport.x + viewport.width, 0.0, window_size.x - viewport.x - viewport.width, window_size.y));
+        }
+        if viewport.y > 0.0 {
+            bars.push(Rectangle::new(0.0, 0.0, window_size.x, viewport.y));
+            bars.push(Rectangle::new(0.0, viewport.y + viewport.height, window_size.x, window_size.y - viewport.y - viewport.height));
+        }
+
+        bars
+    }
+}
+
+fn window_size(ctx: &Context) -> Vec2<f32> {
+    Vec2::new(window::get_width(ctx) as f32, window::get_height(ctx) as f32)
+}
+
+/// Clears the screen to the ClearColor, run this before flushing the DrawBuffer
+pub fn clear_screen(ctx: &mut Context, clear_color: UniqueView<ClearColor>) {
+    graphics::clear(ctx, clear_color.0);
+}
+
+/// Sets the DrawBuffer's viewport from the Letterbox, run this before flushing the DrawBuffer
+pub fn apply_letterbox(ctx: &mut Context, letterbox: UniqueView<Letterbox>, mut draw_buffer: UniqueViewMut<DrawBuffer>) {
+    draw_buffer.viewport = letterbox.transform(window_size(ctx));
+}
+
+/// Covers the areas outside of the letterbox viewport, run this after flushing the DrawBuffer
+pub fn draw_letterbox(ctx: &mut Context, letterbox: UniqueView<Letterbox>) {
+    let bars = letterbox.bars(window_size(ctx));
+    if bars.is_empty() {
+        return;
+    }
+
+    let mut vertices = vec![];
+    for bar in bars.iter() {
+        let (x1, y1) = (bar.x, bar.y);
+        let (x2, y2) = (bar.x + bar.width, bar.y + bar.height);
+        for (x, y) in [(x1, y1), (x2, y1), (x2, y2), (x1, y1), (x2, y2), (x1, y2)].iter() {
+            vertices.push(Vertex::new(Vec2::new(*x, *y), Vec2::zero(), letterbox.bar_color));
+        }
+    }
+
+    // Bars are in window space regardless of the camera
+    graphics::set_transform_matrix(ctx, Mat4::identity());
+    let vertex_buffer = VertexBuffer::new(ctx, &vertices)
+        .expect("Failed to create vertex buffer for letterbox bars");
+    Mesh::new(vertex_buffer).draw(ctx, DrawParams::new());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn letterbox_maps_between_window_and_virtual() {
+        let letterbox = Letterbox::new(320.0, 180.0);
+        let window = Vec2::new(1280.0, 800.0);
+
+        // 4x scale with 40 pixel bars above and below
+        assert_eq!(letterbox.viewport(window), Rectangle::new(0.0, 40.0, 1280.0, 720.0));
+        assert_eq!(letterbox.scale(window), 4.0);
+
+        let drawn = letterbox.transform(window) * tetra::math::Vec4::new(10.0, 10.0, 0.0, 1.0);
+        assert_eq!(Vec2::new(drawn.x, drawn.y), Vec2::new(40.0, 80.0));
+        assert_eq!(letterbox.window_to_virtual(window, Vec2::new(40.0, 80.0)), Vec2::new(10.0, 10.0));
+
+        // Without a virtual resolution nothing changes
+        let none = Letterbox::default();
+        assert_eq!(none.transform(window), Mat4::identity());
+        assert_eq!(none.window_to_virtual(window, Vec2::new(40.0, 80.0)), Vec2::new(40.0, 80.0));
+    }
+}
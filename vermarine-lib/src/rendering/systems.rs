@@ -10,18 +10,70 @@ use crate::{
     components::{
         Transform,
     },
+    logging,
+    pool::Inactive,
+    hexmap::{
+        fog::FogHidden,
+        render::HexPosition,
+    },
     rendering::{
         Sprite,
+        SpriteName,
+        Drawables,
         draw_buffer::{
             DrawBuffer,
-        }
+        },
+        tint::{
+            Flash,
+            Tint,
+            apply_tint,
+        },
     },
 };
 
-/// Adds commands to DrawBuffer for all Sprite components
-pub fn draw_sprites(sprites: View<Sprite>, mut draw_buffer: UniqueViewMut<DrawBuffer>, transforms: View<Transform>) {
-    for (transform, sprite) in (&transforms, &sprites).iter() {
+/// Adds commands to DrawBuffer for all Sprite components, except those hidden by fog of war or waiting in a pool.
+/// The entity's Tint and Flash are applied to the command's color.
+#[allow(clippy::too_many_arguments)]
+pub fn draw_sprites(
+    sprites: View<Sprite>, 
+    names: View<SpriteName>, 
+    tints: View<Tint>,
+    flashes: View<Flash>,
+    hex_positions: View<HexPosition>,
+    hidden: View<FogHidden>,
+    inactive: View<Inactive>,
+    mut draw_buffer: UniqueViewMut<DrawBuffer>, 
+    transforms: View<Transform>, 
+    drawables: NonSendSync<UniqueView<Drawables>>,
+) {
+    for (id, (transform, sprite)) in (&transforms, &sprites).iter().with_id() {
+        // Drawn by `draw_hex_sprites` instead
+        if hex_positions.get(id).is_ok() {
+            continue;
+        }
+
+        if hidden.get(id).is_ok() || inactive.get(id).is_ok() {
+            continue;
+        }
+
         let mut command = sprite.0;
+
+        // Named sprites follow their name, if it has gone stale the sprite is skipped rather than drawing the wrong texture
+        if let Ok(name) = names.get(id) {
+            match drawables.get_id(name.0) {
+                Some(drawable) => command.drawable = drawable,
+                None => {
+                    log::trace!(target: logging::RENDER, "Skipped sprite of {:?}, no drawable named {:?}", id, name.0);
+                    continue;
+                },
+            }
+        }
+
+        if command.sequence.is_none() {
+            command.sequence = Some(id.uindex() as u64);
+        }
+
+        apply_tint(&mut command, tints.get(id).ok(), flashes.get(id).ok());
         command.position += Vec3::new(transform.x as f32, transform.y as f32, 0.0);
         draw_buffer.draw(command);
     }
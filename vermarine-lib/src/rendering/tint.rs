@@ -0,0 +1,181 @@
+use shipyard::*;
+use tetra::graphics::Color;
+use crate::time::Time;
+use super::draw_buffer::DrawCommand;
+
+/// Multiplies the color of an entity's Sprite when it is drawn, so the Sprite's own color never has to be changed and restored
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Tint {
+    pub color: Color,
+    /// Multiplied with the color's alpha. Defaults to 1.0.
+    pub alpha: f32,
+}
+
+impl Default for Tint {
+    fn default() -> Self {
+        Tint::new(Color::WHITE)
+    }
+}
+
+impl Tint {
+    pub fn new(color: Color) -> Self {
+        Tint {
+            color,
+            alpha: 1.0,
+        }
+    }
+
+    /// A tint that only fades the sprite
+    pub fn faded(alpha: f32) -> Self {
+        Tint::new(Color::WHITE).with_alpha(alpha)
+    }
+
+    /// Sets the alpha.
+    pub fn with_alpha(mut self, alpha: f32) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    pub fn apply(&self, color: Color) -> Color {
+        Color::rgba(
+            color.r * self.color.r,
+            color.g * self.color.g,
+            color.b * self.color.b,
+            color.a * self.color.a * self.alpha,
+        )
+    }
+}
+
+/// Blends the color an entity's Sprite is drawn with towards a color, fading back over the duration, e.g. flashing
+/// red when hit. The component stays on the entity once the flash is over so that `start` can flash it again.
+///
+/// Tetra multiplies the texture by the draw color, so a flash can only darken or tint a sprite, never brighten it.
+/// Flashing a sprite drawn with `Color::WHITE` towards white does nothing, use a separate white sprite drawn over
+/// it for that instead.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Flash {
+    pub color: Color,
+    /// How long the flash takes to fade out, in seconds
+    pub duration: f32,
+    remaining: f32,
+}
+
+impl Flash {
+    /// Creates a flash that starts straight away
+    pub fn new(color: Color, duration: f32) -> Self {
+        Flash {
+            color,
+            duration,
+            remaining: duration,
+        }
+    }
+
+    /// Flashes again from full strength
+    pub fn start(&mut self) {
+        self.remaining = self.duration;
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.remaining > 0.0
+    }
+
+    /// How far towards the flash color the sprite is blended, from 1.0 at the start to 0.0 when over
+    pub fn strength(&self) -> f32 {
+        if self.duration <= 0.0 {
+            return 0.0;
+        }
+        (self.remaining / self.duration).max(0.0).min(1.0)
+    }
+
+    /// Blends the rgb towards the flash color, the alpha is left alone so faded sprites stay faded
+    pub fn apply(&self, color: Color) -> Color {
+        let t = self.strength();
+        let lerp = |from: f32, to: f32| from + (to - from) * t;
+        Color::rgba(lerp(color.r, self.color.r), lerp(color.g, self.color.g), lerp(color.b, self.color.b), color.a)
+    }
+}
+
+/// Counts down every Flash with the scaled delta time so that flashes freeze while paused.
+/// Not part of the rendering systems as they don't require Time, run it with the gameplay systems.
+pub fn update_flashes(time: UniqueView<Time>, mut flashes: ViewMut<Flash>) {
+    let delta = time.delta() as f32;
+    for flash in (&mut flashes).iter() {
+        if flash.remaining > 0.0 {
+            flash.remaining -= delta;
+        }
+    }
+}
+
+/// Applies the entity's Tint then its Flash to a command taken from its Sprite
+pub(crate) fn apply_tint(command: &mut DrawCommand, tint: Option<&Tint>, flash: Option<&Flash>) {
+    if let Some(tint) = tint {
+        command.color = tint.apply(command.color);
+    }
+
+    if let Some(flash) = flash {
+        command.color = flash.apply(command.color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tint_multiplies_the_color() {
+        let tint = Tint::new(Color::rgba(0.5, 1.0, 0.25, 0.5)).with_alpha(0.5);
+        assert_eq!(tint.apply(Color::rgba(1.0, 0.5, 1.0, 1.0)), Color::rgba(0.5, 0.5, 0.25, 0.25));
+        assert_eq!(Tint::default().apply(Color::rgb(0.2, 0.4, 0.6)), Color::rgb(0.2, 0.4, 0.6));
+        assert_eq!(Tint::faded(0.25).apply(Color::WHITE), Color::rgba(1.0, 1.0, 1.0, 0.25));
+    }
+
+    #[test]
+    fn flash_fades_out() {
+        let mut flash = Flash::new(Color::RED, 1.0);
+        assert!(flash.is_active());
+        assert_eq!(flash.strength(), 1.0);
+        assert_eq!(flash.apply(Color::rgba(1.0, 1.0, 1.0, 0.5)), Color::rgba(1.0, 0.0, 0.0, 0.5));
+
+        flash.remaining = 0.5;
+        assert_eq!(flash.strength(), 0.5);
+        assert_eq!(flash.apply(Color::WHITE), Color::rgb(1.0, 0.5, 0.5));
+
+        flash.remaining = -0.1;
+        assert!(!flash.is_active());
+        assert_eq!(flash.strength(), 0.0);
+        assert_eq!(flash.apply(Color::WHITE), Color::WHITE);
+
+        flash.start();
+        assert_eq!(flash.strength(), 1.0);
+        assert_eq!(Flash::new(Color::RED, 0.0).strength(), 0.0);
+    }
+
+    #[test]
+    fn flashes_count_down_with_scaled_time() {
+        let world = World::new();
+        world.add_unique(Time::new());
+        let entity = world.run(|mut entities: EntitiesViewMut, mut flashes: ViewMut<Flash>| {
+            entities.add_entity(&mut flashes, Flash::new(Color::RED, 1.0))
+        });
+        let strength = |world: &World| world.run(|flashes: View<Flash>| flashes.get(entity).unwrap().strength());
+
+        world.run(|mut time: UniqueViewMut<Time>| time.update(0.25));
+        world.run(update_flashes);
+        assert_eq!(strength(&world), 0.75);
+
+        // Nothing changes while paused
+        world.run(|mut time: UniqueViewMut<Time>| {
+            time.time_scale = 0.0;
+            time.update(0.25);
+        });
+        world.run(update_flashes);
+        assert_eq!(strength(&world), 0.75);
+
+        world.run(|mut time: UniqueViewMut<Time>| {
+            time.time_scale = 1.0;
+            time.update(1.0);
+        });
+        world.run(update_flashes);
+        assert_eq!(strength(&world), 0.0);
+    }
+}
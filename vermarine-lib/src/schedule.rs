@@ -0,0 +1,276 @@
+use shipyard::*;
+use crate::pause::Paused;
+
+/// The phases of a frame, workloads in an earlier stage always run before workloads in a later one
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Stage {
+    Input,
+    FixedUpdate,
+    Physics,
+    LateUpdate,
+    Rendering,
+}
+
+impl Stage {
+    /// Whether workloads in the stage are skipped by `Schedule::run_pausable` while paused
+    pub fn pauses(&self) -> bool {
+        match self {
+            Stage::FixedUpdate | Stage::Physics => true,
+            Stage::Input | Stage::LateUpdate | Stage::Rendering => false,
+        }
+    }
+}
+
+/// Declares the order workloads are run in, instead of it being implied by the order of the `run_workload` calls.
+///
+/// ```ignore
+/// let schedule = ScheduleBuilder::new()
+///     .workload("Physics", Stage::Physics)
+///     .workload("Rendering", Stage::Rendering)
+///     .workload("Enemies", Stage::FixedUpdate)
+///     .workload("Player", Stage::FixedUpdate)
+///     .before("Player", "Enemies")
+///     .build();
+///
+/// schedule.run(&world);
+/// ```
+#[derive(Default)]
+pub struct ScheduleBuilder {
+    workloads: Vec<(&'static str, Stage)>,
+    /// Pairs of workloads where the first has to run before the second
+    constraints: Vec<(&'static str, &'static str)>,
+}
+
+impl ScheduleBuilder {
+    pub fn new() -> Self {
+        ScheduleBuilder {
+            workloads: vec![],
+            constraints: vec![],
+        }
+    }
+
+    /// Adds a workload, workloads in the same stage without constraints run in the order they were added
+    pub fn workload(mut self, name: &'static str, stage: Stage) -> Self {
+        self.workloads.push((name, stage));
+        self
+    }
+
+    /// Makes `name` run before `other`
+    pub fn before(mut self, name: &'static str, other: &'static str) -> Self {
+        self.constraints.push((name, other));
+        self
+    }
+
+    /// Makes `name` run after `other`
+    pub fn after(mut self, name: &'static str, other: &'static str) -> Self {
+        self.constraints.push((other, name));
+        self
+    }
+
+    /// Orders the workloads. Panics if a constraint names a workload that wasn't added,
+    /// goes against the stage order, or if the constraints form a cycle.
+    pub fn build(self) -> Schedule {
+        let index_of = |name: &str| {
+            self.workloads.iter().position(|(workload, _)| *workload == name)
+                .unwrap_or_else(|| panic!("Schedule constraint names unknown workload \"{}\"", name))
+        };
+
+        let mut successors = vec![vec![]; self.workloads.len()];
+        let mut incoming = vec![0; self.workloads.len()];
+        for (first, then) in self.constraints.iter() {
+            let (first, then) = (index_of(first), index_of(then));
+            if self.workloads[first].1 > self.workloads[then].1 {
+                panic!(
+                    "Workload \"{}\" can't run before \"{}\" as it is in a later stage",
+                    self.workloads[first].0, self.workloads[then].0,
+                );
+            }
+
+            successors[first].push(then);
+            incoming[then] += 1;
+        }
+
+        // Always taking the ready workload from the earliest stage keeps the stages in order,
+        // as constraints never point from a later stage to an earlier one
+        let mut ready: Vec<usize> = (0..self.workloads.len()).filter(|index| incoming[*index] == 0).collect();
+        let mut order = vec![];
+        while let Some(position) = (0..ready.len()).min_by_key(|position| (self.workloads[ready[*position]].1, ready[*position])) {
+            let index = ready.swap_remove(position);
+            order.push(self.workloads[index]);
+
+            for next in successors[index].iter() {
+                incoming[*next] -= 1;
+                if incoming[*next] == 0 {
+                    ready.push(*next);
+                }
+            }
+        }
+
+        if order.len() != self.workloads.len() {
+            let cycle: Vec<&str> = (0..self.workloads.len())
+                .filter(|index| incoming[*index] > 0)
+                .map(|index| self.workloads[index].0)
+                .collect();
+            panic!("Schedule constraints form a cycle between {:?}", cycle);
+        }
+
+        Schedule {
+            order,
+        }
+    }
+}
+
+/// The workloads in the order they run, built by a ScheduleBuilder
+#[derive(Clone, Debug)]
+pub struct Schedule {
+    order: Vec<(&'static str, Stage)>,
+}
+
+impl Schedule {
+    /// The names of the workloads in the order they run
+    pub fn order(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.order.iter().map(|(name, _)| *name)
+    }
+
+    /// Runs every workload in order
+    pub fn run(&self, world: &World) {
+        for (name, _) in self.order.iter() {
+            world.run_workload(name);
+        }
+    }
+
+    /// Runs every workload in order, skipping the stages that pause while the Paused unique is paused.
+    /// Panics if Paused hasn't been added.
+    pub fn run_pausable(&self, world: &World) {
+        let paused = world.borrow::<UniqueView<Paused>>().is_paused();
+        for (name, stage) in self.order.iter() {
+            if !(paused && stage.pauses()) {
+                world.run_workload(name);
+            }
+        }
+    }
+
+    /// Runs the workloads of a single stage in order, e.g. to run `Stage::FixedUpdate` several times in a frame
+    pub fn run_stage(&self, world: &World, stage: Stage) {
+        for (name, _) in self.order.iter().filter(|(_, workload_stage)| *workload_stage == stage) {
+            world.run_workload(name);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::Time;
+
+    fn order(schedule: &Schedule) -> Vec<&'static str> {
+        schedule.order().collect()
+    }
+
+    #[test]
+    fn stages_run_in_order() {
+        let schedule = ScheduleBuilder::new()
+            .workload("Rendering", Stage::Rendering)
+            .workload("Enemies", Stage::FixedUpdate)
+            .workload("Input", Stage::Input)
+            .workload("Player", Stage::FixedUpdate)
+            .workload("Physics", Stage::Physics)
+            .build();
+
+        // Workloads in the same stage keep the order they were added in
+        assert_eq!(order(&schedule), vec!["Input", "Enemies", "Player", "Physics", "Rendering"]);
+    }
+
+    #[test]
+    fn constraints_reorder_within_a_stage() {
+        let schedule = ScheduleBuilder::new()
+            .workload("Enemies", Stage::FixedUpdate)
+            .workload("Projectiles", Stage::FixedUpdate)
+            .workload("Player", Stage::FixedUpdate)
+            .before("Player", "Enemies")
+            .after("Enemies", "Projectiles")
+            .build();
+
+        assert_eq!(order(&schedule), vec!["Player", "Projectiles", "Enemies"]);
+
+        // Constraints that agree with the stage order are allowed
+        let schedule = ScheduleBuilder::new()
+            .workload("Rendering", Stage::Rendering)
+            .workload("Input", Stage::Input)
+            .before("Input", "Rendering")
+            .build();
+        assert_eq!(order(&schedule), vec!["Input", "Rendering"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "unknown workload \"Missing\"")]
+    fn unknown_workloads_panic() {
+        ScheduleBuilder::new()
+            .workload("Player", Stage::FixedUpdate)
+            .before("Player", "Missing")
+            .build();
+    }
+
+    #[test]
+    #[should_panic(expected = "later stage")]
+    fn constraints_against_the_stage_order_panic() {
+        ScheduleBuilder::new()
+            .workload("Rendering", Stage::Rendering)
+            .workload("Physics", Stage::Physics)
+            .before("Rendering", "Physics")
+            .build();
+    }
+
+    #[test]
+    #[should_panic(expected = "cycle")]
+    fn cycles_panic() {
+        ScheduleBuilder::new()
+            .workload("A", Stage::FixedUpdate)
+            .workload("B", Stage::FixedUpdate)
+            .workload("C", Stage::FixedUpdate)
+            .before("A", "B")
+            .before("B", "C")
+            .before("C", "A")
+            .build();
+    }
+
+    fn log_physics(mut log: UniqueViewMut<Vec<&'static str>>) {
+        log.push("Physics");
+    }
+
+    fn log_rendering(mut log: UniqueViewMut<Vec<&'static str>>) {
+        log.push("Rendering");
+    }
+
+    #[test]
+    fn run_pausable_skips_paused_stages() {
+        let mut world = World::new();
+        world.add_unique(Vec::<&'static str>::new());
+        world.add_unique(Paused::new());
+        world.add_unique(Time::new());
+
+        world
+            .add_workload("Physics")
+            .with_system(system!(log_physics))
+            .build();
+        world
+            .add_workload("Rendering")
+            .with_system(system!(log_rendering))
+            .build();
+
+        let schedule = ScheduleBuilder::new()
+            .workload("Rendering", Stage::Rendering)
+            .workload("Physics", Stage::Physics)
+            .build();
+
+        schedule.run_pausable(&world);
+        world.run(|mut paused: UniqueViewMut<Paused>, mut time: UniqueViewMut<Time>| {
+            paused.pause(&mut time);
+        });
+        schedule.run_pausable(&world);
+        schedule.run_stage(&world, Stage::Physics);
+
+        let log = world.borrow::<UniqueView<Vec<&'static str>>>();
+        assert_eq!(*log, vec!["Physics", "Rendering", "Rendering", "Physics"]);
+    }
+}
@@ -0,0 +1,128 @@
+use shipyard::*;
+use tetra::{
+    input::{
+        self,
+        Key,
+    },
+    Context,
+    Event,
+};
+
+/// Text typed by the player for name entry and chat. While capturing, `handle_event` takes tetra's text input
+/// events (which include IME composition results) and the editing keys, so input systems that poll the keyboard
+/// should skip keys that `blocks_key` returns true for. Enter submits the text and Escape cancels, both end the capture.
+#[derive(Clone, Debug, Default)]
+pub struct TextInputBuffer {
+    text: String,
+    capturing: bool,
+    submitted: Option<String>,
+    /// The key that ended the last capture, until another key is pressed
+    ended_by: Option<Key>,
+
+    /// The most characters the text can hold. Defaults to `None`.
+    pub max_chars: Option<usize>,
+}
+
+impl TextInputBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the most characters the text can hold.
+    pub fn with_max_chars(mut self, max_chars: usize) -> Self {
+        self.max_chars = Some(max_chars);
+        self
+    }
+
+    /// Starts capturing with the text already filled in, e.g. the player's current name
+    pub fn begin_capture(&mut self, initial: &str) {
+        self.text.clear();
+        self.capturing = true;
+        self.submitted = None;
+        self.ended_by = None;
+        self.push_str(initial);
+    }
+
+    /// Stops capturing without submitting, returning the text typed so far
+    pub fn end_capture(&mut self) -> String {
+        self.capturing = false;
+        std::mem::take(&mut self.text)
+    }
+
+    pub fn is_capturing(&self) -> bool {
+        self.capturing
+    }
+
+    /// Whether input systems polling the keyboard should ignore the key: every key while capturing, and the key
+    /// that ended the capture on the frame it was pressed, as tetra still reports it as pressed to everything else.
+    /// Without this, pressing Escape to cancel typing would also e.g. toggle `Paused`.
+    pub fn blocks_key(&self, ctx: &Context, key: Key) -> bool {
+        self.capturing || (self.ended_by == Some(key) && input::is_key_pressed(ctx, key))
+    }
+
+    /// The text typed since the capture began
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Takes the text submitted with Enter, if any
+    pub fn take_submitted(&mut self) -> Option<String> {
+        self.submitted.take()
+    }
+
+    /// Appends the text, dropping control characters and anything past `max_chars`
+    pub fn push_str(&mut self, text: &str) {
+        let room = self.max_chars.map_or(usize::MAX, |max| max.saturating_sub(self.text.chars().count()));
+        self.text.extend(text.chars().filter(|c| !c.is_control()).take(room));
+    }
+
+    /// Handles the event if capturing, returning whether it was used up
+    pub fn handle_event(&mut self, ctx: &Context, event: &Event) -> bool {
+        if !self.capturing {
+            // A new press of the key that ended the capture belongs to the game again
+            if let Event::KeyPressed { .. } = event {
+                self.ended_by = None;
+            }
+            return false;
+        }
+
+        match event {
+            Event::TextInput { text } => self.push_str(text),
+            Event::KeyPressed { key: Key::Backspace } => { self.text.pop(); },
+            Event::KeyPressed { key: key @ Key::Enter } | Event::KeyPressed { key: key @ Key::NumPadEnter } => {
+                self.submitted = Some(self.end_capture());
+                self.ended_by = Some(*key);
+            },
+            Event::KeyPressed { key: Key::Escape } => {
+                self.end_capture();
+                self.ended_by = Some(Key::Escape);
+            },
+            Event::KeyPressed { key: Key::V } if input::is_key_modifier_down(ctx, input::KeyModifier::Ctrl) => {
+                if let Ok(text) = input::get_clipboard_text(ctx) {
+                    self.push_str(&text);
+                }
+            },
+            Event::KeyPressed { .. } | Event::KeyReleased { .. } => {},
+            _ => return false,
+        }
+
+        true
+    }
+}
+
+/// Passes a tetra event to the World's TextInputBuffer, call from `State::event` or `PDAState::event`.
+/// Returns whether the buffer used the event up so the caller can skip its own key handling.
+pub fn forward_text_input(ctx: &Context, world: &World, event: &Event) -> bool {
+    world.run(|mut buffer: UniqueViewMut<TextInputBuffer>| buffer.handle_event(ctx, event))
+}
+
+/// Dummy trait to allow adding a method to World
+pub trait TextInputCreator {
+    fn add_text_input(&mut self);
+}
+
+impl TextInputCreator for World {
+    fn add_text_input(&mut self) {
+        self.add_unique(TextInputBuffer::new());
+    }
+}
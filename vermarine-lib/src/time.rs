@@ -0,0 +1,97 @@
+use shipyard::*;
+use tetra::{
+    time,
+    Context,
+};
+
+/// Frame timing for systems, updated once per tick by `Time::update_system`.
+/// Setting `time_scale` slows down, speeds up or pauses the systems that respect it, e.g. the physics systems.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Time {
+    /// Multiplier applied to the delta time, 0.0 pauses and 0.5 is half speed. Defaults to 1.0.
+    pub time_scale: f64,
+
+    delta: f64,
+    unscaled_delta: f64,
+    elapsed: f64,
+    unscaled_elapsed: f64,
+    frame_count: u64,
+}
+
+impl Default for Time {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Time {
+    pub fn new() -> Self {
+        Time {
+            time_scale: 1.0,
+
+            delta: 0.0,
+            unscaled_delta: 0.0,
+            elapsed: 0.0,
+            unscaled_elapsed: 0.0,
+            frame_count: 0,
+        }
+    }
+
+    /// Advances the clocks by the real time that passed since the last update, in seconds
+    pub fn update(&mut self, unscaled_delta: f64) {
+        self.unscaled_delta = unscaled_delta;
+        self.delta = unscaled_delta * self.time_scale.max(0.0);
+        self.unscaled_elapsed += self.unscaled_delta;
+        self.elapsed += self.delta;
+        self.frame_count += 1;
+    }
+
+    /// Seconds since the last update, multiplied by `time_scale`
+    pub fn delta(&self) -> f64 {
+        self.delta
+    }
+
+    /// Seconds since the last update, ignoring `time_scale`. Useful for UI that keeps animating while paused.
+    pub fn unscaled_delta(&self) -> f64 {
+        self.unscaled_delta
+    }
+
+    /// Total scaled seconds, doesn't advance while paused
+    pub fn elapsed(&self) -> f64 {
+        self.elapsed
+    }
+
+    pub fn unscaled_elapsed(&self) -> f64 {
+        self.unscaled_elapsed
+    }
+
+    /// The number of updates so far
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// The time scale clamped to be non negative, per tick systems multiply their movement by this
+    pub fn scale(&self) -> f64 {
+        self.time_scale.max(0.0)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.time_scale <= 0.0
+    }
+
+    pub fn update_system(ctx: &mut Context, mut time: UniqueViewMut<Time>) {
+        time.update(time::get_delta_time(ctx).as_secs_f64());
+    }
+}
+
+/// Dummy trait to allow adding a method to World
+pub trait TimeCreator {
+    fn add_time(&mut self);
+}
+
+impl TimeCreator for World {
+    /// The physics workload adds Time itself, so this is only needed without physics
+    fn add_time(&mut self) {
+        self.add_unique(Time::new());
+    }
+}
@@ -0,0 +1,221 @@
+use shipyard::*;
+
+/// An entity that takes turns. Every time nobody is ready to act each actor gains `speed` energy,
+/// and an actor may act once its energy reaches `TurnScheduler::energy_per_turn`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Actor {
+    pub speed: i32,
+    pub energy: i32,
+    /// Player controlled actors wait for `queue_action` to be called from input handling,
+    /// other actors are expected to queue their action from a system in the turn workload
+    pub player_controlled: bool,
+}
+
+impl Actor {
+    pub fn new(speed: i32) -> Self {
+        Actor {
+            speed,
+            energy: 0,
+            player_controlled: false,
+        }
+    }
+
+    pub fn player(speed: i32) -> Self {
+        Actor {
+            speed,
+            energy: 0,
+            player_controlled: true,
+        }
+    }
+}
+
+/// Tracks whose turn it is. The turn workload only needs to run when there's an action to resolve
+/// or a non-player actor to decide for, see `run_turn_workload`.
+pub struct TurnScheduler {
+    pub energy_per_turn: i32,
+    /// Counts every action taken
+    pub turn: u64,
+
+    current: Option<EntityId>,
+    current_is_player: bool,
+    queued_cost: Option<i32>,
+}
+
+impl TurnScheduler {
+    pub fn new(energy_per_turn: i32) -> Self {
+        TurnScheduler {
+            energy_per_turn,
+            turn: 0,
+
+            current: None,
+            current_is_player: false,
+            queued_cost: None,
+        }
+    }
+
+    /// The actor whose turn it is
+    pub fn current_actor(&self) -> Option<EntityId> {
+        self.current
+    }
+
+    /// True if it is a player controlled actor's turn and nothing has been queued yet
+    pub fn is_waiting_for_input(&self) -> bool {
+        self.current_is_player && self.queued_cost.is_none()
+    }
+
+    /// Ends the current actor's turn, spending cost energy. Usually called with `energy_per_turn`.
+    pub fn queue_action(&mut self, cost: i32) {
+        self.queued_cost = Some(cost);
+    }
+
+    pub fn should_run(&self) -> bool {
+        self.queued_cost.is_some() || !self.current_is_player
+    }
+
+    /// Spends the queued action's energy then hands out energy until an actor is ready.
+    /// Ties go to the actor with the most energy, then the lowest entity index.
+    pub fn advance_turns(mut actors: ViewMut<Actor>, mut scheduler: UniqueViewMut<TurnScheduler>) {
+        if let (Some(current), Some(cost)) = (scheduler.current, scheduler.queued_cost.take()) {
+            if let Ok(actor) = (&mut actors).get(current) {
+                actor.energy -= cost;
+            }
+            scheduler.turn += 1;
+        } else if scheduler.current_is_player {
+            return;
+        }
+
+        scheduler.current = None;
+        scheduler.current_is_player = false;
+
+        // Nobody can ever be ready if every actor has no speed
+        if !actors.iter().any(|actor| actor.speed > 0 || actor.energy >= scheduler.energy_per_turn) {
+            return;
+        }
+
+        loop {
+            let ready = actors.iter().with_id()
+                .filter(|(_, actor)| actor.energy >= scheduler.energy_per_turn)
+                .max_by(|(id1, a1), (id2, a2)| a1.energy.cmp(&a2.energy).then(id2.uindex().cmp(&id1.uindex())))
+                .map(|(id, actor)| (id, actor.player_controlled));
+
+            if let Some((id, player_controlled)) = ready {
+                scheduler.current = Some(id);
+                scheduler.current_is_player = player_controlled;
+                return;
+            }
+
+            for actor in (&mut actors).iter() {
+                actor.energy += actor.speed;
+            }
+        }
+    }
+}
+
+/// Runs the workload if the scheduler has something to resolve, returns true if it ran
+pub fn run_turn_workload(world: &World, workload: &str) -> bool {
+    let should_run = world.borrow::<UniqueView<TurnScheduler>>().should_run();
+    if should_run {
+        world.run_workload(workload);
+    }
+    should_run
+}
+
+/// Dummy trait to allow adding a method to World
+pub trait TurnSchedulerCreator {
+    fn add_turn_scheduler(&mut self, energy_per_turn: i32);
+}
+
+impl TurnSchedulerCreator for World {
+    fn add_turn_scheduler(&mut self, energy_per_turn: i32) {
+        self.add_unique(TurnScheduler::new(energy_per_turn));
+    }
+}
+
+/// Dummy trait to allow adding a method to WorkloadBuilder
+pub trait TurnWorkloadSystems<'a> {
+    fn with_turn_systems(self) -> WorkloadBuilder<'a>;
+}
+
+impl<'a> TurnWorkloadSystems<'a> for WorkloadBuilder<'a> {
+    /// Should be added after the systems that resolve actions
+    fn with_turn_systems(self) -> WorkloadBuilder<'a> {
+        self
+            .with_system(system!(TurnScheduler::advance_turns))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_world(speeds: &[(i32, bool)]) -> (World, Vec<EntityId>) {
+        let mut world = World::new();
+        world.add_turn_scheduler(10);
+
+        let ids = world.run(|mut entities: EntitiesViewMut, mut actors: ViewMut<Actor>| {
+            speeds
+                .iter()
+                .map(|(speed, player)| {
+                    let actor = if *player { Actor::player(*speed) } else { Actor::new(*speed) };
+                    entities.add_entity(&mut actors, actor)
+                })
+                .collect()
+        });
+        (world, ids)
+    }
+
+    /// Advances until an actor is ready, then ends its turn. Returns whose turn it was.
+    fn take_turn(world: &World) -> Option<EntityId> {
+        world.run(TurnScheduler::advance_turns);
+        let mut scheduler = world.borrow::<UniqueViewMut<TurnScheduler>>();
+        let current = scheduler.current_actor();
+        let cost = scheduler.energy_per_turn;
+        scheduler.queue_action(cost);
+        current
+    }
+
+    #[test]
+    fn faster_actors_act_more_often() {
+        let (world, ids) = new_world(&[(10, false), (5, false)]);
+        let (fast, slow) = (ids[0], ids[1]);
+
+        let order: Vec<_> = (0..6).map(|_| take_turn(&world).unwrap()).collect();
+        // The tie on the second turn goes to the lower entity index
+        assert_eq!(order, vec![fast, fast, slow, fast, fast, slow]);
+        assert_eq!(world.borrow::<UniqueView<TurnScheduler>>().turn, 5);
+    }
+
+    #[test]
+    fn waits_for_the_player() {
+        let (world, ids) = new_world(&[(10, true), (5, false)]);
+
+        world.run(TurnScheduler::advance_turns);
+        {
+            let scheduler = world.borrow::<UniqueView<TurnScheduler>>();
+            assert_eq!(scheduler.current_actor(), Some(ids[0]));
+            assert!(scheduler.is_waiting_for_input());
+            assert!(!scheduler.should_run());
+        }
+
+        // Nothing happens until the player acts
+        world.run(TurnScheduler::advance_turns);
+        assert_eq!(world.borrow::<UniqueView<TurnScheduler>>().current_actor(), Some(ids[0]));
+        assert_eq!(world.borrow::<UniqueView<TurnScheduler>>().turn, 0);
+
+        world.borrow::<UniqueViewMut<TurnScheduler>>().queue_action(10);
+        assert!(world.borrow::<UniqueView<TurnScheduler>>().should_run());
+        world.run(TurnScheduler::advance_turns);
+
+        // Both actors reach 10 energy together and the tie goes to the player again
+        let scheduler = world.borrow::<UniqueView<TurnScheduler>>();
+        assert_eq!(scheduler.turn, 1);
+        assert_eq!(scheduler.current_actor(), Some(ids[0]));
+        assert!(scheduler.is_waiting_for_input());
+    }
+
+    #[test]
+    fn actors_without_speed_never_act() {
+        let (world, _) = new_world(&[(0, false), (0, false)]);
+        assert_eq!(take_turn(&world), None);
+    }
+}
@@ -0,0 +1,141 @@
+use shipyard::*;
+use tetra::{
+    window,
+    Context,
+    Event,
+};
+
+/// The window events tetra sent since the last `WindowEvents::apply`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WindowEvent {
+    FocusGained,
+    FocusLost,
+    Minimized,
+    Restored,
+    Resized { width: i32, height: i32 },
+    CloseRequested,
+}
+
+/// Window events forwarded from `State::event` so that systems can pause when the window loses focus or veto quitting.
+///
+/// Call `forward_window_event` from the `event` of the state owning the World, then run `WindowEvents::apply` after
+/// the workloads each frame. Tetra closes the window itself when its close button is pressed, so a close request
+/// is made by the game with `request_close` (e.g. from a quit button or the escape key) and systems get one frame
+/// to `cancel_close` it, to show a confirmation dialog for example.
+#[derive(Clone, Debug)]
+pub struct WindowEvents {
+    events: Vec<WindowEvent>,
+    focused: bool,
+    minimized: bool,
+    close_requested: bool,
+    close_cancelled: bool,
+}
+
+impl Default for WindowEvents {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WindowEvents {
+    pub fn new() -> Self {
+        WindowEvents {
+            events: vec![],
+            focused: true,
+            minimized: false,
+            close_requested: false,
+            close_cancelled: false,
+        }
+    }
+
+    /// Records the event if it is a window event, everything else is ignored
+    pub fn handle_event(&mut self, event: &Event) {
+        let event = match event {
+            Event::FocusGained => WindowEvent::FocusGained,
+            Event::FocusLost => WindowEvent::FocusLost,
+            Event::Minimized => WindowEvent::Minimized,
+            Event::Restored | Event::Maximized => WindowEvent::Restored,
+            Event::Resized { width, height } => WindowEvent::Resized { width: *width, height: *height },
+            _ => return,
+        };
+        self.push(event);
+    }
+
+    fn push(&mut self, event: WindowEvent) {
+        match event {
+            WindowEvent::FocusGained => self.focused = true,
+            WindowEvent::FocusLost => self.focused = false,
+            WindowEvent::Minimized => self.minimized = true,
+            WindowEvent::Restored => self.minimized = false,
+            WindowEvent::CloseRequested => self.close_requested = true,
+            WindowEvent::Resized { .. } => {},
+        }
+        self.events.push(event);
+    }
+
+    /// The events received this frame, oldest first
+    pub fn events(&self) -> &[WindowEvent] {
+        &self.events
+    }
+
+    pub fn is_focused(&self) -> bool {
+        self.focused
+    }
+
+    pub fn is_minimized(&self) -> bool {
+        self.minimized
+    }
+
+    /// Whether the focus was lost or the window minimized this frame, for auto pausing
+    pub fn lost_focus(&self) -> bool {
+        self.events.iter().any(|event| *event == WindowEvent::FocusLost || *event == WindowEvent::Minimized)
+    }
+
+    /// Asks for the game to quit at the end of the frame unless a system cancels it
+    pub fn request_close(&mut self) {
+        self.push(WindowEvent::CloseRequested);
+    }
+
+    pub fn is_close_requested(&self) -> bool {
+        self.close_requested
+    }
+
+    /// Vetoes this frame's close request
+    pub fn cancel_close(&mut self) {
+        self.close_cancelled = true;
+    }
+
+    /// Whether a close was requested this frame and nothing cancelled it
+    pub fn should_close(&self) -> bool {
+        self.close_requested && !self.close_cancelled
+    }
+
+    /// Quits if a close request wasn't cancelled and clears the frame's events, run after the workloads
+    pub fn apply(ctx: &mut Context, mut window_events: UniqueViewMut<WindowEvents>) {
+        if window_events.should_close() {
+            window::quit(ctx);
+        }
+
+        window_events.events.clear();
+        window_events.close_requested = false;
+        window_events.close_cancelled = false;
+    }
+}
+
+/// Records a tetra event in the World's WindowEvents unique, call from `State::event` or `PDAState::event`
+pub fn forward_window_event(world: &World, event: &Event) {
+    world.run(|mut window_events: UniqueViewMut<WindowEvents>| {
+        window_events.handle_event(event);
+    });
+}
+
+/// Dummy trait to allow adding a method to World
+pub trait WindowEventsCreator {
+    fn add_window_events(&mut self);
+}
+
+impl WindowEventsCreator for World {
+    fn add_window_events(&mut self) {
+        self.add_unique(WindowEvents::new());
+    }
+}